@@ -0,0 +1,473 @@
+//! Analysis of captured frames, producing aggregate statistics for link-budget reviews.
+//!
+//! A [`FrameStats`] accumulates statistics of encoded frames: the frame count, a histogram of
+//! decoded frame sizes, the distribution of encoding overhead, counts of decoding errors by type,
+//! and the bytes that COBS/R saves compared to COBS. [`analyze()`] analyzes an iterator of
+//! encoded frames, and [`analyze_capture()`] (feature `std`) analyzes a capture recorded by
+//! [`crate::capture::CaptureWriter`].
+//!
+//! For quick triage of large captures, [`validate_stream()`] (feature `std`) scans an entire
+//! framed stream, checking the encoding of every frame, and its length and checksum according to a
+//! [`Profile`](crate::profile::Profile), without storing the decoded frames. It returns a [`StreamReport`] with counts of
+//! valid and invalid frames.
+//!
+//! Frames are given as the encoded bytes, without the zero-byte delimiter. Empty frames are
+//! skipped.
+//!
+//! Example:
+//!
+//!     use cobs2::analysis::analyze;
+//!     use cobs2::profile::Variant;
+//!
+//!     let frames = [&b"\x04ABC\x05ghij\x04xyz"[..], b"\x03AB", b"\x05AB"];
+//!     let stats = analyze(Variant::Cobs, frames);
+//!     assert_eq!(stats.frames, 3);
+//!     assert_eq!(stats.valid_frames, 2);
+//!     assert_eq!(stats.error_count(&cobs2::Error::TruncatedEncodedData), 1);
+//!     // COBS/R would save 1 byte on each valid frame.
+//!     assert_eq!(stats.cobsr_savings, 2);
+//!     println!("{}", stats);
+
+#[cfg(feature = "std")]
+use crate::crc::{crc16_ccitt_update, crc32_update, CRC16_CCITT_INIT, CRC32_INIT};
+use crate::profile::Variant;
+#[cfg(feature = "std")]
+use crate::profile::{Checksum, Profile};
+use crate::{cobs, cobsr, Error};
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use core::fmt;
+use core::ops::RangeInclusive;
+
+/// Aggregate statistics of encoded frames.
+///
+/// See the [`crate::analysis`] module for details.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrameStats {
+    /// The encoding variant of the frames.
+    pub variant: Variant,
+    /// Number of frames, including invalid frames.
+    pub frames: u64,
+    /// Number of frames which decoded successfully.
+    pub valid_frames: u64,
+    /// Total encoded bytes of all frames, not including delimiters.
+    pub encoded_bytes: u64,
+    /// Total decoded bytes of the valid frames.
+    pub decoded_bytes: u64,
+    /// Histogram of the decoded sizes of the valid frames.
+    pub size_histogram: Histogram,
+    /// Number of valid frames for each encoding overhead, in bytes.
+    pub overhead: BTreeMap<usize, u64>,
+    /// Number of invalid frames for each decoding error.
+    pub errors: Vec<(Error, u64)>,
+    /// Total bytes that COBS/R encoding saves compared to COBS, over the valid frames.
+    pub cobsr_savings: u64,
+}
+
+impl FrameStats {
+    /// Create empty statistics, for frames encoded with the given variant.
+    pub fn new(variant: Variant) -> FrameStats {
+        FrameStats {
+            variant,
+            frames: 0,
+            valid_frames: 0,
+            encoded_bytes: 0,
+            decoded_bytes: 0,
+            size_histogram: Histogram::default(),
+            overhead: BTreeMap::new(),
+            errors: Vec::new(),
+            cobsr_savings: 0,
+        }
+    }
+
+    /// Add an encoded frame, without the zero-byte delimiter, to the statistics. An empty frame
+    /// is skipped.
+    pub fn add(&mut self, frame_encoded: &[u8]) {
+        if frame_encoded.is_empty() {
+            return;
+        }
+        self.frames += 1;
+        self.encoded_bytes += frame_encoded.len() as u64;
+        let result = match self.variant {
+            Variant::Cobs => cobs::decode_vector(frame_encoded),
+            Variant::Cobsr => cobsr::decode_vector(frame_encoded),
+        };
+        let frame = match result {
+            Ok(frame) => frame,
+            Err(e) => {
+                match self.errors.iter_mut().find(|(error, _)| *error == e) {
+                    Some((_, count)) => *count += 1,
+                    None => self.errors.push((e, 1)),
+                }
+                return;
+            }
+        };
+        self.valid_frames += 1;
+        self.decoded_bytes += frame.len() as u64;
+        self.size_histogram.add(frame.len());
+        *self
+            .overhead
+            .entry(frame_encoded.len().saturating_sub(frame.len()))
+            .or_insert(0) += 1;
+        let cobs_len = cobs::encode_output_size(&frame);
+        let cobsr_len = cobsr::encode_output_size(&frame);
+        self.cobsr_savings += cobs_len.saturating_sub(cobsr_len) as u64;
+    }
+
+    /// Get the number of invalid frames with the given decoding error.
+    pub fn error_count(&self, error: &Error) -> u64 {
+        self.errors
+            .iter()
+            .find(|(e, _)| e == error)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Get the number of invalid frames.
+    pub fn invalid_frames(&self) -> u64 {
+        self.frames - self.valid_frames
+    }
+
+    /// Get the total encoding overhead of the valid frames, in bytes.
+    pub fn overhead_bytes(&self) -> u64 {
+        self.overhead
+            .iter()
+            .map(|(&overhead, &count)| overhead as u64 * count)
+            .sum()
+    }
+}
+
+/// Implement trait [fmt::Display], as a summary report.
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} frames, {} valid, {} invalid",
+            self.frames,
+            self.valid_frames,
+            self.invalid_frames()
+        )?;
+        writeln!(
+            f,
+            "{} encoded bytes, {} decoded bytes, {} overhead bytes, {} COBS/R savings bytes",
+            self.encoded_bytes,
+            self.decoded_bytes,
+            self.overhead_bytes(),
+            self.cobsr_savings
+        )?;
+        writeln!(f, "Frame sizes:")?;
+        for (range, count) in self.size_histogram.buckets() {
+            writeln!(f, "  {}..={}: {}", range.start(), range.end(), count)?;
+        }
+        writeln!(f, "Overhead:")?;
+        for (overhead, count) in &self.overhead {
+            writeln!(f, "  {}: {}", overhead, count)?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(f, "Errors:")?;
+            for (error, count) in &self.errors {
+                writeln!(f, "  {}: {}", error, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Histogram of frame sizes, with power-of-two buckets.
+///
+/// Bucket 0 counts sizes of 0. Bucket `i`, for `i` of 1 or more, counts sizes from `2^(i-1)` to
+/// `2^i - 1`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Histogram {
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    /// Count a size.
+    pub fn add(&mut self, size: usize) {
+        let bucket = (usize::BITS - size.leading_zeros()) as usize;
+        if self.counts.len() <= bucket {
+            self.counts.resize(bucket + 1, 0);
+        }
+        self.counts[bucket] += 1;
+    }
+
+    /// Get the count of each bucket, with the range of sizes of the bucket, up to the last
+    /// non-empty bucket.
+    pub fn buckets(&self) -> impl Iterator<Item = (RangeInclusive<usize>, u64)> + '_ {
+        self.counts.iter().enumerate().map(|(bucket, &count)| {
+            let range = match bucket {
+                0 => 0..=0,
+                _ => (1 << (bucket - 1))..=((1 << (bucket - 1)) * 2 - 1),
+            };
+            (range, count)
+        })
+    }
+}
+
+/// Analyze encoded frames, without zero-byte delimiters, encoded with the given variant.
+pub fn analyze<I>(variant: Variant, frames: I) -> FrameStats
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+{
+    let mut stats = FrameStats::new(variant);
+    for frame in frames {
+        stats.add(frame.as_ref());
+    }
+    stats
+}
+
+/// Analyze the frames of a capture, encoded with the given variant.
+///
+/// The return value is an [`std::io::Result`], which is an error if reading the capture failed.
+#[cfg(feature = "std")]
+pub fn analyze_capture<R>(
+    variant: Variant,
+    reader: crate::capture::CaptureReader<R>,
+) -> std::io::Result<FrameStats>
+where
+    R: std::io::Read,
+{
+    let mut stats = FrameStats::new(variant);
+    for record in reader {
+        stats.add(&record?.data);
+    }
+    Ok(stats)
+}
+
+/// Counts of valid and invalid frames in a framed stream, from [`validate_stream()`].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct StreamReport {
+    /// Number of bytes in the stream.
+    pub bytes: u64,
+    /// Number of frames, including invalid frames. Empty frames are not counted.
+    pub frames: u64,
+    /// Number of valid frames.
+    pub valid_frames: u64,
+    /// Number of invalid frames for each error.
+    pub errors: Vec<(Error, u64)>,
+    /// The offset in the stream of the start of the first invalid frame, if any.
+    pub first_invalid_offset: Option<u64>,
+}
+
+impl StreamReport {
+    /// Get the number of invalid frames with the given error.
+    pub fn error_count(&self, error: &Error) -> u64 {
+        self.errors
+            .iter()
+            .find(|(e, _)| e == error)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// Get the number of invalid frames.
+    pub fn invalid_frames(&self) -> u64 {
+        self.frames - self.valid_frames
+    }
+
+    /// Returns `true` if every frame is valid.
+    pub fn is_valid(&self) -> bool {
+        self.frames == self.valid_frames
+    }
+}
+
+/// Implement trait [fmt::Display], as a summary report.
+impl fmt::Display for StreamReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "{} bytes, {} frames, {} valid, {} invalid",
+            self.bytes,
+            self.frames,
+            self.valid_frames,
+            self.invalid_frames()
+        )?;
+        if let Some(offset) = self.first_invalid_offset {
+            writeln!(f, "First invalid frame at offset {}", offset)?;
+            writeln!(f, "Errors:")?;
+            for (error, count) in &self.errors {
+                writeln!(f, "  {}: {}", error, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate every frame of a framed stream, according to the framing profile, and count the
+/// valid and invalid frames.
+///
+/// Frames are checked as they are read, without storing the decoded data, so the memory used
+/// doesn't depend on the frame lengths. Empty frames, such as from leading delimiters, are skipped.
+/// A partial frame at the end of the stream is validated as if a delimiter followed it.
+///
+/// The return value is an [`std::io::Result`], which is an error if reading the stream failed.
+/// Invalid frames are counted in the [`StreamReport`], by the [`Error`] that [`Profile::decode()`]
+/// would return for them.
+///
+/// Example:
+///
+///     use cobs2::analysis::validate_stream;
+///     use cobs2::profile::Profile;
+///
+///     let stream = &b"\x04ABC\x00\x05AB\x00\x03xy\x00"[..];
+///     let report = validate_stream(stream, Profile::Cobs).unwrap();
+///     assert_eq!(report.frames, 3);
+///     assert_eq!(report.valid_frames, 2);
+///     assert_eq!(report.error_count(&cobs2::Error::TruncatedEncodedData), 1);
+///     assert_eq!(report.first_invalid_offset, Some(5));
+///
+#[cfg(feature = "std")]
+pub fn validate_stream<R>(mut reader: R, profile: Profile) -> std::io::Result<StreamReport>
+where
+    R: std::io::Read,
+{
+    let mut validator = Validator::new(profile);
+    let mut buf = alloc::vec![0_u8; 64 * 1024];
+    loop {
+        let len = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        for &byte_val in buf.iter().take(len) {
+            validator.push_encoded(byte_val);
+        }
+    }
+    validator.end_frame();
+    Ok(validator.report)
+}
+
+/// Validates frames one encoded byte at a time, keeping only the state of the current frame.
+#[cfg(feature = "std")]
+struct Validator {
+    profile: Profile,
+    report: StreamReport,
+    frame_start: u64,
+    /// The length code of the current block, or `0xFF` at the start of the frame, when there is
+    /// no implied zero byte before the next block.
+    code: u8,
+    /// The number of data bytes remaining in the current block.
+    remaining: u8,
+    decoded_len: u64,
+    crc: u32,
+    /// The last decoded bytes, which are held back from the CRC because they could be the
+    /// checksum.
+    tail: [u8; 4],
+    tail_len: usize,
+}
+
+#[cfg(feature = "std")]
+impl Validator {
+    fn new(profile: Profile) -> Validator {
+        let mut validator = Validator {
+            profile,
+            report: StreamReport::default(),
+            frame_start: 0,
+            code: 0xFF,
+            remaining: 0,
+            decoded_len: 0,
+            crc: 0,
+            tail: [0; 4],
+            tail_len: 0,
+        };
+        validator.reset();
+        validator
+    }
+
+    fn reset(&mut self) {
+        self.code = 0xFF;
+        self.remaining = 0;
+        self.decoded_len = 0;
+        self.crc = match self.profile.checksum() {
+            Checksum::None => 0,
+            Checksum::Crc16Ccitt => CRC16_CCITT_INIT as u32,
+            Checksum::Crc32 => CRC32_INIT,
+        };
+        self.tail_len = 0;
+    }
+
+    fn push_encoded(&mut self, byte_val: u8) {
+        if byte_val == 0 {
+            self.end_frame();
+            self.report.bytes += 1;
+            self.frame_start = self.report.bytes;
+            return;
+        }
+        self.report.bytes += 1;
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            self.push_decoded(byte_val);
+        } else {
+            if self.code != 0xFF {
+                self.push_decoded(0);
+            }
+            self.code = byte_val;
+            self.remaining = byte_val - 1;
+        }
+    }
+
+    fn push_decoded(&mut self, byte_val: u8) {
+        self.decoded_len += 1;
+        let checksum_len = self.profile.checksum().len();
+        if self.tail_len < checksum_len {
+            self.tail[self.tail_len] = byte_val;
+            self.tail_len += 1;
+            return;
+        }
+        if let Some((&oldest, _)) = self.tail[..checksum_len].split_first() {
+            self.crc = match self.profile.checksum() {
+                Checksum::None => self.crc,
+                Checksum::Crc16Ccitt => crc16_ccitt_update(self.crc as u16, oldest) as u32,
+                Checksum::Crc32 => crc32_update(self.crc, oldest),
+            };
+            self.tail.copy_within(1..checksum_len, 0);
+            self.tail[checksum_len - 1] = byte_val;
+        }
+    }
+
+    fn end_frame(&mut self) {
+        if self.report.bytes > self.frame_start {
+            self.report.frames += 1;
+            match self.check_frame() {
+                Ok(()) => self.report.valid_frames += 1,
+                Err(e) => {
+                    match self.report.errors.iter_mut().find(|(error, _)| *error == e) {
+                        Some((_, count)) => *count += 1,
+                        None => self.report.errors.push((e, 1)),
+                    }
+                    self.report
+                        .first_invalid_offset
+                        .get_or_insert(self.frame_start);
+                }
+            }
+        }
+        self.reset();
+    }
+
+    fn check_frame(&mut self) -> crate::Result<()> {
+        if self.remaining > 0 {
+            match self.profile.variant() {
+                Variant::Cobs => return Err(Error::TruncatedEncodedData),
+                // The final length code is the final data byte.
+                Variant::Cobsr => self.push_decoded(self.code),
+            }
+        }
+        let checksum = self.profile.checksum();
+        let data_len = self.decoded_len.saturating_sub(checksum.len() as u64);
+        if matches!(self.profile.max_len(), Some(max_len) if data_len > max_len as u64) {
+            return Err(Error::FrameTooLong);
+        }
+        if self.tail_len < checksum.len() {
+            return Err(Error::ChecksumMismatch);
+        }
+        let valid = match checksum {
+            Checksum::None => true,
+            Checksum::Crc16Ccitt => self.tail[..2] == (self.crc as u16).to_le_bytes(),
+            Checksum::Crc32 => self.tail[..] == (!self.crc).to_le_bytes(),
+        };
+        if !valid {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,652 @@
+//! Async full-duplex framed link, over streams that implement the `futures-io` traits.
+//!
+//! An [`AsyncLink`] is the async twin of [`crate::stream::Link`]. It owns the read and write
+//! halves of a connection, which implement [`futures_io::AsyncRead`] and
+//! [`futures_io::AsyncWrite`], with one framing profile and combined [`LinkStats`]. It works with
+//! any executor. Streams of other async runtimes, such as `tokio`, can be used through their
+//! compatibility adapters.
+//!
+//! If a received frame is invalid, [`AsyncLink::recv()`] returns an error of kind
+//! [`io::ErrorKind::InvalidData`], which wraps the [`crate::Error`]. The invalid frame is
+//! discarded, so the next call receives the following frame. Empty frames are skipped. A frame
+//! longer than the maximum set by [`AsyncLink::set_max_frame_len()`] is discarded as it is read,
+//! and reported as [`crate::Error::FrameTooLong`].
+//!
+//! [`AsyncLink::set_inter_byte_timeout()`] sets an inter-byte timeout. If no more of a partly
+//! received frame arrives within it, such as because the sending device was reset part way through
+//! the frame, the partial frame is discarded, rather than corrupting the next frame. It is either
+//! skipped or reported as an error, according to [`TimedOutFrames`]. The timeout doesn't depend on
+//! an async runtime. The task is woken by a thread when it ends.
+//!
+//! [`AsyncLink::set_flush_policy()`] sets a [`FlushPolicy`], to buffer sent frames, and write them
+//! together in fewer write calls, up to a byte or frame budget.
+//!
+//! ### Cancellation safety
+//!
+//! [`AsyncLink::recv()`] and [`AsyncLink::send()`] are cancellation-safe: their futures can be
+//! dropped before they complete, such as by a timeout, or by another branch of a `select!`
+//! completing first, without losing or corrupting frames.
+//!
+//! * Received data is buffered in the link, not in the [`AsyncLink::recv()`] future. So if the
+//!   future is dropped, a partly received frame is kept, and the next call continues with it.
+//! * A frame to send is encoded into a buffer in the link, when the [`AsyncLink::send()`] future
+//!   is first polled. If the future is dropped before the frame is completely written, the rest of
+//!   it is written by the next call of [`AsyncLink::send()`], before its own frame, or by
+//!   [`AsyncLink::flush()`]. So a frame is never partly written, nor interleaved with another.
+//!
+//! Example:
+//!
+//!     use cobs2::async_stream::AsyncLink;
+//!     use futures::executor::block_on;
+//!     use futures::io::Cursor;
+//!
+//!     block_on(async {
+//!         let mut link = AsyncLink::new(Cursor::new(b"\x04ABC\x00".to_vec()), Vec::new());
+//!         let mut frame = Vec::new();
+//!         link.recv(&mut frame).await.unwrap();
+//!         assert_eq!(frame, b"ABC");
+//!         link.send(b"xy").await.unwrap();
+//!         assert_eq!(link.get_ref().1, b"\x03xy\x00");
+//!     });
+//!
+//! ### Stream pumps
+//!
+//! For a one-call bridge between an async reader and writer, [`encode_stream_async()`] reads a
+//! reader to its end, and writes its data as frames to a writer, and [`decode_stream_async()`]
+//! does the reverse, writing the data of each decoded frame. They are the async counterparts of
+//! [`crate::stream::decode_stream()`].
+//!
+//! Example:
+//!
+//!     use cobs2::async_stream::{decode_stream_async, encode_stream_async};
+//!     use cobs2::profile::Profile;
+//!     use futures::executor::block_on;
+//!     use futures::io::Cursor;
+//!
+//!     block_on(async {
+//!         let data = &b"ABC\0xyz"[..];
+//!         let mut encoded = Vec::new();
+//!         encode_stream_async(data, &mut encoded, Profile::Cobs).await.unwrap();
+//!         assert_eq!(encoded, b"\x04ABC\x04xyz\x00");
+//!         let mut decoded = Vec::new();
+//!         decode_stream_async(Cursor::new(encoded), &mut decoded, Profile::Cobs).await.unwrap();
+//!         assert_eq!(decoded, data);
+//!     });
+
+use crate::profile::Profile;
+use crate::stream::{
+    to_io_error, FlushPolicy, IoErrorAction, IoErrorPolicy, IoErrorRecovery, LinkStats,
+    DEFAULT_MAX_FRAME_LEN,
+};
+use crate::Error;
+use core::future::poll_fn;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use futures_io::{AsyncRead, AsyncWrite};
+use std::io;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// An async full-duplex framed link, over separate read and write halves of a connection.
+///
+/// See the [`crate::async_stream`] module for details.
+pub struct AsyncLink<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    reader: R,
+    writer: W,
+    profile: Profile,
+    rx_buf: Vec<u8>,
+    scan_start: usize,
+    // The start of the frame in `rx_buf` has been discarded, because it was too long.
+    rx_too_long: bool,
+    max_frame_len: usize,
+    // The time the last data was read, while a frame is partly received.
+    rx_last: Instant,
+    inter_byte_timeout: Option<(Duration, TimedOutFrames)>,
+    timed_out_frames: u64,
+    timer: Timer,
+    tx_buf: Vec<u8>,
+    tx_pos: usize,
+    tx_frames: u64,
+    flush_policy: FlushPolicy,
+    stats: LinkStats,
+    recovery: IoErrorRecovery,
+}
+
+impl<R, W> AsyncLink<R, W>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    /// Create a new link over the reader and writer, using COBS encoding.
+    pub fn new(reader: R, writer: W) -> AsyncLink<R, W> {
+        AsyncLink::with_profile(reader, writer, Profile::Cobs)
+    }
+
+    /// Create a new link over the reader and writer, using COBS/R encoding.
+    pub fn new_cobsr(reader: R, writer: W) -> AsyncLink<R, W> {
+        AsyncLink::with_profile(reader, writer, Profile::Cobsr)
+    }
+
+    /// Create a new link over the reader and writer, using the given framing profile.
+    pub fn with_profile(reader: R, writer: W, profile: Profile) -> AsyncLink<R, W> {
+        AsyncLink {
+            reader,
+            writer,
+            profile,
+            rx_buf: Vec::new(),
+            scan_start: 0,
+            rx_too_long: false,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            rx_last: Instant::now(),
+            inter_byte_timeout: None,
+            timed_out_frames: 0,
+            timer: Timer::new(),
+            tx_buf: Vec::new(),
+            tx_pos: 0,
+            tx_frames: 0,
+            flush_policy: FlushPolicy::EachFrame,
+            stats: LinkStats::default(),
+            recovery: IoErrorRecovery::new(),
+        }
+    }
+
+    /// Set the maximum length of a received encoded frame, not including its delimiter. By
+    /// default, it is [`DEFAULT_MAX_FRAME_LEN`].
+    ///
+    /// A longer frame is not buffered, but is discarded as it is read up to its delimiter,
+    /// counted as an invalid frame, and reported as an error by [`AsyncLink::recv()`].
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Set an inter-byte timeout. If a frame is partly received, and no more data arrives within
+    /// the timeout, the partial frame is discarded, so that [`AsyncLink::recv()`] resynchronises
+    /// at the next frame. By default, there is no timeout.
+    ///
+    /// Timed-out frames are counted by [`timed_out_frames()`](AsyncLink::timed_out_frames), and
+    /// are handled according to `timed_out_frames`.
+    ///
+    /// Example:
+    ///
+    ///     use cobs2::async_stream::{AsyncLink, TimedOutFrames};
+    ///     use futures::executor::block_on;
+    ///     use futures::io::{AsyncRead, Cursor};
+    ///     use std::io;
+    ///     use std::pin::Pin;
+    ///     use std::task::{Context, Poll};
+    ///     use std::time::Duration;
+    ///
+    ///     // A device which is reset part way through a frame, and then goes quiet.
+    ///     struct Device(Cursor<Vec<u8>>);
+    ///     impl AsyncRead for Device {
+    ///         fn poll_read(
+    ///             mut self: Pin<&mut Self>,
+    ///             cx: &mut Context<'_>,
+    ///             buf: &mut [u8],
+    ///         ) -> Poll<io::Result<usize>> {
+    ///             match Pin::new(&mut self.0).poll_read(cx, buf) {
+    ///                 Poll::Ready(Ok(0)) => Poll::Pending,
+    ///                 result => result,
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     let device = Device(Cursor::new(b"\x04ABC\x00\x05AB".to_vec()));
+    ///     let mut link = AsyncLink::new(device, futures::io::sink());
+    ///     link.set_inter_byte_timeout(Duration::from_millis(10), TimedOutFrames::Report);
+    ///     block_on(async {
+    ///         let mut frame = Vec::new();
+    ///         link.recv(&mut frame).await.unwrap();
+    ///         assert_eq!(frame, b"ABC");
+    ///         let err = link.recv(&mut frame).await.unwrap_err();
+    ///         assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    ///     });
+    ///     assert_eq!(link.timed_out_frames(), 1);
+    ///
+    pub fn set_inter_byte_timeout(&mut self, timeout: Duration, timed_out_frames: TimedOutFrames) {
+        self.inter_byte_timeout = Some((timeout, timed_out_frames));
+    }
+
+    /// Remove the inter-byte timeout.
+    pub fn clear_inter_byte_timeout(&mut self) {
+        self.inter_byte_timeout = None;
+    }
+
+    /// Get the number of partly received frames which have been discarded, because of the
+    /// inter-byte timeout.
+    pub fn timed_out_frames(&self) -> u64 {
+        self.timed_out_frames
+    }
+
+    /// Set how transient read errors are handled by [`AsyncLink::recv()`], as for
+    /// [`crate::stream::FrameReader::set_io_error_policy()`]. By default, they are returned, like
+    /// other errors.
+    ///
+    /// The delay between retries doesn't depend on an async runtime. The task is woken by a
+    /// thread when the delay ends.
+    pub fn set_io_error_policy(&mut self, policy: IoErrorPolicy) {
+        self.recovery.set_policy(policy);
+    }
+
+    /// Set the function which decides whether a read error is transient, and so is handled
+    /// according to the [`IoErrorPolicy`]. By default, it is
+    /// [`crate::stream::is_transient_io_error()`].
+    pub fn set_transient_io_errors(&mut self, is_transient: fn(&io::Error) -> bool) {
+        self.recovery.set_transient(is_transient);
+    }
+
+    /// Get the number of transient read errors which have been skipped or retried.
+    pub fn recovered_io_errors(&self) -> u64 {
+        self.recovery.recovered()
+    }
+
+    /// Set when frames sent by [`AsyncLink::send()`] are written to the writer. By default, it is
+    /// [`FlushPolicy::EachFrame`].
+    ///
+    /// With another policy, frames are buffered, and written together in one write call if
+    /// possible, when the policy says so, or when [`AsyncLink::flush()`] is called. This saves
+    /// a write call per frame when forwarding many small frames.
+    ///
+    /// Example:
+    ///
+    ///     use cobs2::async_stream::AsyncLink;
+    ///     use cobs2::stream::FlushPolicy;
+    ///     use futures::executor::block_on;
+    ///
+    ///     let mut link = AsyncLink::new(futures::io::empty(), Vec::new());
+    ///     link.set_flush_policy(FlushPolicy::Frames(2));
+    ///     block_on(async {
+    ///         link.send(b"ABC").await.unwrap();
+    ///         assert_eq!(link.get_ref().1, b"");
+    ///         link.send(b"xy").await.unwrap();
+    ///         assert_eq!(link.get_ref().1, b"\x04ABC\x00\x03xy\x00");
+    ///         link.send(b"z").await.unwrap();
+    ///         link.flush().await.unwrap();
+    ///         assert_eq!(link.get_ref().1, b"\x04ABC\x00\x03xy\x00\x02z\x00");
+    ///     });
+    ///
+    pub fn set_flush_policy(&mut self, policy: FlushPolicy) {
+        self.flush_policy = policy;
+    }
+
+    /// Get the flush policy.
+    pub fn flush_policy(&self) -> FlushPolicy {
+        self.flush_policy
+    }
+
+    /// Get the number of bytes of buffered frames, which have not been written yet.
+    pub fn buffered_len(&self) -> usize {
+        self.tx_buf.len() - self.tx_pos
+    }
+
+    /// Encode a frame, followed by a zero-byte delimiter (and preceded by one, if the framing
+    /// profile has leading delimiters), then write and flush the buffered frames if the flush
+    /// policy says so.
+    ///
+    /// If previous calls were cancelled before their frames were completely written, the rest of
+    /// those frames is written first.
+    pub async fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        self.tx_buf.extend_from_slice(&frame_encoded);
+        self.tx_frames += 1;
+        if self.tx_pos != 0
+            || self
+                .flush_policy
+                .is_due(self.tx_buf.len(), self.tx_frames as usize)
+        {
+            self.flush().await
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write any buffered frames, and the rest of any frames from calls of [`AsyncLink::send()`]
+    /// which were cancelled before their frames were completely written, then flush the writer.
+    pub async fn flush(&mut self) -> io::Result<()> {
+        while self.tx_pos < self.tx_buf.len() {
+            let (writer, remaining) = (&mut self.writer, &self.tx_buf[self.tx_pos..]);
+            let len = poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, remaining)).await?;
+            if len == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            self.tx_pos += len;
+        }
+        poll_fn(|cx| Pin::new(&mut self.writer).poll_flush(cx)).await?;
+        self.stats.frames_sent += self.tx_frames;
+        self.stats.bytes_sent += self.tx_buf.len() as u64;
+        self.tx_buf.clear();
+        self.tx_pos = 0;
+        self.tx_frames = 0;
+        Ok(())
+    }
+
+    /// Read the next frame from the reader, and decode it into `frame`.
+    ///
+    /// Received data is buffered in the link, so the future can be dropped before it completes,
+    /// such as by a timeout, without losing data. See [Cancellation
+    /// safety](crate::async_stream#cancellation-safety).
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader, other than a transient error which is recovered
+    ///   according to the [`IoErrorPolicy`].
+    /// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+    ///   than the maximum set by [`AsyncLink::set_max_frame_len()`]. The frame is discarded.
+    /// * [`io::ErrorKind::TimedOut`], wrapping [`Error::FrameTimeout`], if the frame times out,
+    ///   with [`TimedOutFrames::Report`]. The partial frame is discarded.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the reader ends before a complete frame.
+    pub async fn recv(&mut self, frame: &mut Vec<u8>) -> io::Result<()> {
+        let mut read_buf = [0_u8; 256];
+        loop {
+            while let Some(pos) = self.rx_buf[self.scan_start..].iter().position(|&x| x == 0) {
+                let end = self.scan_start + pos;
+                self.scan_start = 0;
+                self.stats.bytes_received += end as u64 + 1;
+                if self.rx_too_long || end > self.max_frame_len {
+                    self.rx_buf.drain(..=end);
+                    self.rx_too_long = false;
+                    self.stats.received(false);
+                    return Err(to_io_error(Error::FrameTooLong));
+                }
+                if end == 0 {
+                    self.rx_buf.drain(..=end);
+                    continue;
+                }
+                let result = self.profile.decode(&self.rx_buf[..end]);
+                self.rx_buf.drain(..=end);
+                self.stats.received(result.is_ok());
+                *frame = result.map_err(to_io_error)?;
+                return Ok(());
+            }
+            if self.rx_buf.len() > self.max_frame_len {
+                self.stats.bytes_received += self.rx_buf.len() as u64;
+                self.rx_buf.clear();
+                self.rx_too_long = true;
+            }
+            self.scan_start = self.rx_buf.len();
+            let deadline = match self.inter_byte_timeout {
+                Some((timeout, _)) if !self.rx_buf.is_empty() || self.rx_too_long => {
+                    self.rx_last.checked_add(timeout)
+                }
+                _ => None,
+            };
+            let (reader, timer) = (&mut self.reader, &self.timer);
+            let result = poll_fn(
+                |cx| match Pin::new(&mut *reader).poll_read(cx, &mut read_buf) {
+                    Poll::Ready(result) => Poll::Ready(Some(result)),
+                    Poll::Pending => match deadline {
+                        Some(deadline) => timer.poll(cx, deadline).map(|()| None),
+                        None => Poll::Pending,
+                    },
+                },
+            )
+            .await;
+            let len = match result {
+                Some(Ok(len)) => len,
+                Some(Err(e)) => {
+                    match self.recovery.handle(e)? {
+                        IoErrorAction::Skip => {
+                            self.rx_buf.clear();
+                            self.scan_start = 0;
+                            self.rx_too_long = false;
+                        }
+                        IoErrorAction::Retry(delay) => sleep(delay).await,
+                    }
+                    continue;
+                }
+                None => {
+                    // The partial frame timed out.
+                    self.stats.bytes_received += self.rx_buf.len() as u64;
+                    self.rx_buf.clear();
+                    self.scan_start = 0;
+                    self.rx_too_long = false;
+                    self.timed_out_frames += 1;
+                    match self.inter_byte_timeout {
+                        Some((_, TimedOutFrames::Report)) => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                Error::FrameTimeout,
+                            ))
+                        }
+                        _ => continue,
+                    }
+                }
+            };
+            if len == 0 {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            self.recovery.read_ok();
+            self.rx_last = Instant::now();
+            self.rx_buf.extend_from_slice(&read_buf[..len]);
+        }
+    }
+
+    /// Get the framing profile.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Get the statistics of both directions of the link.
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    /// Get references to the underlying reader and writer.
+    pub fn get_ref(&self) -> (&R, &W) {
+        (&self.reader, &self.writer)
+    }
+
+    /// Get mutable references to the underlying reader and writer.
+    ///
+    /// Reading directly from the reader could lose data, because of the read buffering.
+    pub fn get_mut(&mut self) -> (&mut R, &mut W) {
+        (&mut self.reader, &mut self.writer)
+    }
+
+    /// Consume the link, returning the underlying reader and writer. Any buffered received data,
+    /// buffered frames, and the rest of any frames from cancelled calls of [`AsyncLink::send()`],
+    /// is lost. So [`AsyncLink::flush()`] should be called first.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+}
+
+/// How an [`AsyncLink`] handles partly received frames which time out, according to its
+/// inter-byte timeout.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TimedOutFrames {
+    /// Timed-out frames are skipped.
+    Skip,
+    /// Timed-out frames are returned as an error of kind [`io::ErrorKind::TimedOut`], which wraps
+    /// [`Error::FrameTimeout`].
+    Report,
+}
+
+/// A timer which wakes the task polling it when its deadline passes, without an async runtime.
+/// At most one thread waits for it at a time, however many times it is polled.
+struct Timer {
+    shared: Arc<Mutex<TimerState>>,
+}
+
+struct TimerState {
+    deadline: Instant,
+    waker: Option<Waker>,
+    running: bool,
+}
+
+impl Timer {
+    fn new() -> Timer {
+        Timer {
+            shared: Arc::new(Mutex::new(TimerState {
+                deadline: Instant::now(),
+                waker: None,
+                running: false,
+            })),
+        }
+    }
+
+    /// Returns [`Poll::Ready`] if the deadline has passed, otherwise wakes the task when it does.
+    fn poll(&self, cx: &mut Context<'_>, deadline: Instant) -> Poll<()> {
+        if Instant::now() >= deadline {
+            return Poll::Ready(());
+        }
+        let mut state = self.shared.lock().unwrap_or_else(|e| e.into_inner());
+        state.deadline = deadline;
+        state.waker = Some(cx.waker().clone());
+        if !state.running {
+            state.running = true;
+            let shared = self.shared.clone();
+            std::thread::spawn(move || loop {
+                let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                if now >= state.deadline {
+                    state.running = false;
+                    if let Some(waker) = state.waker.take() {
+                        waker.wake();
+                    }
+                    return;
+                }
+                let delay = state.deadline - now;
+                drop(state);
+                std::thread::sleep(delay);
+            });
+        }
+        Poll::Pending
+    }
+}
+
+/// Read a reader to its end, encode its data with the given framing profile, and write the frames
+/// to a writer, each followed by a zero-byte delimiter (and preceded by one, if the framing profile
+/// has leading delimiters). Then flush the writer. Returns the number of bytes read.
+///
+/// Each read becomes a frame, so the frame boundaries depend on how the reader returns its data.
+/// A read is at most 4096 bytes, or the profile's maximum frame length. [`decode_stream_async()`]
+/// gives back the data, whatever the frame boundaries.
+///
+/// The following errors could be returned:
+///
+/// * An error from reading the reader, or writing the writer.
+pub async fn encode_stream_async<R, W>(
+    mut reader: R,
+    mut writer: W,
+    profile: Profile,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut read_buf = [0_u8; 4096];
+    let read_len = profile
+        .max_len()
+        .map_or(read_buf.len(), |max_len| max_len.min(read_buf.len()));
+    let mut bytes = 0_u64;
+    loop {
+        let result = poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut read_buf[..read_len]));
+        let len = match result.await {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes += len as u64;
+        let frame_encoded = profile
+            .encode_frame(&read_buf[..len])
+            .map_err(to_io_error)?;
+        write_all(&mut writer, &frame_encoded).await?;
+    }
+    poll_fn(|cx| Pin::new(&mut writer).poll_flush(cx)).await?;
+    Ok(bytes)
+}
+
+/// Read a reader to its end, decode its frames with the given framing profile, and write the data
+/// of each frame to a writer. Then flush the writer. Returns the number of bytes read.
+///
+/// Empty frames are skipped. A partial frame at the end of the data is decoded as if a delimiter
+/// followed it. Unlike [`crate::stream::decode_stream()`], decoding stops at an invalid frame,
+/// since the written data would otherwise silently have a gap.
+///
+/// The following errors could be returned:
+///
+/// * An error from reading the reader, or writing the writer.
+/// * [`io::ErrorKind::InvalidData`], if a frame is invalid. It wraps the [`crate::Error`].
+pub async fn decode_stream_async<R, W>(
+    mut reader: R,
+    mut writer: W,
+    profile: Profile,
+) -> io::Result<u64>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut read_buf = [0_u8; 4096];
+    let mut frame_buf = Vec::new();
+    let mut bytes = 0_u64;
+    loop {
+        let len = match poll_fn(|cx| Pin::new(&mut reader).poll_read(cx, &mut read_buf)).await {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes += len as u64;
+        let mut chunks = read_buf[..len].split(|&x| x == 0).peekable();
+        while let Some(chunk) = chunks.next() {
+            frame_buf.extend_from_slice(chunk);
+            if chunks.peek().is_none() {
+                // No delimiter after this chunk yet.
+                break;
+            }
+            if !frame_buf.is_empty() {
+                let frame = profile.decode(&frame_buf).map_err(to_io_error)?;
+                write_all(&mut writer, &frame).await?;
+                frame_buf.clear();
+            }
+        }
+    }
+    if !frame_buf.is_empty() {
+        let frame = profile.decode(&frame_buf).map_err(to_io_error)?;
+        write_all(&mut writer, &frame).await?;
+    }
+    poll_fn(|cx| Pin::new(&mut writer).poll_flush(cx)).await?;
+    Ok(bytes)
+}
+
+/// Write all of the buffer to the writer.
+async fn write_all<W>(writer: &mut W, mut buf: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    while !buf.is_empty() {
+        match poll_fn(|cx| Pin::new(&mut *writer).poll_write(cx, buf)).await {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(len) => buf = &buf[len..],
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// Wait for the duration, without an async runtime. A thread wakes the task when it ends.
+async fn sleep(duration: Duration) {
+    let deadline = Instant::now() + duration;
+    poll_fn(|cx| {
+        let now = Instant::now();
+        if now >= deadline {
+            return Poll::Ready(());
+        }
+        let waker = cx.waker().clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(deadline - now);
+            waker.wake();
+        });
+        Poll::Pending
+    })
+    .await
+}
@@ -0,0 +1,120 @@
+//! Decoding of frames into vectors reused from a thread-local pool, to avoid an allocation per
+//! frame in high-throughput services.
+//!
+//! The decode functions are [`cobs::decode_vector_pooled()`](crate::cobs::decode_vector_pooled)
+//! and [`cobsr::decode_vector_pooled()`](crate::cobsr::decode_vector_pooled). Each returns a
+//! [`PooledVec`], which dereferences to the decoded data, and returns its vector to the pool of
+//! the current thread when it is dropped. Once the pool has warmed up, decoding doesn't allocate,
+//! unless a frame is longer than any before.
+//!
+//! Each thread keeps at most [`MAX_POOLED`] vectors. A vector with a capacity of more than
+//! [`MAX_POOLED_CAPACITY`] bytes is freed rather than kept, so an occasional huge frame doesn't
+//! hold memory for the life of the thread. [`clear()`] frees the current thread's vectors.
+//!
+//! Example:
+//!
+//!     use cobs2::bufpool;
+//!     use cobs2::cobs;
+//!
+//!     for _ in 0..3 {
+//!         let frame = cobs::decode_vector_pooled(b"\x04ABC\x05ghij\x04xyz").unwrap();
+//!         assert_eq!(&frame[..], b"ABC\0ghij\0xyz");
+//!     }
+//!     // The same vector was reused for each frame.
+//!     assert_eq!(bufpool::pooled_len(), 1);
+
+use crate::Result;
+use core::cell::RefCell;
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use std::vec::Vec;
+
+/// The maximum number of vectors kept in each thread's pool.
+pub const MAX_POOLED: usize = 32;
+
+/// The maximum capacity, in bytes, of a vector kept in a pool.
+pub const MAX_POOLED_CAPACITY: usize = 1024 * 1024;
+
+std::thread_local! {
+    static POOL: RefCell<Vec<Vec<u8>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Get the number of vectors in the current thread's pool.
+pub fn pooled_len() -> usize {
+    POOL.with(|pool| pool.borrow().len())
+}
+
+/// Free the vectors in the current thread's pool.
+pub fn clear() {
+    POOL.with(|pool| pool.borrow_mut().clear());
+}
+
+/// A decoded frame, stored in a vector taken from a thread-local pool.
+///
+/// This dereferences to a slice of the decoded data. When it is dropped, the vector is returned to
+/// the pool of the thread which drops it.
+pub struct PooledVec {
+    data: Vec<u8>,
+}
+
+impl PooledVec {
+    /// Take a vector from the pool, resized to `len` bytes, and fill it using the given decode
+    /// function, which returns the decoded length.
+    pub(crate) fn decode_with<F>(len: usize, decode: F) -> Result<PooledVec>
+    where
+        F: FnOnce(&mut [u8]) -> Result<usize>,
+    {
+        let mut data = POOL
+            .with(|pool| pool.borrow_mut().pop())
+            .unwrap_or_default();
+        data.resize(len, 0);
+        let mut pooled = PooledVec { data };
+        let decoded_len = decode(&mut pooled.data)?;
+        pooled.data.truncate(decoded_len);
+        Ok(pooled)
+    }
+
+    /// Consume the frame, returning its vector, which is not returned to the pool.
+    pub fn into_vec(mut self) -> Vec<u8> {
+        core::mem::take(&mut self.data)
+    }
+}
+
+impl Deref for PooledVec {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl DerefMut for PooledVec {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+}
+
+/// Implement trait [fmt::Debug].
+impl fmt::Debug for PooledVec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PooledVec").field(&self.deref()).finish()
+    }
+}
+
+/// Implement trait [Drop], returning the vector to the pool.
+impl Drop for PooledVec {
+    fn drop(&mut self) {
+        let mut data = core::mem::take(&mut self.data);
+        if data.capacity() == 0 || data.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        data.clear();
+        // The pool is gone if the thread is exiting; then the vector is simply freed.
+        let _ = POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.len() < MAX_POOLED {
+                pool.push(data);
+            }
+        });
+    }
+}
@@ -0,0 +1,252 @@
+//! Recording of captured frames, with timestamps, for reproducing issues later.
+//!
+//! A [`CaptureWriter`] records frames to any [`std::io::Write`], such as a file. A
+//! [`CaptureReader`] reads them back as [`CaptureRecord`]s. The frames are recorded as the raw
+//! encoded bytes, as received, so invalid frames can be captured too.
+//!
+//! ### Capture format
+//!
+//! All integers are little-endian.
+//!
+//! * The capture starts with the 8-byte header `COBS2CAP`, followed by a format version `u16`,
+//!   which is 1.
+//! * Each frame is a record of:
+//!     * Monotonic timestamp: `u64` nanoseconds since the start of the capture.
+//!     * Wall-clock timestamp: `u64` nanoseconds since the Unix epoch.
+//!     * Frame length: `u32` number of bytes.
+//!     * Frame data: the raw encoded bytes of the frame, without the zero-byte delimiter.
+//!
+//! Example:
+//!
+//!     use cobs2::capture::{CaptureReader, CaptureWriter};
+//!
+//!     let mut capture = Vec::new();
+//!     let mut writer = CaptureWriter::new(&mut capture).unwrap();
+//!     writer.write_frame(b"\x04ABC").unwrap();
+//!     writer.write_frame(b"\x03xy").unwrap();
+//!
+//!     let reader = CaptureReader::new(&capture[..]).unwrap();
+//!     let frames: Vec<Vec<u8>> = reader.map(|record| record.unwrap().data).collect();
+//!     assert_eq!(frames, [b"\x04ABC".to_vec(), b"\x03xy".to_vec()]);
+//!
+//! ### Replay
+//!
+//! [`CaptureReader::replay_to()`] re-sends the frames of a capture to a writer, such as a serial
+//! port or a pipe to a device handler under test, optionally with the original timing.
+//! [`CaptureReader::replay_with()`] gives each frame to a function instead.
+
+use std::convert::TryFrom;
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use std::vec::Vec;
+
+/// Header at the start of a capture.
+pub(crate) const HEADER: &[u8; 8] = b"COBS2CAP";
+
+/// Version of the capture format.
+pub(crate) const VERSION: u16 = 1;
+
+/// One frame of a capture.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CaptureRecord {
+    /// Time since the start of the capture.
+    pub monotonic: Duration,
+    /// Wall-clock time, as the time since the Unix epoch.
+    pub wall_clock: Duration,
+    /// Raw encoded bytes of the frame, without the zero-byte delimiter.
+    pub data: Vec<u8>,
+}
+
+/// Timing of replayed frames, for [`CaptureReader::replay_to()`] and
+/// [`CaptureReader::replay_with()`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReplayTiming {
+    /// Replay frames as fast as possible.
+    Immediate,
+    /// Replay frames with the original timing, from their monotonic timestamps.
+    Original,
+    /// Replay frames with the original timing, sped up by the given factor. For example, 2.0
+    /// replays at double speed.
+    Speed(f64),
+}
+
+/// Records frames, with timestamps, to a writer.
+///
+/// See the [`crate::capture`] module for details.
+pub struct CaptureWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    start: Instant,
+}
+
+impl<W> CaptureWriter<W>
+where
+    W: Write,
+{
+    /// Start a capture, writing the capture header to the writer.
+    pub fn new(mut writer: W) -> io::Result<CaptureWriter<W>> {
+        writer.write_all(HEADER)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        Ok(CaptureWriter {
+            writer,
+            start: Instant::now(),
+        })
+    }
+
+    /// Record a frame, timestamped with the current time.
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let wall_clock = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        self.write_record(self.start.elapsed(), wall_clock, frame)
+    }
+
+    /// Record a frame, with the given timestamps.
+    pub fn write_record(
+        &mut self,
+        monotonic: Duration,
+        wall_clock: Duration,
+        frame: &[u8],
+    ) -> io::Result<()> {
+        let frame_len = u32::try_from(frame.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too long"))?;
+        self.writer
+            .write_all(&duration_to_nanos(monotonic).to_le_bytes())?;
+        self.writer
+            .write_all(&duration_to_nanos(wall_clock).to_le_bytes())?;
+        self.writer.write_all(&frame_len.to_le_bytes())?;
+        self.writer.write_all(frame)
+    }
+
+    /// Flush the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Consume the capture writer, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads frames of a capture from a reader, as an iterator of [`CaptureRecord`]s.
+///
+/// See the [`crate::capture`] module for details.
+pub struct CaptureReader<R>
+where
+    R: Read,
+{
+    reader: R,
+}
+
+impl<R> CaptureReader<R>
+where
+    R: Read,
+{
+    /// Start reading a capture, checking the capture header.
+    ///
+    /// An error of kind [`io::ErrorKind::InvalidData`] is returned if the header is not valid.
+    pub fn new(mut reader: R) -> io::Result<CaptureReader<R>> {
+        let mut header = [0_u8; 10];
+        reader.read_exact(&mut header)?;
+        if header[..8] != HEADER[..] || header[8..] != VERSION.to_le_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a supported capture",
+            ));
+        }
+        Ok(CaptureReader { reader })
+    }
+
+    /// Replay the frames of the capture, giving each frame's raw encoded bytes to the function.
+    ///
+    /// The return value is an [`io::Result`], which in the [`Ok`] case is the number of frames
+    /// replayed. An error from reading the capture, or from the function, stops the replay.
+    pub fn replay_with<F>(self, timing: ReplayTiming, mut f: F) -> io::Result<usize>
+    where
+        F: FnMut(&[u8]) -> io::Result<()>,
+    {
+        let speed = match timing {
+            ReplayTiming::Immediate => None,
+            ReplayTiming::Original => Some(1.0),
+            ReplayTiming::Speed(speed) => Some(speed),
+        };
+        let start = Instant::now();
+        let mut first_monotonic = None;
+        let mut count = 0;
+        for record in self {
+            let record = record?;
+            if let Some(speed) = speed {
+                let first_monotonic = *first_monotonic.get_or_insert(record.monotonic);
+                let delay = record
+                    .monotonic
+                    .saturating_sub(first_monotonic)
+                    .div_f64(speed);
+                if let Some(wait) = delay.checked_sub(start.elapsed()) {
+                    thread::sleep(wait);
+                }
+            }
+            f(&record.data)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Replay the frames of the capture to the writer, each followed by a zero-byte delimiter.
+    ///
+    /// The return value is an [`io::Result`], which in the [`Ok`] case is the number of frames
+    /// replayed. An error from reading the capture, or from writing, stops the replay.
+    pub fn replay_to<W>(self, timing: ReplayTiming, writer: &mut W) -> io::Result<usize>
+    where
+        W: Write,
+    {
+        self.replay_with(timing, |frame| {
+            writer.write_all(frame)?;
+            writer.write_all(&[0])?;
+            writer.flush()
+        })
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<CaptureRecord>> {
+        let mut record_header = [0_u8; 20];
+        // Check for the end of the capture, at a record boundary.
+        let header_len = self.reader.read(&mut record_header)?;
+        if header_len == 0 {
+            return Ok(None);
+        }
+        self.reader.read_exact(&mut record_header[header_len..])?;
+
+        let mut monotonic = [0_u8; 8];
+        let mut wall_clock = [0_u8; 8];
+        let mut frame_len = [0_u8; 4];
+        monotonic.copy_from_slice(&record_header[..8]);
+        wall_clock.copy_from_slice(&record_header[8..16]);
+        frame_len.copy_from_slice(&record_header[16..]);
+        let mut data = vec![0_u8; u32::from_le_bytes(frame_len) as usize];
+        self.reader.read_exact(&mut data)?;
+        Ok(Some(CaptureRecord {
+            monotonic: Duration::from_nanos(u64::from_le_bytes(monotonic)),
+            wall_clock: Duration::from_nanos(u64::from_le_bytes(wall_clock)),
+            data,
+        }))
+    }
+}
+
+/// Implement trait [Iterator].
+impl<R> Iterator for CaptureReader<R>
+where
+    R: Read,
+{
+    type Item = io::Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+fn duration_to_nanos(duration: Duration) -> u64 {
+    u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX)
+}
@@ -1,11 +1,13 @@
 //! This module contains functions for standard COBS encoding and decoding.
 
-use crate::{Error, Result};
+use crate::sink::FrameSink;
+use crate::{EmptyFrames, Error, ErrorDetail, Result};
+use core::borrow::Borrow;
 
 /// Calculate the minimum possible COBS encoded output size, for a given size of input data.
 pub const fn encode_min_output_size(input_len: usize) -> usize {
-    if input_len >= usize::max_value() - 1 {
-        usize::max_value()
+    if input_len >= usize::MAX - 1 {
+        usize::MAX
     } else {
         input_len + 1
     }
@@ -15,22 +17,85 @@ pub const fn encode_min_output_size(input_len: usize) -> usize {
 pub const fn encode_max_output_size(input_len: usize) -> usize {
     if input_len == 0 {
         1
-    } else if input_len >= usize::max_value() - 253 {
-        usize::max_value()
+    } else if input_len >= usize::MAX - 253 {
+        usize::MAX
     } else {
-        let increase = (input_len + 253) / 254;
-        if input_len >= usize::max_value() - increase {
-            usize::max_value()
+        let increase = input_len.div_ceil(254);
+        if input_len >= usize::MAX - increase {
+            usize::MAX
         } else {
             input_len + increase
         }
     }
 }
 
+/// Calculate the minimum possible COBS encoded output size, for a given size of input data.
+///
+/// Unlike [`encode_min_output_size()`], which saturates at [`usize::MAX`], this returns [`None`]
+/// if the size is not representable in a [`usize`].
+pub const fn encode_min_output_size_checked(input_len: usize) -> Option<usize> {
+    input_len.checked_add(1)
+}
+
+/// Calculate the maximum possible COBS encoded output size, for a given size of input data.
+///
+/// Unlike [`encode_max_output_size()`], which saturates at [`usize::MAX`], this returns [`None`]
+/// if the size is not representable in a [`usize`].
+///
+/// Example:
+///
+///     assert_eq!(cobs2::cobs::encode_max_output_size_checked(254), Some(255));
+///     assert_eq!(cobs2::cobs::encode_max_output_size_checked(usize::MAX), None);
+///
+pub const fn encode_max_output_size_checked(input_len: usize) -> Option<usize> {
+    if input_len == 0 {
+        Some(1)
+    } else {
+        input_len.checked_add(input_len.div_ceil(254))
+    }
+}
+
+/// Calculate the exact COBS encoded output size, for the given input data.
+///
+/// Unlike [`encode_max_output_size()`], this scans the input data, so it is slower, but it allows
+/// an output buffer of exactly the right size to be allocated.
+///
+/// Example:
+///
+///     assert_eq!(cobs2::cobs::encode_output_size(b"ABC\0ghij\0xyz"), 13);
+///
+pub fn encode_output_size(in_buf: &[u8]) -> usize {
+    encode_output_size_iter(in_buf)
+}
+
+/// Calculate the exact COBS encoded output size, for input data given by an iterator, such as
+/// of data in several parts.
+pub(crate) fn encode_output_size_iter<'a, I>(in_iter: I) -> usize
+where
+    I: IntoIterator<Item = &'a u8>,
+{
+    let mut code_i = 0;
+    let mut out_i = 1;
+
+    for x in in_iter {
+        if out_i - code_i >= 0xFF {
+            code_i = out_i;
+            out_i = code_i + 1;
+        }
+        if *x == 0 {
+            code_i = out_i;
+            out_i = code_i + 1;
+        } else {
+            out_i += 1;
+        }
+    }
+    out_i
+}
+
 /// Common function for converting an iterator encoder's input iterator size hint to an output size hint.
 fn encode_size_hint(in_hint: (usize, Option<usize>)) -> (usize, Option<usize>) {
     let lower_bound = encode_min_output_size(in_hint.0);
-    let upper_bound = in_hint.1.map(|x| encode_max_output_size(x));
+    let upper_bound = in_hint.1.map(encode_max_output_size);
     (lower_bound, upper_bound)
 }
 
@@ -46,20 +111,178 @@ pub const fn decode_min_output_size(input_len: usize) -> usize {
 
 /// Calculate the maximum possible decoded output size, for a given size of COBS-encoded input.
 pub const fn decode_max_output_size(input_len: usize) -> usize {
-    if input_len > 1 {
-        input_len - 1
-    } else {
-        0
+    input_len.saturating_sub(1)
+}
+
+/// Calculate the exact decoded output size, for the given COBS-encoded input data.
+///
+/// This only reads the length codes, so it is fast, but it does not check the data for errors.
+/// If the data is invalid, the result is the size needed for a decoding function to detect the
+/// error.
+///
+/// Example:
+///
+///     assert_eq!(cobs2::cobs::decode_output_size(b"\x04ABC\x05ghij\x04xyz"), 12);
+///
+pub fn decode_output_size(in_buf: &[u8]) -> usize {
+    let mut code_i = 0;
+    let mut out_len = 0;
+
+    while let Some(&code) = in_buf.get(code_i) {
+        if code == 0 {
+            break;
+        }
+        let run_end = code_i + code as usize;
+        out_len += core::cmp::min(run_end, in_buf.len()) - code_i - 1;
+        code_i = run_end;
+        if code_i >= in_buf.len() {
+            break;
+        }
+        if code < 0xFF {
+            // Trailing zero.
+            out_len += 1;
+        }
     }
+    out_len
 }
 
 /// Common function for converting an iterator decoder's input iterator size hint to an output size hint.
 fn decode_size_hint(in_hint: (usize, Option<usize>)) -> (usize, Option<usize>) {
     let lower_bound = decode_min_output_size(in_hint.0);
-    let upper_bound = in_hint.1.map(|x| decode_max_output_size(x));
+    let upper_bound = in_hint.1.map(decode_max_output_size);
     (lower_bound, upper_bound)
 }
 
+/// Write a byte to the output buffer, or return [`Error::OutputBufferTooSmall`] if the index is
+/// out of range.
+fn put_byte(out_buf: &mut [u8], index: usize, value: u8) -> Result<()> {
+    match out_buf.get_mut(index) {
+        Some(out_byte) => {
+            *out_byte = value;
+            Ok(())
+        }
+        None => Err(Error::OutputBufferTooSmall),
+    }
+}
+
+/// Write a byte to the output split in two slices, such as across the wrap-around point of a ring
+/// buffer, at an index counted from the start of `out_head`, or return
+/// [`Error::OutputBufferTooSmall`] if the index is out of range of both slices.
+fn put_split_byte(out_head: &mut [u8], out_tail: &mut [u8], index: usize, value: u8) -> Result<()> {
+    match index.checked_sub(out_head.len()) {
+        None => put_byte(out_head, index, value),
+        Some(tail_index) => put_byte(out_tail, tail_index, value),
+    }
+}
+
+/// Split the next block from the input data: up to 254 non-zero bytes, ending before the next
+/// zero byte. Returns the block, and the remaining input starting at the zero byte (if any).
+fn split_block(in_buf: &[u8]) -> (&[u8], &[u8]) {
+    let max_len = in_buf.len().min(0xFE);
+    let len = in_buf
+        .iter()
+        .take(0xFE)
+        .position(|&x| x == 0)
+        .unwrap_or(max_len);
+    in_buf.split_at(len)
+}
+
+/// Write a code (length) byte followed by the data bytes to the start of the output buffer, and
+/// advance the output buffer past them. The output buffer size is checked once for the whole
+/// block, returning [`Error::OutputBufferTooSmall`] if it is too small.
+fn put_block(out_buf: &mut &mut [u8], code: u8, data: &[u8]) -> Result<()> {
+    let out = core::mem::take(out_buf);
+    if out.len() <= data.len() {
+        return Err(Error::OutputBufferTooSmall);
+    }
+    let (block, rest) = out.split_at_mut(data.len() + 1);
+    if let Some((out_code, out_data)) = block.split_first_mut() {
+        *out_code = code;
+        out_data.copy_from_slice(data);
+    }
+    *out_buf = rest;
+    Ok(())
+}
+
+/// Check that the encoded data contains no zero-bytes, and decodes back to the input data, if
+/// feature `self-check` is enabled. Otherwise this does nothing.
+///
+/// # Panics
+///
+/// Panics if the check fails, which indicates a bug in the encoder.
+fn self_check<'a, E>(in_buf: &[u8], encoded: E)
+where
+    E: Iterator<Item = &'a u8> + Clone,
+{
+    if cfg!(feature = "self-check") {
+        assert!(
+            encoded.clone().all(|x| *x != 0),
+            "COBS self-check failed: zero-byte in encoded data"
+        );
+        assert!(
+            decode_result_iter(encoded).eq(in_buf.iter().map(|x| Ok(*x))),
+            "COBS self-check failed: encoded data doesn't decode to the input data"
+        );
+    }
+}
+
+/// The input data and encoded output of a frame of an incremental encoder, which are checked with
+/// [`self_check()`] at the end of the frame, if feature `self-check` is enabled. Otherwise this
+/// records nothing.
+#[derive(Debug, Clone)]
+struct FrameCheck {
+    #[cfg(feature = "self-check")]
+    input: alloc::vec::Vec<u8>,
+    #[cfg(feature = "self-check")]
+    encoded: alloc::vec::Vec<u8>,
+}
+
+impl FrameCheck {
+    const fn new() -> FrameCheck {
+        FrameCheck {
+            #[cfg(feature = "self-check")]
+            input: alloc::vec::Vec::new(),
+            #[cfg(feature = "self-check")]
+            encoded: alloc::vec::Vec::new(),
+        }
+    }
+
+    /// Record input data of the current frame.
+    fn input(&mut self, _in_buf: &[u8]) {
+        #[cfg(feature = "self-check")]
+        self.input.extend_from_slice(_in_buf);
+    }
+
+    /// Record encoded output, in which a zero-byte is a frame delimiter, which ends the current
+    /// frame.
+    fn output(&mut self, _out_buf: &[u8]) {
+        #[cfg(feature = "self-check")]
+        {
+            let mut parts = _out_buf.split(|&x| x == 0);
+            if let Some(part) = parts.next() {
+                self.encoded.extend_from_slice(part);
+            }
+            for part in parts {
+                self.end_frame();
+                self.encoded.extend_from_slice(part);
+            }
+        }
+    }
+
+    /// End the current frame, and check it. Nothing is checked if nothing was recorded since the
+    /// end of the previous frame, such as for repeated calls of an iterator after its end.
+    fn end_frame(&mut self) {
+        #[cfg(feature = "self-check")]
+        {
+            if !self.input.is_empty() || !self.encoded.is_empty() {
+                self_check(&self.input, self.encoded.iter());
+            }
+            self.input.clear();
+            self.encoded.clear();
+        }
+    }
+}
+
 /// Encode data into COBS encoded form, writing output to the given output buffer.
 ///
 /// The output data is COBS-encoded, containing no zero-bytes.
@@ -68,6 +291,9 @@ fn decode_size_hint(in_hint: (usize, Option<usize>)) -> (usize, Option<usize>) {
 /// [`encode_max_output_size()`] calculates the required output buffer size, for a given input
 /// size.
 ///
+/// This function does not panic. The output buffer size is checked once for each encoded block,
+/// rather than for each byte, and an output buffer that is too small is reported as an error.
+///
 /// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
 /// output buffer.
 ///
@@ -82,44 +308,364 @@ fn decode_size_hint(in_hint: (usize, Option<usize>)) -> (usize, Option<usize>) {
 ///     let data_cobs = cobs2::cobs::encode_array(&mut cobs_buf, data);
 ///     assert_eq!(data_cobs.unwrap(), b"\x04ABC\x05ghij\x04xyz");
 ///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
 pub fn encode_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    let mut out_rest = &mut *out_buf;
+    let mut out_len = 0;
+    let mut in_rest = in_buf;
+
+    loop {
+        let (block, after) = split_block(in_rest);
+        put_block(&mut out_rest, (block.len() + 1) as u8, block)?;
+        out_len += block.len() + 1;
+        if block.len() == 0xFE {
+            // A maximum-length block has no implied zero byte after it.
+            if after.is_empty() {
+                break;
+            }
+            in_rest = after;
+        } else {
+            // Skip the zero byte that ended the block, if any.
+            match after.split_first() {
+                Some((_, rest)) => in_rest = rest,
+                None => break,
+            }
+        }
+    }
+
+    let encoded = out_buf.get(..out_len).ok_or(Error::OutputBufferTooSmall)?;
+    self_check(in_buf, encoded.iter());
+    Ok(encoded)
+}
+
+/// Encode data into COBS encoded form, writing output to the given output buffer, and return the
+/// encoded length.
+///
+/// This is the same as [`encode_array()`], except that the return value is the length of the
+/// valid data in the output buffer, rather than a slice. This is convenient for callers that only
+/// need the length, such as C FFI wrappers.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is the length of the valid data in
+/// the output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x55_u8; 1000];
+///     let data = b"ABC\0ghij\0xyz";
+///     let cobs_len = cobs2::cobs::encode_array_len(&mut cobs_buf, data).unwrap();
+///     assert_eq!(&cobs_buf[..cobs_len], b"\x04ABC\x05ghij\x04xyz");
+///
+pub fn encode_array_len(out_buf: &mut [u8], in_buf: &[u8]) -> Result<usize> {
+    encode_array(out_buf, in_buf).map(|x| x.len())
+}
+
+/// Encode as much data into COBS encoded form as fits in the given output buffer, so that
+/// encoding can continue once the output buffer has been drained.
+///
+/// Unlike [`encode_array()`], which fails with [`Error::OutputBufferTooSmall`] if the whole encoded
+/// frame doesn't fit, this encodes data a whole block at a time, until the next block doesn't fit
+/// in the output buffer. The caller sends the encoded data, then continues with the input data
+/// that was not consumed, with the same or another output buffer. So a frame of any length can be
+/// encoded with a bounded output buffer. The end of the input data is the end of the frame, so the
+/// output is the same as [`encode_array()`], in pieces.
+///
+/// If nothing is produced, more output space is needed. A block needs at most 255 bytes, so an
+/// output buffer of 255 bytes is always enough.
+///
+/// The return value is a tuple of: the number of input bytes consumed, the number of encoded bytes
+/// written to the output buffer, and `true` if the frame is complete, so there is nothing more to
+/// encode.
+///
+/// Example:
+///
+///     let mut tx_buf = [0_u8; 6];
+///     let mut sent = Vec::new();
+///     let mut data: &[u8] = b"ABC\0ghij\0xyz";
+///     loop {
+///         let (consumed, produced, complete) =
+///             cobs2::cobs::encode_array_partial(&mut tx_buf, data);
+///         sent.extend_from_slice(&tx_buf[..produced]);
+///         data = &data[consumed..];
+///         if complete {
+///             break;
+///         }
+///     }
+///     assert_eq!(sent, b"\x04ABC\x05ghij\x04xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_array_partial(out_buf: &mut [u8], in_buf: &[u8]) -> (usize, usize, bool) {
+    let mut in_i = 0;
+    let mut out_i = 0;
+    let mut complete = false;
+
+    loop {
+        let in_rest = in_buf.get(in_i..).unwrap_or_default();
+        let run_len = in_rest
+            .iter()
+            .take(0xFE)
+            .position(|&x| x == 0)
+            .unwrap_or_else(|| in_rest.len().min(0xFE));
+        let run = in_rest.get(..run_len).unwrap_or_default();
+        let (code, consumed, last) = match in_rest.get(run_len) {
+            None => ((run_len + 1) as u8, run_len, true),
+            // Maximum length run, which is not followed by an implicit zero-byte.
+            Some(_) if run_len == 0xFE => (0xFF, run_len, false),
+            // The run is ended by a zero byte, which is consumed.
+            Some(_) => ((run_len + 1) as u8, run_len + 1, false),
+        };
+        let out_block = match out_buf.get_mut(out_i..out_i + run_len + 1) {
+            Some(out_block) => out_block,
+            None => break,
+        };
+        if let Some((out_code, out_run)) = out_block.split_first_mut() {
+            *out_code = code;
+            out_run.copy_from_slice(run);
+        }
+        in_i += consumed;
+        out_i += run_len + 1;
+        if last {
+            complete = true;
+            break;
+        }
+    }
+
+    if out_i != 0 {
+        // Until the frame is complete, the encoded data decodes to the consumed data, without the
+        // zero byte which ended the last block, if any.
+        let consumed = in_buf.get(..in_i).unwrap_or_default();
+        let decoded = match consumed.split_last() {
+            Some((0, init)) if !complete => init,
+            _ => consumed,
+        };
+        self_check(decoded, out_buf.get(..out_i).unwrap_or_default().iter());
+    }
+    (in_i, out_i, complete)
+}
+
+/// Encode data into COBS encoded form, writing output to two output slices, one after the other.
+///
+/// This suits a transmit ring buffer, such as for DMA, in which the free space wraps around the
+/// end of the buffer. A frame can be encoded directly across the wrap-around point, with
+/// `out_head` being the free space up to the end of the buffer, and `out_tail` the free space from
+/// the start of the buffer, without an intermediate contiguous buffer.
+///
+/// This gives the same output as [`encode_array()`], with the first part of it in `out_head`, and
+/// the rest in `out_tail`.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is the length of the valid data in
+/// `out_head` and in `out_tail`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`], if the encoded data doesn't fit in both slices together.
+///
+/// Example:
+///
+///     let mut ring = [0x55_u8; 16];
+///     let (out_tail, out_head) = ring.split_at_mut(12);
+///     let data = b"ABC\0ghij";
+///     let lens = cobs2::cobs::encode_into_split(out_head, out_tail, data).unwrap();
+///     assert_eq!(lens, (4, 5));
+///     assert_eq!(&ring[12..], b"\x04ABC");
+///     assert_eq!(&ring[..5], b"\x05ghij");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_into_split(
+    out_head: &mut [u8],
+    out_tail: &mut [u8],
+    in_buf: &[u8],
+) -> Result<(usize, usize)> {
     let mut code_i = 0;
     let mut out_i = 1;
 
-    if code_i >= out_buf.len() {
-        return Err(Error::OutputBufferTooSmall);
-    }
     for x in in_buf {
         if out_i - code_i >= 0xFF {
-            out_buf[code_i] = 0xFF;
+            put_split_byte(out_head, out_tail, code_i, 0xFF)?;
             code_i = out_i;
-            if code_i >= out_buf.len() {
-                return Err(Error::OutputBufferTooSmall);
-            }
             out_i = code_i + 1;
         }
         if *x == 0 {
-            out_buf[code_i] = (out_i - code_i) as u8;
+            put_split_byte(out_head, out_tail, code_i, (out_i - code_i) as u8)?;
             code_i = out_i;
-            if code_i >= out_buf.len() {
-                return Err(Error::OutputBufferTooSmall);
-            }
             out_i = code_i + 1;
         } else {
-            if out_i >= out_buf.len() {
-                return Err(Error::OutputBufferTooSmall);
-            }
-            out_buf[out_i] = *x;
+            put_split_byte(out_head, out_tail, out_i, *x)?;
             out_i += 1;
         }
     }
+    put_split_byte(out_head, out_tail, code_i, (out_i - code_i) as u8)?;
+
+    let head_len = out_i.min(out_head.len());
+    let tail_len = out_i - head_len;
+    self_check(
+        in_buf,
+        out_head
+            .iter()
+            .take(head_len)
+            .chain(out_tail.iter().take(tail_len)),
+    );
+    Ok((head_len, tail_len))
+}
+
+/// Encode a short frame of data, of at most 253 bytes, into COBS encoded form, writing output to
+/// the given output buffer.
+///
+/// This gives the same output as [`encode_array()`]. But because the data is short, every length
+/// code is less than `FF`, so no block is a maximum length run without a following zero byte, and
+/// this function is simpler and faster.
+///
+/// The caller must provide a reference to an output buffer at least one byte longer than the
+/// input data.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ShortFrameTooLong`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x55_u8; 254];
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs = cobs2::cobs::encode_short_array(&mut cobs_buf, data);
+///     assert_eq!(data_cobs.unwrap(), b"\x04ABC\x05ghij\x04xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_short_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    if in_buf.len() > 253 {
+        return Err(Error::ShortFrameTooLong);
+    }
+    let mut out_i = 0;
+
+    for run in in_buf.split(|x| *x == 0) {
+        put_byte(out_buf, out_i, (run.len() + 1) as u8)?;
+        out_buf
+            .get_mut(out_i + 1..out_i + 1 + run.len())
+            .ok_or(Error::OutputBufferTooSmall)?
+            .copy_from_slice(run);
+        out_i += run.len() + 1;
+    }
+
+    let encoded = out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)?;
+    self_check(in_buf, encoded.iter());
+    Ok(encoded)
+}
+
+/// Encoder for [`encode_fmt()`], which writes COBS-encoded data directly to the output buffer.
+struct FmtEncoder<'a> {
+    out_buf: &'a mut [u8],
+    code_i: usize,
+    out_i: usize,
+    check: FrameCheck,
+}
+
+impl FmtEncoder<'_> {
+    /// Write a byte to the output buffer. Writes out of range are ignored, because they are
+    /// detected by the final output length.
+    fn put(&mut self, index: usize, value: u8) {
+        if let Some(out_byte) = self.out_buf.get_mut(index) {
+            *out_byte = value;
+        }
+    }
+}
+
+/// Implement trait [core::fmt::Write].
+impl core::fmt::Write for FmtEncoder<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.check.input(s.as_bytes());
+        for x in s.as_bytes() {
+            if self.out_i - self.code_i >= 0xFF {
+                self.put(self.code_i, 0xFF);
+                self.code_i = self.out_i;
+                self.out_i = self.code_i + 1;
+            }
+            if *x == 0 {
+                self.put(self.code_i, (self.out_i - self.code_i) as u8);
+                self.code_i = self.out_i;
+                self.out_i = self.code_i + 1;
+            } else {
+                self.put(self.out_i, *x);
+                self.out_i += 1;
+            }
+        }
+        if self.out_i > self.out_buf.len() {
+            // Stop formatting, because the output buffer is too small.
+            return Err(core::fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+/// Format text, and encode it into COBS encoded form in one pass, writing output to the given
+/// output buffer.
+///
+/// This avoids the need for a separate buffer for the formatted text. The arguments are made by
+/// [`format_args!`].
+///
+/// The output data is COBS-encoded, containing no zero-bytes.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x55_u8; 100];
+///     let data_cobs = cobs2::cobs::encode_fmt(&mut cobs_buf, format_args!("temp={}", 21));
+///     assert_eq!(data_cobs.unwrap(), b"\x08temp=21");
+///
+pub fn encode_fmt<'a>(out_buf: &'a mut [u8], args: core::fmt::Arguments) -> Result<&'a [u8]> {
+    let mut encoder = FmtEncoder {
+        out_buf,
+        code_i: 0,
+        out_i: 1,
+        check: FrameCheck::new(),
+    };
+    // Formatting only fails if the encoder returns an error.
+    core::fmt::write(&mut encoder, args).map_err(|_| Error::OutputBufferTooSmall)?;
+    let FmtEncoder {
+        out_buf,
+        code_i,
+        out_i,
+        mut check,
+    } = encoder;
 
-    // We've reached the end of the source data.
     // Finalise the remaining output. In particular, write the code (length) byte.
-    // Update the pointer to calculate the final output length.
-    out_buf[code_i] = (out_i - code_i) as u8;
+    put_byte(out_buf, code_i, (out_i - code_i) as u8)?;
 
-    Ok(&out_buf[..out_i])
+    let encoded = out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)?;
+    check.output(encoded);
+    check.end_frame();
+    Ok(encoded)
 }
 
 /// Encode data into COBS encoded form, returning output as a vector of `u8`.
@@ -171,9 +717,47 @@ pub fn encode_vector(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
         out_vec[code_i] = run_len;
     }
 
+    self_check(in_buf, out_vec.iter());
+    Ok(out_vec)
+}
+
+/// Encode data into COBS encoded form, returning output as a vector of `u8` of exactly the
+/// required capacity.
+///
+/// This is the same as [`encode_vector()`], except that the input data is first scanned by
+/// [`encode_output_size()`], so that the output vector is allocated with exactly the required
+/// capacity, rather than [`encode_max_output_size()`]. This is useful for large data.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs = cobs2::cobs::encode_vector_exact(data).unwrap();
+///     assert_eq!(data_cobs, b"\x04ABC\x05ghij\x04xyz");
+///     assert_eq!(data_cobs.capacity(), data_cobs.len());
+///
+#[cfg(feature = "alloc")]
+pub fn encode_vector_exact(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    let mut out_vec = alloc::vec![0_u8; encode_output_size(in_buf)];
+    encode_array(&mut out_vec, in_buf)?;
     Ok(out_vec)
 }
 
+/// Encode data into COBS encoded form, returning output as a vector of `u8`.
+///
+/// This is the same as [`encode_vector()`], except that the input data can be any type that
+/// implements [`AsRef<[u8]>`](AsRef), such as a slice, an array, a vector or `bytes::Bytes`.
+///
+/// Example:
+///
+///     let data = vec![b'A', 0, b'B'];
+///     assert_eq!(cobs2::cobs::encode(&data).unwrap(), b"\x02A\x02B");
+///     assert_eq!(cobs2::cobs::encode([0_u8; 2]).unwrap(), b"\x01\x01\x01");
+///
+#[cfg(feature = "alloc")]
+pub fn encode(in_buf: impl AsRef<[u8]>) -> Result<alloc::vec::Vec<u8>> {
+    encode_vector(in_buf.as_ref())
+}
+
 struct EncodeIterator<I>
 where
     I: Iterator<Item = u8>,
@@ -184,6 +768,7 @@ where
     hold_write_i: u8,
     hold_read_i: u8,
     hold_buf: [u8; 255],
+    check: FrameCheck,
 }
 
 impl<I> EncodeIterator<I>
@@ -191,24 +776,18 @@ where
     I: Iterator<Item = u8>,
 {
     fn new(i: I) -> EncodeIterator<I> {
-        return EncodeIterator {
+        EncodeIterator {
             in_iter: i,
             eof: false,
             last_run_0xff: false,
             hold_write_i: 0,
             hold_read_i: 0,
             hold_buf: [1; 255],
-        };
+            check: FrameCheck::new(),
+        }
     }
-}
-
-impl<I> Iterator for EncodeIterator<I>
-where
-    I: Iterator<Item = u8>,
-{
-    type Item = u8;
 
-    fn next(&mut self) -> Option<Self::Item> {
+    fn next_byte(&mut self) -> Option<u8> {
         if self.hold_write_i != 0 {
             if self.hold_read_i < self.hold_write_i {
                 let byte_val = self.hold_buf[self.hold_read_i as usize];
@@ -229,6 +808,9 @@ where
                 return Some(0xFF);
             } else {
                 let in_iter_next = self.in_iter.next();
+                if let Some(byte_val) = in_iter_next {
+                    self.check.input(&[byte_val]);
+                }
                 let byte_val = in_iter_next.unwrap_or_else(|| {
                     self.eof = true;
                     0
@@ -248,6 +830,22 @@ where
             }
         }
     }
+}
+
+impl<I> Iterator for EncodeIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_byte();
+        match next {
+            Some(byte_val) => self.check.output(&[byte_val]),
+            None => self.check.end_frame(),
+        }
+        next
+    }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         let in_iter_size_hint = self.in_iter.size_hint();
@@ -255,11 +853,12 @@ where
     }
 }
 
-/// Encode data into COBS encoded form, getting data from a `u8` iterator, and providing the output as an iterator.
+/// Encode data into COBS encoded form, getting data from an iterator, and providing the output as an iterator.
 ///
 /// The output data is COBS-encoded, containing no zero-bytes.
 ///
-/// The caller must provide a `u8` iterator.
+/// The caller must provide anything that can be iterated to give `u8` or `&u8` items, such as a
+/// `u8` iterator, a `&u8` iterator, a slice or a vector.
 ///
 /// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
 /// container.
@@ -268,15 +867,21 @@ where
 ///     let data_cobs: Vec<u8> = cobs2::cobs::encode_iter(data.into_iter()).collect();
 ///     assert_eq!(data_cobs, b"\x04ABC\x05ghij\x04xyz");
 ///
-pub fn encode_iter(i: impl Iterator<Item = u8>) -> impl Iterator<Item = u8> {
-    EncodeIterator::new(i)
+///     let data_cobs: Vec<u8> = cobs2::cobs::encode_iter(b"ABC\0ghij\0xyz").collect();
+///     assert_eq!(data_cobs, b"\x04ABC\x05ghij\x04xyz");
+///
+pub fn encode_iter<I>(i: I) -> impl Iterator<Item = u8>
+where
+    I: IntoIterator,
+    I::Item: Borrow<u8>,
+{
+    EncodeIterator::new(i.into_iter().map(|x| *x.borrow()))
 }
 
 /// Encode data into COBS encoded form, getting data from a `&u8` iterator, and providing the output as an iterator.
 ///
-/// The output data is COBS-encoded, containing no zero-bytes.
-///
-/// The caller must provide a `&u8` iterator.
+/// This is the same as [`encode_iter()`], which also accepts a `&u8` iterator. It is kept for
+/// compatibility.
 ///
 /// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
 /// container.
@@ -289,15 +894,139 @@ pub fn encode_ref_iter<'a, I>(i: I) -> impl Iterator<Item = u8> + 'a
 where
     I: Iterator<Item = &'a u8> + 'a,
 {
-    EncodeIterator::<_>::new(i.copied())
+    encode_iter(i)
 }
 
-/// Decode COBS-encoded data, writing decoded data to the given output buffer.
-///
+struct EncodeCloneIterator<I>
+where
+    I: Iterator<Item = u8> + Clone,
+{
+    in_iter: I,
+    run_left: u8,
+    zero_next: bool,
+    eof: bool,
+    check: FrameCheck,
+}
+
+impl<I> EncodeCloneIterator<I>
+where
+    I: Iterator<Item = u8> + Clone,
+{
+    fn new(i: I) -> EncodeCloneIterator<I> {
+        EncodeCloneIterator {
+            in_iter: i,
+            run_left: 0,
+            zero_next: false,
+            eof: false,
+            check: FrameCheck::new(),
+        }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        if self.run_left != 0 {
+            self.run_left -= 1;
+            let next = self.in_iter.next();
+            if let Some(byte_val) = next {
+                self.check.input(&[byte_val]);
+            }
+            return next;
+        }
+        if self.eof {
+            return None;
+        }
+        if self.zero_next {
+            // Skip the zero-byte at the end of the previous run.
+            self.zero_next = false;
+            self.in_iter.next();
+            self.check.input(&[0]);
+        }
+
+        // Look ahead with a clone of the input iterator, to find the length of the next run.
+        let mut lookahead = self.in_iter.clone();
+        let mut run_len = 0_u8;
+        loop {
+            match lookahead.next() {
+                None => {
+                    self.eof = true;
+                    break;
+                }
+                Some(0) => {
+                    self.zero_next = true;
+                    break;
+                }
+                Some(_) => {
+                    run_len += 1;
+                    if run_len == 0xFE {
+                        // Maximum length run, which is not followed by an implicit zero-byte.
+                        self.eof = lookahead.next().is_none();
+                        break;
+                    }
+                }
+            }
+        }
+        self.run_left = run_len;
+        Some(run_len + 1)
+    }
+}
+
+impl<I> Iterator for EncodeCloneIterator<I>
+where
+    I: Iterator<Item = u8> + Clone,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let next = self.next_byte();
+        match next {
+            Some(byte_val) => self.check.output(&[byte_val]),
+            None => self.check.end_frame(),
+        }
+        next
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let in_iter_size_hint = self.in_iter.size_hint();
+        (
+            self.run_left as usize,
+            in_iter_size_hint.1.map(encode_max_output_size),
+        )
+    }
+}
+
+/// Encode data into COBS encoded form, getting data from a cloneable `u8` iterator, and
+/// providing the output as an iterator, with low memory use.
+///
+/// The output data is COBS-encoded, containing no zero-bytes.
+///
+/// Unlike [`encode_iter()`], which holds up to 254 bytes of input data in a buffer, this reads
+/// ahead through a clone of the input iterator to find the length of each run, so its state is
+/// only the input iterator and a few bytes. The input data is read twice, so this suits iterators
+/// that are cheap to clone, such as slice iterators.
+///
+/// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
+/// container.
+///
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs: Vec<u8> = cobs2::cobs::encode_clone_iter(data.iter().copied()).collect();
+///     assert_eq!(data_cobs, b"\x04ABC\x05ghij\x04xyz");
+///
+pub fn encode_clone_iter<I>(i: I) -> impl Iterator<Item = u8>
+where
+    I: Iterator<Item = u8> + Clone,
+{
+    EncodeCloneIterator::new(i)
+}
+
+
+/// Decode COBS-encoded data, writing decoded data to the given output buffer.
+///
 /// The caller must provide a reference to a suitably-sized output buffer.
 /// [`decode_max_output_size()`] calculates the required output buffer size, for a given input
 /// size.
 ///
+/// This function does not panic. Every index into the input and output buffers is checked, and an
+/// output buffer that is too small is reported as an error.
+///
 /// The return value is a [`Result`] that in the [`Ok`] case is a slice of the decoded data in the
 /// output buffer.
 ///
@@ -315,323 +1044,2276 @@ where
 ///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
 ///
 pub fn decode_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
-    let mut code_i = 0;
+    decode_array_detail(out_buf, in_buf).map_err(|e| e.error)
+}
+
+/// Decode COBS-encoded data, writing decoded data to the given output buffer, and return the
+/// decoded length.
+///
+/// This is the same as [`decode_array()`], except that the return value is the length of the
+/// decoded data in the output buffer, rather than a slice. This is convenient for callers that
+/// only need the length, such as C FFI wrappers.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is the length of the decoded data in
+/// the output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let mut decode_buf = [0x55_u8; 1000];
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_len = cobs2::cobs::decode_array_len(&mut decode_buf, data_cobs).unwrap();
+///     assert_eq!(&decode_buf[..decode_len], b"ABC\0ghij\0xyz");
+///
+pub fn decode_array_len(out_buf: &mut [u8], in_buf: &[u8]) -> Result<usize> {
+    decode_array(out_buf, in_buf).map(|x| x.len())
+}
+
+/// Decode as much as possible of COBS-encoded data received so far, writing decoded data to the
+/// given output buffer, so that decoding can resume when more data is received.
+///
+/// Unlike [`decode_array()`], which decodes a whole frame or fails, this decodes the input data up
+/// to the end of the frame, which is a zero-byte delimiter, or until either the input data or the
+/// output buffer runs out. Input data is consumed a whole block at a time, along with its decoded
+/// data, including the zero byte implied by its length code, which depends on the byte that follows
+/// the block. So the caller resumes with the input data that was not consumed, followed by more
+/// received data, and with the rest of the output buffer, or another output buffer.
+///
+/// If nothing is consumed, more input data is needed, or more output space. Given a block and the
+/// byte that follows it, up to 256 bytes of input, an output buffer of 255 bytes is always enough.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a tuple of: the number of input
+/// bytes consumed, the number of decoded bytes written to the output buffer, and `true` if the end
+/// of the frame was reached. Then the delimiter is included in the bytes consumed, and the input
+/// data after it is the next frame.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::TruncatedEncodedData`], if the delimiter is within a block. The caller should
+///   discard the input data up to and including the delimiter, to resynchronise with the next
+///   frame.
+///
+/// Example:
+///
+///     let mut decode_buf = [0_u8; 16];
+///     // The first received data ends within the second block.
+///     let progress = cobs2::cobs::decode_array_partial(&mut decode_buf, b"\x04ABC\x05gh");
+///     assert_eq!(progress.unwrap(), (4, 4, false));
+///     // Resume with the data not consumed, followed by the next received data.
+///     let progress =
+///         cobs2::cobs::decode_array_partial(&mut decode_buf[4..], b"\x05ghij\x04xyz\x00");
+///     assert_eq!(progress.unwrap(), (10, 8, true));
+///     assert_eq!(&decode_buf[..12], b"ABC\0ghij\0xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_array_partial(out_buf: &mut [u8], in_buf: &[u8]) -> Result<(usize, usize, bool)> {
+    let mut in_i = 0;
     let mut out_i = 0;
 
-    if !in_buf.is_empty() {
-        loop {
-            let code = in_buf[code_i];
-            if code == 0 {
-                return Err(Error::ZeroInEncodedData);
+    while let Some(&code) = in_buf.get(in_i) {
+        if code == 0 {
+            return Ok((in_i + 1, out_i, true));
+        }
+        let run_start = in_i + 1;
+        let run_end = in_i + code as usize;
+        let run = match in_buf.get(run_start..run_end) {
+            Some(run) if !run.contains(&0) => run,
+            Some(_) => return Err(Error::TruncatedEncodedData),
+            None if in_buf.get(run_start..).unwrap_or_default().contains(&0) => {
+                return Err(Error::TruncatedEncodedData)
             }
-            for in_i in (code_i + 1)..(code_i + code as usize) {
-                if in_i >= in_buf.len() {
-                    return Err(Error::TruncatedEncodedData);
-                }
-                let in_byte = in_buf[in_i];
-                if in_byte == 0 {
-                    return Err(Error::ZeroInEncodedData);
-                }
-                if out_i >= out_buf.len() {
-                    return Err(Error::OutputBufferTooSmall);
-                }
-                out_buf[out_i] = in_byte;
+            None => break,
+        };
+        // A run with a length code below `FF` is followed by a zero byte, unless it is the last.
+        let trailing_zero = match in_buf.get(run_end) {
+            _ if code == 0xFF => false,
+            Some(&next) => next != 0,
+            None => break,
+        };
+        let out_len = run.len() + usize::from(trailing_zero);
+        let out_run = match out_buf.get_mut(out_i..out_i + out_len) {
+            Some(out_run) => out_run,
+            None => break,
+        };
+        let (out_data, out_zero) = out_run.split_at_mut(run.len());
+        out_data.copy_from_slice(run);
+        out_zero.fill(0);
+        in_i = run_end;
+        out_i += out_len;
+    }
+    Ok((in_i, out_i, false))
+}
+
+/// Decode COBS-encoded data, reading input from two input slices, one after the other, and writing
+/// decoded data to the given output buffer.
+///
+/// This suits a receive ring buffer, such as for DMA, in which a frame can straddle the
+/// wrap-around point. `in_head` is the part of the frame up to the end of the buffer, and
+/// `in_tail` the rest of it from the start of the buffer. The frame is decoded without first
+/// copying it to make it contiguous.
+///
+/// This gives the same output, and the same errors, as [`decode_array()`] for the input data
+/// `in_head` followed by `in_tail`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let ring = *b"ghij\0\x55\x55\x55\x55\x04ABC\x05";
+///     let (in_tail, in_head) = ring.split_at(9);
+///     let mut decode_buf = [0_u8; 16];
+///     let decode_data = cobs2::cobs::decode_from_split(&mut decode_buf, in_head, &in_tail[..4]);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_from_split<'a>(
+    out_buf: &'a mut [u8],
+    in_head: &[u8],
+    in_tail: &[u8],
+) -> Result<&'a [u8]> {
+    let mut out_i = 0;
+    // The length code of the current run, and the number of data bytes left in it. `None` before
+    // the first length code.
+    let mut code = None;
+    let mut run_left = 0_u8;
+
+    for &x in in_head.iter().chain(in_tail) {
+        if run_left == 0 {
+            if matches!(code, Some(code) if code < 0xFF) {
+                // Output trailing zero of the previous run.
+                put_byte(out_buf, out_i, 0)?;
                 out_i += 1;
             }
-            code_i += code as usize;
-            if code_i >= in_buf.len() {
-                // End of data. Exit, without outputting a trailing zero for the end of the data.
-                break;
+            if x == 0 {
+                return Err(Error::ZeroInEncodedData);
             }
-            if code < 0xFF {
-                // Output trailing zero.
-                if out_i >= out_buf.len() {
-                    return Err(Error::OutputBufferTooSmall);
-                }
-                out_buf[out_i] = 0;
-                out_i += 1;
+            code = Some(x);
+            run_left = x - 1;
+        } else {
+            if x == 0 {
+                return Err(Error::ZeroInEncodedData);
+            }
+            put_byte(out_buf, out_i, x)?;
+            out_i += 1;
+            run_left -= 1;
+        }
+    }
+    if run_left > 0 {
+        return Err(Error::TruncatedEncodedData);
+    }
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Decode a short COBS-encoded frame, of at most 254 bytes, writing decoded data to the given
+/// output buffer.
+///
+/// This gives the same output as [`decode_array()`], for valid encoded data from
+/// [`encode_short_array()`]. But because the data is short, there are no `FF` length codes to
+/// handle, so this function is simpler and faster.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`decode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the decoded data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+/// * [`Error::ShortFrameTooLong`]
+///
+/// Example:
+///
+///     let mut decode_buf = [0x55_u8; 253];
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobs::decode_short_array(&mut decode_buf, data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_short_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    if in_buf.len() > 254 {
+        return Err(Error::ShortFrameTooLong);
+    }
+    let mut in_rest = in_buf;
+    let mut out_i = 0;
+
+    while let Some((&code, after_code)) = in_rest.split_first() {
+        if code == 0 {
+            return Err(Error::ZeroInEncodedData);
+        }
+        let run_len = code as usize - 1;
+        if run_len > after_code.len() {
+            if after_code.contains(&0) {
+                return Err(Error::ZeroInEncodedData);
             }
+            return Err(Error::TruncatedEncodedData);
+        }
+        let (run, remainder) = after_code.split_at(run_len);
+        if run.contains(&0) {
+            return Err(Error::ZeroInEncodedData);
+        }
+        out_buf
+            .get_mut(out_i..out_i + run_len)
+            .ok_or(Error::OutputBufferTooSmall)?
+            .copy_from_slice(run);
+        out_i += run_len;
+
+        in_rest = remainder;
+        if in_rest.is_empty() {
+            // End of data. Exit, without outputting a trailing zero for the end of the data.
+            break;
+        }
+        // Output trailing zero. A short frame can't have an `FF` length code followed by more data.
+        put_byte(out_buf, out_i, 0)?;
+        out_i += 1;
+    }
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Decode COBS-encoded data, writing decoded data to the given output buffer, with detailed error
+/// information.
+///
+/// This is the same as [`decode_array()`], except that in the [`Err`] case, the return value is an
+/// [`ErrorDetail`], which also gives the index in the input data at which the error was detected.
+///
+/// Example:
+///
+///     let mut decode_buf = [0x55_u8; 1000];
+///     let data_cobs = b"\x00\x04ABC";
+///     let decode_result = cobs2::cobs::decode_array_detail(&mut decode_buf, data_cobs);
+///     let error_detail = decode_result.unwrap_err();
+///     assert!(error_detail.is_leading_zero());
+///     assert_eq!(error_detail.resync_index(), Some(1));
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_array_detail<'a>(
+    out_buf: &'a mut [u8],
+    in_buf: &[u8],
+) -> core::result::Result<&'a [u8], ErrorDetail> {
+    let mut code_i = 0;
+    let mut out_i = 0;
+
+    while let Some(&code) = in_buf.get(code_i) {
+        if code == 0 {
+            return Err(ErrorDetail::new(Error::ZeroInEncodedData, code_i));
+        }
+        let run_start = code_i + 1;
+        let run_end = code_i + code as usize;
+        // The run is shorter than the length code says, if the data ends early.
+        let run = in_buf
+            .get(run_start..core::cmp::min(run_end, in_buf.len()))
+            .unwrap_or(&[]);
+
+        // Check the whole run at once, but report the first error that decoding byte-by-byte
+        // would find. A fold without early exit has no branches, so it vectorises well.
+        let zero_pos = if run.iter().fold(false, |found, x| found | (*x == 0)) {
+            run.iter().position(|x| *x == 0)
+        } else {
+            None
+        };
+        let out_space = out_buf.len().saturating_sub(out_i);
+        if out_space < zero_pos.unwrap_or(run.len()) {
+            return Err(ErrorDetail::new(
+                Error::OutputBufferTooSmall,
+                run_start + out_space,
+            ));
         }
+        if let Some(zero_pos) = zero_pos {
+            return Err(ErrorDetail::new(
+                Error::ZeroInEncodedData,
+                run_start + zero_pos,
+            ));
+        }
+        if run_end > in_buf.len() {
+            return Err(ErrorDetail::new(Error::TruncatedEncodedData, in_buf.len()));
+        }
+        out_buf
+            .get_mut(out_i..out_i + run.len())
+            .ok_or(ErrorDetail::new(Error::OutputBufferTooSmall, run_start))?
+            .copy_from_slice(run);
+        out_i += run.len();
+
+        code_i = run_end;
+        if code_i >= in_buf.len() {
+            // End of data. Exit, without outputting a trailing zero for the end of the data.
+            break;
+        }
+        if code < 0xFF {
+            // Output trailing zero.
+            put_byte(out_buf, out_i, 0).map_err(|e| ErrorDetail::new(e, code_i))?;
+            out_i += 1;
+        }
+    }
+    out_buf
+        .get(..out_i)
+        .ok_or(ErrorDetail::new(Error::OutputBufferTooSmall, in_buf.len()))
+}
+
+/// Decode COBS-encoded data, returning output as a vector of `u8`.
+///
+/// The output vector is allocated once, with exactly the required capacity, calculated by
+/// [`decode_output_size()`].
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobs::decode_vector(data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "alloc")]
+pub fn decode_vector(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    decode_vector_detail(in_buf).map_err(|e| e.error)
+}
+
+/// Decode COBS-encoded data, returning output as a vector of `u8`, with detailed error
+/// information.
+///
+/// This is the same as [`decode_vector()`], except that in the [`Err`] case, the return value is
+/// an [`ErrorDetail`], which also gives the index in the input data at which the error was
+/// detected.
+///
+/// Example:
+///
+///     let data_cobs = b"\x04AB\x00C";
+///     let error_detail = cobs2::cobs::decode_vector_detail(data_cobs).unwrap_err();
+///     assert_eq!(error_detail.error, cobs2::Error::ZeroInEncodedData);
+///     assert_eq!(error_detail.index, 3);
+///     assert!(!error_detail.is_leading_zero());
+///
+#[cfg(feature = "alloc")]
+pub fn decode_vector_detail(
+    in_buf: &[u8],
+) -> core::result::Result<alloc::vec::Vec<u8>, ErrorDetail> {
+    let mut out_vec = alloc::vec![0_u8; decode_output_size(in_buf)];
+    let out_len = decode_array_detail(&mut out_vec, in_buf)?.len();
+    out_vec.truncate(out_len);
+    Ok(out_vec)
+}
+
+/// Decode COBS-encoded data, returning output as a vector of `u8`.
+///
+/// This is the same as [`decode_vector()`], except that the input data can be any type that
+/// implements [`AsRef<[u8]>`](AsRef), such as a slice, an array, a vector or `bytes::Bytes`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = vec![2, b'A', 2, b'B'];
+///     assert_eq!(cobs2::cobs::decode(&data_cobs).unwrap(), b"A\0B");
+///     assert_eq!(cobs2::cobs::decode(data_cobs).unwrap(), b"A\0B");
+///
+#[cfg(feature = "alloc")]
+pub fn decode(in_buf: impl AsRef<[u8]>) -> Result<alloc::vec::Vec<u8>> {
+    decode_vector(in_buf.as_ref())
+}
+
+/// Decode COBS-encoded data, returning output in a vector reused from a thread-local pool
+/// (feature `std`).
+///
+/// This is the same as [`decode_vector()`], except that the output vector is taken from the
+/// current thread's pool, and returned to it when the [`PooledVec`](crate::bufpool::PooledVec) is
+/// dropped, to avoid an allocation per frame. See the [`crate::bufpool`] module for details.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobs::decode_vector_pooled(data_cobs).unwrap();
+///     assert_eq!(&decode_data[..], b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "std")]
+pub fn decode_vector_pooled(in_buf: &[u8]) -> Result<crate::bufpool::PooledVec> {
+    crate::bufpool::PooledVec::decode_with(decode_output_size(in_buf), |out_buf| {
+        decode_array(out_buf, in_buf).map(|x| x.len())
+    })
+}
+
+/// Re-encode COBS-encoded data into the canonical form, as produced by [`encode_vector()`].
+///
+/// Other encoders can produce valid COBS encoding that differs from this crate's, such as a
+/// redundant `01` length code after a maximum length run. This normalises such data, for
+/// byte-wise comparison.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a [`Cow`](alloc::borrow::Cow),
+/// which is borrowed from the input if it is already in canonical form, or owned if it changed.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     use std::borrow::Cow;
+///
+///     let data_cobs = b"\x03AB\x03CD\x01";
+///     let data_canonical = cobs2::cobs::canonicalize(data_cobs).unwrap();
+///     assert!(matches!(data_canonical, Cow::Borrowed(_)));
+///
+///     let mut data_cobs = vec![0xFF_u8; 255];
+///     data_cobs.push(0x01);
+///     let data_canonical = cobs2::cobs::canonicalize(&data_cobs).unwrap();
+///     assert!(matches!(data_canonical, Cow::Owned(_)));
+///     assert_eq!(data_canonical, &data_cobs[..255]);
+///
+#[cfg(feature = "alloc")]
+pub fn canonicalize(in_buf: &[u8]) -> Result<alloc::borrow::Cow<'_, [u8]>> {
+    let out_vec = encode_vector(&decode_vector(in_buf)?)?;
+    if out_vec == in_buf {
+        Ok(alloc::borrow::Cow::Borrowed(in_buf))
+    } else {
+        Ok(alloc::borrow::Cow::Owned(out_vec))
+    }
+}
+
+/// Decode COBS-encoded data, writing decoded data to the given output buffer, and check that it
+/// is valid UTF-8 text, such as JSON.
+///
+/// This is the same as [`decode_array()`], except that the return value is a [`Result`] that in
+/// the [`Ok`] case is a string slice of the decoded text in the output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+/// * [`Error::InvalidUtf8`]
+///
+/// Example:
+///
+///     let mut decode_buf = [0_u8; 100];
+///     let text = cobs2::cobs::decode_utf8(&mut decode_buf, b"\x06hello");
+///     assert_eq!(text.unwrap(), "hello");
+///     let text = cobs2::cobs::decode_utf8(&mut decode_buf, b"\x03\xFF\xFE");
+///     assert_eq!(text, Err(cobs2::Error::InvalidUtf8));
+///
+pub fn decode_utf8<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a str> {
+    core::str::from_utf8(decode_array(out_buf, in_buf)?).map_err(|_| Error::InvalidUtf8)
+}
+
+/// Decode COBS-encoded data, and check that it is valid UTF-8 text, returning output as a
+/// [`String`](alloc::string::String).
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+/// * [`Error::InvalidUtf8`]
+///
+/// Example:
+///
+///     let text = cobs2::cobs::decode_utf8_vector(b"\x06hello");
+///     assert_eq!(text.unwrap(), "hello");
+///
+#[cfg(feature = "alloc")]
+pub fn decode_utf8_vector(in_buf: &[u8]) -> Result<alloc::string::String> {
+    alloc::string::String::from_utf8(decode_vector(in_buf)?).map_err(|_| Error::InvalidUtf8)
+}
+
+/// Encode data into COBS encoded form, followed by a zero-byte sentinel, writing output to the
+/// given output buffer.
+///
+/// This follows the framing convention of the `corncobs` crate, and of postcard's COBS flavour,
+/// in which the encoded output includes the trailing frame delimiter.
+///
+/// The caller must provide a reference to a suitably-sized output buffer. The required output
+/// buffer size is one more than [`encode_max_output_size()`], for a given input size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
+/// output buffer, including the trailing zero-byte.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x55_u8; 1000];
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs = cobs2::cobs::encode_array_sentinel(&mut cobs_buf, data);
+///     assert_eq!(data_cobs.unwrap(), b"\x04ABC\x05ghij\x04xyz\x00");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_array_sentinel<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    let out_len = encode_array(out_buf, in_buf)?.len();
+    put_byte(out_buf, out_len, 0)?;
+    out_buf
+        .get(..out_len + 1)
+        .ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Encode data into COBS encoded form, followed by a zero-byte sentinel, returning output as a
+/// vector of `u8`.
+///
+/// This follows the framing convention of the `corncobs` crate, and of postcard's COBS flavour,
+/// in which the encoded output includes the trailing frame delimiter.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`, including the
+/// trailing zero-byte.
+///
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs = cobs2::cobs::encode_vector_sentinel(data);
+///     assert_eq!(data_cobs.unwrap(), b"\x04ABC\x05ghij\x04xyz\x00");
+///
+#[cfg(feature = "alloc")]
+pub fn encode_vector_sentinel(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    let mut out_vec = encode_vector(in_buf)?;
+    out_vec.push(0);
+    Ok(out_vec)
+}
+
+/// Decode COBS-encoded data that is followed by a zero-byte sentinel, writing decoded data to the
+/// given output buffer.
+///
+/// This follows the framing convention of the `corncobs` crate, and of postcard's COBS flavour,
+/// in which the encoded data includes the trailing frame delimiter. The input must end with a
+/// zero-byte, and contain no other zero-bytes.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`decode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the decoded data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`] (including if the trailing zero-byte is missing)
+///
+/// Example:
+///
+///     let mut decode_buf = [0x55_u8; 1000];
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz\x00";
+///     let decode_data = cobs2::cobs::decode_array_sentinel(&mut decode_buf, data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_array_sentinel<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    match in_buf.split_last() {
+        Some((0, in_data)) => decode_array(out_buf, in_data),
+        _ => Err(Error::TruncatedEncodedData),
+    }
+}
+
+/// Decode COBS-encoded data that is followed by a zero-byte sentinel, returning output as a
+/// vector of `u8`.
+///
+/// This follows the framing convention of the `corncobs` crate, and of postcard's COBS flavour,
+/// in which the encoded data includes the trailing frame delimiter. The input must end with a
+/// zero-byte, and contain no other zero-bytes.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`] (including if the trailing zero-byte is missing)
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz\x00";
+///     let decode_data = cobs2::cobs::decode_vector_sentinel(data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "alloc")]
+pub fn decode_vector_sentinel(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    match in_buf.split_last() {
+        Some((0, in_data)) => decode_vector(in_data),
+        _ => Err(Error::TruncatedEncodedData),
+    }
+}
+
+/// Decode all COBS-encoded frames in a [`bytes::Bytes`] buffer, returning a vector of decoded
+/// frames.
+///
+/// The input buffer is split into frames at each zero-byte delimiter, and each frame is decoded.
+/// Empty frames (consecutive delimiters) are skipped. A final frame that is not followed by a
+/// delimiter is also decoded.
+///
+/// A frame that consists of a single block (that is, its decoded data contains no zero-bytes)
+/// decodes to a contiguous sub-slice of the input. In that case the returned [`bytes::Bytes`]
+/// shares the input buffer's allocation, rather than being copied.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of decoded frames. If any
+/// frame fails to decode, the error is returned.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = bytes::Bytes::from_static(b"\x04ABC\x00\x04ABC\x05ghij\x04xyz\x00");
+///     let frames = cobs2::cobs::decode_frames_bytes(data_cobs).unwrap();
+///     assert_eq!(frames, [&b"ABC"[..], &b"ABC\0ghij\0xyz"[..]]);
+///
+#[cfg(feature = "bytes")]
+pub fn decode_frames_bytes(buf: bytes::Bytes) -> Result<alloc::vec::Vec<bytes::Bytes>> {
+    let mut frames = alloc::vec::Vec::new();
+    let mut frame_start = 0;
+
+    for frame in buf.split(|x| *x == 0) {
+        let frame_end = frame_start + frame.len();
+        if !frame.is_empty() {
+            if frame[0] as usize == frame.len() {
+                // Single block, so the decoded data is just the data following the code byte.
+                frames.push(buf.slice(frame_start + 1..frame_end));
+            } else {
+                frames.push(bytes::Bytes::from(decode_vector(frame)?));
+            }
+        }
+        frame_start = frame_end + 1;
+    }
+    Ok(frames)
+}
+
+/// Parse one COBS-encoded frame, as a [`nom`] parser.
+///
+/// The frame is the input data up to and including the next zero-byte delimiter. The output is the
+/// decoded frame data, and the remaining input data following the delimiter. A delimiter at the
+/// start of the input data gives an empty frame.
+///
+/// If the input data doesn't contain a delimiter, the result is [`nom::Err::Incomplete`]. If the
+/// frame can't be decoded, the result is [`nom::Err::Error`], with the decoding [`Error`] passed
+/// to [`FromExternalError`](nom::error::FromExternalError).
+///
+/// Example:
+///
+///     use nom::multi::count;
+///
+///     let data_cobs = b"\x03AB\x00\x02C\x00";
+///     let result: nom::IResult<_, _> = count(cobs2::cobs::parse_frame, 2)(&data_cobs[..]);
+///     let (remaining, frames) = result.unwrap();
+///     assert_eq!(frames, [&b"AB"[..], b"C"]);
+///     assert!(remaining.is_empty());
+///
+#[cfg(feature = "nom")]
+pub fn parse_frame<'a, E>(input: &'a [u8]) -> nom::IResult<&'a [u8], alloc::vec::Vec<u8>, E>
+where
+    E: nom::error::ParseError<&'a [u8]> + nom::error::FromExternalError<&'a [u8], Error>,
+{
+    match input.iter().position(|x| *x == 0) {
+        Some(delim_i) => match decode_vector(&input[..delim_i]) {
+            Ok(out_vec) => Ok((&input[delim_i + 1..], out_vec)),
+            Err(e) => Err(nom::Err::Error(E::from_external_error(
+                input,
+                nom::error::ErrorKind::MapRes,
+                e,
+            ))),
+        },
+        None => Err(nom::Err::Incomplete(nom::Needed::Unknown)),
+    }
+}
+
+/// Compress data with DEFLATE, then encode it into COBS encoded form, returning output as a
+/// vector of `u8`.
+///
+/// This suits frames with repetitive content, such as telemetry in ASCII text. The data is
+/// compressed as raw DEFLATE data (without a zlib header), by [`miniz_oxide`]. Decode it with
+/// [`decode_compressed()`].
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+///     let data = b"temperature=21.5;temperature=21.5;temperature=21.5;temperature=21.5";
+///     let data_cobs = cobs2::cobs::encode_compressed(data).unwrap();
+///     assert!(data_cobs.len() < data.len());
+///     let decode_data = cobs2::cobs::decode_compressed(&data_cobs, 1000).unwrap();
+///     assert_eq!(decode_data, data);
+///
+#[cfg(feature = "deflate")]
+pub fn encode_compressed(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    // Level 6 is the default compression level of zlib and miniz.
+    encode_vector(&miniz_oxide::deflate::compress_to_vec(in_buf, 6))
+}
+
+/// Decode COBS-encoded data, then decompress it with DEFLATE, returning output as a vector of
+/// `u8`.
+///
+/// This reverses [`encode_compressed()`]. The decompressed data is limited to `max_len` bytes, to
+/// limit memory use for data that decompresses to a very large size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::DecompressionFailed`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+#[cfg(feature = "deflate")]
+pub fn decode_compressed(in_buf: &[u8], max_len: usize) -> Result<alloc::vec::Vec<u8>> {
+    miniz_oxide::inflate::decompress_to_vec_with_limit(&decode_vector(in_buf)?, max_len)
+        .map_err(|_| Error::DecompressionFailed)
+}
+
+/// Encode data into COBS encoded form, returning output as a hex string.
+///
+/// The output is a string of upper-case hex byte values, separated by spaces.
+///
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_hex = cobs2::cobs::encode_to_hex(data);
+///     assert_eq!(data_hex, "04 41 42 43 05 67 68 69 6A 04 78 79 7A");
+///
+#[cfg(feature = "alloc")]
+pub fn encode_to_hex(in_buf: &[u8]) -> alloc::string::String {
+    // Encoding to a vector can't fail.
+    crate::hex::to_hex(&encode_vector(in_buf).unwrap_or_default())
+}
+
+/// Decode COBS-encoded data given as a hex string, returning output as a vector of `u8`.
+///
+/// Upper-case and lower-case hex digits are accepted. Whitespace is permitted between byte values,
+/// but not within a byte value.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::InvalidHexData`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_hex = "04 41 42 43 05 67 68 69 6a 04 78 79 7a";
+///     let decode_data = cobs2::cobs::decode_from_hex(data_hex);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "alloc")]
+pub fn decode_from_hex(in_str: &str) -> Result<alloc::vec::Vec<u8>> {
+    decode_vector(&crate::hex::from_hex(in_str)?)
+}
+
+/// Decode COBS-encoded data, writing decoded data to a memory block of a [`heapless::pool`]
+/// memory pool.
+///
+/// The memory pool `P` must have a data type of `[u8; N]`, where `N` is the maximum decoded frame
+/// size. See the [`crate::pool`] module for details.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a [`crate::pool::PoolFrame`] of
+/// the decoded data. The memory block is returned to the pool when that is dropped.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::PoolExhausted`]
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     use heapless::box_pool;
+///     use heapless::pool::boxed::{BoxBlock, BoxPool};
+///
+///     box_pool!(FramePool: [u8; 64]);
+///
+///     let block: &'static mut BoxBlock<[u8; 64]> = unsafe {
+///         static mut BLOCK: BoxBlock<[u8; 64]> = BoxBlock::new();
+///         &mut *core::ptr::addr_of_mut!(BLOCK)
+///     };
+///     FramePool.manage(block);
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobs::decode_to_pool::<FramePool, 64>(data_cobs).unwrap();
+///     assert_eq!(&decode_data[..], b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "heapless")]
+pub fn decode_to_pool<P, const N: usize>(in_buf: &[u8]) -> Result<crate::pool::PoolFrame<P>>
+where
+    P: heapless::pool::boxed::BoxPool<Data = [u8; N]>,
+{
+    crate::pool::PoolFrame::decode_with(|out_buf| decode_array(out_buf, in_buf).map(|x| x.len()))
+}
+
+/// Take bytes from the consumer of a [`heapless::spsc`] ring buffer, and decode the next complete
+/// COBS-encoded frame, writing decoded data to the given output buffer.
+///
+/// Bytes of a partly received frame are held in the [`crate::ring::RingReceiver`], so this can be
+/// called again when more bytes have been received. See the [`crate::ring`] module for details.
+///
+/// The return value is [`None`] if the consumer becomes empty before the end of a frame.
+/// Otherwise it is a [`Result`] that in the [`Ok`] case is a slice of the decoded frame data in
+/// the output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     use heapless::spsc::Queue;
+///     use cobs2::ring::RingReceiver;
+///
+///     let mut queue: Queue<u8, 16> = Queue::new();
+///     let (mut producer, mut consumer) = queue.split();
+///     let mut receiver: RingReceiver<32> = RingReceiver::new();
+///     let mut decode_buf = [0_u8; 32];
+///
+///     for &byte in b"\x04ABC\x00\x05gh" {
+///         producer.enqueue(byte).unwrap();
+///     }
+///     let decode_result = cobs2::cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+///     assert_eq!(decode_result.unwrap().unwrap(), b"ABC");
+///     let decode_result = cobs2::cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+///     assert!(decode_result.is_none());
+///
+#[cfg(feature = "heapless")]
+pub fn decode_from_ring<'a, const N: usize>(
+    receiver: &mut crate::ring::RingReceiver<N>,
+    consumer: &mut heapless::spsc::Consumer<'_, u8>,
+    out_buf: &'a mut [u8],
+) -> Option<Result<&'a [u8]>> {
+    receiver.poll_with(consumer, move |in_buf| decode_array(out_buf, in_buf))
+}
+
+struct DecodeIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    in_iter: I,
+    eof: bool,
+    last_run: u8,
+    count_run: u8,
+}
+
+impl<I> DecodeIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn new(i: I) -> DecodeIterator<I> {
+        DecodeIterator {
+            in_iter: i,
+            eof: false,
+            last_run: 0,
+            count_run: 0,
+        }
+    }
+}
+
+impl<I> Iterator for DecodeIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let in_iter_next = self.in_iter.next();
+            let byte_val = in_iter_next.unwrap_or(0);
+            if byte_val == 0 {
+                return None;
+            }
+            if self.count_run == 0 {
+                let last_run = self.last_run;
+                self.last_run = byte_val;
+                self.count_run = byte_val - 1;
+                if last_run != 0 && last_run != 0xFF {
+                    return Some(0);
+                }
+            } else {
+                self.count_run -= 1;
+                return Some(byte_val);
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let in_iter_size_hint = self.in_iter.size_hint();
+        decode_size_hint(in_iter_size_hint)
+    }
+}
+
+/// Decode COBS-encoded data, getting data from an iterator, and providing the output as an iterator.
+///
+/// The input data should be COBS-encoded, containing no zero-bytes.
+///
+/// The caller must provide anything that can be iterated to give `u8` or `&u8` items, such as a
+/// `u8` iterator, a `&u8` iterator, a slice or a vector.
+///
+/// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
+/// container.
+///
+/// Unlike the other decode functions, no errors are returned by this function. Rather, decoding is
+/// best-effort. In the event of any zero in the input, this will be regarded as end-of-data. If
+/// insufficient bytes are present following a length code, the output will simply stop at the end
+/// of the available data.
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
+///     let decode_data: Vec<u8> = cobs2::cobs::decode_iter(data_cobs.into_iter()).collect();
+///     assert_eq!(decode_data, b"ABC\0ghij\0xyz");
+///
+pub fn decode_iter<I>(i: I) -> impl Iterator<Item = u8>
+where
+    I: IntoIterator,
+    I::Item: Borrow<u8>,
+{
+    DecodeIterator::new(i.into_iter().map(|x| *x.borrow()))
+}
+
+/// Decode COBS-encoded data, getting data from a `&u8` iterator, and providing the output as an iterator.
+///
+/// This is the same as [`decode_iter()`], which also accepts a `&u8` iterator. It is kept for
+/// compatibility.
+///
+/// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
+/// container.
+///
+/// Unlike the other decode functions, no errors are returned by this function. Rather, decoding is
+/// best-effort. In the event of any zero in the input, this will be regarded as end-of-data. If
+/// insufficient bytes are present following a length code, the output will simply stop at the end
+/// of the available data.
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
+///     let decode_data: Vec<u8> = cobs2::cobs::decode_ref_iter(data_cobs.iter()).collect();
+///     assert_eq!(decode_data, b"ABC\0ghij\0xyz");
+///
+pub fn decode_ref_iter<'a, I>(i: I) -> impl Iterator<Item = u8> + 'a
+where
+    I: Iterator<Item = &'a u8> + 'a,
+{
+    decode_iter(i)
+}
+
+struct DecodeResultIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    in_iter: I,
+    eof: bool,
+    last_run: u8,
+    count_run: u8,
+}
+
+impl<I> DecodeResultIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn new(i: I) -> DecodeResultIterator<I> {
+        DecodeResultIterator {
+            in_iter: i,
+            eof: false,
+            last_run: 0,
+            count_run: 0,
+        }
+    }
+}
+
+impl<I> Iterator for DecodeResultIterator<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.eof {
+                return None;
+            }
+            let in_iter_next = self.in_iter.next();
+            if in_iter_next.is_none() {
+                self.eof = true;
+                if self.count_run != 0 {
+                    return Some(Err(Error::TruncatedEncodedData));
+                } else {
+                    return None;
+                }
+            }
+            let byte_val = in_iter_next.unwrap();
+            if byte_val == 0 {
+                self.eof = true;
+                return Some(Err(Error::ZeroInEncodedData));
+            }
+            if self.count_run == 0 {
+                let last_run = self.last_run;
+                self.last_run = byte_val;
+                self.count_run = byte_val - 1;
+                if last_run != 0 && last_run != 0xFF {
+                    return Some(Ok(0));
+                }
+            } else {
+                self.count_run -= 1;
+                return Some(Ok(byte_val));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let in_iter_size_hint = self.in_iter.size_hint();
+        decode_size_hint(in_iter_size_hint)
+    }
+}
+
+/// Decode COBS-encoded data, getting data from an iterator, and providing the output as an iterator.
+///
+/// The input data should be COBS-encoded, containing no zero-bytes.
+///
+/// The caller must provide anything that can be iterated to give `u8` or `&u8` items, such as a
+/// `u8` iterator, a `&u8` iterator, a slice or a vector.
+///
+/// The return value is a [`Result<u8>`] iterator. This is suitable to [`Iterator::collect()`] into a
+/// byte container wrapped in [`Result`].
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
+///     let decode_data: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_iter(data_cobs.into_iter()).collect();
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+pub fn decode_result_iter<I>(i: I) -> impl Iterator<Item = Result<u8>>
+where
+    I: IntoIterator,
+    I::Item: Borrow<u8>,
+{
+    DecodeResultIterator::new(i.into_iter().map(|x| *x.borrow()))
+}
+
+/// Decode COBS-encoded data, getting data from a `&u8` iterator, and providing the output as an iterator.
+///
+/// This is the same as [`decode_result_iter()`], which also accepts a `&u8` iterator. It is kept
+/// for compatibility.
+///
+/// The return value is a [`Result<u8>`] iterator. This is suitable to [`Iterator::collect()`] into a
+/// byte container wrapped in [`Result`].
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
+///     let decode_data: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_ref_iter(data_cobs.iter()).collect();
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+pub fn decode_result_ref_iter<'a, I>(i: I) -> impl Iterator<Item = Result<u8>> + 'a
+where
+    I: Iterator<Item = &'a u8> + 'a,
+{
+    decode_result_iter(i)
+}
+
+/// Encode data into COBS encoded form, getting data from an iterator, and writing the output to
+/// a writer (feature `std`).
+///
+/// The output is streamed from the iterator to the writer in small chunks, without collecting it
+/// into a vector first, so this is suitable for large generated payloads. No frame delimiter is
+/// written, and the writer is not flushed.
+///
+/// The caller must provide anything that can be iterated to give `u8` or `&u8` items, such as a
+/// `u8` iterator, a `&u8` iterator, a slice or a vector.
+///
+/// The return value is an [`io::Result`](std::io::Result) that in the [`Ok`] case is the number of
+/// bytes written. It is an error if writing failed.
+///
+/// Example:
+///
+///     let mut out_vec = Vec::new();
+///     let len = cobs2::cobs::encode_iter_to_writer(b"ABC\0ghij\0xyz", &mut out_vec).unwrap();
+///     assert_eq!(out_vec, b"\x04ABC\x05ghij\x04xyz");
+///     assert_eq!(len, out_vec.len() as u64);
+///
+#[cfg(feature = "std")]
+pub fn encode_iter_to_writer<I, W>(i: I, writer: &mut W) -> std::io::Result<u64>
+where
+    I: IntoIterator,
+    I::Item: Borrow<u8>,
+    W: std::io::Write + ?Sized,
+{
+    write_result_iter(encode_iter(i).map(Ok), writer)
+}
+
+/// Decode COBS-encoded data, getting data from an iterator, and writing the output to a writer
+/// (feature `std`).
+///
+/// The output is streamed from the iterator to the writer in small chunks, without collecting it
+/// into a vector first. The writer is not flushed.
+///
+/// The caller must provide anything that can be iterated to give `u8` or `&u8` items, such as a
+/// `u8` iterator, a `&u8` iterator, a slice or a vector.
+///
+/// The return value is an [`io::Result`](std::io::Result) that in the [`Ok`] case is the number of
+/// bytes written. It is an error if writing failed, or an error of kind
+/// [`io::ErrorKind::InvalidData`](std::io::ErrorKind::InvalidData), wrapping an [`Error`], if the
+/// encoded data is invalid. After an error, part of the decoded data may have been written.
+///
+/// The decoding errors could be:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let mut out_vec = Vec::new();
+///     let len = cobs2::cobs::decode_iter_to_writer(b"\x04ABC\x05ghij\x04xyz", &mut out_vec).unwrap();
+///     assert_eq!(out_vec, b"ABC\0ghij\0xyz");
+///     assert_eq!(len, 12);
+///
+#[cfg(feature = "std")]
+pub fn decode_iter_to_writer<I, W>(i: I, writer: &mut W) -> std::io::Result<u64>
+where
+    I: IntoIterator,
+    I::Item: Borrow<u8>,
+    W: std::io::Write + ?Sized,
+{
+    write_result_iter(decode_result_iter(i), writer)
+}
+
+/// Write the bytes from an iterator to a writer, in chunks, returning the number of bytes written.
+#[cfg(feature = "std")]
+fn write_result_iter<I, W>(i: I, writer: &mut W) -> std::io::Result<u64>
+where
+    I: Iterator<Item = Result<u8>>,
+    W: std::io::Write + ?Sized,
+{
+    let mut buf = [0_u8; 256];
+    let mut buf_len = 0;
+    let mut written = 0_u64;
+    for byte_result in i {
+        buf[buf_len] =
+            byte_result.map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        buf_len += 1;
+        if buf_len == buf.len() {
+            writer.write_all(&buf)?;
+            written += buf_len as u64;
+            buf_len = 0;
+        }
+    }
+    writer.write_all(&buf[..buf_len])?;
+    Ok(written + buf_len as u64)
+}
+
+/// Adapter for decoding a stream of COBS-encoded frames, getting data from a `u8` iterator, and
+/// providing each frame's decoded data as an iterator.
+///
+/// This is created by [`decode_frames_iter()`]. See its documentation for details.
+pub struct DecodeFrames<I>
+where
+    I: Iterator<Item = u8>,
+{
+    in_iter: I,
+    eof: bool,
+    in_frame: bool,
+    empty_frames: EmptyFrames,
+}
+
+impl<I> DecodeFrames<I>
+where
+    I: Iterator<Item = u8>,
+{
+    fn new(i: I) -> DecodeFrames<I> {
+        DecodeFrames {
+            in_iter: i,
+            eof: false,
+            in_frame: false,
+            empty_frames: EmptyFrames::Skip,
+        }
+    }
+
+    /// Set how empty frames (consecutive delimiters) are handled. By default, they are skipped.
+    ///
+    /// If set to [`EmptyFrames::Surface`], [`next_frame()`](DecodeFrames::next_frame) returns an
+    /// iterator with no data for each empty frame.
+    pub fn set_empty_frames(&mut self, empty_frames: EmptyFrames) {
+        self.empty_frames = empty_frames;
+    }
+
+    /// Get an iterator for the decoded data of the next frame.
+    ///
+    /// If the previous frame's iterator was not consumed to its end, the remainder of the previous
+    /// frame is skipped. Empty frames (consecutive delimiters) are handled according to
+    /// [`set_empty_frames()`](DecodeFrames::set_empty_frames).
+    ///
+    /// Returns [`None`] at the end of the input data.
+    pub fn next_frame(&mut self) -> Option<DecodeFrame<'_, I>> {
+        if self.in_frame {
+            // Skip the remainder of the previous frame.
+            self.in_frame = false;
+            loop {
+                match self.in_iter.next() {
+                    Some(0) => break,
+                    Some(_) => {}
+                    None => {
+                        self.eof = true;
+                        break;
+                    }
+                }
+            }
+        }
+        loop {
+            if self.eof {
+                return None;
+            }
+            match self.in_iter.next() {
+                Some(0) => {
+                    if self.empty_frames == EmptyFrames::Surface {
+                        return Some(DecodeFrame {
+                            frames: self,
+                            done: true,
+                            empty: true,
+                            last_run: 0,
+                            count_run: 0,
+                        });
+                    }
+                }
+                Some(code) => {
+                    self.in_frame = true;
+                    return Some(DecodeFrame {
+                        frames: self,
+                        done: false,
+                        empty: false,
+                        last_run: code,
+                        count_run: code - 1,
+                    });
+                }
+                None => {
+                    self.eof = true;
+                }
+            }
+        }
+    }
+}
+
+/// Iterator for the decoded data of one frame, from [`DecodeFrames::next_frame()`].
+///
+/// The iterator item type is [`Result<u8>`].
+pub struct DecodeFrame<'a, I>
+where
+    I: Iterator<Item = u8>,
+{
+    frames: &'a mut DecodeFrames<I>,
+    done: bool,
+    empty: bool,
+    last_run: u8,
+    count_run: u8,
+}
+
+impl<I> DecodeFrame<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    /// Returns `true` if this is an empty frame, that is, a delimiter that immediately followed
+    /// another delimiter (or the start of the input data).
+    ///
+    /// Empty frames are only returned if set by [`DecodeFrames::set_empty_frames()`]. A protocol
+    /// may use a bare delimiter as a keep-alive, which can be detected with this, without consuming
+    /// the frame's iterator.
+    pub fn is_empty_frame(&self) -> bool {
+        self.empty
+    }
+}
+
+impl<I> Iterator for DecodeFrame<'_, I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = Result<u8>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.done {
+                return None;
+            }
+            let in_iter_next = self.frames.in_iter.next();
+            if in_iter_next.is_none() {
+                self.frames.eof = true;
+            }
+            let byte_val = in_iter_next.unwrap_or(0);
+            if byte_val == 0 {
+                // End of frame.
+                self.done = true;
+                self.frames.in_frame = false;
+                if self.count_run != 0 {
+                    return Some(Err(Error::TruncatedEncodedData));
+                } else {
+                    return None;
+                }
+            }
+            if self.count_run == 0 {
+                let last_run = self.last_run;
+                self.last_run = byte_val;
+                self.count_run = byte_val - 1;
+                if last_run != 0xFF {
+                    return Some(Ok(0));
+                }
+            } else {
+                self.count_run -= 1;
+                return Some(Ok(byte_val));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        if self.done {
+            (0, Some(0))
+        } else {
+            let in_iter_size_hint = self.frames.in_iter.size_hint();
+            (0, in_iter_size_hint.1.map(decode_max_output_size))
+        }
+    }
+}
+
+/// Decode a stream of COBS-encoded frames, getting data from a `u8` iterator, and providing each
+/// frame's decoded data as an iterator.
+///
+/// Frames in the input data are separated by zero-byte delimiters. The final frame doesn't need to
+/// be followed by a delimiter.
+///
+/// The return value is a [`DecodeFrames`] adapter. Each call to [`DecodeFrames::next_frame()`]
+/// returns a [`Result<u8>`] iterator of one frame's decoded data, until the end of the input data.
+/// Frames are decoded lazily, so a large frame can be processed without collecting it into a
+/// container.
+///
+/// The following errors could be returned by a frame's iterator:
+///
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x00\x04ABC\x05ghij\x04xyz\x00".to_vec();
+///     let mut frames = cobs2::cobs::decode_frames_iter(data_cobs.into_iter());
+///     let frame: cobs2::Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+///     assert_eq!(frame.unwrap(), b"ABC");
+///     let frame: cobs2::Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+///     assert_eq!(frame.unwrap(), b"ABC\0ghij\0xyz");
+///     assert!(frames.next_frame().is_none());
+///
+pub fn decode_frames_iter<I>(i: I) -> DecodeFrames<I>
+where
+    I: Iterator<Item = u8>,
+{
+    DecodeFrames::<I>::new(i)
+}
+
+/// Decoder for COBS-encoded frames which are received in arbitrary chunks, with the decoded
+/// frame stored in an internal buffer of `N` bytes.
+///
+/// Received data is given to [`feed()`](FrameAccumulator::feed), which decodes it into the
+/// internal buffer, and stops after the end of a frame. The decoded frame is then available from
+/// [`poll_frame()`](FrameAccumulator::poll_frame). This needs no allocation, so it is suitable for
+/// `no_std` applications which prefer polling to callbacks.
+///
+/// A frame which doesn't fit in the internal buffer is discarded, and reported as
+/// [`Error::OutputBufferTooSmall`]. Empty frames (consecutive delimiters) are handled according to
+/// [`set_empty_frames()`](FrameAccumulator::set_empty_frames).
+///
+/// The buffer is part of the type, with size `N`. For a buffer supplied by the caller at run-time,
+/// see [`SliceFrameAccumulator`].
+///
+/// Example:
+///
+///     let mut accumulator = cobs2::cobs::FrameAccumulator::<64>::new();
+///     let mut received: &[u8] = b"\x04ABC\x00\x03AB\x02z\x00";
+///     let mut frames = Vec::new();
+///     while !received.is_empty() {
+///         let consumed = accumulator.feed(received);
+///         received = &received[consumed..];
+///         if let Some(frame) = accumulator.poll_frame() {
+///             frames.push(frame.unwrap().to_vec());
+///         }
+///     }
+///     assert_eq!(frames, [&b"ABC"[..], b"AB\0z"]);
+///
+#[derive(Debug, Clone)]
+pub struct FrameAccumulator<const N: usize> {
+    buf: [u8; N],
+    state: AccumulatorState,
+}
+
+impl<const N: usize> FrameAccumulator<N> {
+    /// Create a new accumulator, with no partly received frame.
+    pub const fn new() -> FrameAccumulator<N> {
+        FrameAccumulator {
+            buf: [0; N],
+            state: AccumulatorState::new(),
+        }
+    }
+
+    /// Set how empty frames (consecutive delimiters) are handled. By default, they are skipped.
+    ///
+    /// If set to [`EmptyFrames::Surface`], each empty frame is returned by
+    /// [`poll_frame()`](FrameAccumulator::poll_frame) as a frame of zero length.
+    pub fn set_empty_frames(&mut self, empty_frames: EmptyFrames) {
+        self.state.empty_frames = empty_frames;
+    }
+
+    /// Discard any partly received frame, and any frame that has not yet been polled.
+    pub fn clear(&mut self) {
+        self.state.clear();
+    }
+
+    /// Decode received data into the internal buffer.
+    ///
+    /// Decoding stops after a frame delimiter, so the frame can be taken by
+    /// [`poll_frame()`](FrameAccumulator::poll_frame). The return value is the number of bytes
+    /// consumed from the received data. The remaining bytes should be given to a following call,
+    /// after polling the frame.
+    ///
+    /// If a frame is ready that has not yet been polled, no bytes are consumed.
+    pub fn feed(&mut self, in_buf: &[u8]) -> usize {
+        self.state.feed(&mut self.buf, in_buf)
+    }
+
+    /// End a partly received frame, as if a frame delimiter had been received.
+    ///
+    /// This is for legacy senders that end the last frame of a burst by going idle, rather than by
+    /// sending a frame delimiter. The application should call this when no data has been received
+    /// for its idle timeout. Then the frame can be taken by
+    /// [`poll_frame()`](FrameAccumulator::poll_frame).
+    ///
+    /// The return value is `true` if a frame is ready to be polled.
+    pub fn flush_partial(&mut self) -> bool {
+        self.state.flush_partial()
+    }
+
+    /// Take the decoded frame, if a complete frame has been received.
+    ///
+    /// The return value is [`None`] if a complete frame has not been received. Otherwise it is a
+    /// [`Result`] that in the [`Ok`] case is a slice of the decoded frame data in the internal
+    /// buffer.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::OutputBufferTooSmall`]
+    /// * [`Error::TruncatedEncodedData`]
+    ///
+    pub fn poll_frame(&mut self) -> Option<Result<&[u8]>> {
+        self.state.poll_frame(&self.buf)
+    }
+
+    /// Decode received data, giving each decoded frame to the sink, such as a closure, a vector or
+    /// a channel. See [`crate::sink`] for details.
+    ///
+    /// A frame that is ready but has not yet been polled is given to the sink first. Frames that
+    /// fail to decode are discarded. A partly received frame at the end of the data stays in the
+    /// accumulator, to be completed by data given to a following call.
+    ///
+    /// The return value is a [`Result`](core::result::Result) that in the [`Ok`] case is the
+    /// number of frames that failed to decode. In the [`Err`] case it is the error from the sink,
+    /// and the data after the frame that the sink failed to take is not decoded.
+    pub fn feed_sink<S>(
+        &mut self,
+        in_buf: &[u8],
+        sink: &mut S,
+    ) -> core::result::Result<usize, S::Error>
+    where
+        S: FrameSink + ?Sized,
+    {
+        let mut in_rest = in_buf;
+        let mut invalid_frames = 0;
+        loop {
+            let consumed = self.feed(in_rest);
+            in_rest = in_rest.get(consumed..).unwrap_or_default();
+            match self.poll_frame() {
+                Some(Ok(frame)) => sink.on_frame(frame)?,
+                Some(Err(_)) => invalid_frames += 1,
+                None => return Ok(invalid_frames),
+            }
+        }
+    }
+
+    /// Decode frames from received data, one at a time, with no allocation.
+    ///
+    /// The return value is an [`AccumulatorFrames`], whose
+    /// [`next_frame()`](AccumulatorFrames::next_frame) returns each frame in turn, borrowing the
+    /// internal buffer until the next call. A partly received frame at the end of the data stays in
+    /// the accumulator, to be completed by data given to a following call.
+    ///
+    /// Example:
+    ///
+    ///     let mut accumulator = cobs2::cobs::FrameAccumulator::<64>::new();
+    ///     let mut frame_lens = Vec::new();
+    ///     for received in [&b"\x04ABC\x00\x03A"[..], b"B\x00"] {
+    ///         let mut frames = accumulator.frames(received);
+    ///         while let Some(frame) = frames.next_frame() {
+    ///             frame_lens.push(frame.unwrap().len());
+    ///         }
+    ///     }
+    ///     assert_eq!(frame_lens, [3, 2]);
+    ///
+    pub fn frames<'a>(&'a mut self, in_buf: &'a [u8]) -> AccumulatorFrames<'a, N> {
+        AccumulatorFrames {
+            accumulator: self,
+            in_buf,
+        }
+    }
+}
+
+/// Implement trait [Default].
+impl<const N: usize> Default for FrameAccumulator<N> {
+    fn default() -> FrameAccumulator<N> {
+        FrameAccumulator::new()
+    }
+}
+
+/// Lending decoder of frames from received data, returned by [`FrameAccumulator::frames()`].
+///
+/// Each frame returned by [`next_frame()`](AccumulatorFrames::next_frame) borrows the
+/// accumulator's internal buffer, so it must be used or copied before the next call.
+///
+/// With feature `alloc`, it is also an [`Iterator`] of frames copied into vectors, for use with a
+/// `for` loop.
+///
+/// Example:
+///
+///     let mut accumulator = cobs2::cobs::FrameAccumulator::<64>::new();
+///     for frame in accumulator.frames(b"\x04ABC\x00\x05AB\x00\x03xy\x00") {
+///         match frame {
+///             Ok(frame) => println!("frame {:?}", frame),
+///             Err(e) => println!("error {}", e),
+///         }
+///     }
+///     let frames: Vec<_> = accumulator.frames(b"\x02A\x00\x02B\x00").collect();
+///     assert_eq!(frames, [Ok(b"A".to_vec()), Ok(b"B".to_vec())]);
+///
+#[derive(Debug)]
+pub struct AccumulatorFrames<'a, const N: usize> {
+    accumulator: &'a mut FrameAccumulator<N>,
+    in_buf: &'a [u8],
+}
+
+impl<'a, const N: usize> AccumulatorFrames<'a, N> {
+    /// Decode the next frame.
+    ///
+    /// The return value is [`None`] when all the received data has been consumed without
+    /// completing another frame. Otherwise it is the same as for
+    /// [`FrameAccumulator::poll_frame()`].
+    pub fn next_frame(&mut self) -> Option<Result<&[u8]>> {
+        let consumed = self.accumulator.feed(self.in_buf);
+        self.in_buf = &self.in_buf[consumed..];
+        self.accumulator.poll_frame()
+    }
+
+    /// The received data that has not yet been consumed.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.in_buf
+    }
+}
+
+/// Implement trait [Iterator], copying each frame into a vector.
+#[cfg(feature = "alloc")]
+impl<const N: usize> Iterator for AccumulatorFrames<'_, N> {
+    type Item = Result<alloc::vec::Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().map(|frame| frame.map(<[u8]>::to_vec))
+    }
+}
+
+/// Decoder for COBS-encoded frames which are received in arbitrary chunks, with the decoded
+/// frame stored in a buffer supplied by the caller.
+///
+/// This is the same as [`FrameAccumulator`], except that the buffer is given to
+/// [`new()`](SliceFrameAccumulator::new) at run-time, rather than being part of the type. So the
+/// buffer can be placed in a particular memory region, such as DMA-capable RAM, and its size can
+/// be chosen at run-time.
+///
+/// Example:
+///
+///     let mut buf = [0_u8; 64];
+///     let mut accumulator = cobs2::cobs::SliceFrameAccumulator::new(&mut buf);
+///     assert_eq!(accumulator.feed(b"\x04ABC\x00\x03AB"), 5);
+///     assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"ABC");
+///
+#[derive(Debug)]
+pub struct SliceFrameAccumulator<'a> {
+    buf: &'a mut [u8],
+    state: AccumulatorState,
+}
+
+impl<'a> SliceFrameAccumulator<'a> {
+    /// Create a new accumulator, with no partly received frame, which decodes frames into the
+    /// given buffer.
+    pub const fn new(buf: &'a mut [u8]) -> SliceFrameAccumulator<'a> {
+        SliceFrameAccumulator {
+            buf,
+            state: AccumulatorState::new(),
+        }
+    }
+
+    /// Set how empty frames (consecutive delimiters) are handled. This is the same as
+    /// [`FrameAccumulator::set_empty_frames()`].
+    pub fn set_empty_frames(&mut self, empty_frames: EmptyFrames) {
+        self.state.empty_frames = empty_frames;
+    }
+
+    /// Discard any partly received frame, and any frame that has not yet been polled.
+    pub fn clear(&mut self) {
+        self.state.clear();
+    }
+
+    /// Decode received data into the buffer. This is the same as [`FrameAccumulator::feed()`].
+    pub fn feed(&mut self, in_buf: &[u8]) -> usize {
+        self.state.feed(self.buf, in_buf)
+    }
+
+    /// End a partly received frame, as if a frame delimiter had been received. This is the same
+    /// as [`FrameAccumulator::flush_partial()`].
+    pub fn flush_partial(&mut self) -> bool {
+        self.state.flush_partial()
+    }
+
+    /// Take the decoded frame, if a complete frame has been received. This is the same as
+    /// [`FrameAccumulator::poll_frame()`].
+    pub fn poll_frame(&mut self) -> Option<Result<&[u8]>> {
+        self.state.poll_frame(self.buf)
+    }
+
+    /// Consume the accumulator, returning the buffer.
+    pub fn into_inner(self) -> &'a mut [u8] {
+        self.buf
+    }
+}
+
+/// Sans-IO decoder for COBS-encoded frames which are received in arbitrary chunks, such as from a
+/// UART or a socket, with each decoded frame stored in a growable vector.
+///
+/// This owns no IO. Received data is given to [`push()`](Decoder::push), which decodes it as it
+/// arrives, and gives each completed frame to a callback. Or
+/// [`frames()`](Decoder::frames) returns the completed frames, one at a time. A partly received
+/// frame is kept, to be completed by the data of a following call.
+///
+/// Unlike [`FrameAccumulator`], frames are not limited by a fixed buffer, but only by the maximum
+/// length given to [`with_max_len()`](Decoder::with_max_len), if any. A longer frame is discarded,
+/// and reported as [`Error::FrameTooLong`]. Empty frames (consecutive delimiters) are handled
+/// according to [`set_empty_frames()`](Decoder::set_empty_frames).
+///
+/// Example:
+///
+///     let mut decoder = cobs2::cobs::Decoder::new();
+///     let mut frames = Vec::new();
+///     for received in [&b"\x04AB"[..], b"C\x00\x03A", b"B\x02z\x00"] {
+///         decoder.push(received, |frame| frames.push(frame.unwrap().to_vec()));
+///     }
+///     assert_eq!(frames, [&b"ABC"[..], b"AB\0z"]);
+///
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone)]
+pub struct Decoder {
+    buf: alloc::vec::Vec<u8>,
+    max_len: Option<usize>,
+    state: AccumulatorState,
+}
+
+#[cfg(feature = "alloc")]
+impl Decoder {
+    /// Create a new decoder, with no maximum frame length.
+    pub const fn new() -> Decoder {
+        Decoder {
+            buf: alloc::vec::Vec::new(),
+            max_len: None,
+            state: AccumulatorState::new(),
+        }
+    }
+
+    /// Create a new decoder, for frames of up to `max_len` decoded bytes.
+    pub const fn with_max_len(max_len: usize) -> Decoder {
+        Decoder {
+            buf: alloc::vec::Vec::new(),
+            max_len: Some(max_len),
+            state: AccumulatorState::new(),
+        }
+    }
+
+    /// Set how empty frames (consecutive delimiters) are handled. By default, they are skipped.
+    ///
+    /// If set to [`EmptyFrames::Surface`], each empty frame is given as a frame of zero length.
+    pub fn set_empty_frames(&mut self, empty_frames: EmptyFrames) {
+        self.state.empty_frames = empty_frames;
+    }
+
+    /// Discard any partly received frame.
+    pub fn clear(&mut self) {
+        self.state.clear();
+    }
+
+    /// Returns `true` if a frame is partly received.
+    pub fn in_progress(&self) -> bool {
+        self.state.last_run != 0 || self.state.error.is_some()
+    }
+
+    /// Decode received data, giving each completed frame to `on_frame`.
+    ///
+    /// The frame given to `on_frame` is a [`Result`] that in the [`Ok`] case is a slice of the
+    /// decoded frame data, which is only valid for the call. Frames that fail to decode are
+    /// discarded, after they are given to `on_frame` as an error.
+    ///
+    /// The following errors could be given:
+    ///
+    /// * [`Error::FrameTooLong`]
+    /// * [`Error::TruncatedEncodedData`]
+    ///
+    pub fn push<F>(&mut self, in_buf: &[u8], mut on_frame: F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        let mut frames = self.frames(in_buf);
+        while let Some(frame) = frames.next_frame() {
+            on_frame(frame);
+        }
+    }
+
+    /// Decode frames from received data, one at a time.
+    ///
+    /// The return value is a [`DecoderFrames`], whose [`next_frame()`](DecoderFrames::next_frame)
+    /// returns each completed frame in turn, borrowing the decoder's buffer until the next call.
+    ///
+    /// Example:
+    ///
+    ///     let mut decoder = cobs2::cobs::Decoder::with_max_len(4);
+    ///     let frames: Vec<_> = decoder.frames(b"\x04ABC\x00\x06ghijk\x00\x03x").collect();
+    ///     assert_eq!(frames, [Ok(b"ABC".to_vec()), Err(cobs2::Error::FrameTooLong)]);
+    ///     let frames: Vec<_> = decoder.frames(b"y\x00").collect();
+    ///     assert_eq!(frames, [Ok(b"xy".to_vec())]);
+    ///
+    pub fn frames<'a>(&'a mut self, in_buf: &'a [u8]) -> DecoderFrames<'a> {
+        DecoderFrames {
+            decoder: self,
+            in_buf,
+        }
+    }
+
+    /// End a partly received frame, as if a frame delimiter had been received, and return it.
+    ///
+    /// This is for legacy senders that end the last frame of a burst by going idle, rather than by
+    /// sending a frame delimiter. The return value is [`None`] if no frame is partly received.
+    /// Otherwise it is the same as for [`DecoderFrames::next_frame()`].
+    pub fn flush_partial(&mut self) -> Option<Result<&[u8]>> {
+        self.state.flush_partial();
+        self.poll_frame()
+    }
+
+    fn feed(&mut self, in_buf: &[u8]) -> usize {
+        // Each received byte gives at most one decoded byte.
+        let mut len = self.state.len.saturating_add(in_buf.len());
+        if let Some(max_len) = self.max_len {
+            len = len.min(max_len);
+        }
+        if self.buf.len() < len {
+            self.buf.resize(len, 0);
+        }
+        self.state.feed(&mut self.buf, in_buf)
+    }
+
+    fn poll_frame(&mut self) -> Option<Result<&[u8]>> {
+        self.state.poll_frame(&self.buf).map(|frame| {
+            frame.map_err(|e| match e {
+                Error::OutputBufferTooSmall => Error::FrameTooLong,
+                e => e,
+            })
+        })
+    }
+}
+
+/// Implement trait [Default].
+#[cfg(feature = "alloc")]
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder::new()
     }
-    Ok(&out_buf[..out_i])
 }
 
-/// Decode COBS-encoded data, returning output as a vector of `u8`.
-///
-/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
-///
-/// The following errors could be returned:
-///
-/// * [`Error::ZeroInEncodedData`]
-/// * [`Error::TruncatedEncodedData`]
-///
-/// Example:
-///
-///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
-///     let decode_data = cobs2::cobs::decode_vector(data_cobs);
-///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+/// Lending decoder of frames from received data, returned by [`Decoder::frames()`].
 ///
+/// Each frame returned by [`next_frame()`](DecoderFrames::next_frame) borrows the decoder's
+/// buffer, so it must be used or copied before the next call. It is also an [`Iterator`] of frames
+/// copied into vectors, for use with a `for` loop.
 #[cfg(feature = "alloc")]
-pub fn decode_vector(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
-    let mut code_i = 0;
-    let mut out_vec = alloc::vec::Vec::<u8>::with_capacity(decode_max_output_size(in_buf.len()));
+#[derive(Debug)]
+pub struct DecoderFrames<'a> {
+    decoder: &'a mut Decoder,
+    in_buf: &'a [u8],
+}
 
-    if !in_buf.is_empty() {
-        loop {
-            let code = in_buf[code_i];
-            if code == 0 {
-                return Err(Error::ZeroInEncodedData);
-            }
-            for in_i in (code_i + 1)..(code_i + code as usize) {
-                if in_i >= in_buf.len() {
-                    return Err(Error::TruncatedEncodedData);
-                }
-                let in_byte = in_buf[in_i];
-                if in_byte == 0 {
-                    return Err(Error::ZeroInEncodedData);
-                }
-                out_vec.push(in_byte);
-            }
-            code_i += code as usize;
-            if code_i >= in_buf.len() {
-                // End of data. Exit, without outputting a trailing zero for the end of the data.
-                break;
-            }
-            if code < 0xFF {
-                // Output trailing zero.
-                out_vec.push(0);
-            }
-        }
+#[cfg(feature = "alloc")]
+impl<'a> DecoderFrames<'a> {
+    /// Decode the next frame.
+    ///
+    /// The return value is [`None`] when all the received data has been consumed without
+    /// completing another frame. Otherwise it is a [`Result`] that in the [`Ok`] case is a slice of
+    /// the decoded frame data.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::FrameTooLong`]
+    /// * [`Error::TruncatedEncodedData`]
+    ///
+    pub fn next_frame(&mut self) -> Option<Result<&[u8]>> {
+        let consumed = self.decoder.feed(self.in_buf);
+        self.in_buf = &self.in_buf[consumed..];
+        self.decoder.poll_frame()
+    }
+
+    /// The received data that has not yet been consumed.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.in_buf
     }
-    Ok(out_vec)
 }
 
-struct DecodeIterator<I>
-where
-    I: Iterator<Item = u8>,
-{
-    in_iter: I,
-    eof: bool,
+/// Implement trait [Iterator], copying each frame into a vector.
+#[cfg(feature = "alloc")]
+impl Iterator for DecoderFrames<'_> {
+    type Item = Result<alloc::vec::Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_frame().map(|frame| frame.map(<[u8]>::to_vec))
+    }
+}
+
+/// State of a frame accumulator, apart from its buffer.
+#[derive(Debug, Clone)]
+struct AccumulatorState {
+    len: usize,
     last_run: u8,
     count_run: u8,
+    error: Option<Error>,
+    ready: bool,
+    empty_frames: EmptyFrames,
 }
 
-impl<I> DecodeIterator<I>
-where
-    I: Iterator<Item = u8>,
-{
-    fn new(i: I) -> DecodeIterator<I> {
-        return DecodeIterator {
-            in_iter: i,
-            eof: false,
+impl AccumulatorState {
+    const fn new() -> AccumulatorState {
+        AccumulatorState {
+            len: 0,
             last_run: 0,
             count_run: 0,
-        };
+            error: None,
+            ready: false,
+            empty_frames: EmptyFrames::Skip,
+        }
     }
-}
 
-impl<I> Iterator for DecodeIterator<I>
-where
-    I: Iterator<Item = u8>,
-{
-    type Item = u8;
+    fn clear(&mut self) {
+        self.len = 0;
+        self.last_run = 0;
+        self.count_run = 0;
+        self.error = None;
+        self.ready = false;
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            let in_iter_next = self.in_iter.next();
-            let byte_val = in_iter_next.unwrap_or(0);
+    fn feed(&mut self, buf: &mut [u8], in_buf: &[u8]) -> usize {
+        if self.ready {
+            return 0;
+        }
+        for (in_i, &byte_val) in in_buf.iter().enumerate() {
             if byte_val == 0 {
-                return None;
+                // End of frame.
+                if self.last_run == 0
+                    && self.error.is_none()
+                    && self.empty_frames == EmptyFrames::Skip
+                {
+                    continue;
+                }
+                self.end_frame();
+                return in_i + 1;
             }
             if self.count_run == 0 {
                 let last_run = self.last_run;
                 self.last_run = byte_val;
                 self.count_run = byte_val - 1;
                 if last_run != 0 && last_run != 0xFF {
-                    return Some(0);
+                    self.push(buf, 0);
                 }
             } else {
                 self.count_run -= 1;
-                return Some(byte_val);
+                self.push(buf, byte_val);
             }
         }
+        in_buf.len()
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let in_iter_size_hint = self.in_iter.size_hint();
-        decode_size_hint(in_iter_size_hint)
+    fn flush_partial(&mut self) -> bool {
+        if !self.ready && (self.last_run != 0 || self.error.is_some()) {
+            self.end_frame();
+        }
+        self.ready
     }
-}
 
-/// Decode COBS-encoded data, getting data from a `u8` iterator, and providing the output as an iterator.
-///
-/// The input data should be COBS-encoded, containing no zero-bytes.
-///
-/// The caller must provide a `u8` iterator.
-///
-/// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
-/// container.
-///
-/// Unlike the other decode functions, no errors are returned by this function. Rather, decoding is
-/// best-effort. In the event of any zero in the input, this will be regarded as end-of-data. If
-/// insufficient bytes are present following a length code, the output will simply stop at the end
-/// of the available data.
-///
-///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
-///     let decode_data: Vec<u8> = cobs2::cobs::decode_iter(data_cobs.into_iter()).collect();
-///     assert_eq!(decode_data, b"ABC\0ghij\0xyz");
-///
-pub fn decode_iter<I>(i: I) -> impl Iterator<Item = u8>
-where
-    I: Iterator<Item = u8>,
-{
-    DecodeIterator::<I>::new(i)
+    fn poll_frame<'b>(&mut self, buf: &'b [u8]) -> Option<Result<&'b [u8]>> {
+        if !self.ready {
+            return None;
+        }
+        let frame_len = self.len;
+        let error = self.error.take();
+        self.clear();
+        match error {
+            Some(e) => Some(Err(e)),
+            None => Some(buf.get(..frame_len).ok_or(Error::OutputBufferTooSmall)),
+        }
+    }
+
+    fn end_frame(&mut self) {
+        if self.count_run != 0 && self.error.is_none() {
+            self.error = Some(Error::TruncatedEncodedData);
+        }
+        self.ready = true;
+    }
+
+    fn push(&mut self, buf: &mut [u8], byte_val: u8) {
+        if self.error.is_some() {
+            return;
+        }
+        match buf.get_mut(self.len) {
+            Some(out_byte) => {
+                *out_byte = byte_val;
+                self.len += 1;
+            }
+            None => self.error = Some(Error::OutputBufferTooSmall),
+        }
+    }
 }
 
-/// Decode COBS-encoded data, getting data from a `&u8` iterator, and providing the output as an iterator.
+/// An incremental COBS encoder, which implements [`core::fmt::Write`], to send formatted text as
+/// frames with no intermediate string buffer.
 ///
-/// The input data should be COBS-encoded, containing no zero-bytes.
+/// Data is given by [`core::fmt::Write`] methods such as [`write!`], or by
+/// [`write_bytes()`](FrameWriter::write_bytes). [`finish()`](FrameWriter::finish) ends the frame.
+/// The encoded data is given to the `emit` function, in blocks of up to 255 bytes, followed by a
+/// zero-byte frame delimiter at the end of each frame. The emit function could write to a UART,
+/// for example.
 ///
-/// The caller must provide a `&u8` iterator.
+/// The encoder holds up to one block of data (254 bytes) in an internal buffer, until the block's
+/// length code is known.
 ///
-/// The return value is a `u8` iterator. This is suitable to [`Iterator::collect()`] into a byte
-/// container.
+/// Example:
 ///
-/// Unlike the other decode functions, no errors are returned by this function. Rather, decoding is
-/// best-effort. In the event of any zero in the input, this will be regarded as end-of-data. If
-/// insufficient bytes are present following a length code, the output will simply stop at the end
-/// of the available data.
+///     use core::fmt::Write;
 ///
-///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
-///     let decode_data: Vec<u8> = cobs2::cobs::decode_ref_iter(data_cobs.iter()).collect();
-///     assert_eq!(decode_data, b"ABC\0ghij\0xyz");
+///     let mut out = Vec::new();
+///     let mut logger = cobs2::cobs::FrameWriter::new(|encoded: &[u8]| out.extend_from_slice(encoded));
+///     write!(logger, "temp={}", 21).unwrap();
+///     logger.finish();
+///     logger.write_bytes(b"a\0");
+///     logger.finish();
+///     assert_eq!(out, b"\x08temp=21\x00\x02a\x01\x00");
 ///
-pub fn decode_ref_iter<'a, I>(i: I) -> impl Iterator<Item = u8> + 'a
-where
-    I: Iterator<Item = &'a u8> + 'a,
-{
-    DecodeIterator::<_>::new(i.copied())
-}
-
-struct DecodeResultIterator<I>
+pub struct FrameWriter<F>
 where
-    I: Iterator<Item = u8>,
+    F: FnMut(&[u8]),
 {
-    in_iter: I,
-    eof: bool,
-    last_run: u8,
-    count_run: u8,
+    emit: F,
+    // Length code, followed by the data of the current run.
+    hold_buf: [u8; 255],
+    run_len: usize,
+    check: FrameCheck,
 }
 
-impl<I> DecodeResultIterator<I>
+impl<F> FrameWriter<F>
 where
-    I: Iterator<Item = u8>,
+    F: FnMut(&[u8]),
 {
-    fn new(i: I) -> DecodeResultIterator<I> {
-        return DecodeResultIterator {
-            in_iter: i,
-            eof: false,
-            last_run: 0,
-            count_run: 0,
-        };
+    /// Create a new encoder, which gives the encoded data to the `emit` function.
+    ///
+    /// This is a `const fn`, so with a function pointer as the `emit` function, the encoder can be
+    /// created in a `static`, such as in a `Mutex<RefCell<...>>`.
+    pub const fn new(emit: F) -> FrameWriter<F> {
+        FrameWriter {
+            emit,
+            hold_buf: [0; 255],
+            run_len: 0,
+            check: FrameCheck::new(),
+        }
     }
-}
-
-impl<I> Iterator for DecodeResultIterator<I>
-where
-    I: Iterator<Item = u8>,
-{
-    type Item = Result<u8>;
 
-    fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if self.eof {
-                return None;
-            }
-            let in_iter_next = self.in_iter.next();
-            if in_iter_next.is_none() {
-                self.eof = true;
-                if self.count_run != 0 {
-                    return Some(Err(Error::TruncatedEncodedData));
-                } else {
-                    return None;
-                }
+    /// Add data to the current frame.
+    pub fn write_bytes(&mut self, in_buf: &[u8]) {
+        self.check.input(in_buf);
+        for &byte_val in in_buf {
+            if self.run_len == 0xFE {
+                // Maximum length run, which is not followed by an implicit zero-byte.
+                self.emit_block(0xFF, self.run_len);
             }
-            let byte_val = in_iter_next.unwrap();
             if byte_val == 0 {
-                self.eof = true;
-                return Some(Err(Error::ZeroInEncodedData));
-            }
-            if self.count_run == 0 {
-                let last_run = self.last_run;
-                self.last_run = byte_val;
-                self.count_run = byte_val - 1;
-                if last_run != 0 && last_run != 0xFF {
-                    return Some(Ok(0));
-                }
+                self.emit_block((self.run_len + 1) as u8, self.run_len);
             } else {
-                self.count_run -= 1;
-                return Some(Ok(byte_val));
+                self.run_len += 1;
+                if let Some(hold_byte) = self.hold_buf.get_mut(self.run_len) {
+                    *hold_byte = byte_val;
+                }
             }
         }
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        let in_iter_size_hint = self.in_iter.size_hint();
-        decode_size_hint(in_iter_size_hint)
+    /// End the current frame, emitting the remaining encoded data and a zero-byte delimiter.
+    pub fn finish(&mut self) {
+        self.emit_block((self.run_len + 1) as u8, self.run_len);
+        (self.emit)(&[0]);
+        self.check.end_frame();
+    }
+
+    /// Consume the encoder, returning the emit function. Any data of an unfinished frame is lost.
+    pub fn into_inner(self) -> F {
+        self.emit
+    }
+
+    fn emit_block(&mut self, code: u8, data_len: usize) {
+        if let Some(hold_byte) = self.hold_buf.get_mut(0) {
+            *hold_byte = code;
+        }
+        if let Some(block) = self.hold_buf.get(..=data_len) {
+            (self.emit)(block);
+            self.check.output(block);
+        }
+        self.run_len = 0;
     }
 }
 
-/// Decode COBS-encoded data, getting data from a `u8` iterator, and providing the output as an iterator.
-///
-/// The input data should be COBS-encoded, containing no zero-bytes.
-///
-/// The caller must provide a `u8` iterator.
-///
-/// The return value is a [`Result<u8>`] iterator. This is suitable to [`Iterator::collect()`] into a
-/// byte container wrapped in [`Result`].
-///
-/// The following errors could be returned:
-///
-/// * [`Error::ZeroInEncodedData`]
-/// * [`Error::TruncatedEncodedData`]
-///
-/// Example:
-///
-///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
-///     let decode_data: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_iter(data_cobs.into_iter()).collect();
-///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
-///
-pub fn decode_result_iter<I>(i: I) -> impl Iterator<Item = Result<u8>>
+/// Implement trait [core::fmt::Write].
+impl<F> core::fmt::Write for FrameWriter<F>
 where
-    I: Iterator<Item = u8>,
+    F: FnMut(&[u8]),
 {
-    DecodeResultIterator::<I>::new(i)
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write_bytes(s.as_bytes());
+        Ok(())
+    }
 }
 
-/// Decode COBS-encoded data, getting data from a `&u8` iterator, and providing the output as an iterator.
-///
-/// The input data should be COBS-encoded, containing no zero-bytes.
-///
-/// The caller must provide a `&u8` iterator.
-///
-/// The return value is a [`Result<u8>`] iterator. This is suitable to [`Iterator::collect()`] into a
-/// byte container wrapped in [`Result`].
+/// The progress of a call of [`Encoder::encode()`] or [`Encoder::finish()`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct EncoderProgress {
+    /// The number of bytes consumed from the input data. This is 0 for [`Encoder::finish()`].
+    pub consumed: usize,
+    /// The number of encoded bytes written to the output buffer.
+    pub produced: usize,
+    /// For [`Encoder::encode()`], `true` if all the input data was consumed. For
+    /// [`Encoder::finish()`], `true` if the frame is complete, including its delimiter, so the
+    /// encoder is ready for the next frame.
+    pub done: bool,
+}
+
+/// A resumable sans-IO COBS encoder, which encodes a frame incrementally into output buffers of
+/// limited size, such as fixed-size DMA or transmit buffers smaller than the encoded frame.
 ///
-/// The following errors could be returned:
+/// Data is given to [`encode()`](Encoder::encode), with an output buffer. It encodes as much as
+/// fits in the output buffer, and returns how many input bytes were consumed and output bytes
+/// produced. The caller resumes with the rest of the input data, once more output space is
+/// available. [`finish()`](Encoder::finish) ends the frame, with a zero-byte delimiter, and
+/// likewise may need to be called again until the frame is complete. So the output is the same as
+/// [`encode_array()`], followed by a zero-byte delimiter.
 ///
-/// * [`Error::ZeroInEncodedData`]
-/// * [`Error::TruncatedEncodedData`]
+/// The encoder holds up to one block of data (254 bytes) in an internal buffer, until the block's
+/// length code is known, and until the block has been output.
 ///
 /// Example:
 ///
-///     let data_cobs = b"\x04ABC\x05ghij\x04xyz".to_vec();
-///     let decode_data: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_ref_iter(data_cobs.iter()).collect();
-///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///     let mut encoder = cobs2::cobs::Encoder::new();
+///     let mut tx_buf = [0_u8; 4];
+///     let mut sent = Vec::new();
+///     let mut data: &[u8] = b"ABC\0ghij\0xyz";
+///     while !data.is_empty() {
+///         let progress = encoder.encode(data, &mut tx_buf);
+///         data = &data[progress.consumed..];
+///         sent.extend_from_slice(&tx_buf[..progress.produced]);
+///     }
+///     loop {
+///         let progress = encoder.finish(&mut tx_buf);
+///         sent.extend_from_slice(&tx_buf[..progress.produced]);
+///         if progress.done {
+///             break;
+///         }
+///     }
+///     assert_eq!(sent, b"\x04ABC\x05ghij\x04xyz\x00");
 ///
-pub fn decode_result_ref_iter<'a, I>(i: I) -> impl Iterator<Item = Result<u8>> + 'a
-where
-    I: Iterator<Item = &'a u8> + 'a,
-{
-    DecodeResultIterator::<_>::new(i.copied())
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    // Length code, followed by the data of the current run, or of the block being output.
+    hold_buf: [u8; 255],
+    run_len: usize,
+    // Range of the hold buffer which is still to be output.
+    out_start: usize,
+    out_end: usize,
+    delimiter_pending: bool,
+    finishing: bool,
+    check: FrameCheck,
+}
+
+impl Encoder {
+    /// Create a new encoder, ready for a frame.
+    pub const fn new() -> Encoder {
+        Encoder {
+            hold_buf: [0; 255],
+            run_len: 0,
+            out_start: 0,
+            out_end: 0,
+            delimiter_pending: false,
+            finishing: false,
+            check: FrameCheck::new(),
+        }
+    }
+
+    /// Discard the current frame, including any of its encoded data which has not been output.
+    pub fn clear(&mut self) {
+        *self = Encoder::new();
+    }
+
+    /// Returns `true` if encoded data is waiting for output space.
+    pub fn is_pending(&self) -> bool {
+        self.out_start < self.out_end || self.delimiter_pending
+    }
+
+    /// Add data to the current frame, writing encoded data to the output buffer.
+    ///
+    /// Encoded data which is waiting for output space is written first. Input data is consumed
+    /// until the output buffer is full, so the rest of the input data should be given to a
+    /// following call. While [`finish()`](Encoder::finish) is in progress, no input data is
+    /// consumed.
+    pub fn encode(&mut self, in_buf: &[u8], out_buf: &mut [u8]) -> EncoderProgress {
+        let mut produced = self.drain(out_buf);
+        let mut consumed = 0;
+        while !self.finishing && !self.is_pending() {
+            let byte_val = match in_buf.get(consumed) {
+                Some(&byte_val) => byte_val,
+                None => break,
+            };
+            if self.run_len == 0xFE {
+                // Maximum length run, which is not followed by an implicit zero-byte.
+                self.end_block(0xFF, self.run_len);
+            } else {
+                consumed += 1;
+                if byte_val == 0 {
+                    self.end_block((self.run_len + 1) as u8, self.run_len);
+                } else {
+                    self.run_len += 1;
+                    if let Some(hold_byte) = self.hold_buf.get_mut(self.run_len) {
+                        *hold_byte = byte_val;
+                    }
+                }
+            }
+            produced += self.drain(out_buf.get_mut(produced..).unwrap_or_default());
+        }
+        // A delimiter in the output can only be from the first drain, before any input data of the
+        // next frame is consumed.
+        self.check.output(&out_buf[..produced]);
+        self.check.input(&in_buf[..consumed]);
+        EncoderProgress {
+            consumed,
+            produced,
+            done: consumed == in_buf.len(),
+        }
+    }
+
+    /// End the current frame, writing the remaining encoded data and a zero-byte delimiter to the
+    /// output buffer.
+    ///
+    /// If the output buffer is too small, call this again, with more output space, until the
+    /// returned progress is done. Then the encoder is ready for the next frame.
+    pub fn finish(&mut self, out_buf: &mut [u8]) -> EncoderProgress {
+        self.finishing = true;
+        let mut produced = self.drain(out_buf);
+        // Once any earlier block is output, the final block is ended, and the delimiter follows.
+        if self.finishing && !self.is_pending() {
+            self.end_block((self.run_len + 1) as u8, self.run_len);
+            self.delimiter_pending = true;
+            produced += self.drain(out_buf.get_mut(produced..).unwrap_or_default());
+        }
+        self.check.output(&out_buf[..produced]);
+        EncoderProgress {
+            consumed: 0,
+            produced,
+            done: !self.finishing,
+        }
+    }
+
+    fn end_block(&mut self, code: u8, data_len: usize) {
+        if let Some(hold_byte) = self.hold_buf.get_mut(0) {
+            *hold_byte = code;
+        }
+        self.out_start = 0;
+        self.out_end = data_len + 1;
+        self.run_len = 0;
+    }
+
+    /// Write as much as fits of the encoded data waiting for output, returning its length.
+    fn drain(&mut self, out_buf: &mut [u8]) -> usize {
+        let block = self
+            .hold_buf
+            .get(self.out_start..self.out_end)
+            .unwrap_or_default();
+        let mut len = block.len().min(out_buf.len());
+        out_buf[..len].copy_from_slice(&block[..len]);
+        self.out_start += len;
+        if self.out_start == self.out_end && self.delimiter_pending {
+            if let Some(out_byte) = out_buf.get_mut(len) {
+                *out_byte = 0;
+                len += 1;
+                self.delimiter_pending = false;
+                self.finishing = false;
+            }
+        }
+        len
+    }
+}
+
+/// Implement trait [Default].
+impl Default for Encoder {
+    fn default() -> Encoder {
+        Encoder::new()
+    }
 }
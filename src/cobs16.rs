@@ -0,0 +1,218 @@
+//! Word-oriented COBS, for streams of `u16` words
+//!
+//! This module contains functions for a variant of COBS which operates on `u16` words rather than
+//! bytes. It eliminates the `0x0000` word from the encoded data, so `0x0000` words can be used as
+//! frame delimiters on word-oriented transports, such as 9-bit or 16-bit UART modes, some RF
+//! front-ends, and memory-mapped mailboxes.
+//!
+//! The algorithm is the same as for plain COBS, but with a different symbol width. Each length
+//! code word gives the length of the following run of non-zero words, plus one. A run can contain
+//! up to 65534 words, so the maximum length code is `0xFFFF`, which (as in plain COBS) is not
+//! followed by an implicit zero word.
+//!
+//! Note that byte order is not defined by this module. If the words are transmitted as bytes, the
+//! application must choose a byte order, and each zero word must be transmitted as two zero-bytes.
+
+use crate::{Error, Result};
+
+/// Calculate the minimum possible COBS16 encoded output size in words, for a given size of input
+/// data in words.
+pub const fn encode_min_output_size(input_len: usize) -> usize {
+    input_len.saturating_add(1)
+}
+
+/// Calculate the maximum possible COBS16 encoded output size in words, for a given size of input
+/// data in words.
+pub const fn encode_max_output_size(input_len: usize) -> usize {
+    if input_len == 0 {
+        1
+    } else {
+        input_len.saturating_add(input_len.div_ceil(0xFFFE))
+    }
+}
+
+/// Calculate the minimum possible decoded output size in words, for a given size of
+/// COBS16-encoded input in words.
+pub const fn decode_min_output_size(input_len: usize) -> usize {
+    if input_len >= 1 {
+        let increase = (input_len - 1) / 0xFFFF;
+        input_len - 1 - increase
+    } else {
+        0
+    }
+}
+
+/// Calculate the maximum possible decoded output size in words, for a given size of
+/// COBS16-encoded input in words.
+pub const fn decode_max_output_size(input_len: usize) -> usize {
+    input_len.saturating_sub(1)
+}
+
+/// Write a word to the output buffer, or return [`Error::OutputBufferTooSmall`] if the index is
+/// out of range.
+fn put_word(out_buf: &mut [u16], index: usize, value: u16) -> Result<()> {
+    match out_buf.get_mut(index) {
+        Some(out_word) => {
+            *out_word = value;
+            Ok(())
+        }
+        None => Err(Error::OutputBufferTooSmall),
+    }
+}
+
+/// Encode data into COBS16 encoded form, writing output to the given output buffer.
+///
+/// The output data is COBS16-encoded, containing no zero words.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`encode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x5555_u16; 100];
+///     let data = [0x0102, 0x0000, 0xABCD];
+///     let data_cobs = cobs2::cobs16::encode_array(&mut cobs_buf, &data);
+///     assert_eq!(data_cobs.unwrap(), [0x0002, 0x0102, 0x0002, 0xABCD]);
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_array<'a>(out_buf: &'a mut [u16], in_buf: &[u16]) -> Result<&'a [u16]> {
+    let mut code_i = 0;
+    let mut out_i = 1;
+
+    for x in in_buf {
+        if out_i - code_i >= 0xFFFF {
+            put_word(out_buf, code_i, 0xFFFF)?;
+            code_i = out_i;
+            out_i = code_i + 1;
+        }
+        if *x == 0 {
+            put_word(out_buf, code_i, (out_i - code_i) as u16)?;
+            code_i = out_i;
+            out_i = code_i + 1;
+        } else {
+            put_word(out_buf, out_i, *x)?;
+            out_i += 1;
+        }
+    }
+
+    // We've reached the end of the source data.
+    // Finalise the remaining output. In particular, write the code (length) word.
+    put_word(out_buf, code_i, (out_i - code_i) as u16)?;
+
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Encode data into COBS16 encoded form, returning output as a vector of `u16`.
+///
+/// The output data is COBS16-encoded, containing no zero words.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u16`.
+///
+///     let data = [0x0102, 0x0000, 0xABCD];
+///     let data_cobs = cobs2::cobs16::encode_vector(&data);
+///     assert_eq!(data_cobs.unwrap(), [0x0002, 0x0102, 0x0002, 0xABCD]);
+///
+#[cfg(feature = "alloc")]
+pub fn encode_vector(in_buf: &[u16]) -> Result<alloc::vec::Vec<u16>> {
+    let mut out_vec = alloc::vec![0_u16; encode_max_output_size(in_buf.len())];
+    let out_len = encode_array(&mut out_vec, in_buf)?.len();
+    out_vec.truncate(out_len);
+    Ok(out_vec)
+}
+
+/// Decode COBS16-encoded data, writing decoded data to the given output buffer.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`decode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the decoded data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let mut decode_buf = [0x5555_u16; 100];
+///     let data_cobs = [0x0002, 0x0102, 0x0002, 0xABCD];
+///     let decode_data = cobs2::cobs16::decode_array(&mut decode_buf, &data_cobs);
+///     assert_eq!(decode_data.unwrap(), [0x0102, 0x0000, 0xABCD]);
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_array<'a>(out_buf: &'a mut [u16], in_buf: &[u16]) -> Result<&'a [u16]> {
+    let mut code_i = 0;
+    let mut out_i = 0;
+
+    while let Some(&code) = in_buf.get(code_i) {
+        if code == 0 {
+            return Err(Error::ZeroInEncodedData);
+        }
+        for in_i in (code_i + 1)..(code_i + code as usize) {
+            let in_word = match in_buf.get(in_i) {
+                Some(&in_word) => in_word,
+                None => return Err(Error::TruncatedEncodedData),
+            };
+            if in_word == 0 {
+                return Err(Error::ZeroInEncodedData);
+            }
+            put_word(out_buf, out_i, in_word)?;
+            out_i += 1;
+        }
+        code_i += code as usize;
+        if code_i >= in_buf.len() {
+            // End of data. Exit, without outputting a trailing zero for the end of the data.
+            break;
+        }
+        if code < 0xFFFF {
+            // Output trailing zero.
+            put_word(out_buf, out_i, 0)?;
+            out_i += 1;
+        }
+    }
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Decode COBS16-encoded data, returning output as a vector of `u16`.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u16`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = [0x0002, 0x0102, 0x0002, 0xABCD];
+///     let decode_data = cobs2::cobs16::decode_vector(&data_cobs);
+///     assert_eq!(decode_data.unwrap(), [0x0102, 0x0000, 0xABCD]);
+///
+#[cfg(feature = "alloc")]
+pub fn decode_vector(in_buf: &[u16]) -> Result<alloc::vec::Vec<u16>> {
+    let mut out_vec = alloc::vec![0_u16; decode_max_output_size(in_buf.len())];
+    let out_len = decode_array(&mut out_vec, in_buf)?.len();
+    out_vec.truncate(out_len);
+    Ok(out_vec)
+}
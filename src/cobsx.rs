@@ -0,0 +1,255 @@
+//! Extended COBS (COBS/X), with two-byte length codes for long runs
+//!
+//! This module contains functions for a variant of COBS which is intended for large frames, such
+//! as firmware images. Plain COBS inserts a length code byte at least every 254 bytes, which adds
+//! about 0.4% overhead, and interrupts long runs of data with code bytes. COBS/X instead allows
+//! runs of up to 65024 bytes, so long frames have much less overhead (about 0.005%), and data can
+//! be copied in large blocks.
+//!
+//! The encoded data contains no zero-bytes, so zero-bytes can still be used as frame delimiters.
+//! But COBS/X is not compatible with plain COBS. Encoded data must be decoded by this module.
+//!
+//! ### Encoding
+//!
+//! * A code byte in the range `01` to `FE` is the same as in plain COBS. It is followed by a run
+//!   of (code − 1) non-zero data bytes, which is followed by an implicit zero-byte (unless it is at
+//!   the end of the data).
+//! * A code byte `FF` is followed by two more code bytes *h* and *l*, both non-zero, giving a run
+//!   length of (*h* − 1) × 255 + (*l* − 1), which is in the range 0 to 65024. The run of non-zero
+//!   data bytes follows. If the run length is less than the maximum of 65024, the run is followed
+//!   by an implicit zero-byte (unless it is at the end of the data).
+//!
+//! The encoder uses a single code byte for runs of up to 253 bytes, so short frames and data with
+//! many zero-bytes are encoded the same as in plain COBS.
+
+use crate::{Error, Result};
+
+/// Maximum run length that can be given by a single code byte.
+const MAX_SHORT_RUN: usize = 0xFD;
+
+/// Maximum run length that can be given by an extended code. A run of this length is not followed
+/// by an implicit zero-byte.
+const MAX_EXTENDED_RUN: usize = 255 * 255 - 1;
+
+/// Calculate the minimum possible COBS/X encoded output size, for a given size of input data.
+pub const fn encode_min_output_size(input_len: usize) -> usize {
+    input_len.saturating_add(1)
+}
+
+/// Calculate the maximum possible COBS/X encoded output size, for a given size of input data.
+pub const fn encode_max_output_size(input_len: usize) -> usize {
+    let increase = (input_len / (MAX_SHORT_RUN + 1)) * 2 + input_len / MAX_EXTENDED_RUN + 1;
+    input_len.saturating_add(increase)
+}
+
+/// Calculate the minimum possible decoded output size, for a given size of COBS/X-encoded input.
+pub const fn decode_min_output_size(input_len: usize) -> usize {
+    let blocks = input_len.div_ceil(MAX_EXTENDED_RUN + 3);
+    input_len.saturating_sub(blocks * 3)
+}
+
+/// Calculate the maximum possible decoded output size, for a given size of COBS/X-encoded input.
+pub const fn decode_max_output_size(input_len: usize) -> usize {
+    input_len.saturating_sub(1)
+}
+
+/// Write a byte to the output buffer, or return [`Error::OutputBufferTooSmall`] if the index is
+/// out of range.
+fn put_byte(out_buf: &mut [u8], index: usize, value: u8) -> Result<()> {
+    match out_buf.get_mut(index) {
+        Some(out_byte) => {
+            *out_byte = value;
+            Ok(())
+        }
+        None => Err(Error::OutputBufferTooSmall),
+    }
+}
+
+/// Encode data into COBS/X encoded form, writing output to the given output buffer.
+///
+/// The output data is COBS/X-encoded, containing no zero-bytes.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`encode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the valid data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+///
+/// Example:
+///
+///     let mut cobs_buf = [0x55_u8; 1000];
+///     let data = [0x41_u8; 300];
+///     let data_cobs = cobs2::cobsx::encode_array(&mut cobs_buf, &data).unwrap();
+///     assert_eq!(data_cobs[..3], [0xFF, 0x02, 0x2E]);
+///     assert_eq!(data_cobs[3..], data[..]);
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn encode_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    let mut in_rest = in_buf;
+    let mut out_i = 0;
+
+    loop {
+        let run_len = in_rest
+            .iter()
+            .take(MAX_EXTENDED_RUN)
+            .position(|x| *x == 0)
+            .unwrap_or(core::cmp::min(in_rest.len(), MAX_EXTENDED_RUN));
+        let (run, remainder) = in_rest.split_at(run_len);
+
+        if run_len <= MAX_SHORT_RUN {
+            put_byte(out_buf, out_i, (run_len + 1) as u8)?;
+            out_i += 1;
+        } else {
+            put_byte(out_buf, out_i, 0xFF)?;
+            put_byte(out_buf, out_i + 1, (run_len / 255 + 1) as u8)?;
+            put_byte(out_buf, out_i + 2, (run_len % 255 + 1) as u8)?;
+            out_i += 3;
+        }
+        out_buf
+            .get_mut(out_i..out_i + run_len)
+            .ok_or(Error::OutputBufferTooSmall)?
+            .copy_from_slice(run);
+        out_i += run_len;
+
+        if run_len == MAX_EXTENDED_RUN {
+            // No implicit zero-byte follows a maximum length run.
+            in_rest = remainder;
+            if in_rest.is_empty() {
+                break;
+            }
+        } else {
+            // The run is followed by a zero-byte, or the end of the data.
+            match remainder.split_first() {
+                Some((_, after_zero)) => in_rest = after_zero,
+                None => break,
+            }
+        }
+    }
+
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Encode data into COBS/X encoded form, returning output as a vector of `u8`.
+///
+/// The output data is COBS/X-encoded, containing no zero-bytes.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+///     let data = b"ABC\0ghij\0xyz";
+///     let data_cobs = cobs2::cobsx::encode_vector(data);
+///     assert_eq!(data_cobs.unwrap(), b"\x04ABC\x05ghij\x04xyz");
+///
+#[cfg(feature = "alloc")]
+pub fn encode_vector(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    let mut out_vec = alloc::vec![0_u8; encode_max_output_size(in_buf.len())];
+    let out_len = encode_array(&mut out_vec, in_buf)?.len();
+    out_vec.truncate(out_len);
+    Ok(out_vec)
+}
+
+/// Decode COBS/X-encoded data, writing decoded data to the given output buffer.
+///
+/// The caller must provide a reference to a suitably-sized output buffer.
+/// [`decode_max_output_size()`] calculates the required output buffer size, for a given input
+/// size.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a slice of the decoded data in the
+/// output buffer.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let mut decode_buf = [0x55_u8; 1000];
+///     let data_cobs = b"\x04ABC\xFF\x01\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobsx::decode_array(&mut decode_buf, data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[deny(
+    clippy::indexing_slicing,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::expect_used
+)]
+pub fn decode_array<'a>(out_buf: &'a mut [u8], in_buf: &[u8]) -> Result<&'a [u8]> {
+    let mut in_rest = in_buf;
+    let mut out_i = 0;
+
+    while let Some((&code, after_code)) = in_rest.split_first() {
+        let (run_len, after_header) = match code {
+            0 => return Err(Error::ZeroInEncodedData),
+            0xFF => match after_code {
+                [0, ..] | [_, 0, ..] => return Err(Error::ZeroInEncodedData),
+                [h, l, after_header @ ..] => {
+                    ((*h as usize - 1) * 255 + (*l as usize - 1), after_header)
+                }
+                _ => return Err(Error::TruncatedEncodedData),
+            },
+            _ => (code as usize - 1, after_code),
+        };
+        if run_len > after_header.len() {
+            if after_header.contains(&0) {
+                return Err(Error::ZeroInEncodedData);
+            }
+            return Err(Error::TruncatedEncodedData);
+        }
+        let (run, remainder) = after_header.split_at(run_len);
+        if run.contains(&0) {
+            return Err(Error::ZeroInEncodedData);
+        }
+        out_buf
+            .get_mut(out_i..out_i + run_len)
+            .ok_or(Error::OutputBufferTooSmall)?
+            .copy_from_slice(run);
+        out_i += run_len;
+
+        in_rest = remainder;
+        if in_rest.is_empty() {
+            // End of data. Exit, without outputting a trailing zero for the end of the data.
+            break;
+        }
+        if run_len < MAX_EXTENDED_RUN {
+            // Output trailing zero.
+            put_byte(out_buf, out_i, 0)?;
+            out_i += 1;
+        }
+    }
+    out_buf.get(..out_i).ok_or(Error::OutputBufferTooSmall)
+}
+
+/// Decode COBS/X-encoded data, returning output as a vector of `u8`.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is a vector of `u8`.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+///
+/// Example:
+///
+///     let data_cobs = b"\x04ABC\x05ghij\x04xyz";
+///     let decode_data = cobs2::cobsx::decode_vector(data_cobs);
+///     assert_eq!(decode_data.unwrap(), b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "alloc")]
+pub fn decode_vector(in_buf: &[u8]) -> Result<alloc::vec::Vec<u8>> {
+    let mut out_vec = alloc::vec![0_u8; decode_max_output_size(in_buf.len())];
+    let out_len = decode_array(&mut out_vec, in_buf)?.len();
+    out_vec.truncate(out_len);
+    Ok(out_vec)
+}
@@ -0,0 +1,239 @@
+//! API mirroring the [`cobs` crate](https://crates.io/crates/cobs), version 0.2.
+//!
+//! This provides [`CobsEncoder`], [`CobsDecoder`], [`max_encoding_length()`] and the common
+//! encode/decode functions, with the same signatures and semantics as the `cobs` crate, so
+//! existing call sites can be migrated by changing the `use` path.
+//!
+//! As in the `cobs` crate, the encoding functions don't append a zero-byte sentinel, while the
+//! decoder requires a zero-byte sentinel to complete a message. Errors are reported as `()`, or as
+//! a count of bytes written, rather than as [`crate::Error`].
+//!
+//! There is one difference in encoded output. For data containing a run of exactly 254 non-zero
+//! bytes at the end of the data, the `cobs` crate appends a redundant `0x01` length code. This
+//! implementation doesn't. The output of either implementation decodes correctly with the other.
+
+#![allow(clippy::result_unit_err)]
+
+/// Calculates the maximum possible size of an encoded message given the length of the source
+/// message. This may be useful for calculating how large the `dest` buffer needs to be in the
+/// encoding functions.
+///
+/// This differs from [`crate::cobs::encode_max_output_size()`] only for empty source data, for
+/// which the `cobs` crate's encoder produces no output.
+pub fn max_encoding_length(source_len: usize) -> usize {
+    if source_len == 0 {
+        0
+    } else {
+        crate::cobs::encode_max_output_size(source_len)
+    }
+}
+
+/// The [`CobsEncoder`] type is used to encode a stream of bytes to a given mutable output slice.
+/// This is often useful when heap data structures are not available, or when not all message
+/// bytes are received at a single point in time.
+#[derive(Debug)]
+pub struct CobsEncoder<'a> {
+    dest: &'a mut [u8],
+    code_i: usize,
+    out_i: usize,
+}
+
+impl<'a> CobsEncoder<'a> {
+    /// Create a new streaming COBS encoder.
+    pub fn new(out_buf: &'a mut [u8]) -> CobsEncoder<'a> {
+        CobsEncoder {
+            dest: out_buf,
+            code_i: 0,
+            out_i: 1,
+        }
+    }
+
+    /// Push a slice of data to be encoded.
+    ///
+    /// Returns `Err(())` if the output buffer is too small.
+    pub fn push(&mut self, data: &[u8]) -> Result<(), ()> {
+        for x in data {
+            if self.out_i - self.code_i >= 0xFF {
+                *self.dest.get_mut(self.code_i).ok_or(())? = 0xFF;
+                self.code_i = self.out_i;
+                self.out_i = self.code_i + 1;
+            }
+            if *x == 0 {
+                *self.dest.get_mut(self.code_i).ok_or(())? = (self.out_i - self.code_i) as u8;
+                self.code_i = self.out_i;
+                self.out_i = self.code_i + 1;
+            } else {
+                *self.dest.get_mut(self.out_i).ok_or(())? = *x;
+                self.out_i += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Complete encoding of the output message. Does NOT terminate the message with the sentinel
+    /// value.
+    ///
+    /// Returns the length of the encoded message. If no data was pushed, the length is zero.
+    pub fn finalize(self) -> Result<usize, ()> {
+        if self.out_i == 1 {
+            return Ok(0);
+        }
+        *self.dest.get_mut(self.code_i).ok_or(())? = (self.out_i - self.code_i) as u8;
+        Ok(self.out_i)
+    }
+}
+
+/// The [`CobsDecoder`] type is used to decode a stream of bytes to a given mutable output slice.
+/// This is often useful when heap data structures are not available, or when not all message
+/// bytes are received at a single point in time.
+#[derive(Debug)]
+pub struct CobsDecoder<'a> {
+    dest: &'a mut [u8],
+    dest_idx: usize,
+    idle: bool,
+    last_run: u8,
+    count_run: u8,
+}
+
+impl<'a> CobsDecoder<'a> {
+    /// Create a new streaming COBS decoder. Provide the output buffer for the decoded message to
+    /// be placed in.
+    pub fn new(dest: &'a mut [u8]) -> CobsDecoder<'a> {
+        CobsDecoder {
+            dest,
+            dest_idx: 0,
+            idle: true,
+            last_run: 0,
+            count_run: 0,
+        }
+    }
+
+    fn add(&mut self, data: u8) -> Result<(), usize> {
+        *self.dest.get_mut(self.dest_idx).ok_or(self.dest_idx)? = data;
+        self.dest_idx += 1;
+        Ok(())
+    }
+
+    /// Push a single byte into the streaming decoder. Return values mean:
+    ///
+    /// * `Ok(None)` - State machine okay, more data needed
+    /// * `Ok(Some(N))` - A message of N bytes was successfully decoded
+    /// * `Err(M)` - Message decoding failed, and M bytes were written to output
+    ///
+    /// The sentinel value must be included in the input to this function for the decoding to
+    /// complete. After a message is completed, or fails, the decoder is ready for the next message.
+    pub fn feed(&mut self, data: u8) -> Result<Option<usize>, usize> {
+        if self.idle {
+            if data != 0 {
+                self.idle = false;
+                self.dest_idx = 0;
+                self.last_run = data;
+                self.count_run = data - 1;
+            }
+            Ok(None)
+        } else if data == 0 {
+            self.idle = true;
+            if self.count_run != 0 {
+                Err(self.dest_idx)
+            } else {
+                Ok(Some(self.dest_idx))
+            }
+        } else if self.count_run == 0 {
+            if self.last_run != 0xFF {
+                self.add(0)?;
+            }
+            self.last_run = data;
+            self.count_run = data - 1;
+            Ok(None)
+        } else {
+            self.add(data)?;
+            self.count_run -= 1;
+            Ok(None)
+        }
+    }
+
+    /// Push a slice of bytes into the streaming decoder. Return values mean:
+    ///
+    /// * `Ok(None)` - State machine okay, more data needed
+    /// * `Ok(Some((N, M)))` - A message of N bytes was successfully decoded, using M bytes from
+    ///   `data` (and earlier data)
+    /// * `Err(J)` - Message decoding failed, and J bytes were written to output
+    ///
+    /// The sentinel value must be included in the input to this function for the decoding to
+    /// complete.
+    pub fn push(&mut self, data: &[u8]) -> Result<Option<(usize, usize)>, usize> {
+        for (consumed_idx, d) in data.iter().enumerate() {
+            if let Some(decoded_bytes_ct) = self.feed(*d)? {
+                return Ok(Some((decoded_bytes_ct, consumed_idx + 1)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Encodes the `source` buffer into the `dest` buffer.
+///
+/// This function uses the typical sentinel value of 0. It returns the number of bytes written to
+/// in the `dest` buffer.
+///
+/// # Panics
+///
+/// This function will panic if the `dest` buffer is not large enough for the encoded message. You
+/// can calculate the size the `dest` buffer needs to be with the [`max_encoding_length()`]
+/// function.
+pub fn encode(source: &[u8], dest: &mut [u8]) -> usize {
+    try_encode(source, dest).expect("COBS encode output buffer too small")
+}
+
+/// Attempts to encode the `source` buffer into the `dest` buffer.
+///
+/// This function uses the typical sentinel value of 0. It returns the number of bytes written to
+/// in the `dest` buffer.
+///
+/// If the destination buffer does not have enough room, an error will be returned.
+pub fn try_encode(source: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+    let mut enc = CobsEncoder::new(dest);
+    enc.push(source)?;
+    enc.finalize()
+}
+
+/// Decodes the `source` buffer into the `dest` buffer.
+///
+/// This function uses the typical sentinel value of 0. Decoding stops at the first sentinel after
+/// the message. If there is no sentinel, the end of the `source` buffer is taken as the end of the
+/// message.
+///
+/// This will return `Err(())` if there was a decoding error. Otherwise, it will return `Ok(n)`
+/// where `n` is the length of the decoded message.
+pub fn decode(source: &[u8], dest: &mut [u8]) -> Result<usize, ()> {
+    let mut dec = CobsDecoder::new(dest);
+
+    if let Some((d_used, _s_used)) = dec.push(source).or(Err(()))? {
+        return Ok(d_used);
+    }
+    if source.last() != Some(&0) {
+        // Provide the missing sentinel to complete the decoding.
+        if let Some((d_used, _s_used)) = dec.push(&[0]).or(Err(()))? {
+            return Ok(d_used);
+        }
+    }
+    Err(())
+}
+
+/// Encodes the `source` buffer into a vector.
+#[cfg(feature = "alloc")]
+pub fn encode_vec(source: &[u8]) -> alloc::vec::Vec<u8> {
+    let mut encoded = alloc::vec![0; max_encoding_length(source.len())];
+    let encoded_len = encode(source, &mut encoded[..]);
+    encoded.truncate(encoded_len);
+    encoded
+}
+
+/// Decodes the `source` buffer into a vector.
+#[cfg(feature = "alloc")]
+pub fn decode_vec(source: &[u8]) -> Result<alloc::vec::Vec<u8>, ()> {
+    let mut decoded = alloc::vec![0; source.len()];
+    let decoded_len = decode(source, &mut decoded[..])?;
+    decoded.truncate(decoded_len);
+    Ok(decoded)
+}
@@ -0,0 +1,9 @@
+//! Compatibility APIs, mirroring other COBS implementations.
+//!
+//! These are intended to ease migration of existing code to this crate, by allowing call sites to
+//! be kept as they are while the implementation is replaced.
+
+pub mod cobs_crate;
+
+#[cfg(feature = "alloc")]
+pub mod python;
@@ -0,0 +1,153 @@
+//! API mirroring the Python [`cobs` package](https://pypi.org/project/cobs/), which provides the
+//! `cobs.cobs` and `cobs.cobsr` modules.
+//!
+//! This provides the [`cobs`] and [`cobsr`] modules, each with `encode()` and `decode()` functions
+//! and a [`DecodeError`], with the same names and semantics as the Python package, so that test
+//! benches and scripts can be ported from Python line by line.
+//!
+//! As in the Python package:
+//!
+//! * Encoding never fails, and doesn't append a zero-byte frame delimiter.
+//! * Decoding expects a single frame, without a zero-byte frame delimiter. Empty input decodes to
+//!   empty output.
+//! * Decoding errors are reported as a [`DecodeError`], whose message is the same as the message
+//!   of the Python exception.
+//!
+//! Example, ported from Python:
+//!
+//!     use cobs2::compat::python::{cobs, cobsr};
+//!
+//!     // encoded = cobs.encode(b'Hello\x00world')
+//!     let encoded = cobs::encode(b"Hello\x00world");
+//!     assert_eq!(encoded, b"\x06Hello\x06world");
+//!     assert_eq!(cobs::decode(&encoded).unwrap(), b"Hello\x00world");
+//!
+//!     assert_eq!(cobsr::encode(b"Hello\x00world"), b"\x06Hellodworl");
+//!
+//!     // with pytest.raises(cobs.DecodeError): cobs.decode(b'\x05AB')
+//!     let error = cobs::decode(b"\x05AB").unwrap_err();
+//!     assert_eq!(error.to_string(), "not enough input bytes for length code");
+
+use crate::Error;
+use core::fmt;
+
+/// A decoding error, the same as the `DecodeError` exception of the Python package.
+///
+/// It is [`cobs::DecodeError`] and [`cobsr::DecodeError`], as in the Python package's modules.
+/// The underlying [`Error`] is given by [`DecodeError::error()`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DecodeError {
+    error: Error,
+}
+
+impl DecodeError {
+    /// Get the underlying [`Error`].
+    pub fn error(&self) -> Error {
+        self.error.clone()
+    }
+
+    /// Get the message of the Python exception.
+    pub fn message(&self) -> &'static str {
+        match self.error {
+            Error::TruncatedEncodedData => "not enough input bytes for length code",
+            _ => "zero byte found in input",
+        }
+    }
+}
+
+/// Apply trait [std::error::Error].
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Implement trait [fmt::Display], as the message of the Python exception.
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+/// Convert to the plain [Error].
+impl From<DecodeError> for Error {
+    fn from(decode_error: DecodeError) -> Error {
+        decode_error.error
+    }
+}
+
+/// Calculate the maximum number of bytes added by encoding, for a given size of input data.
+fn encoding_overhead(source_len: usize) -> usize {
+    crate::cobs::encode_max_output_size(source_len) - source_len
+}
+
+/// The functions of the Python `cobs.cobs` module.
+pub mod cobs {
+    pub use super::DecodeError;
+    use alloc::vec::Vec;
+
+    /// Encode a string using Consistent Overhead Byte Stuffing (COBS).
+    ///
+    /// Input is any byte string. Output is also a byte string.
+    ///
+    /// Encoding guarantees no zero bytes in the output. The output string will be expanded
+    /// slightly, by a predictable amount. An empty string is encoded to `b"\x01"`.
+    pub fn encode(in_bytes: impl AsRef<[u8]>) -> Vec<u8> {
+        crate::cobs::encode_vector_exact(in_bytes.as_ref())
+            .expect("COBS encode output buffer is sized for the input")
+    }
+
+    /// Decode a string using Consistent Overhead Byte Stuffing (COBS).
+    ///
+    /// Input should be a byte string that has been COBS encoded. Output is also a byte string.
+    ///
+    /// A [`DecodeError`] is returned if the encoded data is invalid.
+    pub fn decode(in_bytes: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+        crate::cobs::decode_vector(in_bytes.as_ref()).map_err(|error| DecodeError { error })
+    }
+
+    /// Calculate the maximum overhead when encoding a message with the given length. The overhead
+    /// is a maximum of one byte in 254, rounded up, and one byte for an empty message.
+    pub fn encoding_overhead(source_len: usize) -> usize {
+        super::encoding_overhead(source_len)
+    }
+
+    /// Calculate how many bytes are needed to store the maximum length of an encoded message.
+    pub fn max_encoded_length(source_len: usize) -> usize {
+        crate::cobs::encode_max_output_size(source_len)
+    }
+}
+
+/// The functions of the Python `cobs.cobsr` module.
+pub mod cobsr {
+    pub use super::DecodeError;
+    use alloc::vec::Vec;
+
+    /// Encode a string using Consistent Overhead Byte Stuffing/Reduced (COBS/R).
+    ///
+    /// Input is any byte string. Output is also a byte string.
+    ///
+    /// Encoding guarantees no zero bytes in the output. The output string may be expanded
+    /// slightly, by a predictable amount. An empty string is encoded to `b"\x01"`.
+    pub fn encode(in_bytes: impl AsRef<[u8]>) -> Vec<u8> {
+        crate::cobsr::encode_vector_exact(in_bytes.as_ref())
+            .expect("COBS/R encode output buffer is sized for the input")
+    }
+
+    /// Decode a string using Consistent Overhead Byte Stuffing/Reduced (COBS/R).
+    ///
+    /// Input should be a byte string that has been COBS/R encoded. Output is also a byte string.
+    ///
+    /// A [`DecodeError`] is returned if the encoded data is invalid.
+    pub fn decode(in_bytes: impl AsRef<[u8]>) -> Result<Vec<u8>, DecodeError> {
+        crate::cobsr::decode_vector(in_bytes.as_ref()).map_err(|error| DecodeError { error })
+    }
+
+    /// Calculate the maximum overhead when encoding a message with the given length. The overhead
+    /// is a maximum of one byte in 254, rounded up, and one byte for an empty message.
+    pub fn encoding_overhead(source_len: usize) -> usize {
+        super::encoding_overhead(source_len)
+    }
+
+    /// Calculate how many bytes are needed to store the maximum length of an encoded message.
+    pub fn max_encoded_length(source_len: usize) -> usize {
+        crate::cobsr::encode_max_output_size(source_len)
+    }
+}
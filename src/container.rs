@@ -0,0 +1,227 @@
+//! Self-describing container files of frames, for captures and logs exchanged between tools.
+//!
+//! A file of delimited frames doesn't say how its frames are encoded, so a tool reading it must
+//! know by convention. A container starts with a header, which identifies the format and its
+//! version, and the [`Profile`] of its frames, so a [`ContainerReader`] decodes the frames of any
+//! container, whichever profile a [`ContainerWriter`] used.
+//!
+//! ### Container format
+//!
+//! All integers are little-endian.
+//!
+//! * The container starts with the 8-byte magic `COBS2CTR`, followed by a format version `u16`,
+//!   which is 1, and the profile identifier byte of [`Profile::id()`], which gives the encoding
+//!   variant and the checksum.
+//! * The frames follow, each encoded with the profile by [`Profile::encode_frame()`], so with a
+//!   trailing zero-byte delimiter, and a leading one if the profile has leading delimiters.
+//!
+//! Since the frames are delimited, a damaged frame doesn't affect the frames after it. The reader
+//! returns it as an error of kind [`io::ErrorKind::InvalidData`], which wraps the [`Error`], and
+//! reading continues with the next frame. A torn final frame, which has no delimiter, as when
+//! writing was interrupted, is ignored, and its length is reported by
+//! [`ContainerReader::torn_len()`].
+//!
+//! Example:
+//!
+//!     use cobs2::container::{ContainerReader, ContainerWriter};
+//!     use cobs2::profile::Profile;
+//!
+//!     let mut file = Vec::new();
+//!     let mut writer = ContainerWriter::new(&mut file, Profile::CobsCrc32).unwrap();
+//!     writer.write_frame(b"ABC\0ghij\0xyz").unwrap();
+//!     writer.write_frame(b"second").unwrap();
+//!
+//!     let reader = ContainerReader::new(&file[..]).unwrap();
+//!     assert_eq!(reader.profile(), Profile::CobsCrc32);
+//!     let frames: Vec<Vec<u8>> = reader.map(|frame| frame.unwrap()).collect();
+//!     assert_eq!(frames, [&b"ABC\0ghij\0xyz"[..], b"second"]);
+
+use crate::profile::Profile;
+use crate::stream::to_io_error;
+use crate::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::vec::Vec;
+
+/// Magic at the start of a container.
+pub const MAGIC: &[u8; 8] = b"COBS2CTR";
+
+/// Version of the container format.
+pub const VERSION: u16 = 1;
+
+/// Length of the container header.
+pub const HEADER_LEN: usize = 11;
+
+/// Writes frames to a container, encoded with a framing profile.
+///
+/// See the [`crate::container`] module for details.
+pub struct ContainerWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    profile: Profile,
+}
+
+impl ContainerWriter<File> {
+    /// Create a container file, replacing any existing file, and write the container header.
+    pub fn create<P>(path: P, profile: Profile) -> io::Result<ContainerWriter<File>>
+    where
+        P: AsRef<Path>,
+    {
+        ContainerWriter::new(File::create(path)?, profile)
+    }
+}
+
+impl<W> ContainerWriter<W>
+where
+    W: Write,
+{
+    /// Start a container, writing the container header to the writer.
+    pub fn new(mut writer: W, profile: Profile) -> io::Result<ContainerWriter<W>> {
+        let mut header = [0_u8; HEADER_LEN];
+        header[..8].copy_from_slice(MAGIC);
+        header[8..10].copy_from_slice(&VERSION.to_le_bytes());
+        header[10] = profile.id();
+        writer.write_all(&header)?;
+        Ok(ContainerWriter { writer, profile })
+    }
+
+    /// Encode a frame with the profile, and write it with its delimiters.
+    ///
+    /// The frame is written in one write call, so an interrupted write can only tear the end of
+    /// the container.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from writing the writer.
+    /// * [`io::ErrorKind::InvalidData`], if the frame is longer than the profile's maximum length.
+    ///   It wraps [`Error::FrameTooLong`].
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        self.writer.write_all(&frame_encoded)
+    }
+
+    /// Get the framing profile.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Flush the writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    /// Consume the container writer, returning the writer.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the frames of a container, as an iterator of each frame's decoded data.
+///
+/// See the [`crate::container`] module for details.
+pub struct ContainerReader<R>
+where
+    R: Read,
+{
+    reader: BufReader<R>,
+    profile: Profile,
+    frame_buf: Vec<u8>,
+    torn_len: usize,
+}
+
+impl ContainerReader<File> {
+    /// Open a container file for reading, checking the container header.
+    pub fn open<P>(path: P) -> io::Result<ContainerReader<File>>
+    where
+        P: AsRef<Path>,
+    {
+        ContainerReader::new(File::open(path)?)
+    }
+}
+
+impl<R> ContainerReader<R>
+where
+    R: Read,
+{
+    /// Start reading a container, checking the container header.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader.
+    /// * [`io::ErrorKind::InvalidData`], if the data is not a container, or its version is not
+    ///   supported.
+    /// * [`io::ErrorKind::InvalidData`], if the profile is not known. It wraps
+    ///   [`Error::UnknownFormat`].
+    pub fn new(mut reader: R) -> io::Result<ContainerReader<R>> {
+        let mut header = [0_u8; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if header[..8] != MAGIC[..] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a container",
+            ));
+        }
+        if header[8..10] != VERSION.to_le_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a supported container version",
+            ));
+        }
+        let profile =
+            Profile::from_id(header[10]).ok_or_else(|| to_io_error(Error::UnknownFormat))?;
+        Ok(ContainerReader {
+            reader: BufReader::new(reader),
+            profile,
+            frame_buf: Vec::new(),
+            torn_len: 0,
+        })
+    }
+
+    /// Get the framing profile, from the container header.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Get the length of a torn final frame, which has no delimiter. This is 0 if the container
+    /// doesn't end with a torn frame, or if the end of the container hasn't been reached yet.
+    pub fn torn_len(&self) -> usize {
+        self.torn_len
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            self.frame_buf.clear();
+            self.reader.read_until(0, &mut self.frame_buf)?;
+            match self.frame_buf.split_last() {
+                None => return Ok(None),
+                Some((0, [])) => continue,
+                Some((0, frame_encoded)) => {
+                    return self
+                        .profile
+                        .decode(frame_encoded)
+                        .map(Some)
+                        .map_err(to_io_error)
+                }
+                Some(_) => {
+                    self.torn_len = self.frame_buf.len();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Implement trait [Iterator].
+impl<R> Iterator for ContainerReader<R>
+where
+    R: Read,
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
@@ -0,0 +1,42 @@
+//! CRC calculation, shared by the checksum stage, the framing profiles and the frame logs.
+
+/// Calculate the common CRC-32 (as used by Ethernet and zlib) of the data.
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data
+        .iter()
+        .fold(CRC32_INIT, |crc, &byte_val| crc32_update(crc, byte_val))
+}
+
+/// The initial value of the CRC-32, for [`crc32_update()`].
+pub(crate) const CRC32_INIT: u32 = 0xFFFF_FFFF;
+
+/// Update the CRC-32 with one byte. The final CRC is the bitwise inverse of the result.
+pub(crate) fn crc32_update(mut crc: u32, byte_val: u8) -> u32 {
+    crc ^= byte_val as u32;
+    for _ in 0..8 {
+        let mask = (crc & 1).wrapping_neg();
+        crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+    }
+    crc
+}
+
+/// Calculate the CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xFFFF`, not reflected)
+/// of the data.
+pub(crate) fn crc16_ccitt(data: &[u8]) -> u16 {
+    data.iter().fold(CRC16_CCITT_INIT, |crc, &byte_val| {
+        crc16_ccitt_update(crc, byte_val)
+    })
+}
+
+/// The initial value of the CRC-16/CCITT-FALSE, for [`crc16_ccitt_update()`].
+pub(crate) const CRC16_CCITT_INIT: u16 = 0xFFFF;
+
+/// Update the CRC-16/CCITT-FALSE with one byte.
+pub(crate) fn crc16_ccitt_update(mut crc: u16, byte_val: u8) -> u16 {
+    crc ^= (byte_val as u16) << 8;
+    for _ in 0..8 {
+        let mask = (crc >> 15).wrapping_neg();
+        crc = (crc << 1) ^ (0x1021 & mask);
+    }
+    crc
+}
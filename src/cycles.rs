@@ -0,0 +1,244 @@
+//! Measurement of encoding and decoding cycle counts on the target hardware (feature `cycles`).
+//!
+//! The cost of encoding and decoding depends on the processor, its memory and its compiler
+//! settings, so it is best measured on the actual hardware. This module measures the crate's
+//! array functions with a cycle counter, and reports each [`Measurement`] as a small COBS frame,
+//! which can be sent over a UART to a host, and decoded there with
+//! [`Measurement::decode_frame()`].
+//!
+//! The cycle counter is anything which implements [`CycleCounter`], including a closure which
+//! returns a `u32` count. This crate doesn't depend on a particular hardware access crate. On
+//! Cortex-M3 and above, with the `cortex-m` crate, enable the DWT cycle counter with
+//! `DCB::enable_trace()` and `DWT::enable_cycle_counter()`, then use the closure
+//! `|| cortex_m::peripheral::DWT::cycle_count()` as the counter.
+//!
+//! The counts include the overhead of reading the counter, which can be measured with
+//! [`measure()`] of an empty function, and subtracted.
+//!
+//! Example, with a simulated counter:
+//!
+//!     use cobs2::cycles::{measure_operation, Measurement, Operation};
+//!
+//!     let mut count = 0_u32;
+//!     let mut counter = || {
+//!         count += 100;
+//!         count
+//!     };
+//!     let data = b"ABC\0xyz";
+//!     let mut encode_buf = [0_u8; 16];
+//!     let mut decode_buf = [0_u8; 16];
+//!     let mut uart = Vec::new();
+//!     for &operation in Operation::ALL.iter() {
+//!         let measurement =
+//!             measure_operation(&mut counter, operation, data, &mut encode_buf, &mut decode_buf)
+//!                 .unwrap();
+//!         assert_eq!(measurement.cycles, 100);
+//!         let mut frame_buf = [0_u8; Measurement::FRAME_MAX_LEN];
+//!         uart.extend_from_slice(measurement.encode_frame(&mut frame_buf).unwrap());
+//!     }
+//!
+//!     // On the host:
+//!     for frame in uart.split(|&x| x == 0).filter(|frame| !frame.is_empty()) {
+//!         let measurement = Measurement::decode_frame(frame).unwrap();
+//!         println!("{}: {} bytes in {} cycles", measurement.operation.name(),
+//!             measurement.data_len, measurement.cycles);
+//!     }
+
+use crate::{cobs, cobsr, Error, Result};
+
+/// A free-running cycle counter, such as the Cortex-M DWT `CYCCNT` register.
+///
+/// This is implemented for closures which return the count. The count may wrap around.
+pub trait CycleCounter {
+    /// Read the current count.
+    fn cycle_count(&mut self) -> u32;
+}
+
+/// Apply trait [`CycleCounter`] to closures.
+impl<F> CycleCounter for F
+where
+    F: FnMut() -> u32,
+{
+    fn cycle_count(&mut self) -> u32 {
+        self()
+    }
+}
+
+/// Call the function, and return its result and the number of cycles it took.
+///
+/// The count is correct if the counter wraps around at most once.
+pub fn measure<C, F, R>(counter: &mut C, f: F) -> (R, u32)
+where
+    C: CycleCounter + ?Sized,
+    F: FnOnce() -> R,
+{
+    let start = counter.cycle_count();
+    let result = f();
+    let end = counter.cycle_count();
+    (result, end.wrapping_sub(start))
+}
+
+/// An operation which can be measured by [`measure_operation()`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Operation {
+    /// [`cobs::encode_array()`].
+    CobsEncodeArray,
+    /// [`cobs::decode_array()`].
+    CobsDecodeArray,
+    /// [`cobsr::encode_array()`].
+    CobsrEncodeArray,
+    /// [`cobsr::decode_array()`].
+    CobsrDecodeArray,
+}
+
+impl Operation {
+    /// All the operations, in the order of their identifiers.
+    pub const ALL: [Operation; 4] = [
+        Operation::CobsEncodeArray,
+        Operation::CobsDecodeArray,
+        Operation::CobsrEncodeArray,
+        Operation::CobsrDecodeArray,
+    ];
+
+    /// The identifier of the operation in a measurement frame.
+    pub const fn id(self) -> u8 {
+        match self {
+            Operation::CobsEncodeArray => 1,
+            Operation::CobsDecodeArray => 2,
+            Operation::CobsrEncodeArray => 3,
+            Operation::CobsrDecodeArray => 4,
+        }
+    }
+
+    /// Get the operation with the given identifier, if there is one.
+    pub const fn from_id(id: u8) -> Option<Operation> {
+        match id {
+            1 => Some(Operation::CobsEncodeArray),
+            2 => Some(Operation::CobsDecodeArray),
+            3 => Some(Operation::CobsrEncodeArray),
+            4 => Some(Operation::CobsrDecodeArray),
+            _ => None,
+        }
+    }
+
+    /// The name of the function which the operation measures, such as `"cobs::encode_array"`.
+    pub const fn name(self) -> &'static str {
+        match self {
+            Operation::CobsEncodeArray => "cobs::encode_array",
+            Operation::CobsDecodeArray => "cobs::decode_array",
+            Operation::CobsrEncodeArray => "cobsr::encode_array",
+            Operation::CobsrDecodeArray => "cobsr::decode_array",
+        }
+    }
+}
+
+/// The cycle count of an operation, for an input of a given length.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct Measurement {
+    /// The operation.
+    pub operation: Operation,
+    /// The length of the unencoded data.
+    pub data_len: u32,
+    /// The number of cycles.
+    pub cycles: u32,
+}
+
+impl Measurement {
+    /// The length of a measurement record, before encoding: the operation identifier, then the
+    /// data length and the cycle count, in little-endian byte order.
+    pub const RECORD_LEN: usize = 9;
+
+    /// The maximum length of a frame from [`Measurement::encode_frame()`], including its
+    /// delimiter.
+    pub const FRAME_MAX_LEN: usize = cobs::encode_max_output_size(Measurement::RECORD_LEN) + 1;
+
+    /// Encode the measurement as a COBS frame, with a trailing zero-byte delimiter.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::OutputBufferTooSmall`]
+    pub fn encode_frame<'a>(&self, out_buf: &'a mut [u8]) -> Result<&'a [u8]> {
+        let mut record = [0_u8; Measurement::RECORD_LEN];
+        record[0] = self.operation.id();
+        record[1..5].copy_from_slice(&self.data_len.to_le_bytes());
+        record[5..].copy_from_slice(&self.cycles.to_le_bytes());
+        let encoded_len = cobs::encode_array_len(out_buf, &record)?;
+        let frame = out_buf
+            .get_mut(..=encoded_len)
+            .ok_or(Error::OutputBufferTooSmall)?;
+        frame[encoded_len] = 0;
+        Ok(frame)
+    }
+
+    /// Decode a measurement from an encoded frame, without its delimiter.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::ZeroInEncodedData`]
+    /// * [`Error::TruncatedEncodedData`]
+    /// * [`Error::UnknownFormat`], if the frame is not a measurement record.
+    pub fn decode_frame(in_buf: &[u8]) -> Result<Measurement> {
+        let mut decode_buf = [0_u8; Measurement::RECORD_LEN + 1];
+        let record = cobs::decode_array(&mut decode_buf, in_buf).map_err(|e| match e {
+            Error::OutputBufferTooSmall => Error::UnknownFormat,
+            e => e,
+        })?;
+        if record.len() != Measurement::RECORD_LEN {
+            return Err(Error::UnknownFormat);
+        }
+        let operation = Operation::from_id(record[0]).ok_or(Error::UnknownFormat)?;
+        let mut data_len = [0_u8; 4];
+        data_len.copy_from_slice(&record[1..5]);
+        let mut cycles = [0_u8; 4];
+        cycles.copy_from_slice(&record[5..]);
+        Ok(Measurement {
+            operation,
+            data_len: u32::from_le_bytes(data_len),
+            cycles: u32::from_le_bytes(cycles),
+        })
+    }
+}
+
+/// Measure the cycle count of an operation, for the given unencoded data.
+///
+/// The output of the operation is written to `encode_buf` for an encoding operation, or
+/// `decode_buf` for a decoding operation. For a decoding operation, the data is first encoded into
+/// `encode_buf`, which is not measured. Size the buffers with [`cobs::encode_max_output_size()`]
+/// and the data length.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::OutputBufferTooSmall`]
+pub fn measure_operation<C>(
+    counter: &mut C,
+    operation: Operation,
+    data: &[u8],
+    encode_buf: &mut [u8],
+    decode_buf: &mut [u8],
+) -> Result<Measurement>
+where
+    C: CycleCounter + ?Sized,
+{
+    let (result, cycles) = match operation {
+        Operation::CobsEncodeArray => measure(counter, || cobs::encode_array_len(encode_buf, data)),
+        Operation::CobsDecodeArray => {
+            let encoded_len = cobs::encode_array_len(encode_buf, data)?;
+            let encoded = encode_buf.get(..encoded_len).unwrap_or_default();
+            measure(counter, || cobs::decode_array_len(decode_buf, encoded))
+        }
+        Operation::CobsrEncodeArray => {
+            measure(counter, || cobsr::encode_array_len(encode_buf, data))
+        }
+        Operation::CobsrDecodeArray => {
+            let encoded_len = cobsr::encode_array_len(encode_buf, data)?;
+            let encoded = encode_buf.get(..encoded_len).unwrap_or_default();
+            measure(counter, || cobsr::decode_array_len(decode_buf, encoded))
+        }
+    };
+    result?;
+    Ok(Measurement {
+        operation,
+        data_len: data.len() as u32,
+        cycles,
+    })
+}
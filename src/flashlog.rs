@@ -0,0 +1,352 @@
+//! Append-only log of frames in NOR flash, for `no_std` targets (feature `embedded-storage`).
+//!
+//! A [`FlashFrameLog`] appends records to a region of any flash that implements the
+//! [`embedded_storage`] [`NorFlash`] trait, and iterates them with a [`FlashLogReader`], such as
+//! at boot. No allocation is needed.
+//!
+//! The records have the same format as the `framelog` module (feature `std`): the frame data
+//! followed by its CRC-32, COBS-encoded, and followed by a zero-byte delimiter. Each record is
+//! padded with zero-bytes to a multiple of the flash's write size. So the records are
+//! self-synchronising, and erased flash (`0xFF` bytes) after the last record is free space.
+//!
+//! If writing was interrupted, such as by a power failure, the log can end with a torn record,
+//! which has no delimiter. The reader ignores a torn final record, and reports its length with
+//! [`FlashLogReader::torn_len()`]. When the log is opened, a torn record is detected, and the next
+//! appended record starts with a delimiter to end it, so the appended record is not affected.
+//! Records that are damaged, so they fail to decode or their CRC doesn't match, are returned by the
+//! reader as [`FlashLogError::Record`] errors. Reading continues with the next record.
+//!
+//! Example:
+//!
+//!     use cobs2::flashlog::FlashFrameLog;
+//!     # use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
+//!     # struct RamFlash([u8; 1024]);
+//!     # impl ErrorType for RamFlash { type Error = NorFlashErrorKind; }
+//!     # impl ReadNorFlash for RamFlash {
+//!     #     const READ_SIZE: usize = 1;
+//!     #     fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+//!     #         bytes.copy_from_slice(&self.0[offset as usize..][..bytes.len()]);
+//!     #         Ok(())
+//!     #     }
+//!     #     fn capacity(&self) -> usize { self.0.len() }
+//!     # }
+//!     # impl NorFlash for RamFlash {
+//!     #     const WRITE_SIZE: usize = 4;
+//!     #     const ERASE_SIZE: usize = 256;
+//!     #     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+//!     #         self.0[from as usize..to as usize].fill(0xFF);
+//!     #         Ok(())
+//!     #     }
+//!     #     fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+//!     #         self.0[offset as usize..][..bytes.len()].copy_from_slice(bytes);
+//!     #         Ok(())
+//!     #     }
+//!     # }
+//!     # let flash = RamFlash([0xFF; 1024]);
+//!
+//!     // Use the first 512 bytes of the flash for the log.
+//!     let mut log = FlashFrameLog::open(flash, 0, 512).unwrap();
+//!     log.append(b"boot").unwrap();
+//!     log.append(b"ABC\0ghij\0xyz").unwrap();
+//!
+//!     let mut reader = log.reader::<64>();
+//!     assert_eq!(reader.next_record().unwrap().unwrap(), b"boot");
+//!     assert_eq!(reader.next_record().unwrap().unwrap(), b"ABC\0ghij\0xyz");
+//!     assert!(reader.next_record().is_none());
+
+use crate::crc::crc32;
+use crate::{cobs, Error};
+use core::fmt;
+use embedded_storage::nor_flash::NorFlash;
+
+/// Size of the buffer used for reading and writing the flash.
+const CHUNK_SIZE: usize = 64;
+
+/// Value of an erased flash byte.
+const ERASED: u8 = 0xFF;
+
+/// Errors that can occur for a [`FlashFrameLog`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FlashLogError<E> {
+    /// An error from the flash.
+    Flash(E),
+    /// The log region is not aligned to the flash's erase size, or is outside the flash, or the
+    /// flash's read or write size is not supported.
+    NotAligned,
+    /// There is not enough free space in the log for the record.
+    Full,
+    /// A record is damaged, so it can't be decoded, or its CRC doesn't match, or it is too long
+    /// for the reader's buffer.
+    Record(Error),
+}
+
+/// Apply trait [std::error::Error].
+#[cfg(feature = "std")]
+impl<E> std::error::Error for FlashLogError<E> where E: fmt::Debug {}
+
+/// Implement trait [fmt::Display].
+impl<E> fmt::Display for FlashLogError<E>
+where
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FlashLogError::Flash(e) => write!(f, "Flash error: {:?}", e),
+            FlashLogError::NotAligned => write!(f, "Log region is not aligned"),
+            FlashLogError::Full => write!(f, "Log is full"),
+            FlashLogError::Record(e) => write!(f, "Damaged record: {}", e),
+        }
+    }
+}
+
+/// An append-only log of frames, in a region of NOR flash.
+///
+/// See the [`crate::flashlog`] module for details.
+pub struct FlashFrameLog<F>
+where
+    F: NorFlash,
+{
+    flash: F,
+    start: u32,
+    end: u32,
+    append_offset: u32,
+    torn: bool,
+}
+
+impl<F> FlashFrameLog<F>
+where
+    F: NorFlash,
+{
+    /// Open a log in the region of the flash from offset `start` to `end`, which must be aligned
+    /// to the flash's erase size.
+    ///
+    /// The region is scanned to find the end of the log, and whether it ends with a torn record.
+    /// A new region should be erased, with [`erase()`](FlashFrameLog::erase), before use.
+    pub fn open(
+        mut flash: F,
+        start: u32,
+        end: u32,
+    ) -> Result<FlashFrameLog<F>, FlashLogError<F::Error>> {
+        if !start.is_multiple_of(F::ERASE_SIZE as u32)
+            || !end.is_multiple_of(F::ERASE_SIZE as u32)
+            || start > end
+            || end as usize > flash.capacity()
+            || !CHUNK_SIZE.is_multiple_of(F::READ_SIZE)
+            || !CHUNK_SIZE.is_multiple_of(F::WRITE_SIZE)
+        {
+            return Err(FlashLogError::NotAligned);
+        }
+
+        // Find the last written byte.
+        let mut chunk = [0_u8; CHUNK_SIZE];
+        let mut last_written = None;
+        let mut offset = start;
+        while offset < end {
+            let chunk_len = CHUNK_SIZE.min((end - offset) as usize);
+            flash
+                .read(offset, &mut chunk[..chunk_len])
+                .map_err(FlashLogError::Flash)?;
+            if let Some(i) = chunk[..chunk_len].iter().rposition(|x| *x != ERASED) {
+                last_written = Some((offset + i as u32, chunk[i]));
+            }
+            offset += chunk_len as u32;
+        }
+
+        let (append_offset, torn) = match last_written {
+            None => (start, false),
+            Some((written_offset, last_byte)) => (
+                align_up(written_offset + 1, F::WRITE_SIZE as u32),
+                last_byte != 0,
+            ),
+        };
+        Ok(FlashFrameLog {
+            flash,
+            start,
+            end,
+            append_offset,
+            torn,
+        })
+    }
+
+    /// Append a record of the frame data.
+    ///
+    /// [`FlashLogError::Full`] is returned if there is not enough free space for the record. If
+    /// writing fails, the record is treated as torn.
+    pub fn append(&mut self, frame: &[u8]) -> Result<(), FlashLogError<F::Error>> {
+        let crc = crc32(frame).to_le_bytes();
+        let leading_len = self.torn as usize;
+        let record_len = leading_len + cobs::encode_output_size_iter(frame.iter().chain(&crc)) + 1;
+        let padded_len = align_up(record_len as u32, F::WRITE_SIZE as u32);
+        if padded_len > self.end - self.append_offset {
+            return Err(FlashLogError::Full);
+        }
+
+        let mut record = core::iter::repeat_n(0, leading_len)
+            .chain(cobs::encode_iter(frame.iter().chain(&crc)))
+            .chain(core::iter::repeat(0));
+        let mut chunk = [0_u8; CHUNK_SIZE];
+        let record_end = self.append_offset + padded_len;
+        while self.append_offset < record_end {
+            let chunk_len = CHUNK_SIZE.min((record_end - self.append_offset) as usize);
+            for (chunk_byte, record_byte) in chunk[..chunk_len].iter_mut().zip(&mut record) {
+                *chunk_byte = record_byte;
+            }
+            let result = self.flash.write(self.append_offset, &chunk[..chunk_len]);
+            self.append_offset += chunk_len as u32;
+            if let Err(e) = result {
+                self.torn = true;
+                return Err(FlashLogError::Flash(e));
+            }
+        }
+        self.torn = false;
+        Ok(())
+    }
+
+    /// Erase the log region, removing all records.
+    pub fn erase(&mut self) -> Result<(), FlashLogError<F::Error>> {
+        self.flash
+            .erase(self.start, self.end)
+            .map_err(FlashLogError::Flash)?;
+        self.append_offset = self.start;
+        self.torn = false;
+        Ok(())
+    }
+
+    /// Get a reader of the records of the log, which can decode records of up to `N - 4` bytes.
+    pub fn reader<const N: usize>(&mut self) -> FlashLogReader<'_, F, N> {
+        let mut accumulator = cobs::FrameAccumulator::new();
+        accumulator.set_empty_frames(crate::EmptyFrames::Surface);
+        FlashLogReader {
+            flash: &mut self.flash,
+            offset: self.start,
+            end: self.append_offset,
+            accumulator,
+            chunk: [0; CHUNK_SIZE],
+            chunk_i: 0,
+            chunk_len: 0,
+            partial_len: 0,
+            torn_len: 0,
+        }
+    }
+
+    /// Get the number of bytes of the log region that are used by records.
+    pub fn used_bytes(&self) -> u32 {
+        self.append_offset - self.start
+    }
+
+    /// Get the number of bytes of the log region that are free for more records.
+    pub fn free_bytes(&self) -> u32 {
+        self.end - self.append_offset
+    }
+
+    /// Returns `true` if the log ends with a torn record.
+    pub fn is_torn(&self) -> bool {
+        self.torn
+    }
+
+    /// Consume the log, returning the flash.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+}
+
+/// Reads the records of a [`FlashFrameLog`], made by [`FlashFrameLog::reader()`].
+///
+/// Each record is decoded into an internal buffer of `N` bytes, which holds the frame data and its
+/// 4-byte CRC.
+pub struct FlashLogReader<'a, F, const N: usize>
+where
+    F: NorFlash,
+{
+    flash: &'a mut F,
+    offset: u32,
+    end: u32,
+    accumulator: cobs::FrameAccumulator<N>,
+    chunk: [u8; CHUNK_SIZE],
+    chunk_i: usize,
+    chunk_len: usize,
+    partial_len: usize,
+    torn_len: usize,
+}
+
+impl<'a, F, const N: usize> FlashLogReader<'a, F, N>
+where
+    F: NorFlash,
+{
+    /// Read the next record.
+    ///
+    /// The return value is [`None`] at the end of the log. Otherwise it is a [`Result`] that in the
+    /// [`Ok`] case is a slice of the record's frame data, in the internal buffer.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`FlashLogError::Flash`], from reading the flash. Reading stops.
+    /// * [`FlashLogError::Record`], for a damaged record. Reading continues with the next record.
+    pub fn next_record(&mut self) -> Option<Result<&[u8], FlashLogError<F::Error>>> {
+        loop {
+            if self.chunk_i == self.chunk_len {
+                if self.offset >= self.end {
+                    // A partial record at the end of the log is torn.
+                    self.torn_len = self.partial_len;
+                    self.partial_len = 0;
+                    self.accumulator.clear();
+                    return None;
+                }
+                self.chunk_len = CHUNK_SIZE.min((self.end - self.offset) as usize);
+                self.chunk_i = 0;
+                if let Err(e) = self
+                    .flash
+                    .read(self.offset, &mut self.chunk[..self.chunk_len])
+                {
+                    self.offset = self.end;
+                    self.chunk_len = 0;
+                    return Some(Err(FlashLogError::Flash(e)));
+                }
+                self.offset += self.chunk_len as u32;
+            }
+
+            let in_buf = &self.chunk[self.chunk_i..self.chunk_len];
+            let consumed = self.accumulator.feed(in_buf);
+            self.chunk_i += consumed;
+            if in_buf[..consumed].last() == Some(&0) {
+                let frame_len = self.partial_len + consumed - 1;
+                self.partial_len = 0;
+                if frame_len != 0 {
+                    break;
+                }
+                // Skip padding.
+                let _ = self.accumulator.poll_frame();
+            } else {
+                self.partial_len += consumed;
+            }
+        }
+
+        let record = match self.accumulator.poll_frame()? {
+            Ok(record) => record,
+            Err(e) => return Some(Err(FlashLogError::Record(e))),
+        };
+        Some(check_crc(record).map_err(FlashLogError::Record))
+    }
+
+    /// Get the length of a torn final record, which has no delimiter. This is 0 if the log doesn't
+    /// end with a torn record, or if the end of the log hasn't been reached yet.
+    pub fn torn_len(&self) -> usize {
+        self.torn_len
+    }
+}
+
+/// Check the CRC at the end of a decoded record, returning the frame data.
+fn check_crc(record: &[u8]) -> Result<&[u8], Error> {
+    if record.len() < 4 {
+        return Err(Error::ChecksumMismatch);
+    }
+    let (frame, crc) = record.split_at(record.len() - 4);
+    if crc != crc32(frame).to_le_bytes() {
+        return Err(Error::ChecksumMismatch);
+    }
+    Ok(frame)
+}
+
+fn align_up(value: u32, align: u32) -> u32 {
+    value.div_ceil(align) * align
+}
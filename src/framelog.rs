@@ -0,0 +1,344 @@
+//! Append-only log of frames in a file, for a simple durable journal.
+//!
+//! A [`FrameLogWriter`] appends records to a file. A [`FrameLogReader`] iterates the records of a
+//! log. Each record is the frame data followed by its CRC-32 (see [`Crc32`]), COBS-encoded, and
+//! followed by a zero-byte delimiter. So record boundaries are self-synchronising: a damaged
+//! record doesn't affect the records after it.
+//!
+//! If writing was interrupted, such as by a power failure, the log can end with a torn record,
+//! which has no delimiter. The reader ignores a torn final record, and reports its length with
+//! [`FrameLogReader::torn_len()`]. The writer ends a torn record with a delimiter when it opens the
+//! log, so appended records are not affected by it.
+//!
+//! Records that are damaged, so they fail to decode or their CRC doesn't match, are returned by
+//! the reader as errors of kind [`io::ErrorKind::InvalidData`], which wrap the [`Error`]. Reading
+//! continues with the next record.
+//!
+//! Example:
+//!
+//!     use cobs2::framelog::{FrameLogReader, FrameLogWriter, SyncPolicy};
+//!
+//!     let path = std::env::temp_dir().join("cobs2-framelog-example.log");
+//!     # let _ = std::fs::remove_file(&path);
+//!     let mut writer = FrameLogWriter::open(&path, SyncPolicy::EachRecord).unwrap();
+//!     writer.append(b"ABC\0ghij\0xyz").unwrap();
+//!     writer.append(b"second").unwrap();
+//!
+//!     let reader = FrameLogReader::open(&path).unwrap();
+//!     let records: Vec<Vec<u8>> = reader.map(|record| record.unwrap()).collect();
+//!     assert_eq!(records, [&b"ABC\0ghij\0xyz"[..], b"second"]);
+//!     # std::fs::remove_file(&path).unwrap();
+//!
+//! ### Recovery scanning
+//!
+//! [`scan()`] scans arbitrary bytes in the record format, such as a damaged log file, or a dumped
+//! flash region of the `flashlog` module (feature `embedded-storage`). It finds the candidate
+//! records between delimiters, checks their CRCs, and reports both the recovered records and the
+//! damaged regions, as [`ScanEntry`]s. Regions of erased flash (`0xFF` bytes) are reported
+//! separately, so they aren't mistaken for damage.
+//!
+//! Example:
+//!
+//!     use cobs2::framelog::{self, ScanEntry};
+//!
+//!     let mut dump = framelog::encode_record(b"first").unwrap();
+//!     dump.extend_from_slice(b"\x07garbage\x00");
+//!     dump.extend(framelog::encode_record(b"second").unwrap());
+//!     dump.extend_from_slice(&[0xFF; 16]);
+//!
+//!     let entries: Vec<ScanEntry> = framelog::scan(&dump).collect();
+//!     assert_eq!(entries.len(), 4);
+//!     assert_eq!(entries[0], ScanEntry::Record { range: 0..11, data: b"first".to_vec() });
+//!     assert!(matches!(entries[1], ScanEntry::Damaged { .. }));
+//!     assert_eq!(entries[1].range(), 11..20);
+//!     assert_eq!(entries[2], ScanEntry::Record { range: 20..32, data: b"second".to_vec() });
+//!     assert_eq!(entries[3], ScanEntry::Erased { range: 32..48 });
+
+use crate::pipeline::{Crc32, Stage};
+use crate::{cobs, Error, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::Path;
+use std::vec::Vec;
+
+/// When a [`FrameLogWriter`] syncs appended records to storage, with [`File::sync_data()`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyncPolicy {
+    /// Sync after each record, so a record is durable when [`FrameLogWriter::append()`] returns.
+    EachRecord,
+    /// Sync after every given number of records.
+    EveryRecords(usize),
+    /// Only sync when [`FrameLogWriter::sync()`] is called. Otherwise the operating system
+    /// decides when data is written to storage.
+    Explicit,
+}
+
+/// Encode a record of the frame log format: the data followed by its CRC-32, COBS-encoded, and
+/// followed by a zero-byte delimiter.
+pub fn encode_record(data: &[u8]) -> Result<Vec<u8>> {
+    let mut record = cobs::encode_vector(&Crc32.encode(data.to_vec())?)?;
+    record.push(0);
+    Ok(record)
+}
+
+/// Decode a record of the frame log format, without its zero-byte delimiter, checking its CRC.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::ZeroInEncodedData`]
+/// * [`Error::TruncatedEncodedData`]
+/// * [`Error::ChecksumMismatch`]
+pub fn decode_record(in_buf: &[u8]) -> Result<Vec<u8>> {
+    Crc32.decode(cobs::decode_vector(in_buf)?)
+}
+
+/// Appends records to a frame log file.
+///
+/// See the [`crate::framelog`] module for details.
+pub struct FrameLogWriter {
+    file: File,
+    policy: SyncPolicy,
+    unsynced: usize,
+}
+
+impl FrameLogWriter {
+    /// Open a frame log file for appending, creating it if it doesn't exist.
+    ///
+    /// If the log ends with a torn record, a zero-byte delimiter is appended to end it.
+    pub fn open<P>(path: P, policy: SyncPolicy) -> io::Result<FrameLogWriter>
+    where
+        P: AsRef<Path>,
+    {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        if file.seek(SeekFrom::End(0))? > 0 {
+            let mut last_byte = [0_u8];
+            file.seek(SeekFrom::End(-1))?;
+            file.read_exact(&mut last_byte)?;
+            if last_byte[0] != 0 {
+                file.write_all(&[0])?;
+                file.sync_data()?;
+            }
+        }
+        Ok(FrameLogWriter {
+            file,
+            policy,
+            unsynced: 0,
+        })
+    }
+
+    /// Append a record of the frame data, then sync if the sync policy says so.
+    ///
+    /// The record is written in one write call, so an interrupted write can only tear the end of
+    /// the log.
+    pub fn append(&mut self, frame: &[u8]) -> io::Result<()> {
+        let record = encode_record(frame).map_err(to_io_error)?;
+        self.file.write_all(&record)?;
+        self.unsynced += 1;
+        match self.policy {
+            SyncPolicy::EachRecord => self.sync(),
+            SyncPolicy::EveryRecords(count) if self.unsynced >= count => self.sync(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sync appended records to storage.
+    pub fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Consume the writer, returning the file. Records that have not been synced are not synced.
+    pub fn into_inner(self) -> File {
+        self.file
+    }
+}
+
+/// Reads the records of a frame log, as an iterator of each record's frame data.
+///
+/// See the [`crate::framelog`] module for details.
+pub struct FrameLogReader<R>
+where
+    R: Read,
+{
+    reader: BufReader<R>,
+    record_buf: Vec<u8>,
+    torn_len: usize,
+}
+
+impl FrameLogReader<File> {
+    /// Open a frame log file for reading.
+    pub fn open<P>(path: P) -> io::Result<FrameLogReader<File>>
+    where
+        P: AsRef<Path>,
+    {
+        Ok(FrameLogReader::new(File::open(path)?))
+    }
+}
+
+impl<R> FrameLogReader<R>
+where
+    R: Read,
+{
+    /// Create a reader of a frame log, from any reader.
+    pub fn new(reader: R) -> FrameLogReader<R> {
+        FrameLogReader {
+            reader: BufReader::new(reader),
+            record_buf: Vec::new(),
+            torn_len: 0,
+        }
+    }
+
+    /// Get the length of a torn final record, which has no delimiter. This is 0 if the log
+    /// doesn't end with a torn record, or if the end of the log hasn't been reached yet.
+    pub fn torn_len(&self) -> usize {
+        self.torn_len
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        loop {
+            self.record_buf.clear();
+            self.reader.read_until(0, &mut self.record_buf)?;
+            match self.record_buf.split_last() {
+                None => return Ok(None),
+                Some((0, [])) => continue,
+                Some((0, record)) => return decode_record(record).map(Some).map_err(to_io_error),
+                Some(_) => {
+                    self.torn_len = self.record_buf.len();
+                    return Ok(None);
+                }
+            }
+        }
+    }
+}
+
+/// Implement trait [Iterator].
+impl<R> Iterator for FrameLogReader<R>
+where
+    R: Read,
+{
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
+
+/// Value of an erased flash byte.
+const ERASED: u8 = 0xFF;
+
+/// One region of the input data found by [`scan()`].
+///
+/// Each range is of the input data. The ranges of the entries are in order, and a record's or
+/// damaged region's range includes its zero-byte delimiter. Padding delimiters between records
+/// are not reported.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ScanEntry {
+    /// A valid record, with its frame data.
+    Record { range: Range<usize>, data: Vec<u8> },
+    /// A damaged record, which can't be decoded, or whose CRC doesn't match.
+    Damaged { range: Range<usize>, error: Error },
+    /// A torn record, which was not completely written: a final record which has no delimiter, or
+    /// a record which is followed by erased flash.
+    Torn { range: Range<usize> },
+    /// Erased flash, which is free space.
+    Erased { range: Range<usize> },
+}
+
+impl ScanEntry {
+    /// Get the range of the input data of the entry.
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            ScanEntry::Record { range, .. }
+            | ScanEntry::Damaged { range, .. }
+            | ScanEntry::Torn { range }
+            | ScanEntry::Erased { range } => range.clone(),
+        }
+    }
+}
+
+/// Scan arbitrary bytes in the frame log record format, for recovery.
+///
+/// The return value is an iterator of [`ScanEntry`]s. See the [`crate::framelog`] module for
+/// details.
+pub fn scan(in_buf: &[u8]) -> Scan<'_> {
+    Scan {
+        in_buf,
+        in_i: 0,
+        erased: None,
+    }
+}
+
+/// Iterator of the regions of a recovery scan, made by [`scan()`].
+pub struct Scan<'a> {
+    in_buf: &'a [u8],
+    in_i: usize,
+    erased: Option<Range<usize>>,
+}
+
+/// Implement trait [Iterator].
+impl<'a> Iterator for Scan<'a> {
+    type Item = ScanEntry;
+
+    fn next(&mut self) -> Option<ScanEntry> {
+        if let Some(range) = self.erased.take() {
+            return Some(ScanEntry::Erased { range });
+        }
+        // Skip padding delimiters.
+        while self.in_buf.get(self.in_i) == Some(&0) {
+            self.in_i += 1;
+        }
+        let start = self.in_i;
+        let candidate = self.in_buf.get(start..)?;
+        if candidate.is_empty() {
+            return None;
+        }
+        let (candidate_len, delimiter) = match candidate.iter().position(|x| *x == 0) {
+            Some(zero_i) => (zero_i, true),
+            None => (candidate.len(), false),
+        };
+        let candidate = &candidate[..candidate_len];
+        let end = start + candidate_len + delimiter as usize;
+        self.in_i = end;
+
+        let error = match (delimiter, decode_record(candidate)) {
+            (true, Ok(data)) => {
+                return Some(ScanEntry::Record {
+                    range: start..end,
+                    data,
+                })
+            }
+            (_, Err(error)) => error,
+            (false, Ok(_)) => Error::TruncatedEncodedData,
+        };
+        // Separate erased flash from the end of a torn record.
+        let written_len = candidate
+            .iter()
+            .rposition(|x| *x != ERASED)
+            .map_or(0, |i| i + 1);
+        if written_len == 0 {
+            Some(ScanEntry::Erased { range: start..end })
+        } else if written_len < candidate_len {
+            self.erased = Some(start + written_len..end);
+            Some(ScanEntry::Torn {
+                range: start..start + written_len,
+            })
+        } else if !delimiter {
+            Some(ScanEntry::Torn { range: start..end })
+        } else {
+            Some(ScanEntry::Damaged {
+                range: start..end,
+                error,
+            })
+        }
+    }
+}
+
+fn to_io_error(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
@@ -0,0 +1,50 @@
+//! Common functions for converting between bytes and hex strings.
+
+use crate::{Error, Result};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Convert bytes to a string of upper-case hex byte values, separated by spaces.
+pub(crate) fn to_hex(in_buf: &[u8]) -> String {
+    let mut out_str = String::with_capacity(in_buf.len() * 3);
+    for (i, x) in in_buf.iter().enumerate() {
+        if i != 0 {
+            out_str.push(' ');
+        }
+        out_str.push(HEX_DIGITS[(x >> 4) as usize] as char);
+        out_str.push(HEX_DIGITS[(x & 0x0F) as usize] as char);
+    }
+    out_str
+}
+
+/// Convert a single hex digit character to its value.
+fn hex_digit_value(c: u8) -> Result<u8> {
+    match c {
+        b'0'..=b'9' => Ok(c - b'0'),
+        b'A'..=b'F' => Ok(c - b'A' + 10),
+        b'a'..=b'f' => Ok(c - b'a' + 10),
+        _ => Err(Error::InvalidHexData),
+    }
+}
+
+/// Convert a string of hex byte values to bytes.
+///
+/// Upper-case and lower-case hex digits are accepted. Whitespace is permitted between byte values,
+/// but not within a byte value. Byte values may also be written without any separating
+/// whitespace.
+pub(crate) fn from_hex(in_str: &str) -> Result<Vec<u8>> {
+    let mut out_vec = Vec::with_capacity(in_str.len() / 2);
+
+    for group in in_str.split_ascii_whitespace() {
+        let group = group.as_bytes();
+        if group.len() % 2 != 0 {
+            return Err(Error::InvalidHexData);
+        }
+        for pair in group.chunks(2) {
+            out_vec.push((hex_digit_value(pair[0])? << 4) | hex_digit_value(pair[1])?);
+        }
+    }
+    Ok(out_vec)
+}
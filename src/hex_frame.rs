@@ -0,0 +1,260 @@
+//! Hex formatting of encoded data, and annotated hexdumps, for debugging.
+
+use core::fmt;
+
+/// Wrapper for a slice of COBS or COBS/R encoded data, to format it as hex for debugging.
+///
+/// Byte values are formatted as upper-case hex, separated by spaces. Length code bytes are
+/// enclosed in square brackets. A zero-byte in the data is regarded as a frame delimiter, so the
+/// byte following it is formatted as a length code byte.
+///
+/// No allocation is needed, so this is suitable for log messages on `no_std` targets.
+/// [fmt::Display] and [fmt::Debug] give the same output.
+///
+/// Example:
+///
+///     let data_cobs = b"\x03\x2F\xA2\x04\x92\x73\x02";
+///     let text = format!("{}", cobs2::HexFrame(data_cobs));
+///     assert_eq!(text, "[03] 2F A2 [04] 92 73 02");
+///
+#[derive(Clone, Copy)]
+pub struct HexFrame<'a>(pub &'a [u8]);
+
+/// Implement trait [fmt::Display].
+impl fmt::Display for HexFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut code_i = 0;
+
+        for (i, x) in self.0.iter().enumerate() {
+            if i != 0 {
+                write!(f, " ")?;
+            }
+            if *x == 0 {
+                // Delimiter. Expect a length code byte next.
+                code_i = i + 1;
+                write!(f, "00")?;
+            } else if i == code_i {
+                code_i += *x as usize;
+                write!(f, "[{:02X}]", x)?;
+            } else {
+                write!(f, "{:02X}", x)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Implement trait [fmt::Debug].
+impl fmt::Debug for HexFrame<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Wrapper for a slice of COBS or COBS/R encoded data, to format it as an annotated hexdump, for
+/// explaining and debugging encoding.
+///
+/// The data is formatted in lines of 16 bytes. Each line has three rows:
+///
+/// * The offset, then the encoded bytes, as upper-case hex.
+/// * Markers: `^^` under each length code byte, and `||` under each frame delimiter.
+/// * The decoded data, aligned with the encoded bytes it comes from. A length code byte decodes
+///   to the `00` that ends the previous block, if there is one. For COBS/R, the final length code
+///   byte of a frame can also be its last data byte, which is shown under the delimiter. `??` is
+///   shown under the delimiter of a truncated COBS frame.
+///
+/// With [`colour()`](HexDump::colour), length code bytes, delimiters and errors are also
+/// highlighted with ANSI colour codes, for a terminal.
+///
+/// No allocation is needed.
+///
+/// Example:
+///
+///     let text = cobs2::HexDump::new(b"\x03AB\x02C\x00").to_string();
+///     let mut lines = text.lines();
+///     assert_eq!(lines.next(), Some("00000000  03 41 42 02 43 00"));
+///     assert_eq!(lines.next(), Some("          ^^       ^^    ||"));
+///     assert_eq!(lines.next(), Some("             41 42 00 43"));
+///
+#[derive(Clone, Copy)]
+pub struct HexDump<'a> {
+    data: &'a [u8],
+    cobsr: bool,
+    colour: bool,
+    offset: usize,
+}
+
+const HEXDUMP_WIDTH: usize = 16;
+const ANSI_CODE: &str = "\x1b[1;33m";
+const ANSI_DELIMITER: &str = "\x1b[36m";
+const ANSI_ERROR: &str = "\x1b[1;31m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+impl<'a> HexDump<'a> {
+    /// Create a hexdump of COBS-encoded data.
+    pub const fn new(data: &'a [u8]) -> HexDump<'a> {
+        HexDump {
+            data,
+            cobsr: false,
+            colour: false,
+            offset: 0,
+        }
+    }
+
+    /// Create a hexdump of COBS/R-encoded data.
+    pub const fn new_cobsr(data: &'a [u8]) -> HexDump<'a> {
+        HexDump {
+            data,
+            cobsr: true,
+            colour: false,
+            offset: 0,
+        }
+    }
+
+    /// Set whether to highlight with ANSI colour codes.
+    pub const fn colour(mut self, colour: bool) -> HexDump<'a> {
+        self.colour = colour;
+        self
+    }
+
+    /// Set the offset shown for the first byte, such as its position in a file.
+    pub const fn offset(mut self, offset: usize) -> HexDump<'a> {
+        self.offset = offset;
+        self
+    }
+
+    fn write_cell(&self, f: &mut fmt::Formatter, text: fmt::Arguments, ansi: &str) -> fmt::Result {
+        if self.colour && !ansi.is_empty() {
+            write!(f, " {}{}{}", ansi, text, ANSI_RESET)
+        } else {
+            write!(f, " {}", text)
+        }
+    }
+}
+
+/// The role of a byte in a hexdump, and what it decodes to.
+#[derive(Clone, Copy)]
+struct HexDumpCell {
+    byte: u8,
+    role: HexDumpRole,
+    decoded: Option<u8>,
+    error: bool,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum HexDumpRole {
+    Code,
+    Data,
+    Delimiter,
+}
+
+/// Implement trait [fmt::Display].
+impl fmt::Display for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut remaining = 0_u8;
+        let mut last_code = None;
+        let mut cells = [HexDumpCell {
+            byte: 0,
+            role: HexDumpRole::Data,
+            decoded: None,
+            error: false,
+        }; HEXDUMP_WIDTH];
+
+        for (line_i, line) in self.data.chunks(HEXDUMP_WIDTH).enumerate() {
+            for (cell, &byte) in cells.iter_mut().zip(line) {
+                *cell = if byte == 0 {
+                    // Delimiter. A COBS/R frame can end within a block, if its final length code
+                    // byte is also its last data byte.
+                    let cobsr_last = if self.cobsr && remaining != 0 {
+                        last_code
+                    } else {
+                        None
+                    };
+                    let error = !self.cobsr && remaining != 0;
+                    remaining = 0;
+                    last_code = None;
+                    HexDumpCell {
+                        byte,
+                        role: HexDumpRole::Delimiter,
+                        decoded: cobsr_last,
+                        error,
+                    }
+                } else if remaining == 0 {
+                    // Length code byte, which ends the previous block with a zero, unless it was
+                    // the first block of the frame, or a block of maximum length.
+                    let decoded = match last_code {
+                        Some(code) if code != 0xFF => Some(0),
+                        _ => None,
+                    };
+                    remaining = byte - 1;
+                    last_code = Some(byte);
+                    HexDumpCell {
+                        byte,
+                        role: HexDumpRole::Code,
+                        decoded,
+                        error: false,
+                    }
+                } else {
+                    remaining -= 1;
+                    HexDumpCell {
+                        byte,
+                        role: HexDumpRole::Data,
+                        decoded: Some(byte),
+                        error: false,
+                    }
+                };
+            }
+            let cells = &cells[..line.len()];
+
+            write!(f, "{:08X} ", self.offset + line_i * HEXDUMP_WIDTH)?;
+            for cell in cells {
+                let ansi = match cell.role {
+                    HexDumpRole::Code => ANSI_CODE,
+                    HexDumpRole::Delimiter => ANSI_DELIMITER,
+                    HexDumpRole::Data => "",
+                };
+                self.write_cell(f, format_args!("{:02X}", cell.byte), ansi)?;
+            }
+            writeln!(f)?;
+
+            let marked = cells
+                .iter()
+                .rposition(|cell| cell.role != HexDumpRole::Data);
+            if let Some(marked) = marked {
+                write!(f, "         ")?;
+                for cell in &cells[..=marked] {
+                    let text = match cell.role {
+                        HexDumpRole::Code => "^^",
+                        HexDumpRole::Delimiter => "||",
+                        HexDumpRole::Data => "  ",
+                    };
+                    write!(f, " {}", text)?;
+                }
+            }
+            writeln!(f)?;
+
+            let decoded = cells
+                .iter()
+                .rposition(|cell| cell.decoded.is_some() || cell.error);
+            if let Some(decoded) = decoded {
+                write!(f, "         ")?;
+                for cell in &cells[..=decoded] {
+                    match (cell.decoded, cell.error) {
+                        (_, true) => self.write_cell(f, format_args!("??"), ANSI_ERROR)?,
+                        (Some(byte), _) => write!(f, " {:02X}", byte)?,
+                        (None, _) => write!(f, "   ")?,
+                    }
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Implement trait [fmt::Debug].
+impl fmt::Debug for HexDump<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
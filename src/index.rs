@@ -0,0 +1,290 @@
+//! Indexes of the frames in large recordings, for random access by frame number.
+//!
+//! Finding the `n`th frame of a recording of encoded frames needs a scan from the start, because
+//! frames have varying lengths. A [`FrameIndex`] is built by scanning a recording once, and holds
+//! the byte offset and length of each frame. Then any frame can be found in constant time, and the
+//! frame containing a byte offset can be found by binary search.
+//!
+//! * [`FrameIndex::scan_stream()`] indexes a stream of encoded frames, each followed by a
+//!   zero-byte delimiter.
+//! * [`FrameIndex::scan_capture()`] indexes a capture, of the [`crate::capture`] format. Each
+//!   entry is the frame data of a record, not including the record's timestamps and length.
+//!
+//! Both scan any [`std::io::Read`], such as a file or a slice. With the index, a frame can be read
+//! from a file with [`FrameIndex::read_frame()`], or taken from a slice or memory map with
+//! [`FrameIndex::frame()`].
+//!
+//! Example:
+//!
+//!     use cobs2::index::FrameIndex;
+//!
+//!     let stream = b"\x04ABC\x00\x00\x03xy\x00\x02z\x00";
+//!     let index = FrameIndex::scan_stream(&stream[..]).unwrap();
+//!     assert_eq!(index.len(), 3);
+//!     assert_eq!(index.frame(stream, 1), Some(&b"\x03xy"[..]));
+//!     assert_eq!(index.frame_containing(11), Some(2));
+//!
+//!     let mut file = std::io::Cursor::new(stream);
+//!     let mut frame = Vec::new();
+//!     index.read_frame(&mut file, 2, &mut frame).unwrap();
+//!     assert_eq!(frame, b"\x02z");
+//!
+//! ### Last frames
+//!
+//! To see only the most recent frames of a stream, such as the last messages before a crash,
+//! [`last_frames()`] scans backwards from the end of the stream to find their delimiters, and
+//! decodes them, without reading the rest of the stream or building an index.
+
+use crate::capture::{HEADER, VERSION};
+use crate::profile::Profile;
+use crate::Result;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::vec::Vec;
+
+/// The position of a frame in a recording.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct FrameSpan {
+    /// The offset of the first byte of the frame, from the start of the recording.
+    pub offset: u64,
+    /// The length of the frame, in bytes, not including any delimiter.
+    pub len: u64,
+}
+
+impl FrameSpan {
+    /// The offset just after the last byte of the frame.
+    pub fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+/// An index of the frames in a recording.
+///
+/// See the [`crate::index`] module for details.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FrameIndex {
+    spans: Vec<FrameSpan>,
+}
+
+impl FrameIndex {
+    /// Index a stream of encoded frames, each followed by a zero-byte delimiter.
+    ///
+    /// Empty frames are skipped. A partial frame at the end of the stream, without a delimiter,
+    /// is indexed too.
+    ///
+    /// The return value is an [`io::Result`], which is an error if reading the stream failed.
+    pub fn scan_stream<R>(mut reader: R) -> io::Result<FrameIndex>
+    where
+        R: Read,
+    {
+        let mut spans = Vec::new();
+        let mut buf = vec![0_u8; 64 * 1024];
+        let mut offset = 0_u64;
+        let mut frame_start = 0_u64;
+        loop {
+            let len = match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            for (i, _) in buf[..len].iter().enumerate().filter(|(_, &x)| x == 0) {
+                let frame_end = offset + i as u64;
+                if frame_end > frame_start {
+                    spans.push(FrameSpan {
+                        offset: frame_start,
+                        len: frame_end - frame_start,
+                    });
+                }
+                frame_start = frame_end + 1;
+            }
+            offset += len as u64;
+        }
+        if offset > frame_start {
+            spans.push(FrameSpan {
+                offset: frame_start,
+                len: offset - frame_start,
+            });
+        }
+        Ok(FrameIndex { spans })
+    }
+
+    /// Index a capture, of the [`crate::capture`] format. Each entry is the frame data of a
+    /// record.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the capture.
+    /// * [`io::ErrorKind::InvalidData`], if the capture header is not valid.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the capture ends with an incomplete record.
+    pub fn scan_capture<R>(mut reader: R) -> io::Result<FrameIndex>
+    where
+        R: Read,
+    {
+        let mut header = [0_u8; 10];
+        reader.read_exact(&mut header)?;
+        if header[..8] != HEADER[..] || header[8..] != VERSION.to_le_bytes() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a supported capture",
+            ));
+        }
+        let mut spans = Vec::new();
+        let mut offset = header.len() as u64;
+        loop {
+            let mut record_header = [0_u8; 20];
+            // Check for the end of the capture, at a record boundary.
+            let header_len = match reader.read(&mut record_header) {
+                Ok(header_len) => header_len,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if header_len == 0 {
+                break;
+            }
+            reader.read_exact(&mut record_header[header_len..])?;
+            let mut frame_len = [0_u8; 4];
+            frame_len.copy_from_slice(&record_header[16..]);
+            let len = u32::from_le_bytes(frame_len) as u64;
+            let data_len = io::copy(&mut reader.by_ref().take(len), &mut io::sink())?;
+            if data_len < len {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+            offset += record_header.len() as u64;
+            spans.push(FrameSpan { offset, len });
+            offset += len;
+        }
+        Ok(FrameIndex { spans })
+    }
+
+    /// Get the number of frames.
+    pub fn len(&self) -> usize {
+        self.spans.len()
+    }
+
+    /// Returns `true` if there are no frames.
+    pub fn is_empty(&self) -> bool {
+        self.spans.is_empty()
+    }
+
+    /// Get the position of frame number `n`, counting from 0.
+    pub fn get(&self, n: usize) -> Option<FrameSpan> {
+        self.spans.get(n).copied()
+    }
+
+    /// Get the positions of all the frames, in order.
+    pub fn spans(&self) -> &[FrameSpan] {
+        &self.spans
+    }
+
+    /// Find the number of the frame which contains the byte at the given offset in the recording,
+    /// by binary search. Returns `None` if the offset is in a delimiter or a record header, or
+    /// outside the recording.
+    pub fn frame_containing(&self, offset: u64) -> Option<usize> {
+        let n = self.spans.partition_point(|span| span.end() <= offset);
+        self.spans
+            .get(n)
+            .filter(|span| span.offset <= offset)
+            .map(|_| n)
+    }
+
+    /// Get frame number `n` from the recording's data, such as a slice or a memory map.
+    ///
+    /// Returns `None` if there is no frame `n`, or if it is outside the data.
+    pub fn frame<'a>(&self, data: &'a [u8], n: usize) -> Option<&'a [u8]> {
+        let span = self.spans.get(n)?;
+        let start = usize::try_from(span.offset).ok()?;
+        let end = usize::try_from(span.end()).ok()?;
+        data.get(start..end)
+    }
+
+    /// Read frame number `n` from the recording into `frame`, seeking to its offset.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from seeking or reading the reader.
+    /// * [`io::ErrorKind::InvalidInput`], if there is no frame `n`.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the recording ends before the end of the frame.
+    pub fn read_frame<R>(&self, reader: &mut R, n: usize, frame: &mut Vec<u8>) -> io::Result<()>
+    where
+        R: Read + Seek,
+    {
+        let span = self
+            .get(n)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no such frame"))?;
+        let len = usize::try_from(span.len)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame is too long"))?;
+        reader.seek(SeekFrom::Start(span.offset))?;
+        frame.clear();
+        frame.resize(len, 0);
+        reader.read_exact(frame)
+    }
+}
+
+/// Decode the last `n` frames of a stream of encoded frames, each followed by a zero-byte
+/// delimiter, scanning backwards from the end of the stream.
+///
+/// Only the end of the stream is read, in chunks from the end backwards, until `n` frames are
+/// found, so this is quick even for a very large file. A slice can be scanned with a
+/// [`std::io::Cursor`].
+///
+/// Empty frames are skipped. A partial frame at the end of the stream, without a delimiter, such
+/// as a frame that was being written during a crash, is skipped too.
+///
+/// The return value is an [`io::Result`], which is an error if seeking or reading the stream
+/// failed. In the [`Ok`] case it is the result of decoding each frame with the framing profile,
+/// for up to `n` frames, oldest first.
+///
+/// Example:
+///
+///     use cobs2::index::last_frames;
+///     use cobs2::profile::Profile;
+///     use std::io::Cursor;
+///
+///     let mut stream = Cursor::new(b"\x04ABC\x00\x03xy\x00\x02z\x00\x03p");
+///     let frames = last_frames(&mut stream, Profile::Cobs, 2).unwrap();
+///     assert_eq!(frames, [Ok(b"xy".to_vec()), Ok(b"z".to_vec())]);
+///
+pub fn last_frames<R>(
+    reader: &mut R,
+    profile: Profile,
+    n: usize,
+) -> io::Result<Vec<Result<Vec<u8>>>>
+where
+    R: Read + Seek,
+{
+    let mut frames_encoded: Vec<Vec<u8>> = Vec::new();
+    // The bytes of the frame after the current scan position, in reverse order.
+    let mut frame_rev = Vec::new();
+    // Whether a delimiter has been found, so the frame after the scan position is complete.
+    let mut delimited = false;
+    let mut buf = vec![0_u8; 64 * 1024];
+    let mut pos = reader.seek(SeekFrom::End(0))?;
+    while pos > 0 && frames_encoded.len() < n {
+        let chunk_len = buf.len().min(usize::try_from(pos).unwrap_or(usize::MAX));
+        pos -= chunk_len as u64;
+        reader.seek(SeekFrom::Start(pos))?;
+        let chunk = &mut buf[..chunk_len];
+        reader.read_exact(chunk)?;
+        for (i, piece) in chunk.rsplit(|x| *x == 0).enumerate() {
+            if i > 0 {
+                // A delimiter is before the frame.
+                if delimited && !frame_rev.is_empty() && frames_encoded.len() < n {
+                    frames_encoded.push(frame_rev.iter().rev().copied().collect());
+                }
+                frame_rev.clear();
+                delimited = true;
+            }
+            frame_rev.extend(piece.iter().rev());
+        }
+    }
+    // The start of the stream is before the first frame.
+    if pos == 0 && delimited && !frame_rev.is_empty() && frames_encoded.len() < n {
+        frames_encoded.push(frame_rev.iter().rev().copied().collect());
+    }
+    Ok(frames_encoded
+        .iter()
+        .rev()
+        .map(|frame_encoded| profile.decode(frame_encoded))
+        .collect())
+}
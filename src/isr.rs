@@ -0,0 +1,207 @@
+//! Sharing of a decoder between an interrupt handler and thread context (feature
+//! `critical-section`).
+//!
+//! A typical bare-metal receive path has a UART interrupt handler which feeds received bytes to an
+//! incremental decoder, such as a [`cobs::FrameAccumulator`](crate::cobs::FrameAccumulator), and a
+//! main loop which takes the decoded frames. A [`StaticDecoder`] holds the decoder in a `static`,
+//! and gives access to it with [`StaticDecoder::with()`], inside a critical section, from either
+//! context. This uses the [`critical_section`] crate, so the target must provide a critical
+//! section implementation. See the `critical-section` documentation for details.
+//!
+//! Example:
+//!
+//!     use cobs2::cobs::FrameAccumulator;
+//!     use cobs2::isr::StaticDecoder;
+//!
+//!     static DECODER: StaticDecoder<FrameAccumulator<64>> =
+//!         StaticDecoder::new(FrameAccumulator::new());
+//!
+//!     // In the UART interrupt handler:
+//!     let received = b"\x04ABC\x00";
+//!     DECODER.with(|decoder| decoder.feed(received));
+//!
+//!     // In the main loop:
+//!     let mut frame = [0_u8; 64];
+//!     let frame_len = DECODER.with(|decoder| match decoder.poll_frame() {
+//!         Some(Ok(data)) => {
+//!             frame[..data.len()].copy_from_slice(data);
+//!             data.len()
+//!         }
+//!         _ => 0,
+//!     });
+//!     assert_eq!(&frame[..frame_len], b"ABC");
+//!
+//! ### Async tasks
+//!
+//! With an async executor, such as Embassy or RTIC, an [`AsyncStaticDecoder`] lets a task `await`
+//! the next frame, rather than polling in a loop. The interrupt handler feeds received bytes with
+//! [`AsyncStaticDecoder::feed_with()`], which wakes the waiting task, and the task waits with
+//! [`AsyncStaticDecoder::wait_with()`]. The task's waker is stored in the same critical section as
+//! the decoder, so a frame completed between the task's check and its wait is not missed.
+//!
+//! Example:
+//!
+//!     use cobs2::cobs::FrameAccumulator;
+//!     use cobs2::isr::AsyncStaticDecoder;
+//!
+//!     static DECODER: AsyncStaticDecoder<FrameAccumulator<64>> =
+//!         AsyncStaticDecoder::new(FrameAccumulator::new());
+//!
+//!     // In the UART interrupt handler:
+//!     DECODER.feed_with(|decoder| decoder.feed(b"\x04ABC\x00"));
+//!
+//!     // In an async task:
+//!     let frame = futures::executor::block_on(DECODER.wait_with(|decoder| {
+//!         decoder.poll_frame().map(|frame| frame.map(|data| data.len()))
+//!     }));
+//!     assert_eq!(frame, Ok(3));
+
+use core::cell::RefCell;
+use core::future::Future;
+use core::task::{Context, Poll, Waker};
+use critical_section::Mutex;
+
+/// A decoder in a `static`, which can be used from both an interrupt handler and thread context.
+///
+/// See the [`crate::isr`] module for details.
+pub struct StaticDecoder<D> {
+    decoder: Mutex<RefCell<D>>,
+}
+
+impl<D> StaticDecoder<D> {
+    /// Create a new static decoder, holding the given decoder.
+    pub const fn new(decoder: D) -> StaticDecoder<D> {
+        StaticDecoder {
+            decoder: Mutex::new(RefCell::new(decoder)),
+        }
+    }
+
+    /// Call the function with mutable access to the decoder, inside a critical section.
+    ///
+    /// The function should be short, because interrupts are disabled while it runs (depending on
+    /// the critical section implementation).
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside the function, for the same static decoder.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut D) -> R,
+    {
+        critical_section::with(|cs| f(&mut self.decoder.borrow_ref_mut(cs)))
+    }
+}
+
+/// Implement trait [Default].
+impl<D> Default for StaticDecoder<D>
+where
+    D: Default,
+{
+    fn default() -> StaticDecoder<D> {
+        StaticDecoder::new(D::default())
+    }
+}
+
+/// A decoder in a `static`, which can be fed from an interrupt handler, and awaited by an async
+/// task.
+///
+/// Only one task can wait at a time. If another task waits, the first task's waker is replaced,
+/// and it is not woken. See the [`crate::isr`] module for details.
+pub struct AsyncStaticDecoder<D> {
+    decoder: Mutex<RefCell<D>>,
+    waker: Mutex<RefCell<Option<Waker>>>,
+}
+
+impl<D> AsyncStaticDecoder<D> {
+    /// Create a new static decoder, holding the given decoder.
+    pub const fn new(decoder: D) -> AsyncStaticDecoder<D> {
+        AsyncStaticDecoder {
+            decoder: Mutex::new(RefCell::new(decoder)),
+            waker: Mutex::new(RefCell::new(None)),
+        }
+    }
+
+    /// Call the function with mutable access to the decoder, inside a critical section, without
+    /// waking the waiting task.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside the function, for the same static decoder.
+    pub fn with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut D) -> R,
+    {
+        critical_section::with(|cs| f(&mut self.decoder.borrow_ref_mut(cs)))
+    }
+
+    /// Call the function with mutable access to the decoder, inside a critical section, such as to
+    /// feed it received data, and then wake the waiting task, if any.
+    ///
+    /// This is intended for the interrupt handler. The task is woken outside the critical section.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside the function, for the same static decoder.
+    pub fn feed_with<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut D) -> R,
+    {
+        let (result, waker) = critical_section::with(|cs| {
+            let result = f(&mut self.decoder.borrow_ref_mut(cs));
+            (result, self.waker.borrow_ref_mut(cs).take())
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+        result
+    }
+
+    /// Call the function with mutable access to the decoder, inside a critical section, such as to
+    /// take a decoded frame. If it returns [`None`], register the task's waker, to be woken by
+    /// [`feed_with()`](AsyncStaticDecoder::feed_with).
+    ///
+    /// This is for implementing a [`Future`] by hand. Usually
+    /// [`wait_with()`](AsyncStaticDecoder::wait_with) is simpler.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from inside the function, for the same static decoder.
+    pub fn poll_with<F, R>(&self, cx: &mut Context<'_>, f: F) -> Poll<R>
+    where
+        F: FnOnce(&mut D) -> Option<R>,
+    {
+        critical_section::with(|cs| match f(&mut self.decoder.borrow_ref_mut(cs)) {
+            Some(result) => Poll::Ready(result),
+            None => {
+                let mut waker = self.waker.borrow_ref_mut(cs);
+                if !waker.as_ref().is_some_and(|w| w.will_wake(cx.waker())) {
+                    *waker = Some(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Wait until the function returns [`Some`], calling it with mutable access to the decoder,
+    /// inside a critical section, each time the task is woken by
+    /// [`feed_with()`](AsyncStaticDecoder::feed_with).
+    ///
+    /// The function is typically a call of the decoder's `poll_frame()`, which copies or processes
+    /// the frame, because the frame can't be borrowed outside the critical section.
+    pub fn wait_with<'a, F, R>(&'a self, mut f: F) -> impl Future<Output = R> + 'a
+    where
+        F: FnMut(&mut D) -> Option<R> + 'a,
+    {
+        core::future::poll_fn(move |cx| self.poll_with(cx, &mut f))
+    }
+}
+
+/// Implement trait [Default].
+impl<D> Default for AsyncStaticDecoder<D>
+where
+    D: Default,
+{
+    fn default() -> AsyncStaticDecoder<D> {
+        AsyncStaticDecoder::new(D::default())
+    }
+}
@@ -65,7 +65,10 @@ use core::fmt;
 extern crate alloc;
 
 /// Errors that can occur during COBS encoding/decoding.
+///
+/// More variants may be added in future, so a `match` on it needs a wildcard arm.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[non_exhaustive]
 pub enum Error {
     /// For functions that generate output in an array, such as [cobs::encode_array()], it
     /// indicates that the output array size is too small for the output data.
@@ -79,13 +82,94 @@ pub enum Error {
     /// More data was expected given the last length-byte value in the data.
     /// This error is only applicable for COBS decoding (not COBS/R).
     TruncatedEncodedData,
+    /// For hex string decoding functions, such as [cobs::decode_from_hex()], it indicates that the
+    /// input string is not valid hex. Each byte value must be a pair of hex digits.
+    InvalidHexData,
+    /// For functions that decode into a memory pool block, such as `cobs::decode_to_pool()`, it
+    /// indicates that the memory pool has no free memory blocks.
+    PoolExhausted,
+    /// For functions that decompress the decoded data, such as `cobs::decode_compressed()`, it
+    /// indicates that the decoded data is not valid compressed data, or that the decompressed data
+    /// is larger than the given limit.
+    DecompressionFailed,
+    /// For functions that check a checksum of the decoded data, such as the
+    /// `pipeline::Crc32` stage, it indicates that the checksum doesn't match the data.
+    ChecksumMismatch,
+    /// For functions that decrypt the decoded data, such as the `pipeline::ChaCha20Poly1305`
+    /// stage, it indicates that the data failed authentication, or that its nonce was rejected.
+    AuthenticationFailed,
+    /// For the short frame functions, such as [cobs::encode_short_array()], it indicates that the
+    /// data is longer than those functions allow.
+    ShortFrameTooLong,
+    /// For a stream decoder with a frame validator, such as `stream::CobsStream::set_validator()`,
+    /// it indicates that a decoded frame was rejected by the validator.
+    FrameRejected,
+    /// For functions with a maximum frame length, such as `profile::Profile::encode()`, it
+    /// indicates that the data is longer than the maximum.
+    FrameTooLong,
+    /// For functions that decode text, such as [cobs::decode_utf8()], it indicates that the decoded
+    /// data is not valid UTF-8.
+    InvalidUtf8,
+    /// For functions that decode frames prefixed with a format identifier byte, such as
+    /// `profile::decode_tagged()`, it indicates that the identifier is not a known format.
+    UnknownFormat,
+    /// For functions that reassemble a message from fragments, such as
+    /// `scheduler::Reassembler::push()`, it indicates that a fragment arrived out of sequence,
+    /// likely because a frame was lost.
+    FragmentLost,
+    /// For a stream decoder with an inter-byte timeout, such as
+    /// `async_stream::AsyncLink::set_inter_byte_timeout()`, it indicates that a partly received
+    /// frame was discarded, because no more of it arrived within the timeout.
+    FrameTimeout,
 }
 
 /// Apply trait [std::error::Error].
 #[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
+impl Error {
+    /// A stable numeric code for the error, for compact logging and for reporting over a link.
+    ///
+    /// The codes are:
+    ///
+    /// * 1: [`Error::OutputBufferTooSmall`]
+    /// * 2: [`Error::ZeroInEncodedData`]
+    /// * 3: [`Error::TruncatedEncodedData`]
+    /// * 4: [`Error::InvalidHexData`]
+    /// * 5: [`Error::PoolExhausted`]
+    /// * 6: [`Error::DecompressionFailed`]
+    /// * 7: [`Error::ChecksumMismatch`]
+    /// * 8: [`Error::AuthenticationFailed`]
+    /// * 9: [`Error::ShortFrameTooLong`]
+    /// * 10: [`Error::FrameRejected`]
+    /// * 11: [`Error::FrameTooLong`]
+    /// * 12: [`Error::InvalidUtf8`]
+    /// * 13: [`Error::UnknownFormat`]
+    /// * 14: [`Error::FragmentLost`]
+    /// * 15: [`Error::FrameTimeout`]
+    pub const fn code(&self) -> u8 {
+        match *self {
+            Error::OutputBufferTooSmall => 1,
+            Error::ZeroInEncodedData => 2,
+            Error::TruncatedEncodedData => 3,
+            Error::InvalidHexData => 4,
+            Error::PoolExhausted => 5,
+            Error::DecompressionFailed => 6,
+            Error::ChecksumMismatch => 7,
+            Error::AuthenticationFailed => 8,
+            Error::ShortFrameTooLong => 9,
+            Error::FrameRejected => 10,
+            Error::FrameTooLong => 11,
+            Error::InvalidUtf8 => 12,
+            Error::UnknownFormat => 13,
+            Error::FragmentLost => 14,
+            Error::FrameTimeout => 15,
+        }
+    }
+}
+
 /// Implement trait [fmt::Display].
+#[cfg(any(not(feature = "terse-errors"), feature = "std"))]
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -98,14 +182,228 @@ impl fmt::Display for Error {
             Error::TruncatedEncodedData => {
                 write!(f, "Unexpected end of encoded input data")
             }
+            Error::InvalidHexData => {
+                write!(f, "Invalid hex input data")
+            }
+            Error::PoolExhausted => {
+                write!(f, "Memory pool is exhausted")
+            }
+            Error::DecompressionFailed => {
+                write!(f, "Invalid compressed data")
+            }
+            Error::ChecksumMismatch => {
+                write!(f, "Checksum mismatch")
+            }
+            Error::AuthenticationFailed => {
+                write!(f, "Authentication of encrypted data failed")
+            }
+            Error::ShortFrameTooLong => {
+                write!(f, "Data is too long for a short frame")
+            }
+            Error::FrameRejected => {
+                write!(f, "Frame rejected by validator")
+            }
+            Error::FrameTooLong => {
+                write!(f, "Frame is longer than the maximum length")
+            }
+            Error::InvalidUtf8 => {
+                write!(f, "Decoded data is not valid UTF-8")
+            }
+            Error::UnknownFormat => {
+                write!(f, "Unknown frame format identifier")
+            }
+            Error::FragmentLost => {
+                write!(f, "Fragment of a message was lost")
+            }
+            Error::FrameTimeout => {
+                write!(f, "Partly received frame timed out")
+            }
+        }
+    }
+}
+
+/// Implement trait [fmt::Display], as only the numeric [code](Error::code), such as `E03`, for
+/// small code size. This is used with feature `terse-errors`, without feature `std`.
+#[cfg(all(feature = "terse-errors", not(feature = "std")))]
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use fmt::Write;
+
+        let code = self.code();
+        f.write_char('E')?;
+        f.write_char(char::from(b'0' + code / 10))?;
+        f.write_char(char::from(b'0' + code % 10))
+    }
+}
+
+/// Detailed information about a decoding error, returned by functions such as
+/// [cobs::decode_array_detail()].
+///
+/// In addition to the [Error], this gives the index in the encoded input data at which the error
+/// was detected. This allows a zero-byte at the start of the input data, which likely indicates
+/// that reading began at a frame boundary, to be distinguished from a zero-byte within the data,
+/// which likely indicates data corruption.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ErrorDetail {
+    /// The error.
+    pub error: Error,
+    /// Index in the encoded input data at which the error was detected.
+    ///
+    /// For [Error::ZeroInEncodedData], this is the index of the zero-byte. For
+    /// [Error::TruncatedEncodedData], this is the length of the input data.
+    pub index: usize,
+}
+
+impl ErrorDetail {
+    pub(crate) const fn new(error: Error, index: usize) -> ErrorDetail {
+        ErrorDetail { error, index }
+    }
+
+    /// Returns `true` if the error is a zero-byte at the start of the encoded input data.
+    ///
+    /// This likely indicates that reading began at a frame boundary (that is, the frame delimiter
+    /// was included in the input data), rather than data corruption.
+    pub fn is_leading_zero(&self) -> bool {
+        self.error == Error::ZeroInEncodedData && self.index == 0
+    }
+
+    /// Index in the encoded input data at which decoding could resume.
+    ///
+    /// For [Error::ZeroInEncodedData], the zero-byte is likely a frame delimiter, so the byte
+    /// following it is likely the start of the next frame. For other errors, there is no
+    /// resynchronisation hint, so this returns [None].
+    pub fn resync_index(&self) -> Option<usize> {
+        match self.error {
+            Error::ZeroInEncodedData => Some(self.index + 1),
+            _ => None,
         }
     }
 }
 
+/// Apply trait [std::error::Error].
+#[cfg(feature = "std")]
+impl std::error::Error for ErrorDetail {}
+
+/// Implement trait [fmt::Display].
+impl fmt::Display for ErrorDetail {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at index {}", self.error, self.index)
+    }
+}
+
+/// Convert to the plain [Error], discarding the detail.
+impl From<ErrorDetail> for Error {
+    fn from(detail: ErrorDetail) -> Error {
+        detail.error
+    }
+}
+
+/// How frame decoders handle empty frames, that is, consecutive zero-byte delimiters.
+///
+/// Some senders pad the line with delimiters between frames, in which case empty frames should be
+/// skipped. Others send empty frames deliberately, such as keep-alives, in which case they should be
+/// surfaced to the application.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum EmptyFrames {
+    /// Silently skip empty frames. This is the default.
+    #[default]
+    Skip,
+    /// Surface empty frames to the application, as frames of zero length.
+    Surface,
+}
+
 /// The return type for encoding and decoding functions, based on [core::result::Result],
 /// in which the error type is [Error].
 pub type Result<T> = core::result::Result<T, crate::Error>;
 
+#[cfg(any(feature = "alloc", feature = "embedded-storage"))]
+mod crc;
+
+#[cfg(feature = "alloc")]
+mod hex;
+
+mod hex_frame;
+pub use hex_frame::{HexDump, HexFrame};
+
+#[cfg(feature = "heapless")]
+pub mod pool;
+
+#[cfg(feature = "heapless")]
+pub mod ring;
+
+#[cfg(feature = "critical-section")]
+pub mod isr;
+
 pub mod cobs;
 
 pub mod cobsr;
+
+pub mod cobs16;
+
+pub mod cobsx;
+
+pub mod compat;
+
+pub mod sink;
+
+pub mod transcode;
+
+#[cfg(feature = "cycles")]
+pub mod cycles;
+
+#[cfg(feature = "alloc")]
+pub mod analysis;
+
+#[cfg(feature = "alloc")]
+pub mod pipeline;
+
+#[cfg(feature = "alloc")]
+pub mod profile;
+
+#[cfg(feature = "alloc")]
+pub mod scheduler;
+
+#[cfg(feature = "alloc")]
+pub mod typed;
+
+#[cfg(feature = "futures-io")]
+pub mod async_stream;
+
+#[cfg(feature = "std")]
+pub mod bufpool;
+
+#[cfg(feature = "std")]
+pub mod capture;
+
+#[cfg(feature = "std")]
+pub mod container;
+
+#[cfg(feature = "embedded-storage")]
+pub mod flashlog;
+
+#[cfg(feature = "std")]
+pub mod framelog;
+
+#[cfg(feature = "std")]
+pub mod index;
+
+#[cfg(feature = "std")]
+pub mod linktest;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "mmap")]
+pub mod mmap;
+
+#[cfg(feature = "std")]
+pub mod router;
+
+#[cfg(feature = "std")]
+pub mod stream;
+
+#[cfg(all(feature = "io-uring", target_os = "linux"))]
+pub mod uring;
+
+#[cfg(feature = "test-util")]
+pub mod testing;
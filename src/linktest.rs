@@ -0,0 +1,216 @@
+//! Link testing, to validate a COBS link end-to-end, like `ping`.
+//!
+//! A [`LinkTester`] sends numbered test frames through a [`Transceiver`], to a device which echoes
+//! each frame back. It checks each echoed frame, and reports loss, corruption and round-trip times
+//! in a [`LinkReport`].
+//!
+//! Each test frame is a 4-byte little-endian sequence number, followed by a pattern payload, in
+//! which byte `i` is `(sequence + i) as u8`. So the frames contain zero-bytes, and vary from frame
+//! to frame.
+//!
+//! Example:
+//!
+//!     use cobs2::linktest::LinkTester;
+//!     use cobs2::stream::{CobsTcpStream, Transceiver};
+//!     use std::net::{TcpListener, TcpStream};
+//!     use std::time::Duration;
+//!
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+//!     client.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+//!     let mut device = CobsTcpStream::new(listener.accept().unwrap().0);
+//!
+//!     // The device echoes each frame.
+//!     std::thread::spawn(move || {
+//!         let mut frame = Vec::new();
+//!         while device.recv(&mut frame).is_ok() {
+//!             device.send(&frame).unwrap();
+//!         }
+//!     });
+//!
+//!     let mut transceiver = Transceiver::new(client);
+//!     let report = LinkTester::new().count(5).run(&mut transceiver).unwrap();
+//!     assert_eq!(report.received, 5);
+//!     assert_eq!(report.lost, 0);
+//!     println!("{}", report);
+
+use crate::stream::Transceiver;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// Sends numbered test frames, and checks the echoed frames.
+///
+/// See the [`crate::linktest`] module for details.
+#[derive(Debug, Clone)]
+pub struct LinkTester {
+    count: u32,
+    payload_len: usize,
+    timeout: Duration,
+}
+
+impl LinkTester {
+    /// Create a new link tester, to send 10 frames with a 32-byte payload, with a timeout of 1
+    /// second for each.
+    pub fn new() -> LinkTester {
+        LinkTester {
+            count: 10,
+            payload_len: 32,
+            timeout: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the number of test frames to send.
+    pub fn count(mut self, count: u32) -> LinkTester {
+        self.count = count;
+        self
+    }
+
+    /// Set the length of the pattern payload of each test frame, after the sequence number.
+    pub fn payload_len(mut self, payload_len: usize) -> LinkTester {
+        self.payload_len = payload_len;
+        self
+    }
+
+    /// Set the time to wait for each echoed frame.
+    pub fn timeout(mut self, timeout: Duration) -> LinkTester {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Run the link test, returning a report of the results.
+    ///
+    /// A test frame for which no frame is received within the timeout is counted as lost. An
+    /// error other than a timeout, such as the end of the stream, stops the test, and is returned.
+    pub fn run<S>(&self, transceiver: &mut Transceiver<S>) -> io::Result<LinkReport>
+    where
+        S: Read + Write,
+    {
+        self.run_with(transceiver, |_, _| {})
+    }
+
+    /// Run the link test, calling `on_ping` with the sequence number and outcome of each test
+    /// frame, such as to print a line for each.
+    pub fn run_with<S, F>(
+        &self,
+        transceiver: &mut Transceiver<S>,
+        mut on_ping: F,
+    ) -> io::Result<LinkReport>
+    where
+        S: Read + Write,
+        F: FnMut(u32, PingOutcome),
+    {
+        let mut report = LinkReport::default();
+        for sequence in 0..self.count {
+            let frame = test_frame(sequence, self.payload_len);
+            let start = Instant::now();
+            let outcome = match transceiver.request(&frame, self.timeout) {
+                Ok(response) if response == frame => PingOutcome::Echoed(start.elapsed()),
+                Ok(_) => PingOutcome::Corrupted,
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => PingOutcome::Lost,
+                Err(e) => return Err(e),
+            };
+            report.add(outcome);
+            on_ping(sequence, outcome);
+        }
+        Ok(report)
+    }
+}
+
+/// Implement trait [Default].
+impl Default for LinkTester {
+    fn default() -> LinkTester {
+        LinkTester::new()
+    }
+}
+
+/// The outcome of one test frame.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PingOutcome {
+    /// The echoed frame matched, with the given round-trip time.
+    Echoed(Duration),
+    /// A frame was received, but it didn't match the test frame.
+    Corrupted,
+    /// No frame was received within the timeout.
+    Lost,
+}
+
+/// The results of a link test.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct LinkReport {
+    /// Number of test frames sent.
+    pub sent: u32,
+    /// Number of test frames echoed correctly.
+    pub received: u32,
+    /// Number of test frames for which a non-matching frame was received.
+    pub corrupted: u32,
+    /// Number of test frames for which no frame was received.
+    pub lost: u32,
+    /// Minimum round-trip time of the correctly echoed frames.
+    pub rtt_min: Option<Duration>,
+    /// Maximum round-trip time of the correctly echoed frames.
+    pub rtt_max: Option<Duration>,
+    /// Total round-trip time of the correctly echoed frames.
+    pub rtt_total: Duration,
+}
+
+impl LinkReport {
+    /// Mean round-trip time of the correctly echoed frames.
+    pub fn rtt_mean(&self) -> Option<Duration> {
+        if self.received == 0 {
+            None
+        } else {
+            Some(self.rtt_total / self.received)
+        }
+    }
+
+    /// Fraction of the test frames which were lost or corrupted, from 0.0 to 1.0.
+    pub fn loss_ratio(&self) -> f64 {
+        if self.sent == 0 {
+            0.0
+        } else {
+            f64::from(self.sent - self.received) / f64::from(self.sent)
+        }
+    }
+
+    fn add(&mut self, outcome: PingOutcome) {
+        self.sent += 1;
+        match outcome {
+            PingOutcome::Echoed(rtt) => {
+                self.received += 1;
+                self.rtt_min = Some(self.rtt_min.map_or(rtt, |min| min.min(rtt)));
+                self.rtt_max = Some(self.rtt_max.map_or(rtt, |max| max.max(rtt)));
+                self.rtt_total += rtt;
+            }
+            PingOutcome::Corrupted => self.corrupted += 1,
+            PingOutcome::Lost => self.lost += 1,
+        }
+    }
+}
+
+/// Implement trait [fmt::Display], as a summary like that of `ping`.
+impl fmt::Display for LinkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} frames sent, {} received, {} corrupted, {} lost, {:.1}% loss",
+            self.sent,
+            self.received,
+            self.corrupted,
+            self.lost,
+            self.loss_ratio() * 100.0
+        )?;
+        if let (Some(min), Some(mean), Some(max)) = (self.rtt_min, self.rtt_mean(), self.rtt_max) {
+            write!(f, "; rtt min/mean/max = {:?}/{:?}/{:?}", min, mean, max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Make the test frame with the given sequence number.
+fn test_frame(sequence: u32, payload_len: usize) -> Vec<u8> {
+    let mut frame = sequence.to_le_bytes().to_vec();
+    frame.extend((0..payload_len).map(|i| (sequence as usize).wrapping_add(i) as u8));
+    frame
+}
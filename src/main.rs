@@ -1,6 +1,148 @@
 #![allow(dead_code)]
 
+/// Link test subcommand: `cobs2 ping <address> [count]`.
+///
+/// Connects by TCP to a device which echoes frames, such as through a serial-to-TCP bridge, and
+/// sends numbered test frames to it.
+#[cfg(feature = "std")]
+fn ping(args: &[String]) -> std::io::Result<()> {
+    use cobs2::linktest::{LinkTester, PingOutcome};
+    use std::time::Duration;
+
+    let address = match args.first() {
+        Some(address) => address,
+        None => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "usage: cobs2 ping <address> [count]",
+            ))
+        }
+    };
+    let count = match args.get(1) {
+        Some(count) => count.parse().map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "count must be a number")
+        })?,
+        None => 10,
+    };
+    let stream = std::net::TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(Duration::from_millis(10)))?;
+    let mut transceiver = cobs2::stream::Transceiver::new(stream);
+    let report = LinkTester::new().count(count).run_with(
+        &mut transceiver,
+        |sequence, outcome| match outcome {
+            PingOutcome::Echoed(rtt) => println!("seq={} time={:?}", sequence, rtt),
+            PingOutcome::Corrupted => println!("seq={} corrupted", sequence),
+            PingOutcome::Lost => println!("seq={} lost", sequence),
+        },
+    )?;
+    println!("{}", report);
+    Ok(())
+}
+
+/// Stream decoding subcommand: `cobs2 stream [--cobsr] [--follow | --hexdump [--colour]] <file>`.
+///
+/// Decodes the frames in a file, and prints each frame in hex. With `--follow`, like `tail -f`, it
+/// keeps waiting for frames to be appended to the file by another process, such as a data logger.
+/// With `--hexdump`, it instead prints an annotated hexdump of the encoded data, optionally with
+/// ANSI colour.
+#[cfg(feature = "std")]
+fn stream(args: &[String]) -> std::io::Result<()> {
+    use cobs2::profile::Profile;
+    use cobs2::stream::FrameReader;
+    use std::io::ErrorKind;
+
+    let usage = || {
+        let message = "usage: cobs2 stream [--cobsr] [--follow | --hexdump [--colour]] <file>";
+        std::io::Error::new(ErrorKind::InvalidInput, message)
+    };
+    let mut profile = Profile::Cobs;
+    let mut follow = false;
+    let mut hexdump = false;
+    let mut colour = false;
+    let mut path = None;
+    for arg in args {
+        match arg.as_str() {
+            "--cobsr" => profile = Profile::Cobsr,
+            "-f" | "--follow" => follow = true,
+            "--hexdump" => hexdump = true,
+            "--colour" | "--color" => colour = true,
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(arg),
+            _ => return Err(usage()),
+        }
+    }
+    let path = path.ok_or_else(usage)?;
+    if hexdump {
+        if follow {
+            return Err(usage());
+        }
+        let data = std::fs::read(path)?;
+        let dump = match profile {
+            Profile::Cobsr => cobs2::HexDump::new_cobsr(&data),
+            _ => cobs2::HexDump::new(&data),
+        };
+        print!("{}", dump.colour(colour));
+        return Ok(());
+    }
+    let file = std::fs::File::open(path)?;
+    let mut reader = FrameReader::with_profile(Follow { file, follow }, profile);
+    loop {
+        match reader.read_frame() {
+            Ok(frame) => println!("{:02X?}", frame),
+            Err(e) if e.kind() == ErrorKind::InvalidData => eprintln!("invalid frame: {}", e),
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Reader of a file which, when following, waits for data to be appended at the end of the file.
+///
+/// At the end of the file, it waits briefly, then returns a [`std::io::ErrorKind::TimedOut`] error,
+/// so a [`cobs2::stream::FrameReader`] keeps any partly written frame, and tries again. If the file
+/// is truncated, such as when a log is rotated, it reads again from the start.
+#[cfg(feature = "std")]
+struct Follow {
+    file: std::fs::File,
+    follow: bool,
+}
+
+#[cfg(feature = "std")]
+impl std::io::Read for Follow {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        use std::io::Seek;
+
+        let len = self.file.read(buf)?;
+        if len > 0 || !self.follow {
+            return Ok(len);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        if self.file.metadata()?.len() < self.file.stream_position()? {
+            self.file.rewind()?;
+        }
+        Err(std::io::ErrorKind::TimedOut.into())
+    }
+}
+
 fn main() -> Result<(), cobs2::Error> {
+    #[cfg(feature = "std")]
+    {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        if args.first().map(String::as_str) == Some("ping") {
+            if let Err(e) = ping(&args[1..]) {
+                eprintln!("ping: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        if args.first().map(String::as_str) == Some("stream") {
+            if let Err(e) = stream(&args[1..]) {
+                eprintln!("stream: {}", e);
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    }
+
     let mut cobs_buf = [0x55_u8; 1000];
     let mut cobs_decode_buf = [0xCC_u8; 1000];
     //let data = b"";
@@ -30,11 +172,12 @@ fn main() -> Result<(), cobs2::Error> {
         let in_data_vec = data.to_vec();
         let data_cobs: Vec<u8> = cobs2::cobs::encode_ref_iter(in_data_vec.iter()).collect();
         println!("COBS encode_ref_iter: {:X?}", data_cobs);
-        let data_cobs: Vec<u8> = cobs2::cobs::encode_iter(in_data_vec.into_iter()).collect();
+        let data_cobs: Vec<u8> = cobs2::cobs::encode_iter(in_data_vec).collect();
         println!("COBS encode_iter: {:X?}", data_cobs);
-        let data_cobs_decoded: Vec<u8> = cobs2::cobs::decode_iter(data_cobs.clone().into_iter()).collect();
+        let data_cobs_decoded: Vec<u8> = cobs2::cobs::decode_iter(data_cobs.clone()).collect();
         println!("COBS decode_iter: {:X?}", data_cobs_decoded);
-        let data_cobs_decoded: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_iter(data_cobs.into_iter()).collect();
+        let data_cobs_decoded: cobs2::Result<Vec<u8>> =
+            cobs2::cobs::decode_result_iter(data_cobs).collect();
         println!("COBS decode_result_iter: {:X?}", data_cobs_decoded);
     }
 
@@ -43,13 +186,15 @@ fn main() -> Result<(), cobs2::Error> {
         let bad_cobs_encoded_data = b"\x00sAAA";
         let result = cobs2::cobs::decode_array(&mut cobs_decode_buf, bad_cobs_encoded_data);
         assert_eq!(result, Err(cobs2::Error::ZeroInEncodedData));
-        let result: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_iter(bad_cobs_encoded_data.into_iter().copied()).collect();
+        let result: cobs2::Result<Vec<u8>> =
+            cobs2::cobs::decode_result_iter(bad_cobs_encoded_data.iter().copied()).collect();
         assert_eq!(result, Err(cobs2::Error::ZeroInEncodedData));
 
         let bad_cobs_encoded_data = b"\x05AAA";
         let result = cobs2::cobs::decode_array(&mut cobs_decode_buf, bad_cobs_encoded_data);
         assert_eq!(result, Err(cobs2::Error::TruncatedEncodedData));
-        let result: cobs2::Result<Vec<u8>> = cobs2::cobs::decode_result_iter(bad_cobs_encoded_data.into_iter().copied()).collect();
+        let result: cobs2::Result<Vec<u8>> =
+            cobs2::cobs::decode_result_iter(bad_cobs_encoded_data.iter().copied()).collect();
         assert_eq!(result, Err(cobs2::Error::TruncatedEncodedData));
     }
 
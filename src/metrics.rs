@@ -0,0 +1,172 @@
+//! Prometheus metrics of long-running decoders.
+//!
+//! A [`Metrics`] is a named set of counters, which can be shared between threads. Once it is
+//! attached to a [`crate::router::FrameRouter`], a [`crate::stream::Link`] or a
+//! [`crate::stream::FrameReader`] with their `metrics()` setter, they update the counters as they
+//! work. So a gateway service can scrape frame rates and error rates, without wrapping each call
+//! site.
+//!
+//! [`render()`] renders metrics in the Prometheus text exposition format, to serve to a scraper.
+//! The name of each [`Metrics`] is given as the value of the `decoder` label. The metrics are:
+//!
+//! | Metric                        | Type    | Description                                       |
+//! |-------------------------------|---------|---------------------------------------------------|
+//! | `cobs2_frames_sent_total`     | counter | Number of frames sent.                            |
+//! | `cobs2_sent_bytes_total`      | counter | Number of encoded bytes sent.                     |
+//! | `cobs2_frames_received_total` | counter | Number of valid frames received.                  |
+//! | `cobs2_received_bytes_total`  | counter | Number of bytes received.                         |
+//! | `cobs2_invalid_frames_total`  | counter | Number of received frames which failed to decode. |
+//!
+//! Example:
+//!
+//!     use cobs2::metrics::{render, Metrics};
+//!     use cobs2::stream::FrameReader;
+//!
+//!     let metrics = Metrics::new("uart0");
+//!     let mut reader = FrameReader::new(&b"\x04ABC\x00\x05AB\x00"[..]).metrics(metrics.clone());
+//!     assert_eq!(reader.read_frame().unwrap(), b"ABC");
+//!     assert!(reader.read_frame().is_err());
+//!     assert_eq!(metrics.stats().invalid_frames, 1);
+//!
+//!     let text = render(&[metrics]);
+//!     assert!(text.contains("cobs2_frames_received_total{decoder=\"uart0\"} 1\n"));
+
+use crate::stream::LinkStats;
+use core::fmt::Write;
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::string::String;
+use std::sync::Arc;
+
+/// A named set of counters of frames and bytes, which can be shared between threads.
+///
+/// Clones share the same counters. See the [`crate::metrics`] module for details.
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    counters: Arc<Counters>,
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    name: String,
+    frames_sent: AtomicU64,
+    bytes_sent: AtomicU64,
+    frames_received: AtomicU64,
+    bytes_received: AtomicU64,
+    invalid_frames: AtomicU64,
+}
+
+impl Metrics {
+    /// Create a new set of counters, all zero, with the given name.
+    pub fn new(name: &str) -> Metrics {
+        Metrics {
+            counters: Arc::new(Counters {
+                name: name.into(),
+                ..Counters::default()
+            }),
+        }
+    }
+
+    /// Get the name.
+    pub fn name(&self) -> &str {
+        &self.counters.name
+    }
+
+    /// Get the current values of the counters.
+    pub fn stats(&self) -> LinkStats {
+        let counters = &self.counters;
+        LinkStats {
+            frames_sent: counters.frames_sent.load(Ordering::Relaxed),
+            bytes_sent: counters.bytes_sent.load(Ordering::Relaxed),
+            frames_received: counters.frames_received.load(Ordering::Relaxed),
+            bytes_received: counters.bytes_received.load(Ordering::Relaxed),
+            invalid_frames: counters.invalid_frames.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Count a frame sent, with its encoded length.
+    pub(crate) fn sent(&self, len: usize) {
+        self.counters.frames_sent.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .bytes_sent
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Count bytes received.
+    pub(crate) fn bytes_received(&self, len: usize) {
+        self.counters
+            .bytes_received
+            .fetch_add(len as u64, Ordering::Relaxed);
+    }
+
+    /// Count a non-empty frame received.
+    pub(crate) fn received(&self, valid: bool) {
+        if valid {
+            self.counters
+                .frames_received
+                .fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.counters.invalid_frames.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// The name, help text and value of each metric.
+type Family = (&'static str, &'static str, fn(&LinkStats) -> u64);
+
+const FAMILIES: [Family; 5] = [
+    ("cobs2_frames_sent_total", "Number of frames sent.", |s| {
+        s.frames_sent
+    }),
+    (
+        "cobs2_sent_bytes_total",
+        "Number of encoded bytes sent.",
+        |s| s.bytes_sent,
+    ),
+    (
+        "cobs2_frames_received_total",
+        "Number of valid frames received.",
+        |s| s.frames_received,
+    ),
+    (
+        "cobs2_received_bytes_total",
+        "Number of bytes received.",
+        |s| s.bytes_received,
+    ),
+    (
+        "cobs2_invalid_frames_total",
+        "Number of received frames which failed to decode.",
+        |s| s.invalid_frames,
+    ),
+];
+
+/// Render metrics in the Prometheus text exposition format.
+///
+/// Each metric has one sample for each [`Metrics`], with its name as the value of the `decoder`
+/// label.
+pub fn render(metrics: &[Metrics]) -> String {
+    let stats: std::vec::Vec<_> = metrics.iter().map(|m| (m.name(), m.stats())).collect();
+    let mut text = String::new();
+    for (metric, help, value) in FAMILIES.iter() {
+        let _ = writeln!(text, "# HELP {} {}", metric, help);
+        let _ = writeln!(text, "# TYPE {} counter", metric);
+        for (name, stats) in &stats {
+            text.push_str(metric);
+            text.push_str("{decoder=\"");
+            push_label_value(&mut text, name);
+            let _ = writeln!(text, "\"}} {}", value(stats));
+        }
+    }
+    text
+}
+
+/// Append a label value, escaping backslash, double-quote and line feed.
+fn push_label_value(text: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => text.push_str("\\\\"),
+            '"' => text.push_str("\\\""),
+            '\n' => text.push_str("\\n"),
+            c => text.push(c),
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Processing of large captures and encoded streams in memory-mapped files (feature `mmap`).
+//!
+//! A multi-GB capture can be processed without reading it into memory, by memory-mapping the file
+//! with [`Mmap`], which is re-exported from the `memmap2` crate. Mapping a file is `unsafe`,
+//! because the mapped data changes if the file is modified while it is mapped, so the application
+//! does the mapping, and this crate doesn't need any unsafe code.
+//!
+//! * [`CaptureMap`] reads a capture, of the [`crate::capture`] format. Its records borrow the
+//!   frame data from the mapping, with no copying.
+//! * [`StreamMap`] splits a stream of encoded frames, each followed by a zero-byte delimiter, and
+//!   decodes them with the frame decoding iterators of [`crate::cobs`] or [`crate::cobsr`].
+//!
+//! Both work with any data that implements `AsRef<[u8]>`, not only [`Mmap`].
+//!
+//! Example:
+//!
+//!     use cobs2::mmap::{CaptureMap, Mmap};
+//!
+//!     # let path = std::env::temp_dir().join("cobs2-mmap-example.cap");
+//!     # let file = std::fs::File::create(&path).unwrap();
+//!     # let mut writer = cobs2::capture::CaptureWriter::new(file).unwrap();
+//!     # writer.write_frame(b"\x04ABC").unwrap();
+//!     # writer.flush().unwrap();
+//!     let file = std::fs::File::open(&path).unwrap();
+//!     // Safety: the capture file is not modified while it is mapped.
+//!     let map = unsafe { Mmap::map(&file) }.unwrap();
+//!     let capture = CaptureMap::new(map).unwrap();
+//!     for record in capture.records() {
+//!         let record = record.unwrap();
+//!         assert_eq!(cobs2::cobs::decode_vector(record.data).unwrap(), b"ABC");
+//!     }
+//!     # std::fs::remove_file(&path).unwrap();
+
+use crate::capture::{CaptureRecord, HEADER, VERSION};
+use crate::{cobs, cobsr};
+use std::io;
+use std::iter::Copied;
+use std::slice;
+use std::time::Duration;
+
+pub use memmap2::Mmap;
+
+/// Length of the capture header.
+const HEADER_LEN: usize = 10;
+
+/// Length of a capture record, before its frame data.
+const RECORD_HEADER_LEN: usize = 20;
+
+/// A capture, of the [`crate::capture`] format, in a memory-mapped file.
+///
+/// See the [`crate::mmap`] module for details.
+pub struct CaptureMap<M>
+where
+    M: AsRef<[u8]>,
+{
+    map: M,
+}
+
+impl<M> CaptureMap<M>
+where
+    M: AsRef<[u8]>,
+{
+    /// Start reading a capture in the mapping, checking the capture header.
+    ///
+    /// An error of kind [`io::ErrorKind::InvalidData`] is returned if the header is not valid.
+    pub fn new(map: M) -> io::Result<CaptureMap<M>> {
+        let bytes = map.as_ref();
+        if bytes.get(..8) != Some(&HEADER[..])
+            || bytes.get(8..HEADER_LEN) != Some(&VERSION.to_le_bytes()[..])
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a supported capture",
+            ));
+        }
+        Ok(CaptureMap { map })
+    }
+
+    /// Get an iterator of the capture's records, which borrow the frame data from the mapping.
+    pub fn records(&self) -> CaptureRecords<'_> {
+        CaptureRecords {
+            in_buf: &self.map.as_ref()[HEADER_LEN..],
+        }
+    }
+
+    /// Consume the capture, returning the mapping.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+}
+
+/// One frame of a capture, borrowing the frame data from a [`CaptureMap`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CaptureRecordRef<'a> {
+    /// Time since the start of the capture.
+    pub monotonic: Duration,
+    /// Wall-clock time, as the time since the Unix epoch.
+    pub wall_clock: Duration,
+    /// Raw encoded bytes of the frame, without the zero-byte delimiter.
+    pub data: &'a [u8],
+}
+
+impl<'a> CaptureRecordRef<'a> {
+    /// Copy the record into a [`CaptureRecord`], which owns its frame data.
+    pub fn to_record(&self) -> CaptureRecord {
+        CaptureRecord {
+            monotonic: self.monotonic,
+            wall_clock: self.wall_clock,
+            data: self.data.to_vec(),
+        }
+    }
+}
+
+/// Iterator of the records of a [`CaptureMap`], made by [`CaptureMap::records()`].
+///
+/// If the capture ends with an incomplete record, an error of kind
+/// [`io::ErrorKind::UnexpectedEof`] is returned, and then the iteration ends.
+pub struct CaptureRecords<'a> {
+    in_buf: &'a [u8],
+}
+
+/// Implement trait [Iterator].
+impl<'a> Iterator for CaptureRecords<'a> {
+    type Item = io::Result<CaptureRecordRef<'a>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.in_buf.is_empty() {
+            return None;
+        }
+        let record = self
+            .in_buf
+            .get(..RECORD_HEADER_LEN)
+            .and_then(|record_header| {
+                let mut monotonic = [0_u8; 8];
+                let mut wall_clock = [0_u8; 8];
+                let mut frame_len = [0_u8; 4];
+                monotonic.copy_from_slice(&record_header[..8]);
+                wall_clock.copy_from_slice(&record_header[8..16]);
+                frame_len.copy_from_slice(&record_header[16..]);
+                let data_end =
+                    RECORD_HEADER_LEN.checked_add(u32::from_le_bytes(frame_len) as usize)?;
+                let data = self.in_buf.get(RECORD_HEADER_LEN..data_end)?;
+                Some((
+                    CaptureRecordRef {
+                        monotonic: Duration::from_nanos(u64::from_le_bytes(monotonic)),
+                        wall_clock: Duration::from_nanos(u64::from_le_bytes(wall_clock)),
+                        data,
+                    },
+                    data_end,
+                ))
+            });
+        match record {
+            Some((record, record_len)) => {
+                self.in_buf = &self.in_buf[record_len..];
+                Some(Ok(record))
+            }
+            None => {
+                self.in_buf = &[];
+                Some(Err(io::ErrorKind::UnexpectedEof.into()))
+            }
+        }
+    }
+}
+
+/// A stream of encoded frames, each followed by a zero-byte delimiter, in a memory-mapped file.
+///
+/// See the [`crate::mmap`] module for details.
+pub struct StreamMap<M>
+where
+    M: AsRef<[u8]>,
+{
+    map: M,
+}
+
+impl<M> StreamMap<M>
+where
+    M: AsRef<[u8]>,
+{
+    /// Create a stream of encoded frames in the mapping.
+    pub fn new(map: M) -> StreamMap<M> {
+        StreamMap { map }
+    }
+
+    /// Get an iterator of the raw encoded frames, without their delimiters. Empty frames are
+    /// skipped.
+    pub fn frames(&self) -> impl Iterator<Item = &[u8]> + '_ {
+        self.map
+            .as_ref()
+            .split(|x| *x == 0)
+            .filter(|frame| !frame.is_empty())
+    }
+
+    /// Get a COBS frame decoder of the stream. See [`cobs::decode_frames_iter()`].
+    pub fn decode_frames(&self) -> cobs::DecodeFrames<Copied<slice::Iter<'_, u8>>> {
+        cobs::decode_frames_iter(self.map.as_ref().iter().copied())
+    }
+
+    /// Get a COBS/R frame decoder of the stream. See [`cobsr::decode_frames_iter()`].
+    pub fn decode_frames_cobsr(&self) -> cobsr::DecodeFrames<Copied<slice::Iter<'_, u8>>> {
+        cobsr::decode_frames_iter(self.map.as_ref().iter().copied())
+    }
+
+    /// Consume the stream, returning the mapping.
+    pub fn into_inner(self) -> M {
+        self.map
+    }
+}
@@ -0,0 +1,297 @@
+//! Composable pipelines of frame transformations, such as compression, checksum and COBS encoding.
+//!
+//! A [`Pipeline`] is built from a sequence of [`Stage`]s. Encoding applies each stage in order,
+//! and decoding applies each stage's reverse transformation in the reverse order. This avoids the
+//! need for a separate helper function for each combination of transformations.
+//!
+//! The stages provided are:
+//!
+//! * [`Cobs`] — COBS encoding. See [`crate::cobs`].
+//! * [`Cobsr`] — COBS/R encoding. See [`crate::cobsr`].
+//! * [`Crc32`] — append a CRC-32 checksum, and check it when decoding.
+//! * [`Deflate`] — DEFLATE compression (feature `deflate`).
+//! * [`ChaCha20Poly1305`] — authenticated encryption (feature `aead`).
+//!
+//! Applications can implement [`Stage`] for other transformations.
+//!
+//! Example:
+//!
+//!     use cobs2::pipeline::{Cobs, Crc32, Pipeline};
+//!
+//!     let pipeline = Pipeline::new().stage(Crc32).stage(Cobs);
+//!     let data = b"ABC\0ghij\0xyz";
+//!     let data_encoded = pipeline.encode(data).unwrap();
+//!     assert!(!data_encoded.contains(&0));
+//!     let data_decoded = pipeline.decode(&data_encoded).unwrap();
+//!     assert_eq!(data_decoded, data);
+
+use crate::{cobs, cobsr, Error, Result};
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+
+/// One transformation of a [`Pipeline`], which can be applied in both directions.
+pub trait Stage {
+    /// Transform the data, for encoding.
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>>;
+
+    /// Reverse the transformation of [`Stage::encode()`], for decoding.
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>>;
+}
+
+/// A sequence of [`Stage`]s, applied in order for encoding, and in reverse order for decoding.
+///
+/// See the [`crate::pipeline`] module for details.
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Stage>>,
+}
+
+impl Pipeline {
+    /// Create a new pipeline, with no stages.
+    pub fn new() -> Pipeline {
+        Pipeline { stages: Vec::new() }
+    }
+
+    /// Add a stage to the end of the pipeline.
+    pub fn stage<S>(mut self, stage: S) -> Pipeline
+    where
+        S: Stage + 'static,
+    {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Encode data, applying each stage in order.
+    pub fn encode(&self, in_buf: &[u8]) -> Result<Vec<u8>> {
+        self.stages
+            .iter()
+            .try_fold(in_buf.to_vec(), |data, stage| stage.encode(data))
+    }
+
+    /// Decode data, applying each stage's reverse transformation in reverse order.
+    pub fn decode(&self, in_buf: &[u8]) -> Result<Vec<u8>> {
+        self.stages
+            .iter()
+            .rev()
+            .try_fold(in_buf.to_vec(), |data, stage| stage.decode(data))
+    }
+}
+
+/// COBS encoding stage. See [`crate::cobs`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cobs;
+
+impl Stage for Cobs {
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        cobs::encode_vector(&data)
+    }
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        cobs::decode_vector(&data)
+    }
+}
+
+/// COBS/R encoding stage. See [`crate::cobsr`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Cobsr;
+
+impl Stage for Cobsr {
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        cobsr::encode_vector(&data)
+    }
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        cobsr::decode_vector(&data)
+    }
+}
+
+/// Checksum stage, which appends a CRC-32 of the data, and checks and removes it when decoding.
+///
+/// The CRC is the common CRC-32 (as used by Ethernet and zlib), appended in little-endian byte
+/// order. Decoding returns [`Error::ChecksumMismatch`] if the CRC doesn't match.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Crc32;
+
+impl Crc32 {
+    /// Calculate the CRC-32 of the data.
+    pub fn checksum(data: &[u8]) -> u32 {
+        crate::crc::crc32(data)
+    }
+}
+
+impl Stage for Crc32 {
+    fn encode(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        let crc = Crc32::checksum(&data);
+        data.extend_from_slice(&crc.to_le_bytes());
+        Ok(data)
+    }
+
+    fn decode(&self, mut data: Vec<u8>) -> Result<Vec<u8>> {
+        if data.len() < 4 {
+            return Err(Error::ChecksumMismatch);
+        }
+        let crc_bytes = data.split_off(data.len() - 4);
+        if crc_bytes[..] != Crc32::checksum(&data).to_le_bytes() {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(data)
+    }
+}
+
+/// DEFLATE compression stage. See [`crate::cobs::encode_compressed()`] for details.
+///
+/// When decoding, the decompressed data is limited to `max_len` bytes.
+#[cfg(feature = "deflate")]
+#[derive(Debug, Clone, Copy)]
+pub struct Deflate {
+    /// Maximum size of decompressed data.
+    pub max_len: usize,
+}
+
+#[cfg(feature = "deflate")]
+impl Stage for Deflate {
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        // Level 6 is the default compression level of zlib and miniz.
+        Ok(miniz_oxide::deflate::compress_to_vec(&data, 6))
+    }
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        miniz_oxide::inflate::decompress_to_vec_with_limit(&data, self.max_len)
+            .map_err(|_| Error::DecompressionFailed)
+    }
+}
+
+/// Source of nonces for the [`ChaCha20Poly1305`] stage.
+///
+/// A nonce must never be used twice with the same key. [`CounterNonce`] is a simple
+/// implementation. Applications can implement this for other nonce schemes, and to reject
+/// replayed frames.
+#[cfg(feature = "aead")]
+pub trait NonceSource {
+    /// Get the nonce for encrypting the next frame.
+    fn next_nonce(&self) -> [u8; 12];
+
+    /// Check the nonce of a received frame, before it is decrypted. Returning `false` rejects the
+    /// frame, for example to detect replayed frames. By default, all nonces are accepted.
+    fn accept_nonce(&self, nonce: &[u8; 12]) -> bool {
+        let _ = nonce;
+        true
+    }
+}
+
+/// A [`NonceSource`] made from a fixed 4-byte prefix and a 64-bit counter.
+///
+/// The prefix should be different for each sender that uses the same key, such as for each
+/// direction of a link.
+#[cfg(feature = "aead")]
+#[derive(Debug)]
+pub struct CounterNonce {
+    prefix: [u8; 4],
+    counter: core::cell::Cell<u64>,
+}
+
+#[cfg(feature = "aead")]
+impl CounterNonce {
+    /// Create a new nonce source, with the given prefix, starting with a counter of zero.
+    pub fn new(prefix: [u8; 4]) -> CounterNonce {
+        CounterNonce {
+            prefix,
+            counter: core::cell::Cell::new(0),
+        }
+    }
+}
+
+#[cfg(feature = "aead")]
+impl NonceSource for CounterNonce {
+    fn next_nonce(&self) -> [u8; 12] {
+        let counter = self.counter.get();
+        self.counter.set(counter.wrapping_add(1));
+        let mut nonce = [0_u8; 12];
+        nonce[..4].copy_from_slice(&self.prefix);
+        nonce[4..].copy_from_slice(&counter.to_le_bytes());
+        nonce
+    }
+}
+
+/// Authenticated encryption stage, using ChaCha20-Poly1305 from RustCrypto.
+///
+/// When encoding, the data is encrypted with a nonce from the [`NonceSource`], and the output is
+/// the 12-byte nonce, then the encrypted data, then the 16-byte authentication tag. When decoding,
+/// the nonce is checked by [`NonceSource::accept_nonce()`], then the data is authenticated and
+/// decrypted. Decoding returns [`Error::AuthenticationFailed`] if that fails.
+///
+/// Example:
+///
+///     use cobs2::pipeline::{ChaCha20Poly1305, Cobs, CounterNonce, Pipeline};
+///
+///     let key = [0x42_u8; 32];
+///     let tx = Pipeline::new().stage(ChaCha20Poly1305::new(&key, CounterNonce::new(*b"host")));
+///     let tx = tx.stage(Cobs);
+///     let rx = Pipeline::new().stage(ChaCha20Poly1305::new(&key, CounterNonce::new(*b"node")));
+///     let rx = rx.stage(Cobs);
+///
+///     let data_encoded = tx.encode(b"ABC\0ghij\0xyz").unwrap();
+///     assert_eq!(rx.decode(&data_encoded).unwrap(), b"ABC\0ghij\0xyz");
+///
+#[cfg(feature = "aead")]
+pub struct ChaCha20Poly1305<N>
+where
+    N: NonceSource,
+{
+    cipher: chacha20poly1305::ChaCha20Poly1305,
+    nonces: N,
+}
+
+#[cfg(feature = "aead")]
+impl<N> ChaCha20Poly1305<N>
+where
+    N: NonceSource,
+{
+    /// Create a new stage, with the given 256-bit key and nonce source.
+    pub fn new(key: &[u8; 32], nonces: N) -> ChaCha20Poly1305<N> {
+        use chacha20poly1305::KeyInit;
+
+        ChaCha20Poly1305 {
+            cipher: chacha20poly1305::ChaCha20Poly1305::new(key.into()),
+            nonces,
+        }
+    }
+}
+
+#[cfg(feature = "aead")]
+impl<N> Stage for ChaCha20Poly1305<N>
+where
+    N: NonceSource,
+{
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        use chacha20poly1305::aead::Aead;
+
+        let nonce = self.nonces.next_nonce();
+        // Encryption only fails if the data is larger than ChaCha20 can encrypt with one nonce.
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce.into(), &data[..])
+            .map_err(|_| Error::OutputBufferTooSmall)?;
+        let mut out_vec = Vec::with_capacity(nonce.len() + ciphertext.len());
+        out_vec.extend_from_slice(&nonce);
+        out_vec.extend_from_slice(&ciphertext);
+        Ok(out_vec)
+    }
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        use chacha20poly1305::aead::Aead;
+
+        if data.len() < 12 {
+            return Err(Error::AuthenticationFailed);
+        }
+        let (nonce_bytes, ciphertext) = data.split_at(12);
+        let mut nonce = [0_u8; 12];
+        nonce.copy_from_slice(nonce_bytes);
+        if !self.nonces.accept_nonce(&nonce) {
+            return Err(Error::AuthenticationFailed);
+        }
+        self.cipher
+            .decrypt(&nonce.into(), ciphertext)
+            .map_err(|_| Error::AuthenticationFailed)
+    }
+}
@@ -0,0 +1,80 @@
+//! Decoding of frames into memory blocks of a [`heapless::pool`] memory pool.
+//!
+//! This allows a `no_std` application to hold several decoded frames in flight, without a global
+//! allocator. The decode functions are [`cobs::decode_to_pool()`](crate::cobs::decode_to_pool) and
+//! [`cobsr::decode_to_pool()`](crate::cobsr::decode_to_pool). Each returns a [`PoolFrame`], which
+//! returns its memory block to the pool when it is dropped.
+//!
+//! The memory pool is defined by the application, using [`heapless::box_pool!`], with a data type
+//! of `[u8; N]`. `N` is the maximum decoded frame size.
+//!
+//! Note that [`heapless::pool`] is only available on some targets, and on others may need
+//! `heapless` feature `portable-atomic`. See the `heapless` documentation for details.
+
+use crate::{Error, Result};
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+use heapless::pool::boxed::{Box, BoxPool};
+
+/// A decoded frame, stored in a memory block of memory pool `P`.
+///
+/// This dereferences to a slice of the decoded data. When it is dropped, the memory block is
+/// returned to the memory pool.
+pub struct PoolFrame<P>
+where
+    P: BoxPool,
+{
+    data: Box<P>,
+    len: usize,
+}
+
+impl<P, const N: usize> PoolFrame<P>
+where
+    P: BoxPool<Data = [u8; N]>,
+{
+    /// Allocate a memory block from the pool, and fill it using the given decode function, which
+    /// returns the decoded length.
+    pub(crate) fn decode_with<F>(decode: F) -> Result<PoolFrame<P>>
+    where
+        F: FnOnce(&mut [u8]) -> Result<usize>,
+    {
+        let mut data = P::alloc([0; N]).map_err(|_| Error::PoolExhausted)?;
+        let len = decode(&mut data[..])?;
+        Ok(PoolFrame { data, len })
+    }
+
+    /// Consume the frame, returning the memory block and the length of the decoded data in it.
+    pub fn into_inner(self) -> (Box<P>, usize) {
+        (self.data, self.len)
+    }
+}
+
+impl<P, const N: usize> Deref for PoolFrame<P>
+where
+    P: BoxPool<Data = [u8; N]>,
+{
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data[..self.len]
+    }
+}
+
+impl<P, const N: usize> DerefMut for PoolFrame<P>
+where
+    P: BoxPool<Data = [u8; N]>,
+{
+    fn deref_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..self.len]
+    }
+}
+
+/// Implement trait [fmt::Debug].
+impl<P, const N: usize> fmt::Debug for PoolFrame<P>
+where
+    P: BoxPool<Data = [u8; N]>,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("PoolFrame").field(&self.deref()).finish()
+    }
+}
@@ -0,0 +1,320 @@
+//! Named framing profiles, which bundle the encoding variant, checksum, delimiters and maximum
+//! frame length.
+//!
+//! Two ends of a link which are "both using COBS" can still disagree, such as about whether there
+//! is a CRC, or whether a frame is preceded by a delimiter. A [`Profile`] gives an unambiguous
+//! name for all these choices, which can be referenced in interface documentation. The presets
+//! are:
+//!
+//! | Profile                      | Variant | Checksum           | Delimiters           | Max length |
+//! |------------------------------|---------|--------------------|----------------------|------------|
+//! | [`Profile::Cobs`]            | COBS    | none               | trailing             | none       |
+//! | [`Profile::Cobsr`]           | COBS/R  | none               | trailing             | none       |
+//! | [`Profile::CobsCrc32`]       | COBS    | CRC-32             | trailing             | none       |
+//! | [`Profile::CobsrCrc16Ccitt`] | COBS/R  | CRC-16/CCITT-FALSE | leading and trailing | 251 bytes  |
+//!
+//! A checksum is appended to the frame data in little-endian byte order, before encoding. See
+//! [`Checksum`] for the CRC parameters. The maximum length is of the frame data, not including
+//! the checksum.
+//!
+//! A profile can be used directly with [`Profile::encode_frame()`] and [`Profile::decode()`], as a
+//! [`pipeline::Stage`](crate::pipeline::Stage), and with the `with_profile()` constructors of the
+//! framing types in [`crate::stream`] and [`crate::router`].
+//!
+//! Example:
+//!
+//!     use cobs2::profile::Profile;
+//!
+//!     let profile = Profile::CobsrCrc16Ccitt;
+//!     let frame = profile.encode_frame(b"ABC\0xyz").unwrap();
+//!     assert_eq!(frame.first(), Some(&0));
+//!     assert_eq!(frame.last(), Some(&0));
+//!     let data_decoded = profile.decode(&frame[1..frame.len() - 1]).unwrap();
+//!     assert_eq!(data_decoded, b"ABC\0xyz");
+//!
+//!     assert_eq!(profile.encode(&[0_u8; 252]), Err(cobs2::Error::FrameTooLong));
+//!
+//! ### Format identifier
+//!
+//! For a bus shared by devices using different profiles, such as old firmware using plain COBS
+//! and new firmware using COBS/R with a CRC, each frame can be prefixed with a format identifier
+//! byte, with [`Profile::encode_tagged()`]. Then [`decode_tagged()`] reads the identifier, and
+//! decodes the frame with the matching profile.
+//!
+//! The identifier is outside the encoded data, and is never zero. Its low 4 bits are the
+//! encoding variant, and its high 4 bits are the checksum:
+//!
+//! | Bits 0–3 | Variant  |   | Bits 4–7 | Checksum           |
+//! |----------|----------|---|----------|--------------------|
+//! | 1        | COBS     |   | 0        | none               |
+//! | 2        | COBS/R   |   | 1        | CRC-16/CCITT-FALSE |
+//! |          |          |   | 2        | CRC-32             |
+//!
+//! Other values are reserved, such as for future encoding variants. So the identifiers of the
+//! presets are `0x01` for [`Profile::Cobs`], `0x02` for [`Profile::Cobsr`], `0x21` for
+//! [`Profile::CobsCrc32`] and `0x12` for [`Profile::CobsrCrc16Ccitt`].
+//!
+//! Example:
+//!
+//!     use cobs2::profile::{decode_tagged, Profile};
+//!
+//!     let old = Profile::Cobs.encode_tagged(b"ABC").unwrap();
+//!     let new = Profile::CobsrCrc16Ccitt.encode_tagged(b"ABC").unwrap();
+//!     assert_eq!(old, b"\x01\x04ABC");
+//!     assert_eq!(decode_tagged(&old).unwrap(), (Profile::Cobs, b"ABC".to_vec()));
+//!     assert_eq!(decode_tagged(&new).unwrap(), (Profile::CobsrCrc16Ccitt, b"ABC".to_vec()));
+
+use crate::crc::{crc16_ccitt, crc32};
+use crate::pipeline::Stage;
+use crate::{cobs, cobsr, Error, Result};
+use alloc::vec::Vec;
+
+/// A named framing profile.
+///
+/// See the [`crate::profile`] module for details.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Profile {
+    /// COBS, with no checksum, a trailing delimiter, and no maximum length.
+    Cobs,
+    /// COBS/R, with no checksum, a trailing delimiter, and no maximum length.
+    Cobsr,
+    /// COBS, with a CRC-32, a trailing delimiter, and no maximum length.
+    CobsCrc32,
+    /// COBS/R, with a CRC-16/CCITT-FALSE, leading and trailing delimiters, and a maximum length of
+    /// 251 bytes. So the data and CRC are at most 253 bytes, and a frame fits in 256 bytes
+    /// including its delimiters.
+    CobsrCrc16Ccitt,
+}
+
+/// The encoding variant of a [`Profile`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Variant {
+    /// COBS. See [`crate::cobs`].
+    Cobs,
+    /// COBS/R. See [`crate::cobsr`].
+    Cobsr,
+}
+
+/// The checksum of a [`Profile`], which is appended to the frame data before encoding.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Checksum {
+    /// No checksum.
+    None,
+    /// CRC-16/CCITT-FALSE (polynomial `0x1021`, initial value `0xFFFF`, not reflected, no final
+    /// XOR), appended in little-endian byte order.
+    Crc16Ccitt,
+    /// The common CRC-32 (as used by Ethernet and zlib), appended in little-endian byte order.
+    Crc32,
+}
+
+impl Checksum {
+    /// The length of the checksum, in bytes.
+    pub const fn len(self) -> usize {
+        match self {
+            Checksum::None => 0,
+            Checksum::Crc16Ccitt => 2,
+            Checksum::Crc32 => 4,
+        }
+    }
+
+    /// Returns `true` for [`Checksum::None`].
+    pub const fn is_empty(self) -> bool {
+        self.len() == 0
+    }
+
+    fn append(self, data: &mut Vec<u8>) {
+        match self {
+            Checksum::None => {}
+            Checksum::Crc16Ccitt => data.extend_from_slice(&crc16_ccitt(data).to_le_bytes()),
+            Checksum::Crc32 => data.extend_from_slice(&crc32(data).to_le_bytes()),
+        }
+    }
+
+    fn check_and_remove(self, data: &mut Vec<u8>) -> Result<()> {
+        let data_len = data
+            .len()
+            .checked_sub(self.len())
+            .ok_or(Error::ChecksumMismatch)?;
+        let (frame, crc) = data.split_at(data_len);
+        let valid = match self {
+            Checksum::None => true,
+            Checksum::Crc16Ccitt => crc == crc16_ccitt(frame).to_le_bytes(),
+            Checksum::Crc32 => crc == crc32(frame).to_le_bytes(),
+        };
+        if !valid {
+            return Err(Error::ChecksumMismatch);
+        }
+        data.truncate(data_len);
+        Ok(())
+    }
+}
+
+/// The frame delimiters of a [`Profile`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Delimiters {
+    /// A zero-byte after each frame.
+    Trailing,
+    /// A zero-byte before and after each frame. The leading delimiter ends any partial frame
+    /// left by noise on an idle line, at the cost of an empty frame between consecutive frames,
+    /// which receivers skip.
+    LeadingAndTrailing,
+}
+
+impl Profile {
+    /// The encoding variant.
+    pub const fn variant(self) -> Variant {
+        match self {
+            Profile::Cobs | Profile::CobsCrc32 => Variant::Cobs,
+            Profile::Cobsr | Profile::CobsrCrc16Ccitt => Variant::Cobsr,
+        }
+    }
+
+    /// The checksum.
+    pub const fn checksum(self) -> Checksum {
+        match self {
+            Profile::Cobs | Profile::Cobsr => Checksum::None,
+            Profile::CobsCrc32 => Checksum::Crc32,
+            Profile::CobsrCrc16Ccitt => Checksum::Crc16Ccitt,
+        }
+    }
+
+    /// The frame delimiters.
+    pub const fn delimiters(self) -> Delimiters {
+        match self {
+            Profile::CobsrCrc16Ccitt => Delimiters::LeadingAndTrailing,
+            _ => Delimiters::Trailing,
+        }
+    }
+
+    /// The maximum length of the frame data, not including the checksum, if there is a maximum.
+    pub const fn max_len(self) -> Option<usize> {
+        match self {
+            Profile::CobsrCrc16Ccitt => Some(251),
+            _ => None,
+        }
+    }
+
+    /// Append the checksum to the frame data, and encode it, without delimiters.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::FrameTooLong`]
+    pub fn encode(self, frame: &[u8]) -> Result<Vec<u8>> {
+        self.check_len(frame.len())?;
+        let mut data = frame.to_vec();
+        self.checksum().append(&mut data);
+        match self.variant() {
+            Variant::Cobs => cobs::encode_vector(&data),
+            Variant::Cobsr => cobsr::encode_vector(&data),
+        }
+    }
+
+    /// Encode the frame data with [`Profile::encode()`], and add the delimiters.
+    pub fn encode_frame(self, frame: &[u8]) -> Result<Vec<u8>> {
+        let frame_encoded = self.encode(frame)?;
+        let mut out_vec = Vec::with_capacity(frame_encoded.len() + 2);
+        if self.delimiters() == Delimiters::LeadingAndTrailing {
+            out_vec.push(0);
+        }
+        out_vec.extend_from_slice(&frame_encoded);
+        out_vec.push(0);
+        Ok(out_vec)
+    }
+
+    /// Decode an encoded frame, without delimiters, and check and remove its checksum.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::ZeroInEncodedData`]
+    /// * [`Error::TruncatedEncodedData`], for COBS.
+    /// * [`Error::FrameTooLong`]
+    /// * [`Error::ChecksumMismatch`]
+    pub fn decode(self, in_buf: &[u8]) -> Result<Vec<u8>> {
+        let mut data = match self.variant() {
+            Variant::Cobs => cobs::decode_vector(in_buf)?,
+            Variant::Cobsr => cobsr::decode_vector(in_buf)?,
+        };
+        self.check_len(data.len().saturating_sub(self.checksum().len()))?;
+        self.checksum().check_and_remove(&mut data)?;
+        Ok(data)
+    }
+
+    /// The format identifier byte, which prefixes a frame encoded with
+    /// [`Profile::encode_tagged()`].
+    ///
+    /// See the [`crate::profile`] module for the layout of the identifier.
+    pub const fn id(self) -> u8 {
+        let variant = match self.variant() {
+            Variant::Cobs => 0x01,
+            Variant::Cobsr => 0x02,
+        };
+        let checksum = match self.checksum() {
+            Checksum::None => 0x00,
+            Checksum::Crc16Ccitt => 0x10,
+            Checksum::Crc32 => 0x20,
+        };
+        variant | checksum
+    }
+
+    /// Get the profile with the given format identifier byte, if there is one.
+    pub const fn from_id(id: u8) -> Option<Profile> {
+        match id {
+            0x01 => Some(Profile::Cobs),
+            0x02 => Some(Profile::Cobsr),
+            0x21 => Some(Profile::CobsCrc32),
+            0x12 => Some(Profile::CobsrCrc16Ccitt),
+            _ => None,
+        }
+    }
+
+    /// Encode the frame data with [`Profile::encode()`], prefixed with the format identifier byte,
+    /// without delimiters.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::FrameTooLong`]
+    pub fn encode_tagged(self, frame: &[u8]) -> Result<Vec<u8>> {
+        let frame_encoded = self.encode(frame)?;
+        let mut out_vec = Vec::with_capacity(frame_encoded.len() + 1);
+        out_vec.push(self.id());
+        out_vec.extend_from_slice(&frame_encoded);
+        Ok(out_vec)
+    }
+
+    fn check_len(self, len: usize) -> Result<()> {
+        match self.max_len() {
+            Some(max_len) if len > max_len => Err(Error::FrameTooLong),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Decode a frame prefixed with a format identifier byte, without delimiters, with the profile
+/// given by the identifier.
+///
+/// This decodes frames from [`Profile::encode_tagged()`], so frames from senders using different
+/// profiles can be received together. See the [`crate::profile`] module for details.
+///
+/// The return value is a [`Result`] that in the [`Ok`] case is the profile of the frame, and the
+/// decoded frame data.
+///
+/// The following errors could be returned:
+///
+/// * [`Error::UnknownFormat`], if the frame is empty, or the identifier is not of a profile.
+/// * The errors of [`Profile::decode()`].
+pub fn decode_tagged(in_buf: &[u8]) -> Result<(Profile, Vec<u8>)> {
+    let (&id, frame_encoded) = in_buf.split_first().ok_or(Error::UnknownFormat)?;
+    let profile = Profile::from_id(id).ok_or(Error::UnknownFormat)?;
+    Ok((profile, profile.decode(frame_encoded)?))
+}
+
+/// Apply trait [`Stage`]. The stage encodes and decodes without delimiters.
+impl Stage for Profile {
+    fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        Profile::encode(*self, &data)
+    }
+
+    fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        Profile::decode(*self, &data)
+    }
+}
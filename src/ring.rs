@@ -0,0 +1,101 @@
+//! Decoding of frames received through a [`heapless::spsc`] ring buffer.
+//!
+//! A typical `no_std` receive path has an interrupt handler (or a reader thread) which puts raw
+//! received bytes into a [`heapless::spsc::Queue`], via its [`Producer`](heapless::spsc::Producer).
+//! The application then drains the queue's [`Consumer`] from its main loop, decoding each complete
+//! frame.
+//!
+//! A [`RingReceiver`] holds the encoded bytes of a frame that has only partly been received. Its
+//! capacity `N` is the maximum encoded frame size, not including the frame delimiter. Frames may
+//! wrap around the end of the ring buffer's storage; the bytes are simply taken from the queue in
+//! order. The decode functions are [`cobs::decode_from_ring()`](crate::cobs::decode_from_ring)
+//! and [`cobsr::decode_from_ring()`](crate::cobsr::decode_from_ring).
+//!
+//! Empty frames (consecutive zero-bytes) are skipped, unless set otherwise by
+//! [`RingReceiver::set_empty_frames()`]. A frame that is longer than `N` is discarded, and reported
+//! as [`Error::OutputBufferTooSmall`] once its frame delimiter is received.
+//!
+//! Note that [`heapless::spsc`] is only available on some targets, and on others may need
+//! `heapless` feature `portable-atomic`. See the `heapless` documentation for details.
+
+use crate::{EmptyFrames, Error, Result};
+use heapless::spsc::Consumer;
+
+/// Holds the encoded bytes of a partly received frame, drained from a [`heapless::spsc`] ring
+/// buffer. See the [`crate::ring`] module for details.
+#[derive(Debug, Clone)]
+pub struct RingReceiver<const N: usize> {
+    buf: heapless::Vec<u8, N>,
+    overflow: bool,
+    empty_frames: EmptyFrames,
+}
+
+impl<const N: usize> RingReceiver<N> {
+    /// Create a new receiver, with no partly received frame.
+    pub const fn new() -> RingReceiver<N> {
+        RingReceiver {
+            buf: heapless::Vec::new(),
+            overflow: false,
+            empty_frames: EmptyFrames::Skip,
+        }
+    }
+
+    /// Set how empty frames (consecutive zero-bytes) are handled. By default, they are skipped.
+    ///
+    /// If set to [`EmptyFrames::Surface`], each empty frame is decoded as a frame of zero length.
+    pub fn set_empty_frames(&mut self, empty_frames: EmptyFrames) {
+        self.empty_frames = empty_frames;
+    }
+
+    /// Discard any partly received frame.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.overflow = false;
+    }
+
+    /// Number of encoded bytes of the partly received frame.
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Returns `true` if there is no partly received frame.
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty() && !self.overflow
+    }
+
+    /// Take bytes from the consumer until the end of a frame, then decode the frame using the
+    /// given decode function.
+    ///
+    /// Returns [`None`] if the consumer becomes empty before the end of a frame.
+    pub(crate) fn poll_with<'a, F>(
+        &mut self,
+        consumer: &mut Consumer<'_, u8>,
+        decode: F,
+    ) -> Option<Result<&'a [u8]>>
+    where
+        F: FnOnce(&[u8]) -> Result<&'a [u8]>,
+    {
+        while let Some(byte) = consumer.dequeue() {
+            if byte != 0 {
+                if self.buf.push(byte).is_err() {
+                    self.overflow = true;
+                }
+            } else if self.overflow {
+                self.clear();
+                return Some(Err(Error::OutputBufferTooSmall));
+            } else if !self.buf.is_empty() || self.empty_frames == EmptyFrames::Surface {
+                let result = decode(&self.buf);
+                self.buf.clear();
+                return Some(result);
+            }
+        }
+        None
+    }
+}
+
+/// Implement trait [Default].
+impl<const N: usize> Default for RingReceiver<N> {
+    fn default() -> RingReceiver<N> {
+        RingReceiver::new()
+    }
+}
@@ -0,0 +1,237 @@
+//! Routing of frames from a stream to several destinations, on a separate thread.
+//!
+//! A [`FrameRouter`] reads a stream of zero-delimited frames from a [`std::io::Read`] source,
+//! decodes each frame, and dispatches it to the first route that matches it. A route matches
+//! frames by a channel-ID byte (the first byte of the decoded frame), or by a user predicate. Each
+//! route delivers frames to a [`Sink`], which can be a channel sender or a handler closure.
+//!
+//! Frames that are empty, that fail to decode, or that match no route, are discarded.
+//!
+//! For a long-running stream, such as a large file, [`FrameRouter::progress()`] sets a callback
+//! which is periodically given the [`Progress`] of decoding.
+//!
+//! ### Backpressure
+//!
+//! Frames are delivered to a [`std::sync::mpsc::SyncSender`] with a blocking send. So if a
+//! receiver falls behind and its channel becomes full, the router stops reading the stream until
+//! there is room, and the stream source (such as the OS buffer of a serial port) holds the
+//! excess data. An application which would rather drop frames for a slow receiver can use a
+//! handler closure which calls [`std::sync::mpsc::SyncSender::try_send()`].
+//!
+//! Example:
+//!
+//!     use cobs2::{cobs, router::FrameRouter};
+//!     use std::sync::mpsc;
+//!
+//!     let (log_tx, log_rx) = mpsc::sync_channel(4);
+//!     let (data_tx, data_rx) = mpsc::sync_channel(4);
+//!     let router = FrameRouter::new(cobs::decode_vector)
+//!         .route_channel_id(0x01, log_tx)
+//!         .route_channel_id(0x02, data_tx);
+//!
+//!     let stream = b"\x04\x01hi\x00\x05\x02\x01\x02\x03\x00".to_vec();
+//!     router.spawn(std::io::Cursor::new(stream)).join().unwrap().unwrap();
+//!     assert_eq!(log_rx.recv().unwrap(), b"\x01hi");
+//!     assert_eq!(data_rx.recv().unwrap(), b"\x02\x01\x02\x03");
+
+use crate::profile::Profile;
+use crate::stream::{Progress, ProgressReporter};
+use crate::Result;
+use std::boxed::Box;
+use std::io::{self, BufRead, BufReader, Read};
+use std::sync::mpsc::{Sender, SyncSender};
+use std::thread;
+use std::vec::Vec;
+
+/// A destination for frames dispatched by a [`FrameRouter`].
+///
+/// This is implemented for [`SyncSender`] and [`Sender`] of `Vec<u8>`, and for closures which take
+/// a `Vec<u8>`. If the receiver of a channel has been dropped, frames for it are discarded.
+pub trait Sink: Send {
+    /// Deliver a decoded frame.
+    fn deliver(&mut self, frame: Vec<u8>);
+}
+
+/// Apply trait [`Sink`], with a blocking send.
+impl Sink for SyncSender<Vec<u8>> {
+    fn deliver(&mut self, frame: Vec<u8>) {
+        let _ = self.send(frame);
+    }
+}
+
+/// Apply trait [`Sink`].
+impl Sink for Sender<Vec<u8>> {
+    fn deliver(&mut self, frame: Vec<u8>) {
+        let _ = self.send(frame);
+    }
+}
+
+/// Apply trait [`Sink`] to handler closures.
+impl<F> Sink for F
+where
+    F: FnMut(Vec<u8>) + Send,
+{
+    fn deliver(&mut self, frame: Vec<u8>) {
+        self(frame)
+    }
+}
+
+/// A predicate which selects the frames for a route.
+type Predicate = Box<dyn Fn(&[u8]) -> bool + Send>;
+
+/// A function which decodes a frame.
+type DecodeFn = Box<dyn Fn(&[u8]) -> Result<Vec<u8>> + Send>;
+
+struct Route {
+    predicate: Predicate,
+    sink: Box<dyn Sink>,
+}
+
+/// Reads a framed stream, and dispatches decoded frames to routes.
+///
+/// See the [`crate::router`] module for details.
+pub struct FrameRouter {
+    decode: DecodeFn,
+    routes: Vec<Route>,
+    progress: Option<ProgressReporter>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::Metrics>,
+}
+
+impl FrameRouter {
+    /// Create a new router, with no routes, which decodes frames with the given decode function,
+    /// such as [`crate::cobs::decode_vector()`] or [`crate::cobsr::decode_vector()`].
+    pub fn new(decode: fn(&[u8]) -> Result<Vec<u8>>) -> FrameRouter {
+        FrameRouter {
+            decode: Box::new(decode),
+            routes: Vec::new(),
+            progress: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Create a new router, with no routes, which decodes frames with the given framing profile.
+    pub fn with_profile(profile: Profile) -> FrameRouter {
+        FrameRouter {
+            decode: Box::new(move |frame_encoded| profile.decode(frame_encoded)),
+            routes: Vec::new(),
+            progress: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Add a route for decoded frames for which the predicate returns `true`.
+    ///
+    /// Routes are checked in the order they were added. A frame is delivered only to the first
+    /// matching route.
+    pub fn route<P, S>(mut self, predicate: P, sink: S) -> FrameRouter
+    where
+        P: Fn(&[u8]) -> bool + Send + 'static,
+        S: Sink + 'static,
+    {
+        self.routes.push(Route {
+            predicate: Box::new(predicate),
+            sink: Box::new(sink),
+        });
+        self
+    }
+
+    /// Add a route for decoded frames whose first byte is the given channel ID. The frame is
+    /// delivered including the channel ID byte.
+    pub fn route_channel_id<S>(self, channel_id: u8, sink: S) -> FrameRouter
+    where
+        S: Sink + 'static,
+    {
+        self.route(move |frame| frame.first() == Some(&channel_id), sink)
+    }
+
+    /// Set a callback, which is given the [`Progress`] of decoding the stream, each time at least
+    /// `interval` more bytes have been consumed, and at the end of the stream.
+    pub fn progress<F>(mut self, interval: u64, callback: F) -> FrameRouter
+    where
+        F: FnMut(Progress) + Send + 'static,
+    {
+        self.progress = Some(ProgressReporter::new(interval, callback));
+        self
+    }
+
+    /// Attach metrics, which are updated as frames are received.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: crate::metrics::Metrics) -> FrameRouter {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Decode a frame, and deliver it to the first matching route.
+    fn dispatch(&mut self, frame_encoded: &[u8]) {
+        let result = (self.decode)(frame_encoded);
+        if let Some(progress) = &mut self.progress {
+            progress.frame_done(result.is_ok());
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.received(result.is_ok());
+        }
+        let frame = match result {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        if let Some(route) = self
+            .routes
+            .iter_mut()
+            .find(|route| (route.predicate)(&frame))
+        {
+            route.sink.deliver(frame);
+        }
+    }
+
+    /// Read and route frames on the current thread, until the end of the stream.
+    ///
+    /// A partial frame at the end of the stream, without a trailing zero-byte, is discarded.
+    ///
+    /// The return value is an [`io::Result`], which is an error if reading the stream failed.
+    pub fn run<R>(mut self, reader: R) -> io::Result<()>
+    where
+        R: Read,
+    {
+        let mut reader = BufReader::new(reader);
+        let mut frame_buf = Vec::new();
+        loop {
+            frame_buf.clear();
+            reader.read_until(0, &mut frame_buf)?;
+            if let Some(progress) = &mut self.progress {
+                progress.consume(frame_buf.len());
+            }
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &self.metrics {
+                metrics.bytes_received(frame_buf.len());
+            }
+            match frame_buf.split_last() {
+                Some((0, frame_encoded)) => {
+                    if !frame_encoded.is_empty() {
+                        self.dispatch(frame_encoded);
+                    }
+                }
+                // End of the stream.
+                _ => {
+                    if let Some(progress) = &mut self.progress {
+                        progress.report();
+                    }
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Read and route frames on a new thread, until the end of the stream.
+    ///
+    /// The thread's return value is the result of [`FrameRouter::run()`].
+    pub fn spawn<R>(self, reader: R) -> thread::JoinHandle<io::Result<()>>
+    where
+        R: Read + Send + 'static,
+    {
+        thread::spawn(move || self.run(reader))
+    }
+}
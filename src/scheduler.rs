@@ -0,0 +1,315 @@
+//! Priority-aware scheduling of frames to transmit, so that small control frames don't wait
+//! behind large bulk transfers on a shared link.
+//!
+//! A [`TxScheduler`] has a queue of high-priority frames and a queue of low-priority messages. A
+//! low-priority message which is longer than the fragment length is sent as a sequence of
+//! fragments, each of which is a complete, valid frame of at most one COBS block. Before each
+//! fragment, any queued high-priority frames are sent. So a high-priority frame waits for at most
+//! one fragment, rather than for the whole message.
+//!
+//! The receiver passes each decoded frame to a [`Reassembler`], which returns the high-priority
+//! frames as they arrive, and each low-priority message once all its fragments have arrived.
+//!
+//! Each frame's data is prefixed with a kind byte, before encoding:
+//!
+//! | Kind | Frame data                                   |
+//! |------|----------------------------------------------|
+//! | 0    | A whole message                              |
+//! | 1    | The first fragment of a message              |
+//! | 2    | A middle fragment of a message               |
+//! | 3    | The last fragment of a message               |
+//!
+//! Only one low-priority message is sent at a time, so the fragments of a message are not
+//! interleaved with the fragments of another message, only with whole frames.
+//!
+//! Example:
+//!
+//!     use cobs2::profile::Profile;
+//!     use cobs2::scheduler::{Priority, Reassembler, TxScheduler};
+//!
+//!     let mut scheduler = TxScheduler::new();
+//!     scheduler.push(Priority::Low, vec![0x55_u8; 1000]).unwrap();
+//!     let mut uart = scheduler.next_frame().unwrap();
+//!     // A control frame is queued while the bulk message is being sent.
+//!     scheduler.push(Priority::High, b"STOP".to_vec()).unwrap();
+//!     while let Some(frame) = scheduler.next_frame() {
+//!         uart.extend_from_slice(&frame);
+//!     }
+//!
+//!     // The receiver gets the control frame before the rest of the bulk message.
+//!     let mut reassembler = Reassembler::new(4096);
+//!     let mut messages = Vec::new();
+//!     for frame in uart.split(|&x| x == 0).filter(|frame| !frame.is_empty()) {
+//!         let frame_decoded = Profile::Cobs.decode(frame).unwrap();
+//!         if let Some(message) = reassembler.push(&frame_decoded).unwrap() {
+//!             messages.push(message);
+//!         }
+//!     }
+//!     assert_eq!(messages, [b"STOP".to_vec(), vec![0x55; 1000]]);
+
+use crate::profile::Profile;
+use crate::{Error, Result};
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+const KIND_WHOLE: u8 = 0;
+const KIND_FIRST: u8 = 1;
+const KIND_MIDDLE: u8 = 2;
+const KIND_LAST: u8 = 3;
+
+/// The priority of a frame given to a [`TxScheduler`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Priority {
+    /// Sent whole, before the next fragment of any low-priority message.
+    High,
+    /// Sent whole if it is no longer than the fragment length, otherwise as fragments.
+    Low,
+}
+
+/// A low-priority message which is partly sent.
+#[derive(Debug, Clone)]
+struct Fragmenting {
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// Schedules frames to transmit, with high-priority frames preempting low-priority messages
+/// between fragments.
+///
+/// See the [`crate::scheduler`] module for details.
+#[derive(Debug, Clone)]
+pub struct TxScheduler {
+    profile: Profile,
+    fragment_len: usize,
+    high: VecDeque<Vec<u8>>,
+    low: VecDeque<Vec<u8>>,
+    fragmenting: Option<Fragmenting>,
+}
+
+impl TxScheduler {
+    /// The default fragment length, so that a fragment and its kind byte are a single COBS block.
+    pub const DEFAULT_FRAGMENT_LEN: usize = 253;
+
+    /// Create a new scheduler, using COBS encoding.
+    pub fn new() -> TxScheduler {
+        TxScheduler::with_profile(Profile::Cobs)
+    }
+
+    /// Create a new scheduler, using COBS/R encoding.
+    pub fn new_cobsr() -> TxScheduler {
+        TxScheduler::with_profile(Profile::Cobsr)
+    }
+
+    /// Create a new scheduler, using the given framing profile.
+    ///
+    /// The fragment length is [`TxScheduler::DEFAULT_FRAGMENT_LEN`], or less if needed for the
+    /// profile's maximum frame length.
+    pub fn with_profile(profile: Profile) -> TxScheduler {
+        let fragment_len = match profile.max_len() {
+            Some(max_len) => TxScheduler::DEFAULT_FRAGMENT_LEN.min(max_len - 1),
+            None => TxScheduler::DEFAULT_FRAGMENT_LEN,
+        };
+        TxScheduler {
+            profile,
+            fragment_len,
+            high: VecDeque::new(),
+            low: VecDeque::new(),
+            fragmenting: None,
+        }
+    }
+
+    /// Set the maximum length of the data of each fragment of a low-priority message, which is
+    /// the most data that a high-priority frame may wait for. It is at least 1.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::FrameTooLong`], if a fragment would be longer than the profile's maximum frame
+    ///   length.
+    pub fn set_fragment_len(&mut self, fragment_len: usize) -> Result<()> {
+        self.check_len(fragment_len)?;
+        self.fragment_len = fragment_len.max(1);
+        Ok(())
+    }
+
+    /// Get the maximum length of the data of each fragment.
+    pub fn fragment_len(&self) -> usize {
+        self.fragment_len
+    }
+
+    /// Queue a frame to transmit.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::FrameTooLong`], if a high-priority frame is longer than the profile's maximum
+    ///   frame length.
+    pub fn push(&mut self, priority: Priority, frame: Vec<u8>) -> Result<()> {
+        match priority {
+            Priority::High => {
+                self.check_len(frame.len())?;
+                self.high.push_back(frame);
+            }
+            Priority::Low => self.low.push_back(frame),
+        }
+        Ok(())
+    }
+
+    /// Get the next frame to transmit, encoded with its delimiters, or [`None`] if there is
+    /// nothing to transmit.
+    pub fn next_frame(&mut self) -> Option<Vec<u8>> {
+        if let Some(frame) = self.high.pop_front() {
+            return Some(self.encode_frame(KIND_WHOLE, &frame));
+        }
+        if self.fragmenting.is_none() {
+            let data = self.low.pop_front()?;
+            if data.len() <= self.fragment_len {
+                return Some(self.encode_frame(KIND_WHOLE, &data));
+            }
+            self.fragmenting = Some(Fragmenting { data, offset: 0 });
+        }
+        let mut fragmenting = self.fragmenting.take()?;
+        let start = fragmenting.offset;
+        let end = fragmenting.data.len().min(start + self.fragment_len);
+        let kind = if start == 0 {
+            KIND_FIRST
+        } else if end == fragmenting.data.len() {
+            KIND_LAST
+        } else {
+            KIND_MIDDLE
+        };
+        let frame = self.encode_frame(kind, &fragmenting.data[start..end]);
+        fragmenting.offset = end;
+        if kind != KIND_LAST {
+            self.fragmenting = Some(fragmenting);
+        }
+        Some(frame)
+    }
+
+    /// Returns `true` if there is nothing to transmit.
+    pub fn is_empty(&self) -> bool {
+        self.high.is_empty() && self.low.is_empty() && self.fragmenting.is_none()
+    }
+
+    /// Get the number of queued high-priority frames.
+    pub fn high_len(&self) -> usize {
+        self.high.len()
+    }
+
+    /// Get the number of queued low-priority messages, including any partly sent message.
+    pub fn low_len(&self) -> usize {
+        self.low.len() + usize::from(self.fragmenting.is_some())
+    }
+
+    fn encode_frame(&self, kind: u8, data: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(data.len() + 1);
+        frame.push(kind);
+        frame.extend_from_slice(data);
+        self.profile
+            .encode_frame(&frame)
+            .expect("scheduled frames are within the profile's maximum length")
+    }
+
+    fn check_len(&self, len: usize) -> Result<()> {
+        match self.profile.max_len() {
+            Some(max_len) if len >= max_len => Err(Error::FrameTooLong),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Apply trait [`Default`], as [`TxScheduler::new()`].
+impl Default for TxScheduler {
+    fn default() -> TxScheduler {
+        TxScheduler::new()
+    }
+}
+
+/// Reassembles the messages sent by a [`TxScheduler`], from the decoded frames.
+///
+/// See the [`crate::scheduler`] module for details.
+#[derive(Debug, Clone)]
+pub struct Reassembler {
+    max_len: usize,
+    buf: Vec<u8>,
+    in_progress: bool,
+}
+
+impl Reassembler {
+    /// Create a new reassembler, for messages of up to `max_len` bytes.
+    pub fn new(max_len: usize) -> Reassembler {
+        Reassembler {
+            max_len,
+            buf: Vec::new(),
+            in_progress: false,
+        }
+    }
+
+    /// Discard any partly reassembled message.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.in_progress = false;
+    }
+
+    /// Returns `true` if a message is partly reassembled.
+    pub fn in_progress(&self) -> bool {
+        self.in_progress
+    }
+
+    /// Process a decoded frame.
+    ///
+    /// The return value is a [`Result`] that in the [`Ok`] case is the whole frame or the
+    /// reassembled message, if the frame completes one, or [`None`] if the frame is the first or
+    /// a middle fragment.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * [`Error::UnknownFormat`], if the frame is empty, or its kind byte is not known.
+    /// * [`Error::FragmentLost`], if a fragment arrives out of sequence, as when a frame is lost.
+    ///   If the fragment is the first fragment of a message, the partly reassembled message is
+    ///   discarded, and reassembly of the new message starts. Otherwise the fragment is discarded.
+    /// * [`Error::FrameTooLong`], if the message is longer than the maximum length. The partly
+    ///   reassembled message is discarded.
+    pub fn push(&mut self, frame: &[u8]) -> Result<Option<Vec<u8>>> {
+        let (&kind, data) = frame.split_first().ok_or(Error::UnknownFormat)?;
+        match kind {
+            KIND_WHOLE => {
+                if data.len() > self.max_len {
+                    return Err(Error::FrameTooLong);
+                }
+                Ok(Some(data.to_vec()))
+            }
+            KIND_FIRST => {
+                let lost = self.in_progress;
+                self.clear();
+                self.in_progress = true;
+                self.append(data)?;
+                if lost {
+                    Err(Error::FragmentLost)
+                } else {
+                    Ok(None)
+                }
+            }
+            KIND_MIDDLE | KIND_LAST => {
+                if !self.in_progress {
+                    return Err(Error::FragmentLost);
+                }
+                self.append(data)?;
+                if kind == KIND_LAST {
+                    self.in_progress = false;
+                    Ok(Some(core::mem::take(&mut self.buf)))
+                } else {
+                    Ok(None)
+                }
+            }
+            _ => Err(Error::UnknownFormat),
+        }
+    }
+
+    fn append(&mut self, data: &[u8]) -> Result<()> {
+        if self.buf.len() + data.len() > self.max_len {
+            self.clear();
+            return Err(Error::FrameTooLong);
+        }
+        self.buf.extend_from_slice(data);
+        Ok(())
+    }
+}
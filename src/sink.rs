@@ -0,0 +1,87 @@
+//! Pluggable consumers of decoded frames.
+//!
+//! A [`FrameSink`] is given each decoded frame by a decoder, such as
+//! [`cobs::FrameAccumulator::feed_sink()`](crate::cobs::FrameAccumulator::feed_sink) or
+//! [`stream::FrameReader::read_to_sink()`](crate::stream::FrameReader::read_to_sink). So the
+//! decoding code doesn't depend on how frames are delivered, and a test can collect the frames in
+//! a vector, while an application sends them to a channel.
+//!
+//! It is implemented for:
+//!
+//! * Closures which take a `&[u8]` and return a [`Result`](core::result::Result).
+//! * `Vec<Vec<u8>>` (feature `alloc`), which collects a copy of each frame.
+//! * [`std::sync::mpsc::Sender`] and [`std::sync::mpsc::SyncSender`] of `Vec<u8>` (feature
+//!   `std`), with a blocking send. The error is a [`std::sync::mpsc::SendError`] if the receiver
+//!   has been dropped.
+//!
+//! Example:
+//!
+//!     use cobs2::cobs::FrameAccumulator;
+//!
+//!     let mut accumulator = FrameAccumulator::<64>::new();
+//!     let mut frames: Vec<Vec<u8>> = Vec::new();
+//!     let invalid = accumulator.feed_sink(b"\x04ABC\x00\x05AB\x00\x02z\x00", &mut frames);
+//!     assert_eq!(invalid, Ok(1));
+//!     assert_eq!(frames, [&b"ABC"[..], b"z"]);
+//!
+//!     let mut total_len = 0;
+//!     let mut count_len = |frame: &[u8]| -> Result<(), ()> {
+//!         total_len += frame.len();
+//!         Ok(())
+//!     };
+//!     accumulator.feed_sink(b"\x04ABC\x00\x02z\x00", &mut count_len).unwrap();
+//!     assert_eq!(total_len, 4);
+
+/// A consumer of decoded frames.
+///
+/// See the [`crate::sink`] module for details.
+pub trait FrameSink {
+    /// The error returned if a frame can't be delivered.
+    type Error;
+
+    /// Deliver a decoded frame.
+    fn on_frame(&mut self, frame: &[u8]) -> core::result::Result<(), Self::Error>;
+}
+
+/// Apply trait [`FrameSink`] to closures.
+impl<F, E> FrameSink for F
+where
+    F: FnMut(&[u8]) -> core::result::Result<(), E>,
+{
+    type Error = E;
+
+    fn on_frame(&mut self, frame: &[u8]) -> core::result::Result<(), E> {
+        self(frame)
+    }
+}
+
+/// Apply trait [`FrameSink`], collecting a copy of each frame.
+#[cfg(feature = "alloc")]
+impl FrameSink for alloc::vec::Vec<alloc::vec::Vec<u8>> {
+    type Error = core::convert::Infallible;
+
+    fn on_frame(&mut self, frame: &[u8]) -> core::result::Result<(), Self::Error> {
+        self.push(frame.to_vec());
+        Ok(())
+    }
+}
+
+/// Apply trait [`FrameSink`], with a blocking send.
+#[cfg(feature = "std")]
+impl FrameSink for std::sync::mpsc::SyncSender<std::vec::Vec<u8>> {
+    type Error = std::sync::mpsc::SendError<std::vec::Vec<u8>>;
+
+    fn on_frame(&mut self, frame: &[u8]) -> core::result::Result<(), Self::Error> {
+        self.send(frame.to_vec())
+    }
+}
+
+/// Apply trait [`FrameSink`].
+#[cfg(feature = "std")]
+impl FrameSink for std::sync::mpsc::Sender<std::vec::Vec<u8>> {
+    type Error = std::sync::mpsc::SendError<std::vec::Vec<u8>>;
+
+    fn on_frame(&mut self, frame: &[u8]) -> core::result::Result<(), Self::Error> {
+        self.send(frame.to_vec())
+    }
+}
@@ -0,0 +1,1629 @@
+//! Blocking framed channel over a byte stream, such as a TCP connection, reading of frames with
+//! read timeouts, and buffered writing of frames.
+//!
+//! A [`CobsStream`] wraps any stream that implements [`std::io::Read`] and [`std::io::Write`].
+//! [`CobsStream::send()`] encodes a frame and writes it followed by a zero-byte delimiter.
+//! [`CobsStream::recv()`] reads until the next delimiter, and decodes the frame. Reads are
+//! buffered, so a frame can arrive in several parts, or several frames in one read.
+//!
+//! If a received frame is invalid, [`CobsStream::recv()`] returns an error of kind
+//! [`io::ErrorKind::InvalidData`], which wraps the [`Error`]. The invalid frame is discarded, so
+//! the next call receives the following frame. Empty frames are skipped. A frame longer than the
+//! maximum set by [`CobsStream::set_max_frame_len()`] is discarded as it is read, without being
+//! buffered, and reported as [`Error::FrameTooLong`].
+//!
+//! [`CobsStream::set_validator()`] sets a validator, which checks each decoded frame before it is
+//! returned, such as for a magic byte, minimum length or version field. Rejected frames are
+//! counted, and are either skipped or reported as an error, according to [`RejectedFrames`].
+//!
+//! For long-running decoding, such as of a large file, [`CobsStream::set_progress()`] sets a
+//! callback which is periodically given the [`Progress`] of decoding, to drive a progress bar or
+//! a watchdog. [`crate::router::FrameRouter::progress()`] does the same for a router.
+//!
+//! [`CobsStream::set_frame_hook()`] and [`FrameReader::set_frame_hook()`] set a callback which is
+//! given a [`FrameEvent`] at each frame boundary: when a frame starts to arrive, when it is
+//! complete, and when it is discarded as invalid. This suits metrics, tracing, or blinking an
+//! activity LED, without changing the decode loop.
+//!
+//! Example:
+//!
+//!     use cobs2::stream::CobsTcpStream;
+//!     use std::net::{TcpListener, TcpStream};
+//!
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let mut client = CobsTcpStream::new(TcpStream::connect(listener.local_addr().unwrap()).unwrap());
+//!     let mut server = CobsTcpStream::new(listener.accept().unwrap().0);
+//!
+//!     client.send(b"ABC\0ghij\0xyz").unwrap();
+//!     let mut frame = Vec::new();
+//!     server.recv(&mut frame).unwrap();
+//!     assert_eq!(frame, b"ABC\0ghij\0xyz");
+//!
+//! ### Request and response
+//!
+//! A [`Transceiver`] is for the common command/response pattern of a device. Its
+//! [`request()`](Transceiver::request) sends a frame, and waits for the next complete frame as the
+//! response, with a timeout.
+//!
+//! Example:
+//!
+//!     use cobs2::stream::Transceiver;
+//!     use std::net::{TcpListener, TcpStream};
+//!     use std::time::Duration;
+//!
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+//!     client.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+//!     let mut transceiver = Transceiver::new(client);
+//!     let mut device = cobs2::stream::CobsTcpStream::new(listener.accept().unwrap().0);
+//!
+//!     let device_thread = std::thread::spawn(move || {
+//!         let mut command = Vec::new();
+//!         device.recv(&mut command).unwrap();
+//!         assert_eq!(command, b"ping");
+//!         device.send(b"pong").unwrap();
+//!     });
+//!     let response = transceiver.request(b"ping", Duration::from_secs(5)).unwrap();
+//!     assert_eq!(response, b"pong");
+//!     device_thread.join().unwrap();
+//!
+//! ### Full-duplex link
+//!
+//! A [`Link`] owns both directions of a connection, as separate reader and writer halves, with
+//! one framing profile and combined [`LinkStats`]. So an application can hold one object per
+//! connection, rather than a separate decoder and encoder. An async twin, for `futures-io`
+//! streams, is `cobs2::async_stream::AsyncLink` (feature `futures-io`).
+//!
+//! Example:
+//!
+//!     use cobs2::stream::Link;
+//!     use std::net::{TcpListener, TcpStream};
+//!
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+//!     let mut client = Link::new(client.try_clone().unwrap(), client);
+//!     let server = listener.accept().unwrap().0;
+//!     let mut server = Link::new(server.try_clone().unwrap(), server);
+//!
+//!     client.send(b"ABC\0xyz").unwrap();
+//!     let mut frame = Vec::new();
+//!     server.recv(&mut frame).unwrap();
+//!     assert_eq!(frame, b"ABC\0xyz");
+//!     assert_eq!(client.stats().frames_sent, 1);
+//!     assert_eq!(server.stats().frames_received, 1);
+//!
+//! ### Reading with timeouts
+//!
+//! A [`FrameReader`] reads frames from any [`std::io::Read`], such as a serial port, which is
+//! configured with a read timeout. A read which times out is not mistaken for the end of the
+//! stream, and a partly received frame is kept. [`FrameReader::try_read_frame()`] returns
+//! `Ok(None)` when a read times out, so the caller can do other work between frames.
+//!
+//! Example:
+//!
+//!     use cobs2::stream::FrameReader;
+//!     use std::net::{TcpListener, TcpStream};
+//!     use std::io::Write;
+//!     use std::time::Duration;
+//!
+//!     let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+//!     let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+//!     let server = listener.accept().unwrap().0;
+//!     server.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+//!     let mut reader = FrameReader::new(server);
+//!
+//!     client.write_all(b"\x04AB").unwrap();
+//!     assert_eq!(reader.try_read_frame().unwrap(), None);
+//!     client.write_all(b"C\x00").unwrap();
+//!     assert_eq!(reader.read_frame().unwrap(), b"ABC");
+//!
+//! [`FrameReader::set_io_error_policy()`] sets how transient read errors are handled, such as a
+//! connection reset, or a USB serial device which briefly disappears while it re-enumerates. By
+//! default they are returned like any other error, but they can be skipped, or retried with
+//! exponential backoff, according to [`IoErrorPolicy`]. Which errors are transient is decided by
+//! [`is_transient_io_error()`], or by a function set with
+//! [`FrameReader::set_transient_io_errors()`]. This is separate from the handling of invalid
+//! frames, which are always discarded, so reading resynchronises at the next frame.
+//!
+//! ### Trait objects
+//!
+//! [`send_frame()`], [`recv_frame()`] and [`decode_stream()`] take the reader or writer as a trait
+//! object, such as `&mut dyn Write`, rather than a generic type. So they are compiled once, for
+//! use across plugin boundaries, with dynamic dispatch, and as stable symbols for FFI shims.
+//!
+//! ### Buffered writing
+//!
+//! A [`BufferedFrameWriter`] encodes frames into a buffer, and writes the buffered data to the
+//! underlying writer according to a [`FlushPolicy`]. This controls the write granularity, which
+//! matters for links such as USB CDC and BLE UART bridges: writing each frame in one write call,
+//! or coalescing many small frames into fewer, larger writes.
+//!
+//! Example:
+//!
+//!     use cobs2::stream::{BufferedFrameWriter, FlushPolicy};
+//!
+//!     let mut writer = BufferedFrameWriter::new(Vec::new(), FlushPolicy::Explicit);
+//!     writer.write_frame(b"ABC").unwrap();
+//!     writer.write_frame(b"xy").unwrap();
+//!     assert!(writer.get_ref().is_empty());
+//!     writer.flush().unwrap();
+//!     assert_eq!(writer.get_ref(), b"\x04ABC\x00\x03xy\x00");
+
+use crate::profile::Profile;
+use crate::sink::FrameSink;
+use crate::Error;
+use std::boxed::Box;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// A blocking framed channel over a stream.
+///
+/// See the [`crate::stream`] module for details.
+pub struct CobsStream<S>
+where
+    S: Read + Write,
+{
+    reader: BufReader<S>,
+    profile: Profile,
+    frame_buf: Vec<u8>,
+    progress: Option<ProgressReporter>,
+    validator: Option<Validator>,
+    frame_hook: Option<FrameHook>,
+    rejected_frames: u64,
+    max_frame_len: usize,
+}
+
+/// How a [`CobsStream`] handles frames which are rejected by its validator.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RejectedFrames {
+    /// Rejected frames are skipped.
+    Skip,
+    /// Rejected frames are returned as an error of kind [`io::ErrorKind::InvalidData`], which
+    /// wraps [`Error::FrameRejected`].
+    Report,
+}
+
+type ValidatorFn = Box<dyn FnMut(&[u8]) -> bool + Send>;
+
+struct Validator {
+    rejected_frames: RejectedFrames,
+    check: ValidatorFn,
+}
+
+/// An event at a frame boundary, given to the callback set by [`CobsStream::set_frame_hook()`]
+/// or [`FrameReader::set_frame_hook()`].
+///
+/// Each [`Start`](FrameEvent::Start) is followed by a [`Complete`](FrameEvent::Complete) or an
+/// [`Error`](FrameEvent::Error) for the same frame, unless the stream ends, or a read error
+/// discards the partial frame, first. Empty frames give no events.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FrameEvent {
+    /// The first byte of a frame has been received.
+    Start,
+    /// A frame has been received and decoded.
+    Complete {
+        /// The length of the decoded frame.
+        len: usize,
+    },
+    /// A frame has been discarded, because it is invalid, too long, or rejected by the validator.
+    Error {
+        /// Why the frame was discarded.
+        reason: Error,
+        /// The number of encoded bytes which were discarded, including the delimiter.
+        discarded: usize,
+    },
+}
+
+type FrameHook = Box<dyn FnMut(FrameEvent) + Send>;
+
+/// A [`CobsStream`] over a TCP connection.
+pub type CobsTcpStream = CobsStream<std::net::TcpStream>;
+
+impl<S> CobsStream<S>
+where
+    S: Read + Write,
+{
+    /// Create a new framed channel over the stream, using COBS encoding.
+    pub fn new(stream: S) -> CobsStream<S> {
+        CobsStream::with_profile(stream, Profile::Cobs)
+    }
+
+    /// Create a new framed channel over the stream, using COBS/R encoding.
+    pub fn new_cobsr(stream: S) -> CobsStream<S> {
+        CobsStream::with_profile(stream, Profile::Cobsr)
+    }
+
+    /// Create a new framed channel over the stream, using the given framing profile.
+    pub fn with_profile(stream: S, profile: Profile) -> CobsStream<S> {
+        CobsStream {
+            reader: BufReader::new(stream),
+            profile,
+            frame_buf: Vec::new(),
+            progress: None,
+            validator: None,
+            frame_hook: None,
+            rejected_frames: 0,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Set the maximum length of a received encoded frame, not including its delimiter. By
+    /// default, it is [`DEFAULT_MAX_FRAME_LEN`].
+    ///
+    /// A longer frame is not buffered, but is read and discarded up to its delimiter, and
+    /// reported as an error by [`recv()`](CobsStream::recv). For
+    /// [`set_progress()`](CobsStream::set_progress), it is counted as an invalid frame.
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Set a callback, which is given the [`Progress`] of decoding received frames, each time at
+    /// least `interval` more bytes have been consumed, and at the end of the stream.
+    pub fn set_progress<F>(&mut self, interval: u64, callback: F)
+    where
+        F: FnMut(Progress) + Send + 'static,
+    {
+        self.progress = Some(ProgressReporter::new(interval, callback));
+    }
+
+    /// Set a validator, which checks each decoded frame before it is returned by
+    /// [`recv()`](CobsStream::recv). It returns `true` to accept the frame, or `false` to reject
+    /// it.
+    ///
+    /// Rejected frames are counted by [`rejected_frames()`](CobsStream::rejected_frames), and are
+    /// handled according to `rejected_frames`. For [`set_progress()`](CobsStream::set_progress),
+    /// they are counted as decoded frames.
+    pub fn set_validator<F>(&mut self, rejected_frames: RejectedFrames, validator: F)
+    where
+        F: FnMut(&[u8]) -> bool + Send + 'static,
+    {
+        self.validator = Some(Validator {
+            rejected_frames,
+            check: Box::new(validator),
+        });
+    }
+
+    /// Set a callback, which is given a [`FrameEvent`] at each frame boundary, as frames are
+    /// received by [`recv()`](CobsStream::recv).
+    ///
+    /// Example:
+    ///
+    ///     use cobs2::stream::{CobsStream, FrameEvent};
+    ///     use std::io::Cursor;
+    ///     use std::sync::mpsc;
+    ///
+    ///     let mut stream = CobsStream::new(Cursor::new(b"\x04ABC\x00\x05AB\x00".to_vec()));
+    ///     let (tx, rx) = mpsc::channel();
+    ///     stream.set_frame_hook(move |event| tx.send(event).unwrap());
+    ///     let mut frame = Vec::new();
+    ///     stream.recv(&mut frame).unwrap();
+    ///     assert!(stream.recv(&mut frame).is_err());
+    ///     assert_eq!(
+    ///         rx.try_iter().collect::<Vec<_>>(),
+    ///         [
+    ///             FrameEvent::Start,
+    ///             FrameEvent::Complete { len: 3 },
+    ///             FrameEvent::Start,
+    ///             FrameEvent::Error {
+    ///                 reason: cobs2::Error::TruncatedEncodedData,
+    ///                 discarded: 4,
+    ///             },
+    ///         ]
+    ///     );
+    ///
+    pub fn set_frame_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FrameEvent) + Send + 'static,
+    {
+        self.frame_hook = Some(Box::new(hook));
+    }
+
+    /// Get the number of frames which have been rejected by the validator.
+    pub fn rejected_frames(&self) -> u64 {
+        self.rejected_frames
+    }
+
+    /// Encode a frame, and write it to the stream followed by a zero-byte delimiter (and preceded
+    /// by one, if the framing profile has leading delimiters).
+    pub fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        let stream = self.reader.get_mut();
+        stream.write_all(&frame_encoded)?;
+        stream.flush()
+    }
+
+    /// Read the next frame from the stream, and decode it into `frame`.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the stream.
+    /// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+    ///   than the maximum set by [`set_max_frame_len()`](CobsStream::set_max_frame_len). The
+    ///   frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameRejected`], if the frame is
+    ///   rejected by the validator, with [`RejectedFrames::Report`]. The frame is discarded.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the stream ends before a complete frame.
+    pub fn recv(&mut self, frame: &mut Vec<u8>) -> io::Result<()> {
+        loop {
+            self.frame_buf.clear();
+            let (read_len, too_long) = read_frame_bounded(
+                &mut self.reader,
+                &mut self.frame_buf,
+                self.max_frame_len,
+                &mut self.frame_hook,
+            )?;
+            if let Some(progress) = &mut self.progress {
+                progress.consume(read_len);
+            }
+            if too_long {
+                if let Some(progress) = &mut self.progress {
+                    progress.frame_done(false);
+                }
+                if let Some(hook) = &mut self.frame_hook {
+                    hook(FrameEvent::Error {
+                        reason: Error::FrameTooLong,
+                        discarded: read_len,
+                    });
+                }
+                return Err(to_io_error(Error::FrameTooLong));
+            }
+            match self.frame_buf.split_last() {
+                Some((0, [])) => continue,
+                Some((0, frame_encoded)) => {
+                    let result = self.profile.decode(frame_encoded);
+                    if let Some(progress) = &mut self.progress {
+                        progress.frame_done(result.is_ok());
+                    }
+                    if let (Err(e), Some(hook)) = (&result, &mut self.frame_hook) {
+                        hook(FrameEvent::Error {
+                            reason: e.clone(),
+                            discarded: self.frame_buf.len(),
+                        });
+                    }
+                    let decoded = result.map_err(to_io_error)?;
+                    if let Some(validator) = &mut self.validator {
+                        if !(validator.check)(&decoded) {
+                            self.rejected_frames += 1;
+                            if let Some(hook) = &mut self.frame_hook {
+                                hook(FrameEvent::Error {
+                                    reason: Error::FrameRejected,
+                                    discarded: self.frame_buf.len(),
+                                });
+                            }
+                            match validator.rejected_frames {
+                                RejectedFrames::Skip => continue,
+                                RejectedFrames::Report => {
+                                    return Err(to_io_error(Error::FrameRejected))
+                                }
+                            }
+                        }
+                    }
+                    if let Some(hook) = &mut self.frame_hook {
+                        hook(FrameEvent::Complete { len: decoded.len() });
+                    }
+                    *frame = decoded;
+                    return Ok(());
+                }
+                _ => {
+                    if let Some(progress) = &mut self.progress {
+                        progress.report();
+                    }
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    ///
+    /// Reading directly from the stream could lose data, because of the read buffering.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    /// Consume the framed channel, returning the underlying stream. Any buffered data is lost.
+    pub fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+}
+
+/// A blocking request/response helper over a stream.
+///
+/// See the [`crate::stream`] module for details.
+///
+/// The timeout of [`request()`](Transceiver::request) is checked each time a read of the stream
+/// returns. So the stream should have a read timeout that is shorter than the request timeout,
+/// such as set by [`std::net::TcpStream::set_read_timeout()`], and reads which time out should
+/// return an error of kind [`io::ErrorKind::WouldBlock`] or [`io::ErrorKind::TimedOut`].
+pub struct Transceiver<S>
+where
+    S: Read + Write,
+{
+    stream: S,
+    profile: Profile,
+    rx_buf: Vec<u8>,
+    rx_too_long: bool,
+    max_frame_len: usize,
+}
+
+impl<S> Transceiver<S>
+where
+    S: Read + Write,
+{
+    /// Create a new request/response helper over the stream, using COBS encoding.
+    pub fn new(stream: S) -> Transceiver<S> {
+        Transceiver::with_profile(stream, Profile::Cobs)
+    }
+
+    /// Create a new request/response helper over the stream, using COBS/R encoding.
+    pub fn new_cobsr(stream: S) -> Transceiver<S> {
+        Transceiver::with_profile(stream, Profile::Cobsr)
+    }
+
+    /// Create a new request/response helper over the stream, using the given framing profile.
+    pub fn with_profile(stream: S, profile: Profile) -> Transceiver<S> {
+        Transceiver {
+            stream,
+            profile,
+            rx_buf: Vec::new(),
+            rx_too_long: false,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+        }
+    }
+
+    /// Set the maximum length of a received encoded frame, not including its delimiter. By
+    /// default, it is [`DEFAULT_MAX_FRAME_LEN`].
+    ///
+    /// A longer frame is not buffered, but is discarded as it is read up to its delimiter, and
+    /// reported as an error by [`request()`](Transceiver::request).
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Send a request frame, then wait for the next complete frame, and return it decoded.
+    ///
+    /// Any data already buffered by the transceiver, such as the rest of the data read with an
+    /// earlier response, is discarded. But data not yet read from the stream isn't. So a late
+    /// response to an earlier request which timed out is taken as the response, if it is received
+    /// before the response to this request. To tell them apart, a protocol could include a
+    /// sequence number in each request and its response. While waiting, empty frames and frames
+    /// which fail to decode are discarded, so the response is the next valid frame.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from writing or reading the stream.
+    /// * [`io::ErrorKind::InvalidData`], if the request can't be encoded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the response is
+    ///   longer than the maximum set by [`set_max_frame_len()`](Transceiver::set_max_frame_len).
+    ///   The response is discarded.
+    /// * [`io::ErrorKind::TimedOut`], if no response is received within the timeout.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the stream ends before a response is received.
+    pub fn request(&mut self, frame: &[u8], timeout: Duration) -> io::Result<Vec<u8>> {
+        let deadline = Instant::now() + timeout;
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        self.rx_buf.clear();
+        self.rx_too_long = false;
+        self.stream.write_all(&frame_encoded)?;
+        self.stream.flush()?;
+
+        let mut read_buf = [0_u8; 256];
+        let mut scan_start = 0;
+        loop {
+            while let Some(pos) = self.rx_buf[scan_start..].iter().position(|&x| x == 0) {
+                let end = scan_start + pos;
+                if self.rx_too_long || end > self.max_frame_len {
+                    self.rx_buf.drain(..=end);
+                    self.rx_too_long = false;
+                    return Err(to_io_error(Error::FrameTooLong));
+                }
+                let result = self.profile.decode(&self.rx_buf[..end]);
+                let is_empty = end == 0;
+                self.rx_buf.drain(..=end);
+                scan_start = 0;
+                match result {
+                    Ok(response) if !is_empty => return Ok(response),
+                    _ => {}
+                }
+            }
+            if self.rx_buf.len() > self.max_frame_len {
+                self.rx_buf.clear();
+                self.rx_too_long = true;
+            }
+            scan_start = self.rx_buf.len();
+            if Instant::now() >= deadline {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            match self.stream.read(&mut read_buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => self.rx_buf.extend_from_slice(&read_buf[..len]),
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                            | io::ErrorKind::Interrupted
+                    ) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Get a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Get a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Consume the request/response helper, returning the underlying stream. Any received data
+    /// that has not been used is lost.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// A blocking full-duplex framed link, over separate read and write halves of a connection.
+///
+/// See the [`crate::stream`] module for details.
+pub struct Link<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    reader: BufReader<R>,
+    writer: W,
+    profile: Profile,
+    frame_buf: Vec<u8>,
+    max_frame_len: usize,
+    stats: LinkStats,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::Metrics>,
+}
+
+impl<R, W> Link<R, W>
+where
+    R: Read,
+    W: Write,
+{
+    /// Create a new link over the reader and writer, using COBS encoding.
+    pub fn new(reader: R, writer: W) -> Link<R, W> {
+        Link::with_profile(reader, writer, Profile::Cobs)
+    }
+
+    /// Create a new link over the reader and writer, using COBS/R encoding.
+    pub fn new_cobsr(reader: R, writer: W) -> Link<R, W> {
+        Link::with_profile(reader, writer, Profile::Cobsr)
+    }
+
+    /// Create a new link over the reader and writer, using the given framing profile.
+    pub fn with_profile(reader: R, writer: W, profile: Profile) -> Link<R, W> {
+        Link {
+            reader: BufReader::new(reader),
+            writer,
+            profile,
+            frame_buf: Vec::new(),
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            stats: LinkStats::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Set the maximum length of a received encoded frame, not including its delimiter. By
+    /// default, it is [`DEFAULT_MAX_FRAME_LEN`].
+    ///
+    /// A longer frame is not buffered, but is read and discarded up to its delimiter, counted as
+    /// an invalid frame, and reported as an error by [`recv()`](Link::recv).
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Attach metrics, which are updated along with the link's own statistics.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: crate::metrics::Metrics) -> Link<R, W> {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Encode a frame, and write it to the writer followed by a zero-byte delimiter (and preceded
+    /// by one, if the framing profile has leading delimiters).
+    pub fn send(&mut self, frame: &[u8]) -> io::Result<()> {
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        self.writer.write_all(&frame_encoded)?;
+        self.writer.flush()?;
+        self.stats.sent(frame_encoded.len());
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.sent(frame_encoded.len());
+        }
+        Ok(())
+    }
+
+    /// Read the next frame from the reader, and decode it into `frame`.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader.
+    /// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+    ///   than the maximum set by [`set_max_frame_len()`](Link::set_max_frame_len). The frame is
+    ///   discarded.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the reader ends before a complete frame.
+    pub fn recv(&mut self, frame: &mut Vec<u8>) -> io::Result<()> {
+        loop {
+            self.frame_buf.clear();
+            let (read_len, too_long) = read_frame_bounded(
+                &mut self.reader,
+                &mut self.frame_buf,
+                self.max_frame_len,
+                &mut None,
+            )?;
+            self.stats.bytes_received += read_len as u64;
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = &self.metrics {
+                metrics.bytes_received(read_len);
+            }
+            if too_long {
+                self.stats.received(false);
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.received(false);
+                }
+                return Err(to_io_error(Error::FrameTooLong));
+            }
+            match self.frame_buf.split_last() {
+                Some((0, [])) => continue,
+                Some((0, frame_encoded)) => {
+                    let result = self.profile.decode(frame_encoded);
+                    self.stats.received(result.is_ok());
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &self.metrics {
+                        metrics.received(result.is_ok());
+                    }
+                    *frame = result.map_err(to_io_error)?;
+                    return Ok(());
+                }
+                _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+            }
+        }
+    }
+
+    /// Get the framing profile.
+    pub fn profile(&self) -> Profile {
+        self.profile
+    }
+
+    /// Get the statistics of both directions of the link.
+    pub fn stats(&self) -> LinkStats {
+        self.stats
+    }
+
+    /// Get references to the underlying reader and writer.
+    pub fn get_ref(&self) -> (&R, &W) {
+        (self.reader.get_ref(), &self.writer)
+    }
+
+    /// Get mutable references to the underlying reader and writer.
+    ///
+    /// Reading directly from the reader could lose data, because of the read buffering.
+    pub fn get_mut(&mut self) -> (&mut R, &mut W) {
+        (self.reader.get_mut(), &mut self.writer)
+    }
+
+    /// Consume the link, returning the underlying reader and writer. Any buffered received data
+    /// is lost.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader.into_inner(), self.writer)
+    }
+}
+
+/// Statistics of both directions of a [`Link`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct LinkStats {
+    /// Number of frames sent.
+    pub frames_sent: u64,
+    /// Number of encoded bytes sent, including delimiters.
+    pub bytes_sent: u64,
+    /// Number of valid frames received.
+    pub frames_received: u64,
+    /// Number of bytes received, including delimiters and invalid frames.
+    pub bytes_received: u64,
+    /// Number of received frames which failed to decode, or were longer than the maximum.
+    pub invalid_frames: u64,
+}
+
+impl LinkStats {
+    /// Count a frame sent, with its encoded length.
+    pub(crate) fn sent(&mut self, len: usize) {
+        self.frames_sent += 1;
+        self.bytes_sent += len as u64;
+    }
+
+    /// Count a non-empty frame received.
+    pub(crate) fn received(&mut self, valid: bool) {
+        if valid {
+            self.frames_received += 1;
+        } else {
+            self.invalid_frames += 1;
+        }
+    }
+}
+
+/// Progress of decoding a stream, given to a progress callback.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Progress {
+    /// Number of bytes consumed from the stream.
+    pub bytes: u64,
+    /// Number of frames decoded.
+    pub frames: u64,
+    /// Number of frames which failed to decode.
+    pub invalid_frames: u64,
+}
+
+/// Calls a progress callback periodically, as a stream is decoded.
+pub(crate) struct ProgressReporter {
+    progress: Progress,
+    interval: u64,
+    next_report: u64,
+    callback: Box<dyn FnMut(Progress) + Send>,
+}
+
+impl ProgressReporter {
+    pub(crate) fn new<F>(interval: u64, callback: F) -> ProgressReporter
+    where
+        F: FnMut(Progress) + Send + 'static,
+    {
+        ProgressReporter {
+            progress: Progress::default(),
+            interval,
+            next_report: interval,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Count bytes consumed from the stream.
+    pub(crate) fn consume(&mut self, len: usize) {
+        self.progress.bytes += len as u64;
+    }
+
+    /// Count a frame, then call the callback if it is due.
+    pub(crate) fn frame_done(&mut self, valid: bool) {
+        if valid {
+            self.progress.frames += 1;
+        } else {
+            self.progress.invalid_frames += 1;
+        }
+        if self.progress.bytes >= self.next_report {
+            self.report();
+        }
+    }
+
+    /// Call the callback.
+    pub(crate) fn report(&mut self) {
+        self.next_report = self.progress.bytes.saturating_add(self.interval);
+        (self.callback)(self.progress);
+    }
+}
+
+pub(crate) fn to_io_error(e: Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// The default maximum length of a received encoded frame, not including its delimiter, of
+/// [`CobsStream`], [`Transceiver`], [`Link`], [`FrameReader`] and
+/// [`crate::async_stream::AsyncLink`].
+pub const DEFAULT_MAX_FRAME_LEN: usize = 64 * 1024;
+
+/// Read up to and including the next zero-byte delimiter into `frame_buf`, like
+/// [`BufRead::read_until()`], but without buffering more than `max_len` bytes of the frame.
+///
+/// Returns the number of bytes read, and whether the frame was longer than `max_len`. If it was,
+/// the rest of it is read and discarded, up to and including its delimiter, and `frame_buf` is
+/// left empty. [`FrameEvent::Start`] is given to `frame_hook` when a non-empty frame starts.
+fn read_frame_bounded<B>(
+    reader: &mut B,
+    frame_buf: &mut Vec<u8>,
+    max_len: usize,
+    frame_hook: &mut Option<FrameHook>,
+) -> io::Result<(usize, bool)>
+where
+    B: BufRead + ?Sized,
+{
+    let mut read_len = 0;
+    let mut too_long = false;
+    loop {
+        let available = match reader.fill_buf() {
+            Ok(available) => available,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if available.is_empty() {
+            return Ok((read_len, too_long));
+        }
+        if read_len == 0 && available[0] != 0 {
+            if let Some(hook) = frame_hook {
+                hook(FrameEvent::Start);
+            }
+        }
+        let (used, done) = match available.iter().position(|&x| x == 0) {
+            Some(pos) => (pos + 1, true),
+            None => (available.len(), false),
+        };
+        let frame_len = read_len + used - usize::from(done);
+        if frame_len > max_len {
+            too_long = true;
+            frame_buf.clear();
+        } else {
+            frame_buf.extend_from_slice(&available[..used]);
+        }
+        reader.consume(used);
+        read_len += used;
+        if done {
+            return Ok((read_len, too_long));
+        }
+    }
+}
+
+/// When a [`BufferedFrameWriter`], or an `async_stream::AsyncLink`, writes its buffered data to
+/// the underlying writer.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FlushPolicy {
+    /// Write and flush after each frame, so each frame is written in one write call.
+    EachFrame,
+    /// Write and flush when the buffered data exceeds the given number of bytes.
+    Threshold(usize),
+    /// Write and flush when the given number of frames are buffered.
+    Frames(usize),
+    /// Only write and flush when [`BufferedFrameWriter::flush()`], or
+    /// `async_stream::AsyncLink::flush()`, is called.
+    Explicit,
+}
+
+impl FlushPolicy {
+    /// Returns `true` if buffered data of `len` bytes, in `frames` frames, is due to be written.
+    pub(crate) fn is_due(self, len: usize, frames: usize) -> bool {
+        match self {
+            FlushPolicy::EachFrame => true,
+            FlushPolicy::Threshold(threshold) => len > threshold,
+            FlushPolicy::Frames(max_frames) => frames >= max_frames,
+            FlushPolicy::Explicit => false,
+        }
+    }
+}
+
+/// A buffered writer of encoded frames, with a configurable [`FlushPolicy`].
+///
+/// See the [`crate::stream`] module for details.
+///
+/// Buffered data that has not been written is lost if the writer is dropped, so
+/// [`flush()`](BufferedFrameWriter::flush) should be called when done.
+pub struct BufferedFrameWriter<W>
+where
+    W: Write,
+{
+    writer: W,
+    profile: Profile,
+    policy: FlushPolicy,
+    buf: Vec<u8>,
+    buf_frames: usize,
+}
+
+impl<W> BufferedFrameWriter<W>
+where
+    W: Write,
+{
+    /// Create a new buffered writer of frames to the writer, using COBS encoding.
+    pub fn new(writer: W, policy: FlushPolicy) -> BufferedFrameWriter<W> {
+        BufferedFrameWriter::with_profile(writer, policy, Profile::Cobs)
+    }
+
+    /// Create a new buffered writer of frames to the writer, using COBS/R encoding.
+    pub fn new_cobsr(writer: W, policy: FlushPolicy) -> BufferedFrameWriter<W> {
+        BufferedFrameWriter::with_profile(writer, policy, Profile::Cobsr)
+    }
+
+    /// Create a new buffered writer of frames to the writer, using the given framing profile.
+    pub fn with_profile(
+        writer: W,
+        policy: FlushPolicy,
+        profile: Profile,
+    ) -> BufferedFrameWriter<W> {
+        BufferedFrameWriter {
+            writer,
+            profile,
+            policy,
+            buf: Vec::new(),
+            buf_frames: 0,
+        }
+    }
+
+    /// Encode a frame into the buffer, with its delimiters, then write and flush the buffered data
+    /// if the flush policy says so.
+    pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+        let frame_encoded = self.profile.encode_frame(frame).map_err(to_io_error)?;
+        self.buf.extend_from_slice(&frame_encoded);
+        self.buf_frames += 1;
+        if self.policy.is_due(self.buf.len(), self.buf_frames) {
+            self.flush()
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Write the buffered data to the underlying writer, in one write call if possible, then
+    /// flush the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if !self.buf.is_empty() {
+            self.writer.write_all(&self.buf)?;
+            self.buf.clear();
+            self.buf_frames = 0;
+        }
+        self.writer.flush()
+    }
+
+    /// Get the number of bytes of buffered data, which have not been written yet.
+    pub fn buffered_len(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Get the flush policy.
+    pub fn policy(&self) -> FlushPolicy {
+        self.policy
+    }
+
+    /// Set the flush policy. It applies from the next frame.
+    pub fn set_policy(&mut self, policy: FlushPolicy) {
+        self.policy = policy;
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    /// Get a mutable reference to the underlying writer.
+    ///
+    /// Writing directly to the writer could interleave data with buffered frames.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Flush the buffered data, then consume the buffered writer, returning the underlying
+    /// writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// How a [`FrameReader`], or an `async_stream::AsyncLink`, handles transient read errors, as
+/// decided by [`is_transient_io_error()`] or the function set by
+/// [`FrameReader::set_transient_io_errors()`]. Other errors are always returned.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum IoErrorPolicy {
+    /// Transient errors are returned, like other errors. This is the default.
+    #[default]
+    Abort,
+    /// Transient errors are skipped, and reading continues. Any partly received frame is
+    /// discarded, since data may have been lost. An error which persists makes the reader retry
+    /// without a delay, so [`IoErrorPolicy::Retry`] suits such errors better.
+    Skip,
+    /// Reading is retried after a delay, which starts at `initial_backoff`, and doubles for each
+    /// consecutive error, up to `max_backoff`. After `max_retries` consecutive errors, the error is
+    /// returned. Any partly received frame is kept.
+    ///
+    /// The reader must be able to recover by itself, such as a wrapper which reopens a serial
+    /// device after it re-enumerates.
+    Retry {
+        /// The maximum number of consecutive errors which are retried.
+        max_retries: u32,
+        /// The delay before the first retry.
+        initial_backoff: Duration,
+        /// The maximum delay between retries.
+        max_backoff: Duration,
+    },
+}
+
+/// Returns `true` if a read error is likely to be transient, so that reading may succeed again:
+///
+/// * [`io::ErrorKind::ConnectionReset`], [`io::ErrorKind::ConnectionAborted`],
+///   [`io::ErrorKind::NotConnected`] and [`io::ErrorKind::BrokenPipe`], as when a connection drops.
+/// * [`io::ErrorKind::UnexpectedEof`] and [`io::ErrorKind::NotFound`].
+/// * On Linux, the OS errors `EIO`, `ENXIO` and `ENODEV`, as when a USB serial device is
+///   unplugged or re-enumerates.
+///
+/// Interruptions and timeouts are not included, since the readers always handle those.
+pub fn is_transient_io_error(e: &io::Error) -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        const EIO: i32 = 5;
+        const ENXIO: i32 = 6;
+        const ENODEV: i32 = 19;
+        if matches!(e.raw_os_error(), Some(EIO | ENXIO | ENODEV)) {
+            return true;
+        }
+    }
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::NotFound
+    )
+}
+
+/// What a reader does after a transient read error, according to its [`IoErrorPolicy`].
+pub(crate) enum IoErrorAction {
+    /// Discard any partly received frame, and continue reading.
+    Skip,
+    /// Wait for the delay, then continue reading.
+    Retry(Duration),
+}
+
+/// The state of a reader's handling of transient read errors.
+pub(crate) struct IoErrorRecovery {
+    policy: IoErrorPolicy,
+    is_transient: fn(&io::Error) -> bool,
+    consecutive: u32,
+    recovered: u64,
+}
+
+impl IoErrorRecovery {
+    pub(crate) fn new() -> IoErrorRecovery {
+        IoErrorRecovery {
+            policy: IoErrorPolicy::Abort,
+            is_transient: is_transient_io_error,
+            consecutive: 0,
+            recovered: 0,
+        }
+    }
+
+    pub(crate) fn set_policy(&mut self, policy: IoErrorPolicy) {
+        self.policy = policy;
+        self.consecutive = 0;
+    }
+
+    pub(crate) fn set_transient(&mut self, is_transient: fn(&io::Error) -> bool) {
+        self.is_transient = is_transient;
+    }
+
+    /// Get the number of transient errors which have been skipped or retried.
+    pub(crate) fn recovered(&self) -> u64 {
+        self.recovered
+    }
+
+    /// Note a successful read, which ends a sequence of consecutive errors.
+    pub(crate) fn read_ok(&mut self) {
+        self.consecutive = 0;
+    }
+
+    /// Decide what to do after a read error, or return the error if it is not to be recovered.
+    pub(crate) fn handle(&mut self, e: io::Error) -> io::Result<IoErrorAction> {
+        if !(self.is_transient)(&e) {
+            return Err(e);
+        }
+        let action = match self.policy {
+            IoErrorPolicy::Abort => return Err(e),
+            IoErrorPolicy::Skip => IoErrorAction::Skip,
+            IoErrorPolicy::Retry {
+                max_retries,
+                initial_backoff,
+                max_backoff,
+            } => {
+                if self.consecutive >= max_retries {
+                    self.consecutive = 0;
+                    return Err(e);
+                }
+                let backoff = initial_backoff
+                    .checked_mul(1 << self.consecutive.min(31))
+                    .map_or(max_backoff, |backoff| backoff.min(max_backoff));
+                self.consecutive += 1;
+                IoErrorAction::Retry(backoff)
+            }
+        };
+        self.recovered += 1;
+        Ok(action)
+    }
+}
+
+/// A blocking reader of encoded frames, which tolerates read timeouts.
+///
+/// See the [`crate::stream`] module for details.
+///
+/// A read which times out, with an error of kind [`io::ErrorKind::WouldBlock`] or
+/// [`io::ErrorKind::TimedOut`], is not treated as the end of the stream, and a partly received
+/// frame is kept until the rest of it arrives. This suits serial ports and sockets which are
+/// configured with a read timeout.
+pub struct FrameReader<R>
+where
+    R: Read,
+{
+    reader: R,
+    profile: Profile,
+    rx_buf: Vec<u8>,
+    scan_start: usize,
+    rx_too_long: bool,
+    rx_discarded: usize,
+    max_frame_len: usize,
+    recovery: IoErrorRecovery,
+    frame_hook: Option<FrameHook>,
+    frame_started: bool,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::Metrics>,
+}
+
+impl<R> FrameReader<R>
+where
+    R: Read,
+{
+    /// Create a new reader of frames from the reader, using COBS encoding.
+    pub fn new(reader: R) -> FrameReader<R> {
+        FrameReader::with_profile(reader, Profile::Cobs)
+    }
+
+    /// Create a new reader of frames from the reader, using COBS/R encoding.
+    pub fn new_cobsr(reader: R) -> FrameReader<R> {
+        FrameReader::with_profile(reader, Profile::Cobsr)
+    }
+
+    /// Create a new reader of frames from the reader, using the given framing profile.
+    pub fn with_profile(reader: R, profile: Profile) -> FrameReader<R> {
+        FrameReader {
+            reader,
+            profile,
+            rx_buf: Vec::new(),
+            scan_start: 0,
+            rx_too_long: false,
+            rx_discarded: 0,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            recovery: IoErrorRecovery::new(),
+            frame_hook: None,
+            frame_started: false,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Set the maximum length of a received encoded frame, not including its delimiter. By
+    /// default, it is [`DEFAULT_MAX_FRAME_LEN`].
+    ///
+    /// A longer frame is not buffered, but is discarded as it is read up to its delimiter, and
+    /// reported as an error by [`try_read_frame()`](FrameReader::try_read_frame).
+    pub fn set_max_frame_len(&mut self, max_frame_len: usize) {
+        self.max_frame_len = max_frame_len;
+    }
+
+    /// Set how transient read errors are handled. By default, they are returned, like other
+    /// errors.
+    ///
+    /// Example:
+    ///
+    ///     use cobs2::stream::{FrameReader, IoErrorPolicy};
+    ///     use std::io::{self, Read};
+    ///     use std::time::Duration;
+    ///
+    ///     // A device which is reset once, part way through a frame.
+    ///     struct Device(Vec<&'static [u8]>);
+    ///     impl Read for Device {
+    ///         fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    ///             match self.0.remove(0) {
+    ///                 b"" => Err(io::ErrorKind::ConnectionReset.into()),
+    ///                 data => {
+    ///                     buf[..data.len()].copy_from_slice(data);
+    ///                     Ok(data.len())
+    ///                 }
+    ///             }
+    ///         }
+    ///     }
+    ///
+    ///     let mut reader = FrameReader::new(Device(vec![b"\x04AB", b"", b"C\x00"]));
+    ///     reader.set_io_error_policy(IoErrorPolicy::Retry {
+    ///         max_retries: 5,
+    ///         initial_backoff: Duration::from_millis(1),
+    ///         max_backoff: Duration::from_millis(100),
+    ///     });
+    ///     assert_eq!(reader.read_frame().unwrap(), b"ABC");
+    ///     assert_eq!(reader.recovered_io_errors(), 1);
+    ///
+    pub fn set_io_error_policy(&mut self, policy: IoErrorPolicy) {
+        self.recovery.set_policy(policy);
+    }
+
+    /// Set the function which decides whether a read error is transient, and so is handled
+    /// according to the [`IoErrorPolicy`]. By default, it is [`is_transient_io_error()`].
+    pub fn set_transient_io_errors(&mut self, is_transient: fn(&io::Error) -> bool) {
+        self.recovery.set_transient(is_transient);
+    }
+
+    /// Get the number of transient read errors which have been skipped or retried.
+    pub fn recovered_io_errors(&self) -> u64 {
+        self.recovery.recovered()
+    }
+
+    /// Set a callback, which is given a [`FrameEvent`] at each frame boundary, as frames are
+    /// received by [`try_read_frame()`](FrameReader::try_read_frame). The start of a frame is
+    /// given as soon as its first byte is read, even if the rest of it arrives in a later call.
+    pub fn set_frame_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FrameEvent) + Send + 'static,
+    {
+        self.frame_hook = Some(Box::new(hook));
+    }
+
+    /// Give an event to the frame hook, if any.
+    fn frame_event(&mut self, event: FrameEvent) {
+        self.frame_started = event == FrameEvent::Start;
+        if let Some(hook) = &mut self.frame_hook {
+            hook(event);
+        }
+    }
+
+    /// Give [`FrameEvent::Start`] to the frame hook, if it hasn't been given for this frame.
+    fn frame_start(&mut self) {
+        if !self.frame_started {
+            self.frame_event(FrameEvent::Start);
+        }
+    }
+
+    /// Attach metrics, which are updated as frames are received.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(mut self, metrics: crate::metrics::Metrics) -> FrameReader<R> {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Read the next frame, and return it decoded, waiting through any read timeouts.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader, other than a timeout, or a transient error which is
+    ///   recovered according to the [`IoErrorPolicy`].
+    /// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+    ///   than the maximum set by [`set_max_frame_len()`](FrameReader::set_max_frame_len). The
+    ///   frame is discarded.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the reader ends before a complete frame.
+    pub fn read_frame(&mut self) -> io::Result<Vec<u8>> {
+        loop {
+            if let Some(frame) = self.try_read_frame()? {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Read the next frame, and return it decoded, or return `Ok(None)` if a read times out
+    /// before a complete frame is received. A partly received frame is kept for the next call.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader, other than a timeout, or a transient error which is
+    ///   recovered according to the [`IoErrorPolicy`].
+    /// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+    /// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+    ///   than the maximum set by [`set_max_frame_len()`](FrameReader::set_max_frame_len). The
+    ///   frame is discarded.
+    /// * [`io::ErrorKind::UnexpectedEof`], if the reader ends before a complete frame.
+    pub fn try_read_frame(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut read_buf = [0_u8; 256];
+        loop {
+            while let Some(pos) = self.rx_buf[self.scan_start..].iter().position(|&x| x == 0) {
+                let end = self.scan_start + pos;
+                self.scan_start = 0;
+                if end != 0 || self.rx_too_long {
+                    self.frame_start();
+                }
+                if self.rx_too_long || end > self.max_frame_len {
+                    let discarded = self.rx_discarded + end + 1;
+                    self.rx_buf.drain(..=end);
+                    self.rx_too_long = false;
+                    self.rx_discarded = 0;
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &self.metrics {
+                        metrics.received(false);
+                    }
+                    self.frame_event(FrameEvent::Error {
+                        reason: Error::FrameTooLong,
+                        discarded,
+                    });
+                    return Err(to_io_error(Error::FrameTooLong));
+                }
+                if end == 0 {
+                    self.rx_buf.drain(..=end);
+                    continue;
+                }
+                let result = self.profile.decode(&self.rx_buf[..end]);
+                self.rx_buf.drain(..=end);
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.received(result.is_ok());
+                }
+                self.frame_event(match &result {
+                    Ok(frame) => FrameEvent::Complete { len: frame.len() },
+                    Err(e) => FrameEvent::Error {
+                        reason: e.clone(),
+                        discarded: end + 1,
+                    },
+                });
+                return result.map(Some).map_err(to_io_error);
+            }
+            if !self.rx_buf.is_empty() {
+                self.frame_start();
+            }
+            if self.rx_buf.len() > self.max_frame_len {
+                self.rx_discarded += self.rx_buf.len();
+                self.rx_buf.clear();
+                self.rx_too_long = true;
+            }
+            self.scan_start = self.rx_buf.len();
+            match self.reader.read(&mut read_buf) {
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(len) => {
+                    self.recovery.read_ok();
+                    self.rx_buf.extend_from_slice(&read_buf[..len]);
+                    #[cfg(feature = "metrics")]
+                    if let Some(metrics) = &self.metrics {
+                        metrics.bytes_received(len);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(e) => match self.recovery.handle(e)? {
+                    IoErrorAction::Skip => {
+                        self.rx_buf.clear();
+                        self.scan_start = 0;
+                        self.rx_too_long = false;
+                        self.rx_discarded = 0;
+                        self.frame_started = false;
+                    }
+                    IoErrorAction::Retry(delay) => std::thread::sleep(delay),
+                },
+            }
+        }
+    }
+
+    /// Read frames, giving each decoded frame to the sink, such as a closure, a vector or a
+    /// channel, until the reader ends or a read times out. See [`crate::sink`] for details.
+    ///
+    /// Frames that fail to decode are discarded. A partly received frame is kept for the next
+    /// call.
+    ///
+    /// The return value is an [`io::Result`] that in the [`Ok`] case is the number of frames that
+    /// failed to decode.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from reading the reader, other than a timeout.
+    /// * [`io::ErrorKind::Other`], with the error from the sink, if the sink failed to take a
+    ///   frame.
+    ///
+    /// Example:
+    ///
+    ///     use cobs2::stream::FrameReader;
+    ///
+    ///     let mut reader = FrameReader::new(&b"\x04ABC\x00\x05AB\x00\x02z\x00"[..]);
+    ///     let mut frames: Vec<Vec<u8>> = Vec::new();
+    ///     assert_eq!(reader.read_to_sink(&mut frames).unwrap(), 1);
+    ///     assert_eq!(frames, [&b"ABC"[..], b"z"]);
+    ///
+    pub fn read_to_sink<S>(&mut self, sink: &mut S) -> io::Result<u64>
+    where
+        S: FrameSink + ?Sized,
+        S::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let mut invalid_frames = 0;
+        loop {
+            match self.try_read_frame() {
+                Ok(Some(frame)) => sink.on_frame(&frame).map_err(io::Error::other)?,
+                Ok(None) => return Ok(invalid_frames),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(invalid_frames),
+                Err(e) if e.get_ref().is_some_and(|inner| inner.is::<Error>()) => {
+                    // A decoding error, rather than an error from the reader.
+                    invalid_frames += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Get the number of bytes of a partly received frame, which are buffered.
+    pub fn buffered_len(&self) -> usize {
+        self.rx_buf.len()
+    }
+
+    /// Get a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+
+    /// Get a mutable reference to the underlying reader.
+    ///
+    /// Reading directly from the reader could lose data, because of the read buffering.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    /// Consume the frame reader, returning the underlying reader. Any buffered data is lost.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Encode a frame with the given framing profile, and write it to a writer with its delimiters,
+/// then flush the writer.
+///
+/// This takes the writer as a trait object, so it is compiled once, rather than for each writer
+/// type. That suits plugin boundaries, dynamic dispatch, and FFI shims which export it.
+///
+/// The following errors could be returned:
+///
+/// * An error from writing the writer.
+/// * [`io::ErrorKind::InvalidData`], if the frame can't be encoded with the profile.
+///
+/// Example:
+///
+///     use cobs2::profile::Profile;
+///
+///     let mut out = Vec::new();
+///     cobs2::stream::send_frame(&mut out, Profile::Cobs, b"AB\0C").unwrap();
+///     assert_eq!(out, b"\x03AB\x02C\x00");
+///
+pub fn send_frame(writer: &mut dyn Write, profile: Profile, frame: &[u8]) -> io::Result<()> {
+    let frame_encoded = profile.encode_frame(frame).map_err(to_io_error)?;
+    writer.write_all(&frame_encoded)?;
+    writer.flush()
+}
+
+/// Read the next frame from a buffered reader, and decode it with the given framing profile into
+/// `frame`.
+///
+/// This takes the reader as a trait object, like [`send_frame()`]. It reads only up to the end of
+/// the frame, so following frames stay in the reader's buffer. Empty frames are skipped.
+///
+/// The following errors could be returned:
+///
+/// * An error from reading the reader.
+/// * [`io::ErrorKind::InvalidData`], if the frame is invalid. The frame is discarded.
+/// * [`io::ErrorKind::InvalidData`], wrapping [`Error::FrameTooLong`], if the frame is longer
+///   than [`DEFAULT_MAX_FRAME_LEN`]. The frame is discarded, without being buffered.
+/// * [`io::ErrorKind::UnexpectedEof`], if the reader ends before a complete frame.
+///
+/// Example:
+///
+///     use cobs2::profile::Profile;
+///
+///     let mut received: &[u8] = b"\x00\x03AB\x02C\x00\x02x\x00";
+///     let mut frame = Vec::new();
+///     cobs2::stream::recv_frame(&mut received, Profile::Cobs, &mut frame).unwrap();
+///     assert_eq!(frame, b"AB\0C");
+///     assert_eq!(received, b"\x02x\x00");
+///
+pub fn recv_frame(
+    reader: &mut dyn BufRead,
+    profile: Profile,
+    frame: &mut Vec<u8>,
+) -> io::Result<()> {
+    let mut frame_buf = Vec::new();
+    loop {
+        frame_buf.clear();
+        let (_, too_long) =
+            read_frame_bounded(reader, &mut frame_buf, DEFAULT_MAX_FRAME_LEN, &mut None)?;
+        if too_long {
+            return Err(to_io_error(Error::FrameTooLong));
+        }
+        match frame_buf.split_last() {
+            Some((0, [])) => continue,
+            Some((0, frame_encoded)) => {
+                *frame = profile.decode(frame_encoded).map_err(to_io_error)?;
+                return Ok(());
+            }
+            _ => return Err(io::ErrorKind::UnexpectedEof.into()),
+        }
+    }
+}
+
+/// Read a reader to its end, decoding its frames with the given framing profile, and give each
+/// frame to `on_frame`. Returns the number of bytes read.
+///
+/// This takes the reader and function as trait objects, like [`send_frame()`]. Invalid frames
+/// are given to `on_frame` as an [`Error`], and decoding continues with the next frame. Frames
+/// longer than [`DEFAULT_MAX_FRAME_LEN`] are discarded without being buffered, and given as
+/// [`Error::FrameTooLong`]. Empty frames are skipped. A partial frame at the end of the data is decoded as if a delimiter
+/// followed it.
+///
+/// The following errors could be returned:
+///
+/// * An error from reading the reader.
+///
+/// Example:
+///
+///     use cobs2::profile::Profile;
+///
+///     let mut received: &[u8] = b"\x03AB\x00\x05AB\x00\x02x";
+///     let mut frames = Vec::new();
+///     let mut errors = 0;
+///     let bytes = cobs2::stream::decode_stream(&mut received, Profile::Cobs, &mut |frame| {
+///         match frame {
+///             Ok(frame) => frames.push(frame.to_vec()),
+///             Err(_) => errors += 1,
+///         }
+///     });
+///     assert_eq!(bytes.unwrap(), 10);
+///     assert_eq!(frames, [&b"AB"[..], b"x"]);
+///     assert_eq!(errors, 1);
+///
+pub fn decode_stream(
+    reader: &mut dyn Read,
+    profile: Profile,
+    on_frame: &mut dyn FnMut(crate::Result<&[u8]>),
+) -> io::Result<u64> {
+    let mut read_buf = [0_u8; 4096];
+    let mut frame_buf = Vec::new();
+    let mut too_long = false;
+    let mut bytes = 0_u64;
+    loop {
+        let len = match reader.read(&mut read_buf) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        bytes += len as u64;
+        let mut chunks = read_buf[..len].split(|&x| x == 0).peekable();
+        while let Some(chunk) = chunks.next() {
+            if frame_buf.len() + chunk.len() > DEFAULT_MAX_FRAME_LEN {
+                frame_buf.clear();
+                too_long = true;
+            } else if !too_long {
+                frame_buf.extend_from_slice(chunk);
+            }
+            if chunks.peek().is_none() {
+                // No delimiter after this chunk yet.
+                break;
+            }
+            if too_long {
+                on_frame(Err(Error::FrameTooLong));
+                too_long = false;
+            } else if !frame_buf.is_empty() {
+                decode_to(profile, &frame_buf, on_frame);
+                frame_buf.clear();
+            }
+        }
+    }
+    if too_long {
+        on_frame(Err(Error::FrameTooLong));
+    } else if !frame_buf.is_empty() {
+        decode_to(profile, &frame_buf, on_frame);
+    }
+    Ok(bytes)
+}
+
+/// Decode a frame, and give it to `on_frame`.
+fn decode_to(
+    profile: Profile,
+    frame_encoded: &[u8],
+    on_frame: &mut dyn FnMut(crate::Result<&[u8]>),
+) {
+    match profile.decode(frame_encoded) {
+        Ok(frame) => on_frame(Ok(&frame)),
+        Err(e) => on_frame(Err(e)),
+    }
+}
@@ -0,0 +1,904 @@
+//! Utilities for testing applications' handling of corrupted data (feature `test-util`).
+//!
+//! A [`Corruptor`] corrupts a stream of encoded frames, as a noisy link might. It can flip bits,
+//! drop bytes, inject zero-bytes, and truncate frames, each at a given probability. The
+//! corruption is pseudo-random from a seed, so a failing test can be reproduced.
+//!
+//! The stream can be given as a slice to [`Corruptor::corrupt()`], as an iterator to
+//! [`Corruptor::corrupt_iter()`], or as a reader to [`Corruptor::corrupt_reader()`].
+//!
+//! Example:
+//!
+//!     use cobs2::testing::Corruptor;
+//!
+//!     let data_cobs = b"\x04ABC\x00\x05ghij\x00\x04xyz\x00".repeat(10);
+//!     let mut corruptor = Corruptor::new(1234).flip_bytes(0.01).truncate_frames(0.1);
+//!     let data_corrupt = corruptor.corrupt(&data_cobs);
+//!     assert_ne!(data_corrupt, data_cobs);
+//!
+//!     // The same seed gives the same corruption.
+//!     let mut corruptor = Corruptor::new(1234).flip_bytes(0.01).truncate_frames(0.1);
+//!     assert_eq!(corruptor.corrupt(&data_cobs), data_corrupt);
+//!
+//! ### Round-trip assertions
+//!
+//! [`assert_cobs_roundtrip!`](crate::assert_cobs_roundtrip) checks that payloads round-trip
+//! consistently through every encode and decode function of [`crate::cobs`] and [`crate::cobsr`],
+//! and through [`crate::pipeline`] combinations with a CRC. Every encode function must give the
+//! same encoded data, and every decode function must give back the payload. A failure panics, with
+//! a message naming the function. The checks are also available as functions:
+//! [`assert_roundtrip()`], [`assert_roundtrip_cobs()`], [`assert_roundtrip_cobsr()`] and
+//! [`assert_roundtrip_pipeline()`].
+//!
+//! Example:
+//!
+//!     cobs2::assert_cobs_roundtrip!(b"", b"ABC\0ghij\0xyz", vec![0_u8; 1000]);
+//!
+//! ### Mock transports
+//!
+//! A [`MockTransport`] lets protocol code be tested against the framing layer, such as
+//! [`crate::stream::CobsStream`], without hardware. It implements [`std::io::Read`] and
+//! [`std::io::Write`], and with feature `futures-io`, [`futures_io::AsyncRead`] and
+//! [`futures_io::AsyncWrite`]. Reads follow a script of data, delays and errors, and can be split
+//! into chunks of a given length, as from a UART driver. Writes are collected, and can be split
+//! into short writes, or fail with an error at a given offset.
+//!
+//! [`loopback()`] makes a connected pair of in-memory transports, for testing both ends of a
+//! protocol together.
+//!
+//! Example:
+//!
+//!     use cobs2::stream::CobsStream;
+//!     use cobs2::testing::MockTransport;
+//!     use std::io::ErrorKind;
+//!
+//!     let transport = MockTransport::new()
+//!         .read_data(b"\x04ABC\x00\x03x")
+//!         .read_error(ErrorKind::Interrupted)
+//!         .read_data(b"y\x00")
+//!         .read_chunk_len(2)
+//!         .write_chunk_len(3);
+//!     let mut stream = CobsStream::new(transport);
+//!     let mut frame = Vec::new();
+//!     stream.recv(&mut frame).unwrap();
+//!     assert_eq!(frame, b"ABC");
+//!     stream.recv(&mut frame).unwrap();
+//!     assert_eq!(frame, b"xy");
+//!     stream.send(b"hello").unwrap();
+//!     assert_eq!(stream.get_ref().written(), b"\x06hello\x00");
+
+use crate::pipeline::{Cobs, Cobsr, Crc32, Pipeline};
+use crate::{cobs, cobsr, Error, Result};
+#[cfg(feature = "futures-io")]
+use core::pin::Pin;
+use core::task::Waker;
+#[cfg(feature = "futures-io")]
+use core::task::{Context, Poll};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::vec::Vec;
+
+/// Size of the [`FrameAccumulator`](cobs::FrameAccumulator) buffer used by the round-trip checks.
+/// Longer payloads are not checked with it.
+const ACCUMULATOR_SIZE: usize = 2048;
+
+/// Corrupts a stream of encoded frames, at configured probabilities.
+///
+/// See the [`crate::testing`] module for details.
+#[derive(Debug, Clone)]
+pub struct Corruptor {
+    rng_state: u64,
+    flip: f64,
+    drop: f64,
+    zero: f64,
+    truncate: f64,
+}
+
+impl Corruptor {
+    /// Create a new corruptor, which doesn't corrupt anything until probabilities are set, with
+    /// the given seed for its pseudo-random number generator.
+    pub fn new(seed: u64) -> Corruptor {
+        Corruptor {
+            rng_state: seed,
+            flip: 0.0,
+            drop: 0.0,
+            zero: 0.0,
+            truncate: 0.0,
+        }
+    }
+
+    /// Set the probability that each byte has one bit flipped.
+    pub fn flip_bytes(mut self, probability: f64) -> Corruptor {
+        self.flip = probability;
+        self
+    }
+
+    /// Set the probability that each byte is dropped.
+    pub fn drop_bytes(mut self, probability: f64) -> Corruptor {
+        self.drop = probability;
+        self
+    }
+
+    /// Set the probability that a zero-byte is inserted before each byte.
+    pub fn inject_zeros(mut self, probability: f64) -> Corruptor {
+        self.zero = probability;
+        self
+    }
+
+    /// Set the probability that each frame is truncated, to a random length. The frame's
+    /// delimiter is kept.
+    pub fn truncate_frames(mut self, probability: f64) -> Corruptor {
+        self.truncate = probability;
+        self
+    }
+
+    /// Corrupt a stream of encoded frames.
+    pub fn corrupt(&mut self, in_buf: &[u8]) -> Vec<u8> {
+        let mut out_vec = Vec::with_capacity(in_buf.len());
+        for frame in in_buf.split_inclusive(|x| *x == 0) {
+            match frame.split_last() {
+                Some((0, frame_data)) => self.corrupt_frame(frame_data, true, &mut out_vec),
+                _ => self.corrupt_frame(frame, false, &mut out_vec),
+            }
+        }
+        out_vec
+    }
+
+    /// Corrupt a stream of encoded frames from an iterator, providing the output as an iterator.
+    pub fn corrupt_iter<I>(self, i: I) -> CorruptIter<I>
+    where
+        I: Iterator<Item = u8>,
+    {
+        CorruptIter {
+            corruptor: self,
+            in_iter: i,
+            frame: Vec::new(),
+            out: Vec::new(),
+            out_i: 0,
+        }
+    }
+
+    /// Corrupt a stream of encoded frames from a reader, providing the output as a reader.
+    pub fn corrupt_reader<R>(self, reader: R) -> CorruptReader<R>
+    where
+        R: Read,
+    {
+        CorruptReader {
+            corruptor: self,
+            reader: BufReader::new(reader),
+            frame: Vec::new(),
+            out: Vec::new(),
+            out_i: 0,
+        }
+    }
+
+    /// Corrupt one frame, and its delimiter if it has one, appending the output.
+    fn corrupt_frame(&mut self, frame: &[u8], delimiter: bool, out_vec: &mut Vec<u8>) {
+        let mut frame = frame;
+        if !frame.is_empty() && self.chance(self.truncate) {
+            frame = &frame[..(self.next_u64() % frame.len() as u64) as usize];
+        }
+        let delimiter: &[u8] = if delimiter { &[0] } else { &[] };
+        for &byte_val in frame.iter().chain(delimiter) {
+            if self.chance(self.drop) {
+                continue;
+            }
+            if self.chance(self.zero) {
+                out_vec.push(0);
+            }
+            if self.chance(self.flip) {
+                out_vec.push(byte_val ^ (1 << (self.next_u64() % 8)));
+            } else {
+                out_vec.push(byte_val);
+            }
+        }
+    }
+
+    fn chance(&mut self, probability: f64) -> bool {
+        probability > 0.0 && ((self.next_u64() >> 11) as f64 / (1_u64 << 53) as f64) < probability
+    }
+
+    /// SplitMix64 pseudo-random number generator.
+    fn next_u64(&mut self) -> u64 {
+        self.rng_state = self.rng_state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.rng_state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// Iterator of corrupted data, made by [`Corruptor::corrupt_iter()`].
+pub struct CorruptIter<I>
+where
+    I: Iterator<Item = u8>,
+{
+    corruptor: Corruptor,
+    in_iter: I,
+    frame: Vec<u8>,
+    out: Vec<u8>,
+    out_i: usize,
+}
+
+/// Implement trait [Iterator].
+impl<I> Iterator for CorruptIter<I>
+where
+    I: Iterator<Item = u8>,
+{
+    type Item = u8;
+
+    fn next(&mut self) -> Option<u8> {
+        while self.out_i >= self.out.len() {
+            // Corrupt the next frame.
+            self.out.clear();
+            self.out_i = 0;
+            self.frame.clear();
+            let delimiter = loop {
+                match self.in_iter.next() {
+                    Some(0) => break true,
+                    Some(byte_val) => self.frame.push(byte_val),
+                    None if self.frame.is_empty() => return None,
+                    None => break false,
+                }
+            };
+            self.corruptor
+                .corrupt_frame(&self.frame, delimiter, &mut self.out);
+        }
+        let byte_val = self.out[self.out_i];
+        self.out_i += 1;
+        Some(byte_val)
+    }
+}
+
+/// Reader of corrupted data, made by [`Corruptor::corrupt_reader()`].
+pub struct CorruptReader<R>
+where
+    R: Read,
+{
+    corruptor: Corruptor,
+    reader: BufReader<R>,
+    frame: Vec<u8>,
+    out: Vec<u8>,
+    out_i: usize,
+}
+
+/// Implement trait [Read].
+impl<R> Read for CorruptReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.out_i >= self.out.len() {
+            // Corrupt the next frame.
+            self.out.clear();
+            self.out_i = 0;
+            self.frame.clear();
+            self.reader.read_until(0, &mut self.frame)?;
+            match self.frame.split_last() {
+                None => return Ok(0),
+                Some((0, frame_data)) => {
+                    self.corruptor
+                        .corrupt_frame(frame_data, true, &mut self.out)
+                }
+                Some(_) => self
+                    .corruptor
+                    .corrupt_frame(&self.frame, false, &mut self.out),
+            }
+        }
+        let out_rest = &self.out[self.out_i..];
+        let len = out_rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&out_rest[..len]);
+        self.out_i += len;
+        Ok(len)
+    }
+}
+
+/// Assert that each payload round-trips consistently through every encode and decode function.
+///
+/// Each payload can be any value that implements `AsRef<[u8]>`. See the [`crate::testing`] module
+/// for details.
+///
+/// Example:
+///
+///     cobs2::assert_cobs_roundtrip!(b"ABC\0ghij\0xyz");
+///
+#[macro_export]
+macro_rules! assert_cobs_roundtrip {
+    ($($data:expr),+ $(,)?) => {
+        $(
+            $crate::testing::assert_roundtrip(::core::convert::AsRef::<[u8]>::as_ref(&$data));
+        )+
+    };
+}
+
+/// Assert that the payload round-trips consistently through every encode and decode function of
+/// [`crate::cobs`] and [`crate::cobsr`], and through [`crate::pipeline`] combinations.
+#[track_caller]
+pub fn assert_roundtrip(data: &[u8]) {
+    assert_roundtrip_cobs(data);
+    assert_roundtrip_cobsr(data);
+    assert_roundtrip_pipeline(data);
+}
+
+/// Generate the round-trip check of one COBS variant's functions.
+macro_rules! assert_roundtrip_variant {
+    ($vis:vis $name:ident, $codec:ident, $variant:literal) => {
+        #[doc = concat!(
+            "Assert that the payload round-trips consistently through every encode and decode ",
+            "function of [`crate::", stringify!($codec), "`]."
+        )]
+        #[track_caller]
+        $vis fn $name(data: &[u8]) {
+            let encoded = expect_ok($variant, "encode_vector", $codec::encode_vector(data));
+            assert!(!encoded.contains(&0), "{} encode_vector: zero-byte in encoded data", $variant);
+            assert!(encoded.len() <= $codec::encode_max_output_size(data.len()));
+
+            // Encoding.
+            let encode_check = |function: &str, encoded_other: &[u8]| {
+                assert_eq!(encoded_other, &encoded[..], "{} {}", $variant, function);
+            };
+            let encode_size = $codec::encode_output_size(data);
+            assert_eq!(encode_size, encoded.len(), "{} encode_output_size", $variant);
+            encode_check("encode_vector_exact", &expect_ok($variant, "encode_vector_exact",
+                $codec::encode_vector_exact(data)));
+            let mut encode_buf = vec![0_u8; $codec::encode_max_output_size(data.len())];
+            encode_check("encode_array", expect_ok($variant, "encode_array",
+                $codec::encode_array(&mut encode_buf, data)));
+            let encode_len = expect_ok($variant, "encode_array_len",
+                $codec::encode_array_len(&mut encode_buf, data));
+            encode_check("encode_array_len", &encode_buf[..encode_len]);
+            let mut short_buf = [0_u8; 254];
+            match $codec::encode_short_array(&mut short_buf, data) {
+                Ok(encoded_short) => encode_check("encode_short_array", encoded_short),
+                Err(Error::ShortFrameTooLong) if data.len() > 253 => {}
+                Err(e) => panic!("{} encode_short_array: {}", $variant, e),
+            }
+            encode_check("encode_iter",
+                &$codec::encode_iter(data.iter().copied()).collect::<Vec<u8>>());
+            encode_check("encode_ref_iter",
+                &$codec::encode_ref_iter(data.iter()).collect::<Vec<u8>>());
+            encode_check("encode_clone_iter",
+                &$codec::encode_clone_iter(data.iter().copied()).collect::<Vec<u8>>());
+            let mut writer_out = Vec::new();
+            let mut writer =
+                $codec::FrameWriter::new(|block: &[u8]| writer_out.extend_from_slice(block));
+            writer.write_bytes(data);
+            writer.finish();
+            let writer_frame = writer_out.split_last();
+            assert_eq!(writer_frame, Some((&0, &encoded[..])), "{} FrameWriter", $variant);
+
+            // Decoding.
+            let decode_check = |function: &str, decoded: &[u8]| {
+                assert_eq!(decoded, data, "{} {}", $variant, function);
+            };
+            let mut frame = encoded.clone();
+            frame.push(0);
+            let decode_size = $codec::decode_output_size(&encoded);
+            assert_eq!(decode_size, data.len(), "{} decode_output_size", $variant);
+            decode_check("decode_vector", &expect_ok($variant, "decode_vector",
+                $codec::decode_vector(&encoded)));
+            match $codec::decode_vector_detail(&encoded) {
+                Ok(decoded) => decode_check("decode_vector_detail", &decoded),
+                Err(e) => panic!("{} decode_vector_detail: {}", $variant, e.error),
+            }
+            let mut decode_buf = vec![0_u8; $codec::decode_max_output_size(encoded.len())];
+            decode_check("decode_array", expect_ok($variant, "decode_array",
+                $codec::decode_array(&mut decode_buf, &encoded)));
+            let decode_len = expect_ok($variant, "decode_array_len",
+                $codec::decode_array_len(&mut decode_buf, &encoded));
+            decode_check("decode_array_len", &decode_buf[..decode_len]);
+            match $codec::decode_array_detail(&mut decode_buf, &encoded) {
+                Ok(decoded) => decode_check("decode_array_detail", decoded),
+                Err(e) => panic!("{} decode_array_detail: {}", $variant, e.error),
+            }
+            match $codec::decode_short_array(&mut short_buf, &encoded) {
+                Ok(decoded) => decode_check("decode_short_array", decoded),
+                Err(Error::ShortFrameTooLong) if encoded.len() > 254 => {}
+                Err(e) => panic!("{} decode_short_array: {}", $variant, e),
+            }
+            decode_check("decode_iter",
+                &$codec::decode_iter(encoded.iter().copied()).collect::<Vec<u8>>());
+            decode_check("decode_ref_iter",
+                &$codec::decode_ref_iter(encoded.iter()).collect::<Vec<u8>>());
+            decode_check("decode_result_iter", &expect_ok($variant, "decode_result_iter",
+                $codec::decode_result_iter(encoded.iter().copied()).collect::<Result<Vec<u8>>>()));
+            let mut frames = $codec::decode_frames_iter(frame.iter().copied());
+            match frames.next_frame() {
+                Some(decoded) => decode_check("decode_frames_iter", &expect_ok($variant,
+                    "decode_frames_iter", decoded.collect::<Result<Vec<u8>>>())),
+                None => panic!("{} decode_frames_iter: no frame", $variant),
+            }
+            assert!(frames.next_frame().is_none(), "{} decode_frames_iter: extra frame", $variant);
+            if data.len() <= ACCUMULATOR_SIZE {
+                let mut accumulator = $codec::FrameAccumulator::<ACCUMULATOR_SIZE>::new();
+                assert_eq!(accumulator.feed(&frame), frame.len(), "{} FrameAccumulator", $variant);
+                match accumulator.poll_frame() {
+                    Some(decoded) => decode_check("FrameAccumulator",
+                        expect_ok($variant, "FrameAccumulator", decoded)),
+                    None => panic!("{} FrameAccumulator: no frame", $variant),
+                }
+            }
+            decode_check("decode_from_hex", &expect_ok($variant, "decode_from_hex",
+                $codec::decode_from_hex(&$codec::encode_to_hex(data))));
+            #[cfg(feature = "bytes")]
+            {
+                let frames = expect_ok($variant, "decode_frames_bytes",
+                    $codec::decode_frames_bytes(bytes::Bytes::from(frame.clone())));
+                assert_eq!(frames, [data], "{} decode_frames_bytes", $variant);
+            }
+            #[cfg(feature = "nom")]
+            {
+                match $codec::parse_frame::<nom::error::Error<&[u8]>>(&frame) {
+                    Ok((remaining, decoded)) if remaining.is_empty() => {
+                        decode_check("parse_frame", &decoded)
+                    }
+                    result => panic!("{} parse_frame: {:?}", $variant, result),
+                }
+            }
+            #[cfg(feature = "deflate")]
+            {
+                let compressed = expect_ok($variant, "encode_compressed",
+                    $codec::encode_compressed(data));
+                decode_check("decode_compressed", &expect_ok($variant, "decode_compressed",
+                    $codec::decode_compressed(&compressed, data.len())));
+            }
+        }
+    };
+}
+
+assert_roundtrip_variant!(assert_roundtrip_cobs_api, cobs, "COBS");
+assert_roundtrip_variant!(pub assert_roundtrip_cobsr, cobsr, "COBS/R");
+
+/// Assert that the payload round-trips consistently through every encode and decode function of
+/// [`crate::cobs`].
+#[track_caller]
+pub fn assert_roundtrip_cobs(data: &[u8]) {
+    assert_roundtrip_cobs_api(data);
+
+    let mut encoded = expect_ok("COBS", "encode_vector", cobs::encode_vector(data));
+    encoded.push(0);
+    let encoded_sentinel = expect_ok(
+        "COBS",
+        "encode_vector_sentinel",
+        cobs::encode_vector_sentinel(data),
+    );
+    assert_eq!(encoded_sentinel, encoded, "COBS encode_vector_sentinel");
+    let decoded = expect_ok(
+        "COBS",
+        "decode_vector_sentinel",
+        cobs::decode_vector_sentinel(&encoded_sentinel),
+    );
+    assert_eq!(decoded, data, "COBS decode_vector_sentinel");
+}
+
+/// Assert that the payload round-trips consistently through [`crate::pipeline`] combinations of
+/// [`Cobs`] or [`Cobsr`] encoding, with or without a [`Crc32`] checksum.
+#[track_caller]
+pub fn assert_roundtrip_pipeline(data: &[u8]) {
+    let mut data_crc = data.to_vec();
+    data_crc.extend_from_slice(&Crc32::checksum(data).to_le_bytes());
+    let cases: [(&str, Pipeline, Vec<u8>); 4] = [
+        (
+            "Cobs",
+            Pipeline::new().stage(Cobs),
+            expect_ok("COBS", "encode_vector", cobs::encode_vector(data)),
+        ),
+        (
+            "Cobsr",
+            Pipeline::new().stage(Cobsr),
+            expect_ok("COBS/R", "encode_vector", cobsr::encode_vector(data)),
+        ),
+        (
+            "Crc32, Cobs",
+            Pipeline::new().stage(Crc32).stage(Cobs),
+            expect_ok("COBS", "encode_vector", cobs::encode_vector(&data_crc)),
+        ),
+        (
+            "Crc32, Cobsr",
+            Pipeline::new().stage(Crc32).stage(Cobsr),
+            expect_ok("COBS/R", "encode_vector", cobsr::encode_vector(&data_crc)),
+        ),
+    ];
+    for (stages, pipeline, encoded) in cases.iter() {
+        let encoded_pipeline = expect_ok("Pipeline", stages, pipeline.encode(data));
+        assert_eq!(&encoded_pipeline, encoded, "Pipeline {} encode", stages);
+        let decoded = expect_ok("Pipeline", stages, pipeline.decode(&encoded_pipeline));
+        assert_eq!(decoded, data, "Pipeline {} decode", stages);
+    }
+}
+
+#[track_caller]
+fn expect_ok<T>(variant: &str, function: &str, result: Result<T>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => panic!("{} {}: {}", variant, function, e),
+    }
+}
+
+/// A step of the read script of a [`MockTransport`].
+#[derive(Debug, Clone)]
+enum ReadStep {
+    Data(Vec<u8>),
+    Error(io::ErrorKind),
+    Delay(Duration),
+}
+
+/// A scripted transport, for testing protocol code against the framing layer without hardware.
+///
+/// See the [`crate::testing`] module for details.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    read_steps: VecDeque<ReadStep>,
+    read_chunk_len: Option<usize>,
+    write_chunk_len: Option<usize>,
+    write_errors: VecDeque<(usize, io::ErrorKind)>,
+    written: Vec<u8>,
+    delay_until: Option<Instant>,
+}
+
+impl MockTransport {
+    /// Create a new transport, with nothing to read, which accepts all writes.
+    pub fn new() -> MockTransport {
+        MockTransport::default()
+    }
+
+    /// Add data to the read script. A read returns data from only one call of this, so the calls
+    /// set where reads are split.
+    pub fn read_data(mut self, data: impl AsRef<[u8]>) -> MockTransport {
+        self.read_steps
+            .push_back(ReadStep::Data(data.as_ref().to_vec()));
+        self
+    }
+
+    /// Add an error to the read script, which is returned by one read.
+    pub fn read_error(mut self, kind: io::ErrorKind) -> MockTransport {
+        self.read_steps.push_back(ReadStep::Error(kind));
+        self
+    }
+
+    /// Add a delay to the read script, before the next read returns.
+    pub fn read_delay(mut self, delay: Duration) -> MockTransport {
+        self.read_steps.push_back(ReadStep::Delay(delay));
+        self
+    }
+
+    /// Set the maximum number of bytes returned by each read. It is at least 1.
+    pub fn read_chunk_len(mut self, len: usize) -> MockTransport {
+        self.read_chunk_len = Some(len.max(1));
+        self
+    }
+
+    /// Set the maximum number of bytes accepted by each write. It is at least 1.
+    pub fn write_chunk_len(mut self, len: usize) -> MockTransport {
+        self.write_chunk_len = Some(len.max(1));
+        self
+    }
+
+    /// Add an error, which is returned by one write once `offset` bytes have been written. Writes
+    /// before it are shortened so that they stop at the offset.
+    pub fn write_error(mut self, offset: usize, kind: io::ErrorKind) -> MockTransport {
+        let i = self
+            .write_errors
+            .partition_point(|(error_offset, _)| *error_offset <= offset);
+        self.write_errors.insert(i, (offset, kind));
+        self
+    }
+
+    /// Returns `true` if the read script has been read to its end.
+    pub fn is_read_done(&self) -> bool {
+        self.read_steps.is_empty()
+    }
+
+    /// Get the data written so far.
+    pub fn written(&self) -> &[u8] {
+        &self.written
+    }
+
+    /// Take the data written so far, leaving it empty.
+    pub fn take_written(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.written)
+    }
+
+    /// Read from the read script, or return `None` if the next step is a delay.
+    fn read_script(&mut self, buf: &mut [u8]) -> Option<io::Result<usize>> {
+        loop {
+            match self.read_steps.front_mut() {
+                None => return Some(Ok(0)),
+                Some(ReadStep::Delay(_)) => return None,
+                Some(ReadStep::Error(kind)) => {
+                    let kind = *kind;
+                    self.read_steps.pop_front();
+                    return Some(Err(kind.into()));
+                }
+                Some(ReadStep::Data(data)) if data.is_empty() => {
+                    self.read_steps.pop_front();
+                }
+                Some(ReadStep::Data(data)) => {
+                    let chunk_len = self.read_chunk_len.unwrap_or(usize::MAX);
+                    let len = data.len().min(buf.len()).min(chunk_len);
+                    buf[..len].copy_from_slice(&data[..len]);
+                    data.drain(..len);
+                    if data.is_empty() {
+                        self.read_steps.pop_front();
+                    }
+                    return Some(Ok(len));
+                }
+            }
+        }
+    }
+
+    /// Take the delay at the front of the read script.
+    fn take_delay(&mut self) -> Duration {
+        match self.read_steps.pop_front() {
+            Some(ReadStep::Delay(delay)) => delay,
+            _ => Duration::ZERO,
+        }
+    }
+}
+
+/// Implement trait [Read].
+impl Read for MockTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.read_script(buf) {
+                Some(result) => return result,
+                None => thread::sleep(self.take_delay()),
+            }
+        }
+    }
+}
+
+/// Implement trait [Write].
+impl Write for MockTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut len = buf.len().min(self.write_chunk_len.unwrap_or(usize::MAX));
+        if let Some(&(offset, kind)) = self.write_errors.front() {
+            if offset <= self.written.len() {
+                self.write_errors.pop_front();
+                return Err(kind.into());
+            }
+            len = len.min(offset - self.written.len());
+        }
+        self.written.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Implement trait [`futures_io::AsyncRead`]. A delay doesn't block the executor; a thread wakes
+/// the task when the delay has passed.
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncRead for MockTransport {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(result) = this.read_script(buf) {
+                return Poll::Ready(result);
+            }
+            let now = Instant::now();
+            let delay_until = match this.delay_until {
+                Some(delay_until) => delay_until,
+                None => {
+                    let delay_until = now + this.take_delay();
+                    this.delay_until = Some(delay_until);
+                    // Keep the delay in the script, until it has passed.
+                    this.read_steps.push_front(ReadStep::Delay(Duration::ZERO));
+                    delay_until
+                }
+            };
+            if now >= delay_until {
+                this.delay_until = None;
+                this.take_delay();
+                continue;
+            }
+            let waker = cx.waker().clone();
+            thread::spawn(move || {
+                thread::sleep(delay_until - now);
+                waker.wake();
+            });
+            return Poll::Pending;
+        }
+    }
+}
+
+/// Implement trait [`futures_io::AsyncWrite`]. Writes complete immediately.
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncWrite for MockTransport {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.get_mut().write(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// One direction of a [`loopback()`] pair.
+#[derive(Debug, Default)]
+struct Pipe {
+    data: VecDeque<u8>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug, Default)]
+struct SharedPipe {
+    pipe: Mutex<Pipe>,
+    readable: Condvar,
+}
+
+impl SharedPipe {
+    fn lock(&self) -> MutexGuard<'_, Pipe> {
+        self.pipe.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn close(&self) {
+        let mut pipe = self.lock();
+        pipe.closed = true;
+        self.readable.notify_all();
+        if let Some(waker) = pipe.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Create a connected pair of in-memory transports. The data written to each end is read from the
+/// other end.
+///
+/// Each end implements [`Read`] and [`Write`], and with feature `futures-io`, the async
+/// equivalents. A read waits until data is available; it returns end-of-file once the other end
+/// has been dropped, or closed with `poll_close()`, and its data has been read. A write never
+/// waits, and returns an error of kind [`io::ErrorKind::BrokenPipe`] if the other end has been
+/// dropped.
+///
+/// Example:
+///
+///     use cobs2::stream::CobsStream;
+///     use cobs2::testing::loopback;
+///
+///     let (host, device) = loopback();
+///     let mut host = CobsStream::new(host);
+///     let mut device = CobsStream::new(device);
+///     host.send(b"ping").unwrap();
+///     let mut frame = Vec::new();
+///     device.recv(&mut frame).unwrap();
+///     assert_eq!(frame, b"ping");
+///
+pub fn loopback() -> (LoopbackEnd, LoopbackEnd) {
+    let a_to_b = Arc::new(SharedPipe::default());
+    let b_to_a = Arc::new(SharedPipe::default());
+    let a = LoopbackEnd {
+        rx: b_to_a.clone(),
+        tx: a_to_b.clone(),
+    };
+    let b = LoopbackEnd {
+        rx: a_to_b,
+        tx: b_to_a,
+    };
+    (a, b)
+}
+
+/// One end of a pair of in-memory transports, made by [`loopback()`].
+#[derive(Debug)]
+pub struct LoopbackEnd {
+    rx: Arc<SharedPipe>,
+    tx: Arc<SharedPipe>,
+}
+
+impl LoopbackEnd {
+    /// Get the number of bytes written by the other end, which haven't yet been read.
+    pub fn available(&self) -> usize {
+        self.rx.lock().data.len()
+    }
+
+    fn read_available(pipe: &mut Pipe, buf: &mut [u8]) -> usize {
+        let len = pipe.data.len().min(buf.len());
+        for (dst, src) in buf.iter_mut().zip(pipe.data.drain(..len)) {
+            *dst = src;
+        }
+        len
+    }
+
+    fn write_pipe(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut pipe = self.tx.lock();
+        if pipe.closed {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        pipe.data.extend(buf);
+        self.tx.readable.notify_all();
+        if let Some(waker) = pipe.waker.take() {
+            waker.wake();
+        }
+        Ok(buf.len())
+    }
+}
+
+/// Implement trait [Read].
+impl Read for LoopbackEnd {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut pipe = self.rx.lock();
+        while pipe.data.is_empty() && !pipe.closed && !buf.is_empty() {
+            pipe = self
+                .rx
+                .readable
+                .wait(pipe)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        Ok(LoopbackEnd::read_available(&mut pipe, buf))
+    }
+}
+
+/// Implement trait [Write].
+impl Write for LoopbackEnd {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_pipe(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Implement trait [`futures_io::AsyncRead`].
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncRead for LoopbackEnd {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.rx.lock();
+        if pipe.data.is_empty() && !pipe.closed && !buf.is_empty() {
+            pipe.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        Poll::Ready(Ok(LoopbackEnd::read_available(&mut pipe, buf)))
+    }
+}
+
+/// Implement trait [`futures_io::AsyncWrite`]. Writes complete immediately.
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncWrite for LoopbackEnd {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.write_pipe(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.tx.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Implement trait [Drop], closing both directions, so the other end reads end-of-file.
+impl Drop for LoopbackEnd {
+    fn drop(&mut self) {
+        self.tx.close();
+        self.rx.close();
+    }
+}
@@ -0,0 +1,168 @@
+//! Streaming conversion of delimited frames between COBS and COBS/R encoding, without decoding.
+//!
+//! COBS and COBS/R encode all but the last block of a frame the same way. They differ only in the
+//! final block, in which COBS/R may replace the length code with the final data byte. So a
+//! [`Transcoder`] passes each block through unchanged, except the final block of each frame, which
+//! it converts. It holds only the current block, of at most 255 bytes, until the following byte
+//! shows whether it is the final block. The frames are never decoded, so there is no intermediate
+//! buffer of decoded data. This suits a gateway bridging devices that use different variants.
+//!
+//! The input is a stream of encoded frames, each ended by a zero-byte delimiter, received in
+//! arbitrary chunks. The output is the same stream, with each frame converted, including its
+//! delimiter. Empty frames (consecutive delimiters) are passed through.
+//!
+//! Example:
+//!
+//!     use cobs2::transcode::Transcoder;
+//!
+//!     let mut transcoder = Transcoder::cobs_to_cobsr();
+//!     let mut cobsr_stream = Vec::new();
+//!     for received in [&b"\x04ABC\x05gh"[..], b"ij\x04xyz\x00\x03AB\x00"] {
+//!         transcoder.push(received, |chunk| cobsr_stream.extend_from_slice(chunk.unwrap()));
+//!     }
+//!     assert_eq!(cobsr_stream, b"\x04ABC\x05ghijzxy\x00BA\x00");
+//!
+//!     let mut transcoder = Transcoder::cobsr_to_cobs();
+//!     let mut cobs_stream = Vec::new();
+//!     transcoder.push(&cobsr_stream, |chunk| cobs_stream.extend_from_slice(chunk.unwrap()));
+//!     assert_eq!(cobs_stream, b"\x04ABC\x05ghij\x04xyz\x00\x03AB\x00");
+
+use crate::{Error, Result};
+
+/// The direction of conversion of a [`Transcoder`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Direction {
+    /// From COBS to COBS/R.
+    CobsToCobsr,
+    /// From COBS/R to COBS.
+    CobsrToCobs,
+}
+
+/// Converts a stream of delimited frames between COBS and COBS/R encoding, block by block.
+///
+/// See the [`crate::transcode`] module for details.
+#[derive(Debug, Clone)]
+pub struct Transcoder {
+    direction: Direction,
+    // Length code, followed by the data of the current block.
+    hold_buf: [u8; 255],
+    hold_len: usize,
+}
+
+impl Transcoder {
+    /// Create a new transcoder, converting in the given direction.
+    pub const fn new(direction: Direction) -> Transcoder {
+        Transcoder {
+            direction,
+            hold_buf: [0; 255],
+            hold_len: 0,
+        }
+    }
+
+    /// Create a new transcoder, converting from COBS to COBS/R.
+    pub const fn cobs_to_cobsr() -> Transcoder {
+        Transcoder::new(Direction::CobsToCobsr)
+    }
+
+    /// Create a new transcoder, converting from COBS/R to COBS.
+    pub const fn cobsr_to_cobs() -> Transcoder {
+        Transcoder::new(Direction::CobsrToCobs)
+    }
+
+    /// Get the direction of conversion.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Discard any partly received frame. Any of it which has already been output is not ended by
+    /// a delimiter.
+    pub fn clear(&mut self) {
+        self.hold_len = 0;
+    }
+
+    /// Returns `true` if a frame is partly received.
+    pub fn in_progress(&self) -> bool {
+        self.hold_len != 0
+    }
+
+    /// Convert received data, giving each chunk of converted data to `on_output`.
+    ///
+    /// The chunk given to `on_output` is a [`Result`] that in the [`Ok`] case is converted data to
+    /// send, which is only valid for the call. Data of the current block is held until the
+    /// following byte is received.
+    ///
+    /// The following errors could be given:
+    ///
+    /// * [`Error::TruncatedEncodedData`], if a COBS frame's final block is shorter than its length
+    ///   code. Since a length code greater than the remaining data is valid in COBS/R, the frame's
+    ///   final block is not output, but its delimiter is, so the following frames are not
+    ///   affected. The output frame is incomplete, but may still decode, so the error should be
+    ///   handled, such as by counting it.
+    ///
+    pub fn push<F>(&mut self, in_buf: &[u8], mut on_output: F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        for &byte_val in in_buf {
+            if byte_val == 0 {
+                self.end_frame(&mut on_output);
+                self.hold_len = 0;
+                on_output(Ok(&[0]));
+            } else if self.hold_len == 0 {
+                self.hold_code(byte_val);
+            } else if self.hold_len == self.hold_buf[0] as usize {
+                // The block is complete, and not the final block, so it is the same in both
+                // encodings.
+                on_output(Ok(&self.hold_buf[..self.hold_len]));
+                self.hold_code(byte_val);
+            } else if let Some(hold_byte) = self.hold_buf.get_mut(self.hold_len) {
+                *hold_byte = byte_val;
+                self.hold_len += 1;
+            }
+        }
+    }
+
+    fn hold_code(&mut self, code: u8) {
+        self.hold_buf[0] = code;
+        self.hold_len = 1;
+    }
+
+    /// Convert and output the final block of a frame, if any, without the delimiter.
+    fn end_frame<F>(&mut self, on_output: &mut F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        if self.hold_len == 0 {
+            // Empty frame.
+            return;
+        }
+        let code = self.hold_buf[0];
+        let data_len = self.hold_len - 1;
+        match self.direction {
+            Direction::CobsToCobsr => {
+                if data_len + 1 < code as usize {
+                    on_output(Err(Error::TruncatedEncodedData));
+                    return;
+                }
+                let last_value = self.hold_buf[data_len];
+                if data_len > 0 && last_value as usize > data_len {
+                    // The final length code is replaced by the final data byte.
+                    self.hold_buf[0] = last_value;
+                    on_output(Ok(&self.hold_buf[..data_len]));
+                    return;
+                }
+            }
+            Direction::CobsrToCobs => {
+                if data_len + 1 < code as usize {
+                    // The final length code is the final data byte, so restore the length code.
+                    // The data is less than 254 bytes, so there is room for the length code.
+                    self.hold_buf[self.hold_len] = code;
+                    self.hold_buf[0] = (data_len + 2) as u8;
+                    on_output(Ok(&self.hold_buf[..self.hold_len + 1]));
+                    return;
+                }
+            }
+        }
+        on_output(Ok(&self.hold_buf[..self.hold_len]));
+    }
+}
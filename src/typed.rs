@@ -0,0 +1,148 @@
+//! Typed wrappers for encoded and decoded data, so that the compiler can check that data is not
+//! encoded twice, or that a raw payload is not given to a decoder.
+//!
+//! A [`Decoded`] holds a payload, and can only be encoded. An [`Encoded`] holds encoded data, and
+//! can only be decoded. The encoding is a type parameter of [`Encoded`], either [`Cobs`] (the
+//! default) or [`Cobsr`], so COBS/R data can't be decoded as COBS by mistake.
+//!
+//! Neither type implements [`AsRef<[u8]>`](AsRef) or [`Deref`](core::ops::Deref), so they can't be
+//! passed to the untyped functions by accident. The data is available from `get()`, or from
+//! `into_inner()` at the point where it is sent or received.
+//!
+//! Example:
+//!
+//!     use cobs2::typed::{Cobs, Decoded, Encoded};
+//!
+//!     let payload = Decoded::new(b"ABC\0ghij\0xyz");
+//!     let data_encoded: Encoded<Vec<u8>> = payload.encode().unwrap();
+//!     assert_eq!(data_encoded.get(), b"\x04ABC\x05ghij\x04xyz");
+//!
+//!     // Received data must be explicitly wrapped as encoded data, before it can be decoded.
+//!     let received = Encoded::<_, Cobs>::new(data_encoded.into_inner());
+//!     let data_decoded = received.decode().unwrap();
+//!     assert_eq!(data_decoded.get(), b"ABC\0ghij\0xyz");
+
+use crate::{cobs, cobsr, Result};
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+/// An encoding of [`Encoded`] data.
+pub trait Codec {
+    /// Encode data, returning output as a vector of `u8`.
+    fn encode_vector(in_buf: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decode data, returning output as a vector of `u8`.
+    fn decode_vector(in_buf: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// COBS encoding. See [`crate::cobs`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Cobs;
+
+impl Codec for Cobs {
+    fn encode_vector(in_buf: &[u8]) -> Result<Vec<u8>> {
+        cobs::encode_vector(in_buf)
+    }
+
+    fn decode_vector(in_buf: &[u8]) -> Result<Vec<u8>> {
+        cobs::decode_vector(in_buf)
+    }
+}
+
+/// COBS/R encoding. See [`crate::cobsr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Cobsr;
+
+impl Codec for Cobsr {
+    fn encode_vector(in_buf: &[u8]) -> Result<Vec<u8>> {
+        cobsr::encode_vector(in_buf)
+    }
+
+    fn decode_vector(in_buf: &[u8]) -> Result<Vec<u8>> {
+        cobsr::decode_vector(in_buf)
+    }
+}
+
+/// Data encoded with encoding `C`, which can be decoded into a [`Decoded`] payload.
+///
+/// See the [`crate::typed`] module for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Encoded<T, C = Cobs> {
+    data: T,
+    codec: PhantomData<C>,
+}
+
+impl<T, C> Encoded<T, C> {
+    /// Wrap data which is encoded with encoding `C`, such as a received frame.
+    pub fn new(data: T) -> Encoded<T, C> {
+        Encoded {
+            data,
+            codec: PhantomData,
+        }
+    }
+
+    /// Get a reference to the encoded data.
+    pub fn get(&self) -> &T {
+        &self.data
+    }
+
+    /// Consume the wrapper, returning the encoded data.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T, C> Encoded<T, C>
+where
+    T: AsRef<[u8]>,
+    C: Codec,
+{
+    /// Decode the data, returning the payload.
+    ///
+    /// The errors that could be returned are the same as for the untyped `decode_vector()`
+    /// function of encoding `C`.
+    pub fn decode(&self) -> Result<Decoded<Vec<u8>>> {
+        C::decode_vector(self.data.as_ref()).map(Decoded::new)
+    }
+}
+
+/// A payload, which can be encoded into [`Encoded`] data.
+///
+/// See the [`crate::typed`] module for details.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Decoded<T> {
+    data: T,
+}
+
+impl<T> Decoded<T> {
+    /// Wrap a payload.
+    pub fn new(data: T) -> Decoded<T> {
+        Decoded { data }
+    }
+
+    /// Get a reference to the payload.
+    pub fn get(&self) -> &T {
+        &self.data
+    }
+
+    /// Consume the wrapper, returning the payload.
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+impl<T> Decoded<T>
+where
+    T: AsRef<[u8]>,
+{
+    /// Encode the payload with encoding `C`.
+    ///
+    /// The encoding is usually inferred from the type of the result. Otherwise it can be given
+    /// explicitly, such as `payload.encode::<Cobsr>()`.
+    pub fn encode<C>(&self) -> Result<Encoded<Vec<u8>, C>>
+    where
+        C: Codec,
+    {
+        C::encode_vector(self.data.as_ref()).map(Encoded::new)
+    }
+}
@@ -0,0 +1,255 @@
+//! High-throughput decoding of files and sockets with `io_uring`, on Linux.
+//!
+//! For bulk ingestion of captures and encoded streams, reading with [`std::io::Read`] can be
+//! bound by the cost of system calls. A [`UringDecoder`] reads with `io_uring`, through the
+//! `tokio-uring` crate, keeping several large reads in flight, and feeds the data to the sans-IO
+//! [`cobs::SliceFrameAccumulator`] or [`cobsr::SliceFrameAccumulator`]. Each decoded frame is
+//! given to a function, borrowed from the accumulator's buffer, so there is no allocation for
+//! each frame.
+//!
+//! Each decoding call runs its own single-threaded `tokio-uring` runtime until the end of the
+//! data, so it must not be called from within an async runtime. It returns an error if
+//! `io_uring` is not available, such as when it is disabled by a container's security policy.
+//!
+//! Invalid frames are given to the function as an [`crate::Error`], and decoding continues with the
+//! next frame. Empty frames are skipped. A partial frame at the end of the data is ended as if a
+//! delimiter followed it.
+//!
+//! Example:
+//!
+//!     use cobs2::profile::Variant;
+//!     use cobs2::uring::UringDecoder;
+//!
+//!     let path = std::env::temp_dir().join("cobs2-uring-doc-example.bin");
+//!     std::fs::write(&path, b"\x04ABC\x00\x05AB\x00\x03xy\x00").unwrap();
+//!
+//!     let mut frames = Vec::new();
+//!     let mut errors = 0;
+//!     let bytes = UringDecoder::new(Variant::Cobs)
+//!         .decode_file(&path, |frame| match frame {
+//!             Ok(frame) => frames.push(frame.to_vec()),
+//!             Err(_) => errors += 1,
+//!         })
+//!         .unwrap();
+//!     assert_eq!(bytes, 13);
+//!     assert_eq!(frames, [&b"ABC"[..], b"xy"]);
+//!     assert_eq!(errors, 1);
+//!     std::fs::remove_file(&path).unwrap();
+
+use crate::profile::Variant;
+use crate::{cobs, cobsr, Result};
+use std::collections::VecDeque;
+use std::io;
+use std::path::Path;
+use std::rc::Rc;
+use std::vec::Vec;
+use tokio_uring::buf::BoundedBuf;
+use tokio_uring::fs::File;
+
+/// Reads and decodes framed files and sockets with `io_uring`.
+///
+/// See the [`crate::uring`] module for details.
+#[derive(Debug, Clone)]
+pub struct UringDecoder {
+    variant: Variant,
+    max_frame_len: usize,
+    chunk_len: usize,
+    queue_depth: usize,
+}
+
+impl UringDecoder {
+    /// Create a new decoder, for frames encoded with the given variant, with a maximum decoded
+    /// frame length of 64 KiB, and reads of 256 KiB, with up to 4 reads of a file in flight.
+    pub fn new(variant: Variant) -> UringDecoder {
+        UringDecoder {
+            variant,
+            max_frame_len: 64 * 1024,
+            chunk_len: 256 * 1024,
+            queue_depth: 4,
+        }
+    }
+
+    /// Set the maximum decoded frame length. A longer frame is given to the function as
+    /// [`crate::Error::OutputBufferTooSmall`].
+    pub fn max_frame_len(mut self, max_frame_len: usize) -> UringDecoder {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    /// Set the length of each read.
+    pub fn chunk_len(mut self, chunk_len: usize) -> UringDecoder {
+        self.chunk_len = chunk_len.max(1);
+        self
+    }
+
+    /// Set the maximum number of reads of a file in flight at once. Reads of a socket are always
+    /// one at a time, because the order of their data isn't known in advance.
+    pub fn queue_depth(mut self, queue_depth: usize) -> UringDecoder {
+        self.queue_depth = queue_depth.max(1);
+        self
+    }
+
+    /// Read and decode a file, giving each frame to `on_frame`. Returns the number of bytes read.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from creating the `io_uring` runtime.
+    /// * An error from opening or reading the file.
+    pub fn decode_file<P, F>(&self, path: P, mut on_frame: F) -> io::Result<u64>
+    where
+        P: AsRef<Path>,
+        F: FnMut(Result<&[u8]>),
+    {
+        let mut frame_buf = vec![0_u8; self.max_frame_len];
+        let mut accumulator = Accumulator::new(self.variant, &mut frame_buf);
+        self.runtime()?.block_on(async {
+            let file = Rc::new(File::open(path.as_ref()).await?);
+            let mut in_flight = VecDeque::with_capacity(self.queue_depth);
+            let mut offset = 0_u64;
+            let mut bytes = 0_u64;
+            let mut at_end = false;
+            for _ in 0..self.queue_depth {
+                let read = read_chunk(file.clone(), Vec::new(), offset, self.chunk_len);
+                in_flight.push_back(tokio_uring::spawn(read));
+                offset += self.chunk_len as u64;
+            }
+            while let Some(read) = in_flight.pop_front() {
+                let (result, mut buf) = read.await.map_err(io::Error::other)?;
+                result?;
+                if at_end {
+                    continue;
+                }
+                accumulator.feed_all(&buf, &mut on_frame);
+                bytes += buf.len() as u64;
+                if buf.len() < self.chunk_len {
+                    at_end = true;
+                    continue;
+                }
+                buf.clear();
+                let read = read_chunk(file.clone(), buf, offset, self.chunk_len);
+                in_flight.push_back(tokio_uring::spawn(read));
+                offset += self.chunk_len as u64;
+            }
+            accumulator.finish(&mut on_frame);
+            Ok(bytes)
+        })
+    }
+
+    /// Read and decode a TCP connection until it is closed, giving each frame to `on_frame`.
+    /// Returns the number of bytes read.
+    ///
+    /// The following errors could be returned:
+    ///
+    /// * An error from creating the `io_uring` runtime.
+    /// * An error from reading the connection.
+    pub fn decode_tcp<F>(&self, stream: std::net::TcpStream, mut on_frame: F) -> io::Result<u64>
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        let mut frame_buf = vec![0_u8; self.max_frame_len];
+        let mut accumulator = Accumulator::new(self.variant, &mut frame_buf);
+        self.runtime()?.block_on(async {
+            let stream = tokio_uring::net::TcpStream::from_std(stream);
+            let mut buf = Vec::with_capacity(self.chunk_len);
+            let mut bytes = 0_u64;
+            loop {
+                buf.clear();
+                let (result, slice) = stream.read(buf.slice(..self.chunk_len)).await;
+                buf = slice.into_inner();
+                if result? == 0 {
+                    break;
+                }
+                accumulator.feed_all(&buf, &mut on_frame);
+                bytes += buf.len() as u64;
+            }
+            accumulator.finish(&mut on_frame);
+            Ok(bytes)
+        })
+    }
+
+    fn runtime(&self) -> io::Result<tokio_uring::Runtime> {
+        let entries = (self.queue_depth * 2).next_power_of_two().max(8);
+        tokio_uring::Runtime::new(tokio_uring::builder().entries(entries as u32))
+    }
+}
+
+/// Read `len` bytes into `buf`, at `offset` in the file. Fewer bytes are read at the end of the
+/// file.
+async fn read_chunk(
+    file: Rc<File>,
+    mut buf: Vec<u8>,
+    offset: u64,
+    len: usize,
+) -> (io::Result<()>, Vec<u8>) {
+    buf.reserve(len);
+    while buf.len() < len {
+        let filled = buf.len();
+        let (result, slice) = file
+            .read_at(buf.slice(filled..len), offset + filled as u64)
+            .await;
+        buf = slice.into_inner();
+        match result {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => return (Err(e), buf),
+        }
+    }
+    (Ok(()), buf)
+}
+
+/// A sans-IO frame accumulator for either encoding variant.
+enum Accumulator<'a> {
+    Cobs(cobs::SliceFrameAccumulator<'a>),
+    Cobsr(cobsr::SliceFrameAccumulator<'a>),
+}
+
+impl<'a> Accumulator<'a> {
+    fn new(variant: Variant, buf: &'a mut [u8]) -> Accumulator<'a> {
+        match variant {
+            Variant::Cobs => Accumulator::Cobs(cobs::SliceFrameAccumulator::new(buf)),
+            Variant::Cobsr => Accumulator::Cobsr(cobsr::SliceFrameAccumulator::new(buf)),
+        }
+    }
+
+    /// Feed all the data, giving each complete frame to `on_frame`.
+    fn feed_all<F>(&mut self, mut in_buf: &[u8], on_frame: &mut F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        while !in_buf.is_empty() {
+            let consumed = match self {
+                Accumulator::Cobs(accumulator) => accumulator.feed(in_buf),
+                Accumulator::Cobsr(accumulator) => accumulator.feed(in_buf),
+            };
+            in_buf = &in_buf[consumed..];
+            self.poll(on_frame);
+        }
+    }
+
+    /// End any partial frame, giving it to `on_frame`.
+    fn finish<F>(&mut self, on_frame: &mut F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        let ready = match self {
+            Accumulator::Cobs(accumulator) => accumulator.flush_partial(),
+            Accumulator::Cobsr(accumulator) => accumulator.flush_partial(),
+        };
+        if ready {
+            self.poll(on_frame);
+        }
+    }
+
+    fn poll<F>(&mut self, on_frame: &mut F)
+    where
+        F: FnMut(Result<&[u8]>),
+    {
+        let frame = match self {
+            Accumulator::Cobs(accumulator) => accumulator.poll_frame(),
+            Accumulator::Cobsr(accumulator) => accumulator.poll_frame(),
+        };
+        if let Some(frame) = frame {
+            on_frame(frame);
+        }
+    }
+}
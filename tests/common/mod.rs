@@ -0,0 +1,27 @@
+//! Helpers shared by the integration tests.
+
+#![allow(dead_code)]
+
+/// Simple pseudo-random generator, so the test data is repeatable.
+pub fn xorshift32(state: &mut u32) -> u32 {
+    *state ^= *state << 13;
+    *state ^= *state >> 17;
+    *state ^= *state << 5;
+    *state
+}
+
+/// Make repeatable pseudo-random data, of length less than `max_len`, in which about one byte in
+/// `zero_mod` is a zero byte.
+pub fn random_data(state: &mut u32, max_len: u32, zero_mod: u32) -> Vec<u8> {
+    let len = (xorshift32(state) % max_len) as usize;
+    (0..len)
+        .map(|_| {
+            let x = xorshift32(state);
+            if x.is_multiple_of(zero_mod) {
+                0
+            } else {
+                x as u8
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,205 @@
+use cobs2_compat_crates::{edge_cases, ends_with_full_block, Rng};
+
+const RANDOM_COUNT: usize = 10_000;
+
+/// An output buffer length which is enough for any of the crates' encodings, including a
+/// redundant length code and a sentinel.
+fn encode_buf_len(data_len: usize) -> usize {
+    cobs2::cobs::encode_max_output_size(data_len) + 2
+}
+
+/// All the data to check: the edge cases, then the random data.
+fn test_data() -> Vec<Vec<u8>> {
+    let mut rng = Rng::new(1);
+    let mut data = edge_cases();
+    data.extend((0..RANDOM_COUNT).map(|_| rng.data()));
+    data
+}
+
+fn cobs_crate_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![0_u8; encode_buf_len(data.len())];
+    let encoded_len = cobs::encode(data, &mut encoded);
+    encoded.truncate(encoded_len);
+    encoded
+}
+
+fn cobs_crate_decode(encoded: &[u8]) -> Result<Vec<u8>, ()> {
+    let mut decoded = vec![0_u8; encoded.len()];
+    let decoded_len = cobs::decode(encoded, &mut decoded)?;
+    decoded.truncate(decoded_len);
+    Ok(decoded)
+}
+
+fn corncobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![0_u8; encode_buf_len(data.len())];
+    let encoded_len = corncobs::encode_buf(data, &mut encoded);
+    encoded.truncate(encoded_len);
+    encoded
+}
+
+fn corncobs_decode(encoded: &[u8]) -> Result<Vec<u8>, corncobs::CobsError> {
+    let mut decoded = vec![0_u8; encoded.len()];
+    let decoded_len = corncobs::decode_buf(encoded, &mut decoded)?;
+    decoded.truncate(decoded_len);
+    Ok(decoded)
+}
+
+#[test]
+fn test_cobs_crate_encode() {
+    for data in test_data() {
+        let expected = cobs2::cobs::encode_vector(&data).unwrap();
+        let encoded = cobs_crate_encode(&data);
+        if data.is_empty() {
+            assert_eq!(encoded, b"");
+        } else if ends_with_full_block(&data) {
+            // The redundant length code.
+            assert_eq!(encoded[..encoded.len() - 1], expected[..], "{:02x?}", data);
+            assert_eq!(encoded.last(), Some(&1), "{:02x?}", data);
+        } else {
+            assert_eq!(encoded, expected, "{:02x?}", data);
+        }
+        assert!(expected.len() <= cobs2::cobs::encode_max_output_size(data.len()));
+    }
+}
+
+#[test]
+fn test_cobs_crate_decode() {
+    for data in test_data() {
+        // cobs2's encoding, with and without a sentinel.
+        let mut encoded = cobs2::cobs::encode_vector(&data).unwrap();
+        assert_eq!(
+            cobs_crate_decode(&encoded),
+            Ok(data.clone()),
+            "{:02x?}",
+            data
+        );
+        encoded.push(0);
+        assert_eq!(
+            cobs_crate_decode(&encoded),
+            Ok(data.clone()),
+            "{:02x?}",
+            data
+        );
+
+        // The cobs crate's encoding, including the redundant length code.
+        let encoded = cobs_crate_encode(&data);
+        if data.is_empty() {
+            assert_eq!(cobs_crate_decode(&encoded), Err(()));
+            assert_eq!(cobs2::cobs::decode_vector(&encoded).unwrap(), b"");
+        } else {
+            assert_eq!(
+                cobs2::cobs::decode_vector(&encoded).unwrap(),
+                data,
+                "{:02x?}",
+                data
+            );
+        }
+    }
+}
+
+#[test]
+fn test_cobs_crate_decode_invalid() {
+    // Truncated data is an error for both.
+    assert_eq!(cobs_crate_decode(b"\x05AB"), Err(()));
+    assert_eq!(
+        cobs2::cobs::decode_vector(b"\x05AB"),
+        Err(cobs2::Error::TruncatedEncodedData)
+    );
+
+    // A zero byte ends the frame for the cobs crate.
+    assert_eq!(cobs_crate_decode(b"\x02A\x00\x02B"), Ok(b"A".to_vec()));
+    assert_eq!(
+        cobs2::cobs::decode_vector(b"\x02A\x00\x02B"),
+        Err(cobs2::Error::ZeroInEncodedData)
+    );
+}
+
+#[test]
+fn test_cobs_crate_compat_api() {
+    // cobs2's mirror of the cobs crate API agrees with it, except for the redundant length code.
+    use cobs2::compat::cobs_crate;
+
+    for data in test_data() {
+        assert_eq!(
+            cobs_crate::max_encoding_length(data.len()),
+            cobs::max_encoding_length(data.len())
+        );
+        let encoded = cobs_crate::encode_vec(&data);
+        let expected = cobs_crate_encode(&data);
+        if ends_with_full_block(&data) {
+            assert_eq!(encoded[..], expected[..expected.len() - 1], "{:02x?}", data);
+        } else {
+            assert_eq!(encoded, expected, "{:02x?}", data);
+        }
+        assert_eq!(
+            cobs_crate::decode_vec(&expected),
+            cobs::decode_vec(&expected)
+        );
+        assert_eq!(cobs_crate::decode_vec(&encoded), cobs::decode_vec(&encoded));
+    }
+}
+
+#[test]
+fn test_corncobs_encode() {
+    for data in test_data() {
+        let mut expected = cobs2::cobs::encode_vector(&data).unwrap();
+        expected.push(0);
+        let encoded = corncobs_encode(&data);
+        if encoded != expected {
+            // Only the redundant length code is allowed to differ.
+            assert!(ends_with_full_block(&data), "{:02x?}", data);
+            assert_eq!(encoded.len(), expected.len() + 1, "{:02x?}", data);
+            assert_eq!(
+                encoded[..expected.len() - 1],
+                expected[..expected.len() - 1]
+            );
+            assert_eq!(encoded[expected.len() - 1..], [1, 0]);
+        }
+        assert!(encoded.len() <= corncobs::max_encoded_len(data.len()));
+        let encoded_iter: Vec<u8> = corncobs::encode_iter(&data).collect();
+        assert_eq!(encoded_iter, encoded, "{:02x?}", data);
+    }
+}
+
+#[test]
+fn test_corncobs_decode() {
+    for data in test_data() {
+        // cobs2's encoding, with the sentinel which corncobs requires.
+        let mut encoded = cobs2::cobs::encode_vector(&data).unwrap();
+        assert!(corncobs_decode(&encoded).is_err(), "{:02x?}", data);
+        encoded.push(0);
+        assert_eq!(corncobs_decode(&encoded).unwrap(), data, "{:02x?}", data);
+
+        // corncobs's encoding, without the sentinel, which cobs2 decodes as an error.
+        let encoded = corncobs_encode(&data);
+        let (&sentinel, frame) = encoded.split_last().unwrap();
+        assert_eq!(sentinel, 0);
+        assert_eq!(
+            cobs2::cobs::decode_vector(frame).unwrap(),
+            data,
+            "{:02x?}",
+            data
+        );
+        assert_eq!(
+            cobs2::cobs::decode_vector(&encoded),
+            Err(cobs2::Error::ZeroInEncodedData)
+        );
+    }
+}
+
+#[test]
+fn test_corncobs_decode_invalid() {
+    // Truncated data is an error for both.
+    assert!(corncobs_decode(b"\x05AB\x00").is_err());
+    assert_eq!(
+        cobs2::cobs::decode_vector(b"\x05AB"),
+        Err(cobs2::Error::TruncatedEncodedData)
+    );
+
+    // A zero byte ends the frame for corncobs.
+    assert_eq!(corncobs_decode(b"\x02A\x00\x02B\x00").unwrap(), b"A");
+    assert_eq!(
+        cobs2::cobs::decode_vector(b"\x02A\x00\x02B"),
+        Err(cobs2::Error::ZeroInEncodedData)
+    );
+}
@@ -0,0 +1,79 @@
+//! Compatibility tests of `cobs2` against the `cobs` and `corncobs` crates.
+//!
+//! The tests encode and decode random inputs with each crate, and check that the crates agree,
+//! except where their conventions legitimately differ:
+//!
+//! * Sentinels: `cobs2` and the `cobs` crate encode without a trailing zero-byte sentinel, while
+//!   `corncobs` always appends one, and requires one to decode.
+//! * Empty data: the `cobs` crate encodes it as no bytes, and fails to decode no bytes, while
+//!   `cobs2` and `corncobs` encode it as `0x01`.
+//! * A run of non-zero bytes at the end of the data, whose length is a multiple of 254: the `cobs`
+//!   crate appends a redundant `0x01` length code after the final `0xFF` block. `cobs2` doesn't,
+//!   and `corncobs` is allowed to do either. Both forms decode to the same data with every crate.
+//! * A zero byte within encoded data: the `cobs` crate and `corncobs` take it as the end of the
+//!   frame, and ignore the rest, while `cobs2` returns `cobs2::Error::ZeroInEncodedData`.
+//!
+//! The random inputs come from [`Rng`], so they are the same on each run.
+
+/// A xorshift32 random number generator, as used by `tests/conformance/gen_vectors.c`.
+pub struct Rng(u32);
+
+impl Rng {
+    /// Create a generator with the given seed.
+    pub fn new(seed: u32) -> Rng {
+        Rng(seed | 1)
+    }
+
+    /// Get the next random number.
+    pub fn next_u32(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+
+    /// Get random data, mostly short, with some spanning several 254-byte blocks, and with a
+    /// density of zero bytes from none to all.
+    pub fn data(&mut self) -> Vec<u8> {
+        let len_max = if self.next_u32().is_multiple_of(4) { 1025 } else { 300 };
+        let len = self.next_u32() % len_max;
+        let zero_odds = self.next_u32() % 5;
+        (0..len)
+            .map(|_| {
+                if zero_odds != 0 && self.next_u32().is_multiple_of(zero_odds * zero_odds) {
+                    0
+                } else {
+                    (self.next_u32() % 255 + 1) as u8
+                }
+            })
+            .collect()
+    }
+}
+
+/// Returns `true` if the data ends with a run of non-zero bytes whose length is a positive
+/// multiple of 254, so that its encoding ends with a `0xFF` block.
+pub fn ends_with_full_block(data: &[u8]) -> bool {
+    let run_len = data.iter().rev().take_while(|&&x| x != 0).count();
+    run_len != 0 && run_len.is_multiple_of(254)
+}
+
+/// Data which exercises the block boundaries, as well as the random data.
+pub fn edge_cases() -> Vec<Vec<u8>> {
+    let mut cases = vec![
+        Vec::new(),
+        vec![0],
+        vec![0; 3],
+        vec![1],
+        b"ABC\0ghij\0xyz".to_vec(),
+    ];
+    for &len in [253, 254, 255, 508, 509].iter() {
+        cases.push(vec![0x55; len]);
+        let mut case = vec![0x55; len];
+        case.push(0);
+        cases.push(case);
+        let mut case = vec![0];
+        case.extend(vec![0x55; len]);
+        cases.push(case);
+    }
+    cases
+}
@@ -0,0 +1,116 @@
+/*
+ * Generate conformance test vectors from the cobs-c reference implementation.
+ *
+ * Build it with the cobs-c sources (https://github.com/cmcqueen/cobs-c), and write the vectors to
+ * a file:
+ *
+ *     cc -O2 -I path/to/cobs-c gen_vectors.c path/to/cobs-c/cobs.c path/to/cobs-c/cobsr.c \
+ *         -o gen_vectors
+ *     ./gen_vectors 10000 1 > cobs_c_vectors.txt
+ *
+ * The arguments are the number of random inputs, and the random seed. Each line of the output is
+ * the codec name, then the unencoded data and the encoded data in hex, or "-" if empty.
+ */
+
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "cobs.h"
+#include "cobsr.h"
+
+#define DATA_LEN_MAX    1024u
+#define ENCODED_LEN_MAX (DATA_LEN_MAX + DATA_LEN_MAX / 254u + 1u)
+
+static uint32_t rng_state;
+
+static uint32_t rng_next(void)
+{
+    /* xorshift32 */
+    rng_state ^= rng_state << 13;
+    rng_state ^= rng_state >> 17;
+    rng_state ^= rng_state << 5;
+    return rng_state;
+}
+
+static void print_hex(const uint8_t * p, size_t len)
+{
+    size_t i;
+
+    if (len == 0)
+    {
+        fputs("-", stdout);
+    }
+    for (i = 0; i < len; i++)
+    {
+        printf("%02x", p[i]);
+    }
+}
+
+static void print_vector(const char * codec, const uint8_t * data, size_t data_len,
+                         const uint8_t * encoded, size_t encoded_len)
+{
+    fputs(codec, stdout);
+    fputs(" ", stdout);
+    print_hex(data, data_len);
+    fputs(" ", stdout);
+    print_hex(encoded, encoded_len);
+    fputs("\n", stdout);
+}
+
+int main(int argc, char ** argv)
+{
+    static uint8_t data[DATA_LEN_MAX];
+    static uint8_t encoded[ENCODED_LEN_MAX];
+    unsigned long count;
+    unsigned long n;
+    size_t data_len;
+    size_t i;
+    uint32_t zero_odds;
+    cobs_encode_result cobs_result;
+    cobsr_encode_result cobsr_result;
+
+    if (argc != 3)
+    {
+        fprintf(stderr, "usage: %s COUNT SEED\n", argv[0]);
+        return 2;
+    }
+    count = strtoul(argv[1], NULL, 0);
+    rng_state = (uint32_t)strtoul(argv[2], NULL, 0) | 1u;
+
+    for (n = 0; n < count; n++)
+    {
+        /* Mostly short inputs, with some spanning several 254-byte blocks. */
+        data_len = rng_next() % ((rng_next() % 4u == 0) ? DATA_LEN_MAX + 1u : 300u);
+        /* Vary the density of zero bytes, from none to all. */
+        zero_odds = rng_next() % 5u;
+        for (i = 0; i < data_len; i++)
+        {
+            if (zero_odds != 0 && rng_next() % (zero_odds * zero_odds) == 0)
+            {
+                data[i] = 0;
+            }
+            else
+            {
+                data[i] = (uint8_t)(rng_next() % 255u + 1u);
+            }
+        }
+
+        cobs_result = cobs_encode(encoded, sizeof(encoded), data, data_len);
+        if (cobs_result.status != COBS_ENCODE_OK)
+        {
+            fprintf(stderr, "cobs_encode failed: %d\n", (int)cobs_result.status);
+            return 1;
+        }
+        print_vector("cobs", data, data_len, encoded, cobs_result.out_len);
+
+        cobsr_result = cobsr_encode(encoded, sizeof(encoded), data, data_len);
+        if (cobsr_result.status != COBSR_ENCODE_OK)
+        {
+            fprintf(stderr, "cobsr_encode failed: %d\n", (int)cobsr_result.status);
+            return 1;
+        }
+        print_vector("cobsr", data, data_len, encoded, cobsr_result.out_len);
+    }
+    return 0;
+}
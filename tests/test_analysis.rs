@@ -0,0 +1,177 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::analysis::{analyze, FrameStats, Histogram};
+use ::cobs2::profile::Variant;
+use ::cobs2::{cobs, cobsr, Error};
+
+#[test]
+fn test_analysis_cobs() {
+    let frames = [
+        cobs::encode_vector(b"ABC\0ghij\0xyz").unwrap(),
+        cobs::encode_vector(b"").unwrap(),
+        cobs::encode_vector(&[1; 300]).unwrap(),
+        b"\x05AB".to_vec(),
+        b"\x03A\0".to_vec(),
+        b"\x05AB".to_vec(),
+        Vec::new(),
+    ];
+    let stats = analyze(Variant::Cobs, &frames);
+    assert_eq!(stats.frames, 6);
+    assert_eq!(stats.valid_frames, 3);
+    assert_eq!(stats.invalid_frames(), 3);
+    assert_eq!(stats.encoded_bytes, 13 + 1 + 302 + 3 + 3 + 3);
+    assert_eq!(stats.decoded_bytes, 12 + 300);
+    assert_eq!(stats.error_count(&Error::TruncatedEncodedData), 2);
+    assert_eq!(stats.error_count(&Error::ZeroInEncodedData), 1);
+    assert_eq!(stats.error_count(&Error::FrameTooLong), 0);
+    assert_eq!(
+        stats.overhead.iter().collect::<Vec<_>>(),
+        [(&1, &2), (&2, &1)]
+    );
+    assert_eq!(stats.overhead_bytes(), 4);
+    // The 300-byte frame ends with 0x01, which COBS/R can't use as the length code.
+    assert_eq!(stats.cobsr_savings, 1);
+
+    let buckets: Vec<_> = stats
+        .size_histogram
+        .buckets()
+        .filter(|(_, count)| *count > 0)
+        .collect();
+    assert_eq!(buckets, [(0..=0, 1), (8..=15, 1), (256..=511, 1)]);
+
+    let report = stats.to_string();
+    assert!(report.starts_with("6 frames, 3 valid, 3 invalid\n"));
+    assert!(
+        report.contains("Unexpected end of encoded input data: 2"),
+        "{}",
+        report
+    );
+}
+
+#[test]
+fn test_analysis_cobsr() {
+    let data = b"ABC\0xyz";
+    let mut stats = FrameStats::new(Variant::Cobsr);
+    stats.add(&cobsr::encode_vector(data).unwrap());
+    assert_eq!(stats.valid_frames, 1);
+    assert_eq!(stats.overhead_bytes(), 0);
+    assert_eq!(stats.cobsr_savings, 1);
+    assert_eq!(
+        stats,
+        analyze(Variant::Cobsr, [cobsr::encode_vector(data).unwrap()])
+    );
+}
+
+#[test]
+fn test_analysis_histogram() {
+    let mut histogram = Histogram::default();
+    for size in [0, 1, 2, 3, 4, 7, 8] {
+        histogram.add(size);
+    }
+    let buckets: Vec<_> = histogram.buckets().collect();
+    assert_eq!(
+        buckets,
+        [(0..=0, 1), (1..=1, 1), (2..=3, 2), (4..=7, 2), (8..=15, 1)]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_analysis_capture() {
+    use ::cobs2::analysis::analyze_capture;
+    use ::cobs2::capture::{CaptureReader, CaptureWriter};
+
+    let mut capture = Vec::new();
+    let mut writer = CaptureWriter::new(&mut capture).unwrap();
+    writer.write_frame(b"\x04ABC").unwrap();
+    writer.write_frame(b"\x05AB").unwrap();
+    let stats = analyze_capture(Variant::Cobs, CaptureReader::new(&capture[..]).unwrap()).unwrap();
+    assert_eq!((stats.frames, stats.valid_frames), (2, 1));
+
+    let truncated = CaptureReader::new(&capture[..capture.len() - 1]).unwrap();
+    assert!(analyze_capture(Variant::Cobs, truncated).is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_validate_stream() {
+    use ::cobs2::analysis::validate_stream;
+    use ::cobs2::profile::Profile;
+
+    let report = validate_stream(&b"\x04ABC\0\0\x05AB\0\x03xy\0\x05AB"[..], Profile::Cobs).unwrap();
+    assert_eq!(report.bytes, 17);
+    assert_eq!(
+        (report.frames, report.valid_frames, report.invalid_frames()),
+        (4, 2, 2)
+    );
+    assert_eq!(report.error_count(&Error::TruncatedEncodedData), 2);
+    assert_eq!(report.first_invalid_offset, Some(6));
+    assert!(!report.is_valid());
+
+    // COBS/R frames are valid when the final length code is greater than the remaining length.
+    let report = validate_stream(&b"\x04ABC\0\x05AB\0"[..], Profile::Cobsr).unwrap();
+    assert_eq!((report.frames, report.valid_frames), (2, 2));
+    assert!(report.is_valid());
+    assert_eq!(report.first_invalid_offset, None);
+    assert_eq!(
+        validate_stream(&b""[..], Profile::Cobs).unwrap(),
+        Default::default()
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_validate_stream_profiles() {
+    use ::cobs2::analysis::validate_stream;
+    use ::cobs2::profile::Profile;
+
+    let frames: [&[u8]; 5] = [b"", b"\0", b"ABC\0xyz", &[0x42; 251], &[0x42; 300]];
+    for &profile in &[
+        Profile::Cobs,
+        Profile::Cobsr,
+        Profile::CobsCrc32,
+        Profile::CobsrCrc16Ccitt,
+    ] {
+        let mut stream = Vec::new();
+        let mut expected_errors = 0;
+        for frame in &frames {
+            match profile.encode(frame) {
+                Ok(frame_encoded) => {
+                    stream.extend_from_slice(&frame_encoded);
+                    stream.push(0);
+                    // Corrupt a copy of the frame, but keep it free of zeros.
+                    let mut corrupted = frame_encoded.clone();
+                    let last = corrupted.len() - 1;
+                    corrupted[last] = corrupted[last].wrapping_add(1).max(1);
+                    if profile.decode(&corrupted).is_err() {
+                        expected_errors += 1;
+                    }
+                    stream.extend_from_slice(&corrupted);
+                    stream.push(0);
+                }
+                Err(e) => assert_eq!(e, Error::FrameTooLong),
+            }
+        }
+        let report = validate_stream(&stream[..], profile).unwrap();
+        assert_eq!(report.invalid_frames(), expected_errors, "{:?}", profile);
+        assert_eq!(
+            report.valid_frames,
+            report.frames - expected_errors,
+            "{:?}",
+            profile
+        );
+        for (error, count) in &report.errors {
+            assert!(*count > 0);
+            assert!(matches!(
+                error,
+                Error::TruncatedEncodedData | Error::ChecksumMismatch
+            ));
+        }
+    }
+
+    let too_long = cobsr::encode_vector(&[0x42; 254]).unwrap();
+    let report = validate_stream(&too_long[..], Profile::CobsrCrc16Ccitt).unwrap();
+    assert_eq!(report.error_count(&Error::FrameTooLong), 1);
+    let report = validate_stream(&b"\x02A\0"[..], Profile::CobsCrc32).unwrap();
+    assert_eq!(report.error_count(&Error::ChecksumMismatch), 1);
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::bufpool::{self, MAX_POOLED, MAX_POOLED_CAPACITY};
+use ::cobs2::{cobs, cobsr, Error};
+
+use std::thread;
+
+// Each test runs in its own thread, so it has its own pool.
+
+#[test]
+fn test_bufpool_reuse() {
+    assert_eq!(bufpool::pooled_len(), 0);
+    let frame = cobs::decode_vector_pooled(b"\x04ABC\x05ghij\x04xyz").unwrap();
+    assert_eq!(&frame[..], b"ABC\0ghij\0xyz");
+    let ptr = frame.as_ptr();
+    assert_eq!(bufpool::pooled_len(), 0);
+    drop(frame);
+    assert_eq!(bufpool::pooled_len(), 1);
+
+    // A shorter frame reuses the same vector.
+    let frame = cobsr::decode_vector_pooled(b"\x03xy").unwrap();
+    assert_eq!(&frame[..], b"xy");
+    assert_eq!(frame.as_ptr(), ptr);
+    assert_eq!(format!("{:?}", frame), "PooledVec([120, 121])");
+    drop(frame);
+
+    // Frames held together use separate vectors.
+    let frames: Vec<_> = (0..3)
+        .map(|_| cobs::decode_vector_pooled(b"\x02A").unwrap())
+        .collect();
+    assert!(frames.iter().all(|frame| &frame[..] == b"A"));
+    assert_eq!(bufpool::pooled_len(), 0);
+    drop(frames);
+    assert_eq!(bufpool::pooled_len(), 3);
+
+    let mut frame = cobs::decode_vector_pooled(b"\x02A").unwrap();
+    frame[0] = b'B';
+    assert_eq!(frame.into_vec(), b"B");
+    assert_eq!(bufpool::pooled_len(), 2);
+
+    bufpool::clear();
+    assert_eq!(bufpool::pooled_len(), 0);
+}
+
+#[test]
+fn test_bufpool_errors() {
+    assert_eq!(
+        cobs::decode_vector_pooled(b"\x05AB").unwrap_err(),
+        Error::TruncatedEncodedData
+    );
+    assert_eq!(
+        cobsr::decode_vector_pooled(b"\x03A\x00").unwrap_err(),
+        Error::ZeroInEncodedData
+    );
+    // The vectors are returned to the pool.
+    assert_eq!(bufpool::pooled_len(), 1);
+}
+
+#[test]
+fn test_bufpool_limits() {
+    let frames: Vec<_> = (0..MAX_POOLED + 5)
+        .map(|_| cobs::decode_vector_pooled(b"\x02A").unwrap())
+        .collect();
+    drop(frames);
+    assert_eq!(bufpool::pooled_len(), MAX_POOLED);
+    bufpool::clear();
+
+    let data = vec![1_u8; MAX_POOLED_CAPACITY + 1];
+    let encoded = cobs::encode_vector(&data).unwrap();
+    let frame = cobs::decode_vector_pooled(&encoded).unwrap();
+    assert_eq!(&frame[..], &data[..]);
+    drop(frame);
+    assert_eq!(bufpool::pooled_len(), 0);
+}
+
+#[test]
+fn test_bufpool_threads() {
+    let frame = cobs::decode_vector_pooled(b"\x02A").unwrap();
+    // A frame dropped in another thread goes to that thread's pool.
+    thread::spawn(move || {
+        drop(frame);
+        assert_eq!(bufpool::pooled_len(), 1);
+    })
+    .join()
+    .unwrap();
+    assert_eq!(bufpool::pooled_len(), 0);
+}
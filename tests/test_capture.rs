@@ -0,0 +1,154 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::capture::{CaptureReader, CaptureRecord, CaptureWriter, ReplayTiming};
+
+use std::io;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_capture_format() {
+    let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+    writer
+        .write_record(
+            Duration::from_nanos(0x0102),
+            Duration::from_secs(1),
+            b"\x03AB",
+        )
+        .unwrap();
+    writer
+        .write_record(Duration::from_millis(5), Duration::from_secs(2), b"")
+        .unwrap();
+    let capture = writer.into_inner();
+
+    let mut expected = b"COBS2CAP\x01\x00".to_vec();
+    expected.extend_from_slice(b"\x02\x01\x00\x00\x00\x00\x00\x00");
+    expected.extend_from_slice(&1_000_000_000_u64.to_le_bytes());
+    expected.extend_from_slice(b"\x03\x00\x00\x00\x03AB");
+    expected.extend_from_slice(&5_000_000_u64.to_le_bytes());
+    expected.extend_from_slice(&2_000_000_000_u64.to_le_bytes());
+    expected.extend_from_slice(b"\x00\x00\x00\x00");
+    assert_eq!(capture, expected);
+
+    let records: Vec<CaptureRecord> = CaptureReader::new(&capture[..])
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(
+        records,
+        [
+            CaptureRecord {
+                monotonic: Duration::from_nanos(0x0102),
+                wall_clock: Duration::from_secs(1),
+                data: b"\x03AB".to_vec(),
+            },
+            CaptureRecord {
+                monotonic: Duration::from_millis(5),
+                wall_clock: Duration::from_secs(2),
+                data: Vec::new(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_capture_timestamps() {
+    let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+    for i in 0..10_u8 {
+        writer.write_frame(&[i + 1; 3]).unwrap();
+    }
+    let capture = writer.into_inner();
+
+    let records: Vec<CaptureRecord> = CaptureReader::new(&capture[..])
+        .unwrap()
+        .collect::<io::Result<_>>()
+        .unwrap();
+    assert_eq!(records.len(), 10);
+    for (i, pair) in records.windows(2).enumerate() {
+        assert_eq!(pair[1].data, [i as u8 + 2; 3]);
+        assert!(pair[0].monotonic <= pair[1].monotonic);
+    }
+    assert!(records[0].wall_clock > Duration::from_secs(1_600_000_000));
+}
+
+#[test]
+fn test_capture_bad() {
+    let e = CaptureReader::new(&b"COBS2CAX\x01\x00"[..]).err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    let e = CaptureReader::new(&b"COBS2CAP\x02\x00"[..]).err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    let e = CaptureReader::new(&b"COBS"[..]).err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+
+    // Truncated record.
+    let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+    writer.write_frame(b"\x03AB").unwrap();
+    let mut capture = writer.into_inner();
+    capture.pop();
+    let mut reader = CaptureReader::new(&capture[..]).unwrap();
+    assert_eq!(
+        reader.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_capture_replay() {
+    let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+    for i in 0..5_u64 {
+        let monotonic = Duration::from_millis(1000 + i * 20);
+        writer
+            .write_record(monotonic, Duration::from_secs(1), &[2, b'a' + i as u8])
+            .unwrap();
+    }
+    let capture = writer.into_inner();
+
+    // Immediate.
+    let mut out = Vec::new();
+    let start = Instant::now();
+    let count = CaptureReader::new(&capture[..])
+        .unwrap()
+        .replay_to(ReplayTiming::Immediate, &mut out)
+        .unwrap();
+    assert!(start.elapsed() < Duration::from_millis(80));
+    assert_eq!(count, 5);
+    assert_eq!(out, b"\x02a\x00\x02b\x00\x02c\x00\x02d\x00\x02e\x00");
+
+    // Original timing, relative to the first frame.
+    let mut times = Vec::new();
+    let start = Instant::now();
+    CaptureReader::new(&capture[..])
+        .unwrap()
+        .replay_with(ReplayTiming::Original, |_| {
+            times.push(start.elapsed());
+            Ok(())
+        })
+        .unwrap();
+    assert!(times[0] < Duration::from_millis(20));
+    assert!(times[4] >= Duration::from_millis(80));
+
+    // Sped up.
+    let start = Instant::now();
+    CaptureReader::new(&capture[..])
+        .unwrap()
+        .replay_with(ReplayTiming::Speed(4.0), |_| Ok(()))
+        .unwrap();
+    let elapsed = start.elapsed();
+    assert!(elapsed >= Duration::from_millis(20));
+    assert!(elapsed < Duration::from_millis(80));
+
+    // Error from the sink stops the replay.
+    let mut count = 0;
+    let result =
+        CaptureReader::new(&capture[..])
+            .unwrap()
+            .replay_with(ReplayTiming::Immediate, |_| {
+                count += 1;
+                if count == 2 {
+                    Err(io::ErrorKind::BrokenPipe.into())
+                } else {
+                    Ok(())
+                }
+            });
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(count, 2);
+}
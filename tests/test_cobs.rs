@@ -1,3 +1,5 @@
+mod common;
+
 use ::cobs2::{cobs, cobsr, Result};
 
 use bytes::Bytes;
@@ -71,6 +73,7 @@ const PREDEFINED_DECODINGS: [DataEncodedMapping; 2] = [
 ];
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_cobs_encode_min_output_size() {
     assert_eq!(1, cobs::encode_min_output_size(0));
     assert_eq!(2, cobs::encode_min_output_size(1));
@@ -97,6 +100,7 @@ fn test_cobs_encode_min_output_size() {
 }
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_cobs_encode_max_output_size() {
     assert_eq!(1, cobs::encode_max_output_size(0));
     assert_eq!(2, cobs::encode_max_output_size(1));
@@ -124,6 +128,32 @@ fn test_cobs_encode_max_output_size() {
 }
 
 #[test]
+fn test_cobs_encode_output_size_checked() {
+    for input_len in [0, 1, 2, 253, 254, 255, 256, 507, 508, 509, 510, 100_000] {
+        assert_eq!(
+            Some(cobs::encode_min_output_size(input_len)),
+            cobs::encode_min_output_size_checked(input_len)
+        );
+        assert_eq!(
+            Some(cobs::encode_max_output_size(input_len)),
+            cobs::encode_max_output_size_checked(input_len)
+        );
+    }
+
+    let increase = usize::MAX / 255;
+    assert_eq!(None, cobs::encode_max_output_size_checked(usize::MAX));
+    assert_eq!(
+        Some(usize::MAX),
+        cobs::encode_max_output_size_checked(usize::MAX - increase)
+    );
+    assert_eq!(
+        None,
+        cobs::encode_max_output_size_checked(usize::MAX - increase + 1)
+    );
+}
+
+#[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_cobs_decode_min_output_size() {
     assert_eq!(0, cobs::decode_min_output_size(0));
     assert_eq!(0, cobs::decode_min_output_size(1));
@@ -151,6 +181,7 @@ fn test_cobs_decode_min_output_size() {
 }
 
 #[test]
+#[allow(clippy::legacy_numeric_constants)]
 fn test_cobs_decode_max_output_size() {
     assert_eq!(0, cobs::decode_max_output_size(0));
     assert_eq!(0, cobs::decode_max_output_size(1));
@@ -168,6 +199,7 @@ fn test_cobs_decode_max_output_size() {
 }
 
 #[test]
+#[allow(clippy::needless_borrow)]
 fn test_cobs_array_predefined() {
     for mapping in PREDEFINED_ENCODINGS.iter() {
         let mut encode_out_vec = vec![0_u8; cobs::encode_max_output_size(mapping.rawdata.len())];
@@ -231,6 +263,69 @@ fn test_cobs_decode_array_predefined() {
     }
 }
 
+#[test]
+fn test_cobs_array_len_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let mut encode_out_vec = vec![0_u8; cobs::encode_max_output_size(mapping.rawdata.len())];
+        let enc_len = cobs::encode_array_len(&mut encode_out_vec[..], mapping.rawdata).unwrap();
+        assert_eq!(
+            &encode_out_vec[..enc_len],
+            mapping.encoded,
+            "{}",
+            mapping.description
+        );
+
+        let mut decode_out_vec = vec![0_u8; cobs::decode_max_output_size(enc_len)];
+        let dec_len =
+            cobs::decode_array_len(&mut decode_out_vec[..], &encode_out_vec[..enc_len]).unwrap();
+        assert_eq!(
+            &decode_out_vec[..dec_len],
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+
+    let mut out_buf = [0xCC_u8; 2];
+    let result = cobs::encode_array_len(&mut out_buf, b"\x01\x01\x01");
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+    let result = cobs::decode_array_len(&mut out_buf, b"\x04AAA");
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+}
+
+#[test]
+fn test_cobs_encode_into_split_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let encoded_len = mapping.encoded.len();
+        for head_len in 0..=encoded_len {
+            let mut out_head = vec![0xCC_u8; head_len];
+            let mut out_tail = vec![0xCC_u8; encoded_len - head_len];
+            let lens =
+                cobs::encode_into_split(&mut out_head, &mut out_tail, mapping.rawdata).unwrap();
+            assert_eq!(
+                lens,
+                (head_len, encoded_len - head_len),
+                "{}",
+                mapping.description
+            );
+            let encoded = [out_head, out_tail].concat();
+            assert_eq!(encoded, mapping.encoded, "{}", mapping.description);
+        }
+
+        if encoded_len > 0 {
+            let mut out_head = vec![0xCC_u8; encoded_len / 2];
+            let mut out_tail = vec![0xCC_u8; encoded_len - encoded_len / 2 - 1];
+            let result = cobs::encode_into_split(&mut out_head, &mut out_tail, mapping.rawdata);
+            assert_eq!(
+                result,
+                Err(::cobs2::Error::OutputBufferTooSmall),
+                "{}",
+                mapping.description
+            );
+        }
+    }
+}
+
 #[test]
 fn test_cobs_encode_array_buffer_too_small() {
     {
@@ -262,6 +357,69 @@ fn test_cobs_encode_array_buffer_too_small() {
     }
 }
 
+/// Encode a frame with `encode_array_partial()`, with an output buffer of the given size.
+fn cobs_encode_partial(data: &[u8], out_len: usize) -> Vec<u8> {
+    let mut out_buf = vec![0xCC_u8; out_len];
+    let mut encoded = Vec::new();
+    let mut data_rest = data;
+    loop {
+        let (consumed, produced, complete) = cobs::encode_array_partial(&mut out_buf, data_rest);
+        assert!(produced > 0);
+        encoded.extend_from_slice(&out_buf[..produced]);
+        data_rest = &data_rest[consumed..];
+        if complete {
+            assert!(data_rest.is_empty());
+            return encoded;
+        }
+    }
+}
+
+#[test]
+fn test_cobs_encode_array_partial() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        for out_len in [255, 256, 1000] {
+            let encoded = cobs_encode_partial(mapping.rawdata, out_len);
+            assert_eq!(
+                encoded, mapping.encoded,
+                "{} {}",
+                mapping.description, out_len
+            );
+        }
+    }
+    for data_len in [0, 1, 253, 254, 255, 508, 509, 1000] {
+        for zero_step in [0, 1, 3, 254, 255] {
+            let data: Vec<u8> = (0..data_len)
+                .map(|i| {
+                    if zero_step != 0 && i % zero_step == 0 {
+                        0
+                    } else {
+                        (i % 255 + 1) as u8
+                    }
+                })
+                .collect();
+            let expected = cobs::encode_vector(&data).unwrap();
+            for out_len in [255, 300] {
+                let encoded = cobs_encode_partial(&data, out_len);
+                assert_eq!(encoded, expected, "{} {} {}", data_len, zero_step, out_len);
+            }
+        }
+    }
+
+    // Encoding stops at a block which doesn't fit in the output buffer.
+    let mut out_buf = [0xCC_u8; 6];
+    assert_eq!(
+        cobs::encode_array_partial(&mut out_buf[..3], b"ABCD"),
+        (0, 0, false)
+    );
+    assert_eq!(
+        cobs::encode_array_partial(&mut out_buf, b"AB\0CD\0"),
+        (6, 6, false)
+    );
+    assert_eq!(&out_buf, b"\x03AB\x03CD");
+    assert_eq!(cobs::encode_array_partial(&mut out_buf, b""), (0, 1, true));
+    assert_eq!(&out_buf[..1], b"\x01");
+}
+
 #[test]
 fn test_cobs_decode_array_buffer_too_small() {
     {
@@ -279,6 +437,149 @@ fn test_cobs_decode_array_buffer_too_small() {
     }
 }
 
+#[test]
+fn test_cobs_decode_from_split() {
+    let bad_inputs: [&[u8]; 6] = [
+        b"\x00sAAA",
+        b"\x05AAA",
+        b"\x05\x00AAA",
+        b"\x03AB\x00",
+        b"\x01\x01",
+        b"\x02\xFF",
+    ];
+    let inputs = PREDEFINED_ENCODINGS
+        .iter()
+        .map(|mapping| mapping.encoded)
+        .chain(bad_inputs.iter().copied());
+    for in_buf in inputs {
+        for split in 0..=in_buf.len() {
+            let (in_head, in_tail) = in_buf.split_at(split);
+            for out_len in [0, 1, 2, 3, 10, 300, 600] {
+                let mut expected_buf = vec![0xCC_u8; out_len];
+                let expected = cobs::decode_array(&mut expected_buf, in_buf);
+                let mut out_buf = vec![0xCC_u8; out_len];
+                let result = cobs::decode_from_split(&mut out_buf, in_head, in_tail);
+                assert_eq!(result, expected, "{:02X?} split at {}", in_buf, split);
+            }
+        }
+    }
+}
+
+/// Decode frames received in chunks of the given size, using `decode_array_partial()`, with an
+/// output buffer of the given size.
+fn cobs_decode_partial(encoded: &[u8], chunk_size: usize, out_len: usize) -> Result<Vec<Vec<u8>>> {
+    let mut pending = Vec::new();
+    let mut out_buf = vec![0xCC_u8; out_len];
+    let mut frame = Vec::new();
+    let mut frames = Vec::new();
+    for chunk in encoded.chunks(chunk_size) {
+        pending.extend_from_slice(chunk);
+        loop {
+            let (consumed, produced, complete) =
+                cobs::decode_array_partial(&mut out_buf, &pending)?;
+            frame.extend_from_slice(&out_buf[..produced]);
+            pending.drain(..consumed);
+            if complete {
+                frames.push(std::mem::take(&mut frame));
+            }
+            if consumed == 0 {
+                break;
+            }
+        }
+    }
+    assert!(pending.is_empty());
+    Ok(frames)
+}
+
+#[test]
+fn test_cobs_decode_array_partial_predefined() {
+    let mut encoded = Vec::new();
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        encoded.extend_from_slice(mapping.encoded);
+        encoded.push(0);
+    }
+    for chunk_size in [1, 2, 7, 300, encoded.len()] {
+        for out_len in [255, 256, 1000] {
+            let frames = cobs_decode_partial(&encoded, chunk_size, out_len).unwrap();
+            assert_eq!(frames.len(), PREDEFINED_ENCODINGS.len());
+            for (frame, mapping) in frames.iter().zip(PREDEFINED_ENCODINGS.iter()) {
+                assert_eq!(frame, mapping.rawdata, "{}", mapping.description);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_cobs_decode_array_partial() {
+    let mut out_buf = [0xCC_u8; 300];
+
+    // Nothing is consumed until the byte following a block is received.
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b""),
+        Ok((0, 0, false))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x05ABC"),
+        Ok((0, 0, false))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x05ABCD"),
+        Ok((0, 0, false))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x05ABCD\x02"),
+        Ok((5, 5, false))
+    );
+    assert_eq!(&out_buf[..5], b"ABCD\0");
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x05ABCD\x00"),
+        Ok((6, 4, true))
+    );
+    assert_eq!(&out_buf[..4], b"ABCD");
+
+    // A maximum length run isn't followed by a zero byte, so it is consumed without the next byte.
+    let mut in_buf = vec![0xFF_u8];
+    in_buf.extend_from_slice(&[b'A'; 254]);
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, &in_buf),
+        Ok((255, 254, false))
+    );
+
+    // Empty frames, and data following the end of the frame, which isn't consumed.
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x00\x00"),
+        Ok((1, 0, true))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x01\x00\x02"),
+        Ok((2, 0, true))
+    );
+
+    // Decoding stops at a block which doesn't fit in the output buffer.
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf[..3], b"\x05ABCD\x00"),
+        Ok((0, 0, false))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf[..4], b"\x05ABCD\x00"),
+        Ok((6, 4, true))
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf[..4], b"\x02A\x02B\x02C\x00"),
+        Ok((4, 4, false))
+    );
+    assert_eq!(&out_buf[..4], b"A\0B\0");
+    // The delimiter within a block.
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x02A\x05AB\x00\x02A"),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+    assert_eq!(
+        cobs::decode_array_partial(&mut out_buf, b"\x05AB\x00"),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+}
+
 #[test]
 fn test_cobs_decode_array_bad() {
     // Try decoding bad data.
@@ -380,6 +681,105 @@ fn test_cobs_decode_vector_bad() {
 
 #[cfg(feature = "alloc")]
 #[test]
+fn test_cobs_as_ref_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let raw_vec = mapping.rawdata.to_vec();
+        let encoded = cobs::encode(&raw_vec).unwrap();
+        assert_eq!(encoded, mapping.encoded, "{}", mapping.description);
+        assert_eq!(
+            cobs::encode(raw_vec).unwrap(),
+            mapping.encoded,
+            "{}",
+            mapping.description
+        );
+        assert_eq!(
+            cobs::encode(Bytes::from(mapping.rawdata)).unwrap(),
+            mapping.encoded,
+            "{}",
+            mapping.description
+        );
+
+        assert_eq!(
+            cobs::decode(&encoded).unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+        assert_eq!(
+            cobs::decode(Bytes::from(encoded)).unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+    assert_eq!(
+        cobs::decode([0_u8, b'A']),
+        Err(::cobs2::Error::ZeroInEncodedData)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_canonicalize() {
+    use std::borrow::Cow;
+
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let data_canonical = cobs::canonicalize(mapping.encoded).unwrap();
+        assert!(
+            matches!(data_canonical, Cow::Borrowed(_)),
+            "{}",
+            mapping.description
+        );
+    }
+
+    // Redundant length code after a maximum length run.
+    let mut data_cobs = vec![0xFF_u8; 255];
+    data_cobs.push(0x01);
+    assert_eq!(cobs::canonicalize(&data_cobs).unwrap(), &data_cobs[..255]);
+    assert_eq!(
+        cobs::canonicalize(b"\x05AB"),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+}
+
+#[test]
+fn test_cobs_decode_utf8() {
+    let data_cobs = b"\x0aJSON: \xE2\x9C\x93";
+    let mut decode_buf = [0_u8; 20];
+    assert_eq!(
+        cobs::decode_utf8(&mut decode_buf, data_cobs).unwrap(),
+        "JSON: \u{2713}"
+    );
+    assert_eq!(
+        cobs::decode_utf8(&mut decode_buf, b"\x02\xC3"),
+        Err(::cobs2::Error::InvalidUtf8)
+    );
+    assert_eq!(
+        cobs::decode_utf8(&mut decode_buf, b"\x03A\x00"),
+        Err(::cobs2::Error::ZeroInEncodedData)
+    );
+    assert_eq!(
+        cobs::decode_utf8(&mut decode_buf[..2], data_cobs),
+        Err(::cobs2::Error::OutputBufferTooSmall)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_utf8_vector() {
+    assert_eq!(
+        cobs::decode_utf8_vector(b"\x0aJSON: \xE2\x9C\x93").unwrap(),
+        "JSON: \u{2713}"
+    );
+    assert_eq!(
+        cobs::decode_utf8_vector(b"\x02\xC3"),
+        Err(::cobs2::Error::InvalidUtf8)
+    );
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+#[allow(clippy::useless_conversion)]
 fn test_cobs_iter_predefined() {
     for mapping in PREDEFINED_ENCODINGS.iter() {
         let encode_in_vec = mapping.rawdata.to_vec();
@@ -406,6 +806,98 @@ fn test_cobs_ref_iter_predefined() {
 
 #[cfg(feature = "alloc")]
 #[test]
+fn test_cobs_iter_borrowed_input() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        // Slices, vector references and `&u8` iterators are accepted, as well as `u8` iterators.
+        let encode_out_vec: Vec<u8> = cobs::encode_iter(mapping.rawdata).collect();
+        assert_eq!(encode_out_vec, mapping.encoded, "{}", mapping.description);
+        let encode_out_vec: Vec<u8> = cobs::encode_iter(mapping.rawdata.iter()).collect();
+        assert_eq!(encode_out_vec, mapping.encoded, "{}", mapping.description);
+
+        let decode_out_vec: Vec<u8> = cobs::decode_iter(&encode_out_vec).collect();
+        assert_eq!(decode_out_vec, mapping.rawdata, "{}", mapping.description);
+        let decode_out_result_vec: Result<Vec<u8>> =
+            cobs::decode_result_iter(&encode_out_vec).collect();
+        assert_eq!(
+            decode_out_result_vec.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_iter_owned_input() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        // Vectors and other owned containers can be passed directly, without `into_iter()`.
+        let encode_out_vec: Vec<u8> = cobs::encode_iter(mapping.rawdata.to_vec()).collect();
+        assert_eq!(encode_out_vec, mapping.encoded, "{}", mapping.description);
+        let encode_out_bytes: Bytes = cobs::encode_iter(Bytes::from(mapping.rawdata)).collect();
+        assert_eq!(encode_out_bytes, mapping.encoded, "{}", mapping.description);
+
+        let decode_out_vec: Vec<u8> = cobs::decode_iter(encode_out_vec.clone()).collect();
+        assert_eq!(decode_out_vec, mapping.rawdata, "{}", mapping.description);
+        let decode_out_bytes: Bytes = cobs::decode_iter(encode_out_bytes).collect();
+        assert_eq!(decode_out_bytes, mapping.rawdata, "{}", mapping.description);
+        let decode_out_result_vec: Result<Vec<u8>> =
+            cobs::decode_result_iter(encode_out_vec).collect();
+        assert_eq!(
+            decode_out_result_vec,
+            Ok(mapping.rawdata.to_vec()),
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_cobs_iter_to_writer() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let mut encode_out_vec = Vec::new();
+        let len = cobs::encode_iter_to_writer(mapping.rawdata, &mut encode_out_vec).unwrap();
+        assert_eq!(encode_out_vec, mapping.encoded, "{}", mapping.description);
+        assert_eq!(len, mapping.encoded.len() as u64, "{}", mapping.description);
+
+        let mut decode_out_vec = Vec::new();
+        let len = cobs::decode_iter_to_writer(mapping.encoded, &mut decode_out_vec).unwrap();
+        assert_eq!(decode_out_vec, mapping.rawdata, "{}", mapping.description);
+        assert_eq!(len, mapping.rawdata.len() as u64, "{}", mapping.description);
+    }
+
+    // A large generated payload, streamed without collecting it.
+    let generate = || (0..100_000_u32).map(|x| (x % 251) as u8);
+    let mut encode_out_vec = Vec::new();
+    cobs::encode_iter_to_writer(generate(), &mut encode_out_vec).unwrap();
+    assert_eq!(
+        encode_out_vec,
+        cobs::encode_vector(&generate().collect::<Vec<u8>>()).unwrap()
+    );
+    let mut decode_out_vec = Vec::new();
+    let len = cobs::decode_iter_to_writer(&encode_out_vec, &mut decode_out_vec).unwrap();
+    assert_eq!(len, 100_000);
+    assert!(decode_out_vec.into_iter().eq(generate()));
+
+    let mut decode_out_vec = Vec::new();
+    let err = cobs::decode_iter_to_writer(b"\x03A\x00B", &mut decode_out_vec).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    let inner = err
+        .into_inner()
+        .unwrap()
+        .downcast::<::cobs2::Error>()
+        .unwrap();
+    assert_eq!(*inner, ::cobs2::Error::ZeroInEncodedData);
+
+    let mut small_buf = [0_u8; 4];
+    let err = cobs::encode_iter_to_writer(b"ABCDEFGH", &mut &mut small_buf[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::WriteZero);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+#[allow(clippy::useless_conversion)]
 fn test_cobs_decode_iter_predefined() {
     for mapping in PREDEFINED_DECODINGS.iter() {
         let decode_in_vec = mapping.encoded.to_vec();
@@ -426,6 +918,7 @@ fn test_cobs_decode_ref_iter_predefined() {
 
 #[cfg(feature = "alloc")]
 #[test]
+#[allow(clippy::useless_conversion)]
 fn test_cobs_decode_result_iter_predefined() {
     for mapping in PREDEFINED_ENCODINGS.iter().chain(PREDEFINED_DECODINGS.iter()) {
         let decode_in_vec = mapping.encoded.to_vec();
@@ -447,6 +940,7 @@ fn test_cobs_decode_result_ref_iter_predefined() {
 }
 
 #[test]
+#[allow(clippy::useless_conversion)]
 fn test_cobs_decode_result_iter_bad() {
     // Try decoding bad data.
     let bad_cobs_encoded_data = b"\x00sAAA".to_vec();
@@ -480,6 +974,7 @@ fn test_cobs_decode_result_ref_iter_bad() {
 
 #[test]
 /// Show how the iterator API can be used with other containers. Eg [Bytes].
+#[allow(clippy::useless_conversion)]
 fn test_cobs_iter_predefined_w_bytes() {
     for mapping in PREDEFINED_ENCODINGS.iter() {
         let encode_in_bytes = Bytes::from(mapping.rawdata);
@@ -503,3 +998,1043 @@ fn test_cobs_ref_iter_predefined_w_bytes() {
         assert_eq!(decode_out_bytes, mapping.rawdata, "{}", mapping.description);
     }
 }
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_cobs_decode_frames_bytes_predefined() {
+    let mut encoded = Vec::new();
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        encoded.extend_from_slice(mapping.encoded);
+        encoded.push(0);
+    }
+    let frames = cobs::decode_frames_bytes(Bytes::from(encoded)).unwrap();
+    assert_eq!(frames.len(), PREDEFINED_ENCODINGS.len());
+    for (frame, mapping) in frames.iter().zip(PREDEFINED_ENCODINGS.iter()) {
+        assert_eq!(frame, mapping.rawdata, "{}", mapping.description);
+    }
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_cobs_decode_frames_bytes_delimiters() {
+    // Leading, repeated and missing trailing delimiters.
+    let encoded = Bytes::from_static(b"\x00\x00\x04ABC\x00\x00\x01\x00\x0612345\x056789");
+    let frames = cobs::decode_frames_bytes(encoded).unwrap();
+    assert_eq!(frames, [&b"ABC"[..], &b""[..], &b"12345\x006789"[..]]);
+
+    let frames = cobs::decode_frames_bytes(Bytes::new()).unwrap();
+    assert!(frames.is_empty());
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_cobs_decode_frames_bytes_zero_copy() {
+    let encoded = Bytes::from_static(b"\x04ABC\x00\x0612345\x056789\x00");
+    let frames = cobs::decode_frames_bytes(encoded.clone()).unwrap();
+    // Single-block frame shares the input buffer.
+    assert_eq!(frames[0].as_ptr(), encoded[1..].as_ptr());
+    // Multi-block frame is copied.
+    assert_eq!(frames[1], &b"12345\x006789"[..]);
+}
+
+#[cfg(feature = "bytes")]
+#[test]
+fn test_cobs_decode_frames_bytes_bad() {
+    let bad_cobs_encoded_data = Bytes::from_static(b"\x04ABC\x00\x05AAA\x00");
+    let result = cobs::decode_frames_bytes(bad_cobs_encoded_data);
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_hex_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let encoded_hex = cobs::encode_to_hex(mapping.rawdata);
+        let expected_hex: Vec<String> = mapping
+            .encoded
+            .iter()
+            .map(|x| format!("{:02X}", x))
+            .collect();
+        assert_eq!(
+            encoded_hex,
+            expected_hex.join(" "),
+            "{}",
+            mapping.description
+        );
+
+        let dec_result = cobs::decode_from_hex(&encoded_hex);
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+
+        // Lower-case, unseparated hex should also be accepted.
+        let dec_result = cobs::decode_from_hex(&expected_hex.concat().to_lowercase());
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_from_hex_bad() {
+    let result = cobs::decode_from_hex("04 41 42 4");
+    assert_eq!(result, Err(::cobs2::Error::InvalidHexData));
+
+    let result = cobs::decode_from_hex("04 41 4 24 3");
+    assert_eq!(result, Err(::cobs2::Error::InvalidHexData));
+
+    let result = cobs::decode_from_hex("04 41 42 4G");
+    assert_eq!(result, Err(::cobs2::Error::InvalidHexData));
+
+    let result = cobs::decode_from_hex("05 00 41 41 41");
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_frames_iter_predefined() {
+    let mut encoded = Vec::new();
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        encoded.extend_from_slice(mapping.encoded);
+        encoded.push(0);
+    }
+    let mut frames = cobs::decode_frames_iter(encoded.into_iter());
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let frame: Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+        assert_eq!(frame.unwrap(), mapping.rawdata, "{}", mapping.description);
+    }
+    assert!(frames.next_frame().is_none());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_frames_iter_empty_frames() {
+    let encoded = b"\x00\x03AB\x00\x00\x02C\x00";
+    let mut frames = cobs::decode_frames_iter(encoded.iter().copied());
+    frames.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    let mut decoded = Vec::new();
+    while let Some(frame) = frames.next_frame() {
+        let frame: Result<Vec<u8>> = frame.collect();
+        decoded.push(frame.unwrap());
+    }
+    assert_eq!(decoded, [&b""[..], b"AB", b"", b"C"]);
+
+    let mut frames = cobs::decode_frames_iter(encoded.iter().copied());
+    frames.set_empty_frames(::cobs2::EmptyFrames::Skip);
+    let mut decoded = Vec::new();
+    while let Some(frame) = frames.next_frame() {
+        let frame: Result<Vec<u8>> = frame.collect();
+        decoded.push(frame.unwrap());
+    }
+    assert_eq!(decoded, [&b"AB"[..], b"C"]);
+}
+
+#[test]
+fn test_cobs_decode_frames_iter_keep_alive() {
+    // Bare delimiters are used as keep-alives between frames.
+    let encoded = b"\x03AB\x00\x00\x00\x02C\x00";
+    let mut frames = cobs::decode_frames_iter(encoded.iter().copied());
+    frames.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    let mut keep_alive_count = 0;
+    let mut data_count = 0;
+    while let Some(mut frame) = frames.next_frame() {
+        if frame.is_empty_frame() {
+            keep_alive_count += 1;
+            assert!(frame.next().is_none());
+        } else {
+            data_count += 1;
+        }
+    }
+    assert_eq!(keep_alive_count, 2);
+    assert_eq!(data_count, 2);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_frames_iter_partial_consume() {
+    // Leading, repeated and missing trailing delimiters.
+    let encoded = b"\x00\x0612345\x056789\x00\x00\x04ABC".to_vec();
+    let mut frames = cobs::decode_frames_iter(encoded.into_iter());
+    {
+        let mut frame = frames.next_frame().unwrap();
+        assert_eq!(frame.next(), Some(Ok(b'1')));
+        assert_eq!(frame.next(), Some(Ok(b'2')));
+        // Drop the frame iterator without consuming the rest of the frame.
+    }
+    let frame: Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+    assert_eq!(frame.unwrap(), b"ABC");
+    assert!(frames.next_frame().is_none());
+    assert!(frames.next_frame().is_none());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_frames_iter_bad() {
+    let bad_cobs_encoded_data = b"\x05AAA\x00\x04ABC\x00\x05AAA".to_vec();
+    let mut frames = cobs::decode_frames_iter(bad_cobs_encoded_data.into_iter());
+    let frame: Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+    assert_eq!(frame, Err(::cobs2::Error::TruncatedEncodedData));
+    let frame: Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+    assert_eq!(frame.unwrap(), b"ABC");
+    let frame: Result<Vec<u8>> = frames.next_frame().unwrap().collect();
+    assert_eq!(frame, Err(::cobs2::Error::TruncatedEncodedData));
+    assert!(frames.next_frame().is_none());
+}
+
+#[test]
+fn test_cobs_array_sentinel_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let mut encoded = mapping.encoded.to_vec();
+        encoded.push(0);
+
+        let mut encode_out_vec =
+            vec![0_u8; cobs::encode_max_output_size(mapping.rawdata.len()) + 1];
+        let enc_result = cobs::encode_array_sentinel(&mut encode_out_vec[..], mapping.rawdata);
+        assert_eq!(enc_result.unwrap(), &encoded[..], "{}", mapping.description);
+
+        let mut decode_out_vec = vec![0_u8; cobs::decode_max_output_size(encoded.len())];
+        let dec_result = cobs::decode_array_sentinel(&mut decode_out_vec[..], &encoded);
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_vector_sentinel_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let mut encoded = mapping.encoded.to_vec();
+        encoded.push(0);
+
+        let enc_result = cobs::encode_vector_sentinel(mapping.rawdata);
+        assert_eq!(enc_result.unwrap(), encoded, "{}", mapping.description);
+
+        let dec_result = cobs::decode_vector_sentinel(&encoded);
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[test]
+fn test_cobs_encode_array_sentinel_buffer_too_small() {
+    let in_data = b"\x01\x01\x01\x01\x01";
+    let mut cobs_encode_buf = [0xCC_u8; 6];
+    let result = cobs::encode_array_sentinel(&mut cobs_encode_buf, in_data);
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+
+    let mut cobs_encode_buf = [0xCC_u8; 7];
+    let result = cobs::encode_array_sentinel(&mut cobs_encode_buf, in_data);
+    assert_eq!(result.unwrap(), b"\x06\x01\x01\x01\x01\x01\x00");
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_sentinel_bad() {
+    let mut cobs_decode_buf = [0xCC_u8; 50];
+
+    // Missing sentinel.
+    let bad_cobs_encoded_data = b"\x04AAA";
+    let result = cobs::decode_array_sentinel(&mut cobs_decode_buf, bad_cobs_encoded_data);
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+    let result = cobs::decode_vector_sentinel(bad_cobs_encoded_data);
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+    let result = cobs::decode_vector_sentinel(b"");
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+
+    // Zero before the sentinel.
+    let bad_cobs_encoded_data = b"\x04AAA\x00\x02B\x00";
+    let result = cobs::decode_array_sentinel(&mut cobs_decode_buf, bad_cobs_encoded_data);
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+    let result = cobs::decode_vector_sentinel(bad_cobs_encoded_data);
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decode_detail_bad() {
+    let mut cobs_decode_buf = [0xCC_u8; 50];
+
+    // Leading delimiter.
+    let bad_cobs_encoded_data = b"\x00\x04AAA";
+    let detail =
+        cobs::decode_array_detail(&mut cobs_decode_buf, bad_cobs_encoded_data).unwrap_err();
+    assert_eq!(detail.error, ::cobs2::Error::ZeroInEncodedData);
+    assert_eq!(detail.index, 0);
+    assert!(detail.is_leading_zero());
+    assert_eq!(detail.resync_index(), Some(1));
+    assert_eq!(
+        cobs::decode_vector_detail(bad_cobs_encoded_data).unwrap_err(),
+        detail
+    );
+
+    // Embedded zero.
+    let bad_cobs_encoded_data = b"\x05AA\x00A";
+    let detail =
+        cobs::decode_array_detail(&mut cobs_decode_buf, bad_cobs_encoded_data).unwrap_err();
+    assert_eq!(detail.error, ::cobs2::Error::ZeroInEncodedData);
+    assert_eq!(detail.index, 3);
+    assert!(!detail.is_leading_zero());
+    assert_eq!(detail.resync_index(), Some(4));
+    assert_eq!(
+        cobs::decode_vector_detail(bad_cobs_encoded_data).unwrap_err(),
+        detail
+    );
+
+    // Truncated.
+    let bad_cobs_encoded_data = b"\x05AAA";
+    let detail =
+        cobs::decode_array_detail(&mut cobs_decode_buf, bad_cobs_encoded_data).unwrap_err();
+    assert_eq!(detail.error, ::cobs2::Error::TruncatedEncodedData);
+    assert_eq!(detail.index, 4);
+    assert_eq!(detail.resync_index(), None);
+    assert_eq!(
+        cobs::decode_vector_detail(bad_cobs_encoded_data).unwrap_err(),
+        detail
+    );
+}
+
+#[test]
+fn test_cobs_array_no_panic() {
+    // Every output buffer size, for encoding and decoding arbitrary data (valid or not), must
+    // return either a result or an error, without panicking.
+    let mut state = 0x1234_5678_u32;
+    for _ in 0..200 {
+        let in_data = common::random_data(&mut state, 600, 8);
+        let len = in_data.len();
+        let mut encode_buf = vec![0xCC_u8; cobs::encode_max_output_size(len)];
+        let encoded = cobs::encode_array(&mut encode_buf, &in_data)
+            .unwrap()
+            .to_vec();
+
+        let mut out_buf = vec![0xCC_u8; encoded.len() + 1];
+        for out_len in 0..=out_buf.len() {
+            let result = cobs::encode_array(&mut out_buf[..out_len], &in_data);
+            if out_len >= encoded.len() {
+                assert_eq!(result.unwrap(), &encoded[..]);
+            } else {
+                assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+            }
+
+            let result = cobs::decode_array(&mut out_buf[..out_len], &encoded);
+            if out_len >= in_data.len() {
+                assert_eq!(result.unwrap(), &in_data[..]);
+            } else {
+                assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+            }
+
+            // Arbitrary data, which may not be valid encoded data.
+            let _ = cobs::decode_array(&mut out_buf[..out_len], &in_data);
+        }
+    }
+}
+
+#[test]
+fn test_cobs_frame_accumulator_predefined() {
+    let mut encoded = vec![0_u8];
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        encoded.extend_from_slice(mapping.encoded);
+        encoded.push(0);
+    }
+
+    // Feed the data in chunks of various sizes, so frames are split across chunks.
+    for chunk_size in [1, 2, 7, 300, encoded.len()] {
+        let mut accumulator = cobs::FrameAccumulator::<300>::new();
+        let mut frames = Vec::new();
+        for mut chunk in encoded.chunks(chunk_size) {
+            while !chunk.is_empty() {
+                let consumed = accumulator.feed(chunk);
+                chunk = &chunk[consumed..];
+                if let Some(frame) = accumulator.poll_frame() {
+                    frames.push(frame.unwrap().to_vec());
+                }
+            }
+        }
+        assert_eq!(frames.len(), PREDEFINED_ENCODINGS.len());
+        for (frame, mapping) in frames.iter().zip(PREDEFINED_ENCODINGS.iter()) {
+            assert_eq!(frame, mapping.rawdata, "{}", mapping.description);
+        }
+        assert!(accumulator.poll_frame().is_none());
+    }
+}
+
+#[test]
+fn test_cobs_frame_accumulator_feed_stops_at_frame() {
+    let mut accumulator = cobs::FrameAccumulator::<16>::new();
+    let data = b"\x03AB\x00\x02C\x00";
+    assert_eq!(accumulator.feed(data), 4);
+    // A frame is ready, so nothing more is consumed until it is polled.
+    assert_eq!(accumulator.feed(&data[4..]), 0);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+    assert!(accumulator.poll_frame().is_none());
+    assert_eq!(accumulator.feed(&data[4..]), 3);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"C");
+}
+
+#[test]
+fn test_cobs_frame_accumulator_empty_frames() {
+    let data = b"\x00\x03AB\x00\x00";
+    let mut accumulator = cobs::FrameAccumulator::<16>::new();
+    assert_eq!(accumulator.feed(data), 5);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+    assert_eq!(accumulator.feed(&data[5..]), 1);
+    assert!(accumulator.poll_frame().is_none());
+
+    accumulator.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    assert_eq!(accumulator.feed(data), 1);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"");
+    assert_eq!(accumulator.feed(&data[1..]), 4);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+    assert_eq!(accumulator.feed(&data[5..]), 1);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"");
+}
+
+#[test]
+fn test_cobs_frame_accumulator_bad() {
+    let mut accumulator = cobs::FrameAccumulator::<4>::new();
+
+    // Frame too large for the internal buffer. Following frames are not affected.
+    let data = b"\x06ABCDE\x00\x03AB\x00";
+    assert_eq!(accumulator.feed(data), 7);
+    assert_eq!(
+        accumulator.poll_frame().unwrap(),
+        Err(::cobs2::Error::OutputBufferTooSmall)
+    );
+    assert_eq!(accumulator.feed(&data[7..]), 4);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+
+    // Discard a partly received frame.
+    assert_eq!(accumulator.feed(b"\x03X"), 2);
+    accumulator.clear();
+    assert_eq!(accumulator.feed(b"\x02Y\x00"), 3);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"Y");
+
+    // Truncated frame.
+    assert_eq!(accumulator.feed(b"\x05AB\x00"), 4);
+    assert_eq!(
+        accumulator.poll_frame().unwrap(),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+}
+
+#[test]
+fn test_cobs_frame_accumulator_flush_partial() {
+    let mut accumulator = cobs::FrameAccumulator::<16>::new();
+
+    // Nothing to flush.
+    assert!(!accumulator.flush_partial());
+    assert!(accumulator.poll_frame().is_none());
+
+    // Last frame of a burst, without a trailing delimiter.
+    assert_eq!(accumulator.feed(b"\x03AB\x00\x03gh\x03ij"), 4);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+    assert_eq!(accumulator.feed(b"\x03gh\x03ij"), 6);
+    assert!(accumulator.poll_frame().is_none());
+    assert!(accumulator.flush_partial());
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"gh\0ij");
+    assert!(!accumulator.flush_partial());
+
+    // Flushing a frame which is ready has no effect.
+    assert_eq!(accumulator.feed(b"\x02X\x00"), 3);
+    assert!(accumulator.flush_partial());
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"X");
+
+    // Partial frame which is truncated partway through a run.
+    assert_eq!(accumulator.feed(b"\x05ABC"), 4);
+    assert!(accumulator.flush_partial());
+    assert_eq!(
+        accumulator.poll_frame().unwrap(),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+}
+
+#[test]
+fn test_cobs_frame_accumulator_frames() {
+    let mut accumulator = cobs::FrameAccumulator::<4>::new();
+
+    // Frames in one chunk, including one too large for the internal buffer.
+    let mut frames = accumulator.frames(b"\x03AB\x00\x06ABCDE\x00\x02C\x00\x03g");
+    assert_eq!(frames.next_frame().unwrap().unwrap(), b"AB");
+    assert_eq!(frames.remaining(), b"\x06ABCDE\x00\x02C\x00\x03g");
+    assert_eq!(
+        frames.next_frame().unwrap(),
+        Err(::cobs2::Error::OutputBufferTooSmall)
+    );
+    assert_eq!(frames.next_frame().unwrap().unwrap(), b"C");
+    assert!(frames.next_frame().is_none());
+    assert_eq!(frames.remaining(), b"");
+    assert!(frames.next_frame().is_none());
+
+    // The partly received frame is completed by the next chunk.
+    let mut frames = accumulator.frames(b"h\x00");
+    assert_eq!(frames.next_frame().unwrap().unwrap(), b"gh");
+    assert!(frames.next_frame().is_none());
+
+    // A frame which is ready, but not yet polled, is returned first.
+    assert_eq!(accumulator.feed(b"\x02X\x00"), 3);
+    let mut frames = accumulator.frames(b"\x05ABC\x00");
+    assert_eq!(frames.next_frame().unwrap().unwrap(), b"X");
+    assert_eq!(
+        frames.next_frame().unwrap(),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+    assert!(frames.next_frame().is_none());
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_frame_accumulator_frames_iter() {
+    let mut accumulator = cobs::FrameAccumulator::<4>::new();
+    let mut frames = Vec::new();
+    for received in [&b"\x03AB\x00\x06ABCDE\x00\x02C\x00\x03g"[..], b"h\x00"] {
+        for frame in accumulator.frames(received) {
+            frames.push(frame);
+        }
+    }
+    assert_eq!(
+        frames,
+        [
+            Ok(b"AB".to_vec()),
+            Err(::cobs2::Error::OutputBufferTooSmall),
+            Ok(b"C".to_vec()),
+            Ok(b"gh".to_vec()),
+        ]
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_cobs_frame_accumulator_feed_sink() {
+    let mut accumulator = cobs::FrameAccumulator::<4>::new();
+    let mut frames: Vec<Vec<u8>> = Vec::new();
+    assert_eq!(
+        accumulator.feed_sink(b"\x03AB\x00\x06ABCDE\x00\x02C\x00\x03g", &mut frames),
+        Ok(1)
+    );
+    assert_eq!(accumulator.feed_sink(b"h\x00", &mut frames), Ok(0));
+    assert_eq!(frames, [&b"AB"[..], b"C", b"gh"]);
+
+    // A channel sink fails when its receiver is dropped, leaving the rest of the data.
+    let (mut tx, rx) = std::sync::mpsc::sync_channel(4);
+    assert_eq!(accumulator.feed_sink(b"\x02x\x00", &mut tx), Ok(0));
+    assert_eq!(rx.recv().unwrap(), b"x");
+    drop(rx);
+    assert!(accumulator
+        .feed_sink(b"\x02y\x00\x02z\x00", &mut tx)
+        .is_err());
+    assert_eq!(accumulator.feed(b"\x02z\x00"), 3);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"z");
+}
+
+#[test]
+fn test_cobs_slice_frame_accumulator() {
+    let mut buf = [0xAA_u8; 6];
+    let mut accumulator = cobs::SliceFrameAccumulator::new(&mut buf[..4]);
+
+    // Frame too large for the buffer. Following frames are not affected.
+    let data = b"\x06ABCDE\x00\x03AB\x00";
+    assert_eq!(accumulator.feed(data), 7);
+    assert_eq!(accumulator.feed(&data[7..]), 0);
+    assert_eq!(
+        accumulator.poll_frame().unwrap(),
+        Err(::cobs2::Error::OutputBufferTooSmall)
+    );
+    assert_eq!(accumulator.feed(&data[7..]), 4);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"AB");
+
+    // Empty frames, and a partial frame.
+    accumulator.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    assert_eq!(accumulator.feed(b"\x00\x05ABC"), 1);
+    assert_eq!(accumulator.poll_frame().unwrap().unwrap(), b"");
+    assert_eq!(accumulator.feed(b"\x05ABC"), 4);
+    assert!(accumulator.flush_partial());
+    assert_eq!(
+        accumulator.poll_frame().unwrap(),
+        Err(::cobs2::Error::TruncatedEncodedData)
+    );
+
+    assert_eq!(accumulator.feed(b"\x02X"), 2);
+    accumulator.clear();
+    assert!(accumulator.poll_frame().is_none());
+
+    // Only the given part of the buffer is used.
+    assert_eq!(accumulator.into_inner().len(), 4);
+    assert_eq!(buf[4..], [0xAA, 0xAA]);
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decoder_predefined() {
+    let mut encoded = vec![0_u8];
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        encoded.extend_from_slice(mapping.encoded);
+        encoded.push(0);
+    }
+
+    // Push the data in chunks of various sizes, so frames are split across chunks.
+    for chunk_size in [1, 2, 7, 300, encoded.len()] {
+        let mut decoder = cobs::Decoder::new();
+        let mut frames = Vec::new();
+        for chunk in encoded.chunks(chunk_size) {
+            decoder.push(chunk, |frame| frames.push(frame.unwrap().to_vec()));
+        }
+        assert_eq!(frames.len(), PREDEFINED_ENCODINGS.len());
+        for (frame, mapping) in frames.iter().zip(PREDEFINED_ENCODINGS.iter()) {
+            assert_eq!(frame, mapping.rawdata, "{}", mapping.description);
+        }
+        assert!(!decoder.in_progress());
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_decoder() {
+    // A long frame, received in fragments.
+    let data: Vec<u8> = (0..5000_u32).map(|x| (x % 7) as u8).collect();
+    let mut encoded = cobs::encode_vector(&data).unwrap();
+    encoded.push(0);
+    let mut decoder = cobs::Decoder::new();
+    for chunk in encoded.chunks(100) {
+        let mut frames = decoder.frames(chunk);
+        if let Some(frame) = frames.next_frame() {
+            assert_eq!(frame.unwrap(), &data[..]);
+            assert!(frames.remaining().is_empty());
+        } else {
+            assert!(decoder.in_progress());
+        }
+    }
+    assert!(!decoder.in_progress());
+
+    // Frames longer than the maximum length are discarded. Following frames are not affected.
+    let mut decoder = cobs::Decoder::with_max_len(4);
+    let frames: Vec<_> = decoder
+        .frames(b"\x05ABCD\x00\x06ABCDE\x00\x03xy\x00")
+        .collect();
+    assert_eq!(
+        frames,
+        [
+            Ok(b"ABCD".to_vec()),
+            Err(::cobs2::Error::FrameTooLong),
+            Ok(b"xy".to_vec())
+        ]
+    );
+
+    // Empty frames, a partial frame, and clearing.
+    let mut decoder = cobs::Decoder::default();
+    decoder.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    let mut frames = Vec::new();
+    decoder.push(b"\x00\x03AB\x00\x03x", |frame| {
+        frames.push(frame.map(<[u8]>::to_vec))
+    });
+    assert_eq!(frames, [Ok(Vec::new()), Ok(b"AB".to_vec())]);
+    assert!(decoder.in_progress());
+    assert_eq!(
+        decoder.flush_partial(),
+        Some(Err(::cobs2::Error::TruncatedEncodedData))
+    );
+    assert!(decoder.flush_partial().is_none());
+    assert_eq!(decoder.frames(b"\x03x").count(), 0);
+    decoder.clear();
+    assert!(!decoder.in_progress());
+    assert_eq!(
+        decoder.frames(b"\x02z\x00").collect::<Vec<_>>(),
+        [Ok(b"z".to_vec())]
+    );
+}
+
+/// Encode a frame with an [`cobs::Encoder`], into output buffers of the given size.
+fn cobs_encoder_encode(encoder: &mut cobs::Encoder, data: &[u8], out_len: usize) -> Vec<u8> {
+    let mut out_buf = vec![0_u8; out_len];
+    let mut encoded = Vec::new();
+    let mut data_rest = data;
+    loop {
+        let progress = encoder.encode(data_rest, &mut out_buf);
+        data_rest = &data_rest[progress.consumed..];
+        encoded.extend_from_slice(&out_buf[..progress.produced]);
+        if progress.done {
+            break;
+        }
+        assert_eq!(progress.produced, out_len);
+    }
+    loop {
+        let progress = encoder.finish(&mut out_buf);
+        assert_eq!(progress.consumed, 0);
+        encoded.extend_from_slice(&out_buf[..progress.produced]);
+        if progress.done {
+            break;
+        }
+        assert!(encoder.is_pending());
+    }
+    assert!(!encoder.is_pending());
+    encoded
+}
+
+#[test]
+fn test_cobs_encoder_predefined() {
+    for out_len in [1, 2, 7, 254, 255, 300] {
+        let mut encoder = cobs::Encoder::new();
+        for mapping in PREDEFINED_ENCODINGS.iter() {
+            let encoded = cobs_encoder_encode(&mut encoder, mapping.rawdata, out_len);
+            let mut expected = mapping.encoded.to_vec();
+            expected.push(0);
+            assert_eq!(encoded, expected, "{} {}", mapping.description, out_len);
+        }
+    }
+}
+
+#[test]
+fn test_cobs_encoder() {
+    let mut encoder = cobs::Encoder::default();
+    for data_len in [0, 1, 253, 254, 255, 508, 509, 1000] {
+        for zero_step in [0, 1, 3, 254, 255] {
+            let data: Vec<u8> = (0..data_len)
+                .map(|i| {
+                    if zero_step != 0 && i % zero_step == 0 {
+                        0
+                    } else {
+                        (i % 255 + 1) as u8
+                    }
+                })
+                .collect();
+            let mut expected = vec![0_u8; cobs::encode_max_output_size(data_len) + 1];
+            let expected_len = cobs::encode_array(&mut expected, &data).unwrap().len();
+            expected[expected_len] = 0;
+            expected.truncate(expected_len + 1);
+            for out_len in [1, 3, 100, 256] {
+                let encoded = cobs_encoder_encode(&mut encoder, &data, out_len);
+                assert_eq!(encoded, expected, "{} {} {}", data_len, zero_step, out_len);
+            }
+        }
+    }
+
+    // No output space: input is consumed until a block is complete.
+    let mut encoder = cobs::Encoder::new();
+    let progress = encoder.encode(b"AB\0CD", &mut []);
+    assert_eq!(
+        progress,
+        cobs::EncoderProgress {
+            consumed: 3,
+            produced: 0,
+            done: false
+        }
+    );
+    assert!(encoder.is_pending());
+
+    // Input isn't consumed while finishing.
+    let mut out_buf = [0_u8; 2];
+    let progress = encoder.finish(&mut out_buf);
+    assert_eq!((progress.produced, progress.done), (2, false));
+    assert_eq!(out_buf, *b"\x03A");
+    let progress = encoder.encode(b"xy", &mut out_buf);
+    assert_eq!(progress.consumed, 0);
+    assert_eq!(&out_buf[..progress.produced], b"B");
+
+    // Clearing discards the frame.
+    encoder.clear();
+    assert!(!encoder.is_pending());
+    assert_eq!(cobs_encoder_encode(&mut encoder, b"z", 8), b"\x02z\x00");
+}
+
+#[test]
+fn test_cobs_encode_output_size_exact() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let encoded_len = cobs::encode_output_size(mapping.rawdata);
+        assert_eq!(
+            encoded_len,
+            mapping.encoded.len(),
+            "{}",
+            mapping.description
+        );
+        let data_encoded = cobs::encode_vector_exact(mapping.rawdata).unwrap();
+        assert_eq!(data_encoded, mapping.encoded, "{}", mapping.description);
+        assert_eq!(
+            data_encoded.capacity(),
+            mapping.encoded.len(),
+            "{}",
+            mapping.description
+        );
+    }
+
+    let mut state = 0x8765_4321_u32;
+    for _ in 0..200 {
+        let in_data = common::random_data(&mut state, 1000, 64);
+        let data_encoded = cobs::encode_vector(&in_data).unwrap();
+        assert_eq!(cobs::encode_output_size(&in_data), data_encoded.len());
+        assert_eq!(cobs::encode_vector_exact(&in_data).unwrap(), data_encoded);
+    }
+}
+
+#[test]
+fn test_cobs_decode_output_size_exact() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let decoded_len = cobs::decode_output_size(mapping.encoded);
+        assert_eq!(
+            decoded_len,
+            mapping.rawdata.len(),
+            "{}",
+            mapping.description
+        );
+        let data_decoded = cobs::decode_vector(mapping.encoded).unwrap();
+        assert_eq!(data_decoded, mapping.rawdata, "{}", mapping.description);
+        assert_eq!(
+            data_decoded.capacity(),
+            mapping.rawdata.len(),
+            "{}",
+            mapping.description
+        );
+    }
+
+    let mut state = 0x1357_9BDF_u32;
+    for _ in 0..200 {
+        let in_data = common::random_data(&mut state, 1000, 64);
+        let data_encoded = cobs::encode_vector(&in_data).unwrap();
+        assert_eq!(cobs::decode_output_size(&data_encoded), in_data.len());
+        let data_decoded = cobs::decode_vector(&data_encoded).unwrap();
+        assert_eq!(data_decoded, in_data);
+        assert_eq!(data_decoded.capacity(), in_data.len());
+
+        // Invalid data gives the same error as decoding to an array.
+        let mut data_corrupt = data_encoded.clone();
+        if !data_corrupt.is_empty() {
+            let corrupt_i = common::xorshift32(&mut state) as usize % data_corrupt.len();
+            data_corrupt[corrupt_i] = 0;
+        }
+        let mut decode_buf = vec![0_u8; cobs::decode_max_output_size(data_corrupt.len())];
+        let array_result =
+            cobs::decode_array_detail(&mut decode_buf, &data_corrupt).map(|x| x.to_vec());
+        assert_eq!(cobs::decode_vector_detail(&data_corrupt), array_result);
+    }
+}
+
+#[test]
+fn test_cobs_short_array() {
+    let mut encode_buf = [0xCC_u8; 254];
+    let mut decode_buf = [0xCC_u8; 254];
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let encode_result = cobs::encode_short_array(&mut encode_buf, mapping.rawdata);
+        let decode_result = cobs::decode_short_array(&mut decode_buf, mapping.encoded);
+        if mapping.rawdata.len() <= 253 {
+            assert_eq!(
+                encode_result.unwrap(),
+                mapping.encoded,
+                "{}",
+                mapping.description
+            );
+            assert_eq!(
+                decode_result.unwrap(),
+                mapping.rawdata,
+                "{}",
+                mapping.description
+            );
+        } else {
+            assert_eq!(
+                encode_result,
+                Err(::cobs2::Error::ShortFrameTooLong),
+                "{}",
+                mapping.description
+            );
+        }
+    }
+
+    // Same output as the general functions.
+    let mut state = 0x2468_ACE0_u32;
+    for _ in 0..1000 {
+        let in_data = common::random_data(&mut state, 254, 16);
+        let data_encoded = cobs::encode_vector(&in_data).unwrap();
+        assert_eq!(
+            cobs::encode_short_array(&mut encode_buf, &in_data).unwrap(),
+            &data_encoded[..]
+        );
+        assert_eq!(
+            cobs::decode_short_array(&mut decode_buf, &data_encoded).unwrap(),
+            &in_data[..]
+        );
+
+        // Output buffer too small.
+        let short_len = data_encoded.len() - 1;
+        let result = cobs::encode_short_array(&mut encode_buf[..short_len], &in_data);
+        assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+        if !in_data.is_empty() {
+            let result =
+                cobs::decode_short_array(&mut decode_buf[..in_data.len() - 1], &data_encoded);
+            assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+        }
+    }
+
+    // Too long.
+    let mut long_buf = [0_u8; 300];
+    let result = cobs::encode_short_array(&mut long_buf, &[0x41_u8; 254]);
+    assert_eq!(result, Err(::cobs2::Error::ShortFrameTooLong));
+    let result = cobs::decode_short_array(&mut long_buf, &[0x41_u8; 255]);
+    assert_eq!(result, Err(::cobs2::Error::ShortFrameTooLong));
+
+    // Bad data.
+    assert_eq!(
+        cobs::decode_short_array(&mut decode_buf, b"\x03A\x00"),
+        Err(::cobs2::Error::ZeroInEncodedData)
+    );
+    assert_eq!(
+        cobs::decode_short_array(&mut decode_buf, b"\x00"),
+        Err(::cobs2::Error::ZeroInEncodedData)
+    );
+}
+
+#[test]
+fn test_cobs_encode_clone_iter() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let data_encoded: Vec<u8> =
+            cobs::encode_clone_iter(mapping.rawdata.iter().copied()).collect();
+        assert_eq!(data_encoded, mapping.encoded, "{}", mapping.description);
+    }
+
+    let mut state = 0x0F1E_2D3C_u32;
+    for zero_mod in [2, 64, 1024] {
+        for _ in 0..100 {
+            let in_data = common::random_data(&mut state, 1000, zero_mod);
+            let data_encoded: Vec<u8> = cobs::encode_clone_iter(in_data.iter().copied()).collect();
+            assert_eq!(data_encoded, cobs::encode_vector(&in_data).unwrap());
+        }
+    }
+
+    // The encoder's state is not much more than its input iterator, except with feature
+    // `self-check`, which records each frame.
+    let data = [0x41_u8; 10];
+    let in_iter = data.iter().copied();
+    let in_iter_size = std::mem::size_of_val(&in_iter);
+    let encode_iter = cobs::encode_clone_iter(in_iter);
+    if !cfg!(feature = "self-check") {
+        assert!(std::mem::size_of_val(&encode_iter) <= in_iter_size + 8);
+    }
+}
+
+#[test]
+fn test_cobs_frame_writer() {
+    let mut out = Vec::new();
+    let mut writer = cobs::FrameWriter::new(|encoded: &[u8]| {
+        // Blocks are emitted as soon as they are complete.
+        assert!(!encoded.is_empty() && encoded.len() <= 255);
+        out.extend_from_slice(encoded)
+    });
+    let mut expected = Vec::new();
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        // Data given in several parts.
+        for part in mapping.rawdata.chunks(7) {
+            writer.write_bytes(part);
+        }
+        writer.finish();
+        expected.extend_from_slice(mapping.encoded);
+        expected.push(0);
+    }
+
+    let mut state = 0x5A5A_A5A5_u32;
+    for zero_mod in [4, 1024] {
+        for _ in 0..50 {
+            let in_data = common::random_data(&mut state, 1000, zero_mod);
+            writer.write_bytes(&in_data);
+            writer.finish();
+            expected.extend(cobs::encode_vector(&in_data).unwrap());
+            expected.push(0);
+        }
+    }
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn test_cobs_frame_writer_fmt() {
+    use std::fmt::Write;
+
+    let mut out = Vec::new();
+    let mut writer = cobs::FrameWriter::new(|encoded: &[u8]| out.extend_from_slice(encoded));
+    for i in 0..3 {
+        write!(writer, "count={} \0 {:>300}", i, "x").unwrap();
+        writer.finish();
+    }
+
+    let frames: Vec<Vec<u8>> = out
+        .split(|x| *x == 0)
+        .filter(|x| !x.is_empty())
+        .map(|x| cobs::decode_vector(x).unwrap())
+        .collect();
+    let expected: Vec<Vec<u8>> = (0..3)
+        .map(|i| format!("count={} \0 {:>300}", i, "x").into_bytes())
+        .collect();
+    assert_eq!(frames, expected);
+}
+
+#[test]
+fn test_cobs_frame_writer_static() {
+    use std::sync::Mutex;
+
+    type Emit = fn(&[u8]);
+    static OUT: Mutex<Vec<u8>> = Mutex::new(Vec::new());
+    static WRITER: Mutex<cobs::FrameWriter<Emit>> = Mutex::new(cobs::FrameWriter::new(|encoded| {
+        OUT.lock().unwrap().extend_from_slice(encoded)
+    }));
+
+    let mut writer = WRITER.lock().unwrap();
+    writer.write_bytes(b"ABC\0xyz");
+    writer.finish();
+    assert_eq!(
+        *OUT.lock().unwrap(),
+        [cobs::encode_vector(b"ABC\0xyz").unwrap(), vec![0]].concat()
+    );
+}
+
+#[test]
+fn test_cobs_encode_fmt() {
+    let mut out_buf = vec![0xCC_u8; 2000];
+    for i in [0_usize, 1, 9, 100, 253, 254, 255, 600] {
+        let args_text = format!("{}{:>w$}\0{}", i, "z", i * 7, w = i);
+        let encoded = cobs::encode_vector(args_text.as_bytes()).unwrap();
+        let result = cobs::encode_fmt(
+            &mut out_buf,
+            format_args!("{}{:>w$}\0{}", i, "z", i * 7, w = i),
+        );
+        assert_eq!(result.unwrap(), &encoded[..]);
+
+        // Every output buffer size.
+        for out_len in 0..encoded.len() + 2 {
+            let result = cobs::encode_fmt(
+                &mut out_buf[..out_len],
+                format_args!("{}{:>w$}\0{}", i, "z", i * 7, w = i),
+            );
+            if out_len >= encoded.len() {
+                assert_eq!(result.unwrap(), &encoded[..]);
+            } else {
+                assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+            }
+        }
+    }
+
+    let result = cobs::encode_fmt(&mut out_buf, format_args!(""));
+    assert_eq!(result.unwrap(), b"\x01");
+}
+
+#[test]
+fn test_error_code() {
+    use ::cobs2::Error;
+
+    assert_eq!(Error::OutputBufferTooSmall.code(), 1);
+    assert_eq!(Error::TruncatedEncodedData.code(), 3);
+    assert_eq!(Error::InvalidUtf8.code(), 12);
+    assert_eq!(Error::UnknownFormat.code(), 13);
+    assert_eq!(Error::FragmentLost.code(), 14);
+    assert_eq!(Error::FrameTimeout.code(), 15);
+    let mut decode_buf = [0_u8; 10];
+    let err = cobs::decode_array(&mut decode_buf, b"\x05AB").unwrap_err();
+    assert_eq!(err.code(), 3);
+}
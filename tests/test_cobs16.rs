@@ -0,0 +1,144 @@
+use ::cobs2::cobs16;
+
+struct DataEncodedMapping<'a> {
+    pub description: &'a str,
+    pub rawdata: &'a [u16],
+    pub encoded: &'a [u16],
+}
+
+const PREDEFINED_ENCODINGS: [DataEncodedMapping; 8] = [
+    DataEncodedMapping {
+        description: "empty",
+        rawdata: &[],
+        encoded: &[0x0001],
+    },
+    DataEncodedMapping {
+        description: "1 non-zero",
+        rawdata: &[0x1234],
+        encoded: &[0x0002, 0x1234],
+    },
+    DataEncodedMapping {
+        description: "low and high bytes",
+        rawdata: &[0x0100, 0x0001],
+        encoded: &[0x0003, 0x0100, 0x0001],
+    },
+    DataEncodedMapping {
+        description: "1 zero in middle",
+        rawdata: &[0x1111, 0x0000, 0x2222, 0x3333],
+        encoded: &[0x0002, 0x1111, 0x0003, 0x2222, 0x3333],
+    },
+    DataEncodedMapping {
+        description: "starting with zero",
+        rawdata: &[0x0000, 0x1111],
+        encoded: &[0x0001, 0x0002, 0x1111],
+    },
+    DataEncodedMapping {
+        description: "ending with zero",
+        rawdata: &[0x1111, 0x0000],
+        encoded: &[0x0002, 0x1111, 0x0001],
+    },
+    DataEncodedMapping {
+        description: "1 zero",
+        rawdata: &[0x0000],
+        encoded: &[0x0001, 0x0001],
+    },
+    DataEncodedMapping {
+        description: "2 zeros",
+        rawdata: &[0x0000, 0x0000],
+        encoded: &[0x0001, 0x0001, 0x0001],
+    },
+];
+
+#[test]
+fn test_cobs16_encode_output_size() {
+    assert_eq!(1, cobs16::encode_min_output_size(0));
+    assert_eq!(2, cobs16::encode_min_output_size(1));
+    assert_eq!(usize::MAX, cobs16::encode_min_output_size(usize::MAX));
+
+    assert_eq!(1, cobs16::encode_max_output_size(0));
+    assert_eq!(2, cobs16::encode_max_output_size(1));
+    assert_eq!(0xFFFF, cobs16::encode_max_output_size(0xFFFE));
+    assert_eq!(0x10001, cobs16::encode_max_output_size(0xFFFF));
+    assert_eq!(usize::MAX, cobs16::encode_max_output_size(usize::MAX));
+
+    assert_eq!(0, cobs16::decode_min_output_size(0));
+    assert_eq!(0, cobs16::decode_min_output_size(1));
+    assert_eq!(0xFFFE, cobs16::decode_min_output_size(0xFFFF));
+    assert_eq!(0xFFFE, cobs16::decode_min_output_size(0x10000));
+
+    assert_eq!(0, cobs16::decode_max_output_size(0));
+    assert_eq!(0, cobs16::decode_max_output_size(1));
+    assert_eq!(0xFFFE, cobs16::decode_max_output_size(0xFFFF));
+}
+
+#[test]
+fn test_cobs16_array_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let mut encode_out_vec = vec![0_u16; cobs16::encode_max_output_size(mapping.rawdata.len())];
+        let enc_result = cobs16::encode_array(&mut encode_out_vec[..], mapping.rawdata);
+        assert_eq!(
+            enc_result.unwrap(),
+            mapping.encoded,
+            "{}",
+            mapping.description
+        );
+
+        let mut decode_out_vec = vec![0_u16; cobs16::decode_max_output_size(mapping.encoded.len())];
+        let dec_result = cobs16::decode_array(&mut decode_out_vec[..], mapping.encoded);
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs16_vector_predefined() {
+    for mapping in PREDEFINED_ENCODINGS.iter() {
+        let enc_result = cobs16::encode_vector(mapping.rawdata);
+        assert_eq!(
+            enc_result.unwrap(),
+            mapping.encoded,
+            "{}",
+            mapping.description
+        );
+
+        let dec_result = cobs16::decode_vector(mapping.encoded);
+        assert_eq!(
+            dec_result.unwrap(),
+            mapping.rawdata,
+            "{}",
+            mapping.description
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs16_long_run() {
+    // Runs of 65534 non-zero words are not followed by an implicit zero word.
+    for len in [0xFFFD, 0xFFFE, 0xFFFF, 2 * 0xFFFE + 1] {
+        let rawdata = vec![0xA5A5_u16; len];
+        let encoded = cobs16::encode_vector(&rawdata).unwrap();
+        assert_eq!(encoded.len(), cobs16::encode_max_output_size(len));
+        assert!(!encoded.contains(&0));
+        assert_eq!(encoded[0], core::cmp::min(len + 1, 0xFFFF) as u16);
+        assert_eq!(cobs16::decode_vector(&encoded).unwrap(), rawdata);
+    }
+}
+
+#[test]
+fn test_cobs16_bad() {
+    let mut out_buf = [0xCCCC_u16; 8];
+    let result = cobs16::encode_array(&mut out_buf[..2], &[0x1111, 0x2222]);
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+    let result = cobs16::decode_array(&mut out_buf[..1], &[0x0003, 0x1111, 0x2222]);
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+    let result = cobs16::decode_array(&mut out_buf, &[0x0003, 0x1111, 0x0000]);
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+    let result = cobs16::decode_array(&mut out_buf, &[0x0004, 0x1111, 0x2222]);
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+}
@@ -0,0 +1,107 @@
+use ::cobs2::{cobs, cobsx};
+
+#[test]
+fn test_cobsx_short_frames_match_cobs() {
+    // Runs of up to 253 bytes are encoded the same as plain COBS.
+    let samples: [&[u8]; 8] = [
+        b"",
+        b"1",
+        b"12345",
+        b"12345\x006789",
+        b"\x0012345\x006789",
+        b"12345\x006789\x00",
+        b"\x00",
+        b"\x00\x00\x00",
+    ];
+    for rawdata in samples {
+        let mut cobs_buf = [0_u8; 32];
+        let mut cobsx_buf = [0_u8; 32];
+        let expected = cobs::encode_array(&mut cobs_buf, rawdata).unwrap();
+        let encoded = cobsx::encode_array(&mut cobsx_buf, rawdata).unwrap();
+        assert_eq!(encoded, expected);
+
+        let mut decode_buf = [0_u8; 32];
+        let decoded = cobsx::decode_array(&mut decode_buf, encoded).unwrap();
+        assert_eq!(decoded, rawdata);
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobsx_long_runs() {
+    for len in [253, 254, 255, 1000, 65023, 65024, 65025, 2 * 65024, 200_000] {
+        let rawdata: Vec<u8> = (0..len).map(|i| (i % 255 + 1) as u8).collect();
+        let encoded = cobsx::encode_vector(&rawdata).unwrap();
+        assert!(!encoded.contains(&0), "{}", len);
+        assert!(
+            encoded.len() <= cobsx::encode_max_output_size(len),
+            "{}",
+            len
+        );
+        assert!(
+            encoded.len() >= cobsx::encode_min_output_size(len),
+            "{}",
+            len
+        );
+        assert_eq!(cobsx::decode_vector(&encoded).unwrap(), rawdata, "{}", len);
+        assert!(
+            cobsx::decode_min_output_size(encoded.len()) <= len,
+            "{}",
+            len
+        );
+
+        // Also with zero-bytes before and after each maximum length run.
+        let mut rawdata_zeros = vec![0_u8];
+        for chunk in rawdata.chunks(65024) {
+            rawdata_zeros.extend_from_slice(chunk);
+            rawdata_zeros.push(0);
+        }
+        let encoded = cobsx::encode_vector(&rawdata_zeros).unwrap();
+        assert!(!encoded.contains(&0), "{}", len);
+        assert!(
+            encoded.len() <= cobsx::encode_max_output_size(rawdata_zeros.len()),
+            "{}",
+            len
+        );
+        assert_eq!(
+            cobsx::decode_vector(&encoded).unwrap(),
+            rawdata_zeros,
+            "{}",
+            len
+        );
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobsx_worst_case_size() {
+    // Runs of 254 non-zero bytes separated by zero-bytes need an extended code for each run.
+    let mut rawdata = Vec::new();
+    for _ in 0..10 {
+        rawdata.extend_from_slice(&[0x41_u8; 254]);
+        rawdata.push(0);
+    }
+    let encoded = cobsx::encode_vector(&rawdata).unwrap();
+    assert_eq!(encoded.len(), rawdata.len() + 1 + 2 * 10);
+    assert!(encoded.len() <= cobsx::encode_max_output_size(rawdata.len()));
+    assert_eq!(cobsx::decode_vector(&encoded).unwrap(), rawdata);
+}
+
+#[test]
+fn test_cobsx_bad() {
+    let mut out_buf = [0xCC_u8; 16];
+    let result = cobsx::decode_array(&mut out_buf, b"\x03A\x00");
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+    let result = cobsx::decode_array(&mut out_buf, b"\xFF\x00\x02A");
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+    let result = cobsx::decode_array(&mut out_buf, b"\xFF\x01\x00A");
+    assert_eq!(result, Err(::cobs2::Error::ZeroInEncodedData));
+    let result = cobsx::decode_array(&mut out_buf, b"\xFF\x01");
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+    let result = cobsx::decode_array(&mut out_buf, b"\xFF\x01\x04AB");
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+    let result = cobsx::decode_array(&mut out_buf[..1], b"\x03AB");
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+    let result = cobsx::encode_array(&mut out_buf[..2], b"AB");
+    assert_eq!(result, Err(::cobs2::Error::OutputBufferTooSmall));
+}
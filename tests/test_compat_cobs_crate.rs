@@ -0,0 +1,107 @@
+use ::cobs2::compat::cobs_crate::{self, CobsDecoder, CobsEncoder};
+
+#[test]
+fn test_cobs_crate_max_encoding_length() {
+    assert_eq!(0, cobs_crate::max_encoding_length(0));
+    assert_eq!(2, cobs_crate::max_encoding_length(1));
+    assert_eq!(255, cobs_crate::max_encoding_length(254));
+    assert_eq!(257, cobs_crate::max_encoding_length(255));
+    assert_eq!(510, cobs_crate::max_encoding_length(508));
+}
+
+#[test]
+fn test_cobs_crate_encoder() {
+    let mut out_buf = [0_u8; 16];
+    let mut enc = CobsEncoder::new(&mut out_buf);
+    enc.push(b"12345").unwrap();
+    enc.push(b"\x006789").unwrap();
+    let out_len = enc.finalize().unwrap();
+    assert_eq!(&out_buf[..out_len], b"\x0612345\x056789");
+
+    // Empty message has no output.
+    let mut out_buf = [0_u8; 16];
+    let enc = CobsEncoder::new(&mut out_buf);
+    assert_eq!(enc.finalize(), Ok(0));
+
+    let mut out_buf = [0_u8; 16];
+    let mut enc = CobsEncoder::new(&mut out_buf);
+    enc.push(b"\x00").unwrap();
+    let out_len = enc.finalize().unwrap();
+    assert_eq!(&out_buf[..out_len], b"\x01\x01");
+
+    // Output buffer too small.
+    let mut out_buf = [0_u8; 5];
+    let mut enc = CobsEncoder::new(&mut out_buf);
+    assert_eq!(enc.push(b"12345"), Err(()));
+}
+
+#[test]
+fn test_cobs_crate_decoder_feed() {
+    let mut out_buf = [0_u8; 16];
+    let mut dec = CobsDecoder::new(&mut out_buf);
+    // Leading sentinels are ignored.
+    assert_eq!(dec.feed(0), Ok(None));
+    for x in b"\x0612345\x056789" {
+        assert_eq!(dec.feed(*x), Ok(None));
+    }
+    assert_eq!(dec.feed(0), Ok(Some(10)));
+    assert_eq!(&out_buf[..10], b"12345\x006789");
+}
+
+#[test]
+fn test_cobs_crate_decoder_push() {
+    let mut out_buf = [0_u8; 16];
+    let mut dec = CobsDecoder::new(&mut out_buf);
+    assert_eq!(dec.push(b"\x0612"), Ok(None));
+    assert_eq!(dec.push(b"345\x056789\x00\x02A\x00"), Ok(Some((10, 9))));
+    // Decoder is ready for the next message.
+    assert_eq!(dec.push(b"\x02A\x00"), Ok(Some((1, 3))));
+    assert_eq!(&out_buf[..1], b"A");
+
+    // Truncated message.
+    let mut out_buf = [0_u8; 16];
+    let mut dec = CobsDecoder::new(&mut out_buf);
+    assert_eq!(dec.push(b"\x05AAA\x00"), Err(3));
+
+    // Output buffer too small.
+    let mut out_buf = [0_u8; 2];
+    let mut dec = CobsDecoder::new(&mut out_buf);
+    assert_eq!(dec.push(b"\x04AAA\x00"), Err(2));
+}
+
+#[test]
+fn test_cobs_crate_encode_decode() {
+    let data = b"ABC\0ghij\0xyz";
+    let mut encode_buf = [0_u8; 20];
+    let encode_len = cobs_crate::encode(data, &mut encode_buf);
+    assert_eq!(&encode_buf[..encode_len], b"\x04ABC\x05ghij\x04xyz");
+    assert_eq!(cobs_crate::try_encode(data, &mut encode_buf[..12]), Err(()));
+
+    let mut decode_buf = [0_u8; 20];
+    // With or without the sentinel.
+    let decode_len = cobs_crate::decode(&encode_buf[..encode_len], &mut decode_buf).unwrap();
+    assert_eq!(&decode_buf[..decode_len], data);
+    let decode_len =
+        cobs_crate::decode(b"\x04ABC\x05ghij\x04xyz\x00\x02A", &mut decode_buf).unwrap();
+    assert_eq!(&decode_buf[..decode_len], data);
+
+    assert_eq!(cobs_crate::decode(b"", &mut decode_buf), Err(()));
+    assert_eq!(cobs_crate::decode(b"\x05AAA", &mut decode_buf), Err(()));
+}
+
+#[cfg(feature = "alloc")]
+#[test]
+fn test_cobs_crate_vec() {
+    let data = b"ABC\0ghij\0xyz";
+    let encoded = cobs_crate::encode_vec(data);
+    assert_eq!(encoded, b"\x04ABC\x05ghij\x04xyz");
+    assert_eq!(cobs_crate::decode_vec(&encoded).unwrap(), data);
+    assert_eq!(cobs_crate::decode_vec(b"\x05AAA"), Err(()));
+
+    // Redundant trailing length code, as produced by the cobs crate.
+    let data = [0x41_u8; 254];
+    let mut encoded = cobs_crate::encode_vec(&data);
+    assert_eq!(encoded.len(), 255);
+    encoded.push(1);
+    assert_eq!(cobs_crate::decode_vec(&encoded).unwrap(), data);
+}
@@ -0,0 +1,73 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::compat::python::{cobs, cobsr, DecodeError};
+use ::cobs2::Error;
+
+#[test]
+fn test_python_cobs() {
+    assert_eq!(cobs::encode(b""), b"\x01");
+    assert_eq!(cobs::encode(b"\x00"), b"\x01\x01");
+    assert_eq!(
+        cobs::encode(b"ABC\x00ghij\x00xyz"),
+        b"\x04ABC\x05ghij\x04xyz"
+    );
+    assert_eq!(cobs::encode(vec![b'x'; 254]).len(), 255);
+
+    assert_eq!(cobs::decode(b"").unwrap(), b"");
+    assert_eq!(cobs::decode(b"\x01").unwrap(), b"");
+    assert_eq!(
+        cobs::decode(b"\x04ABC\x05ghij\x04xyz").unwrap(),
+        b"ABC\x00ghij\x00xyz"
+    );
+    assert_eq!(
+        cobs::decode(cobs::encode(b"\x00\x01\x02")).unwrap(),
+        b"\x00\x01\x02"
+    );
+
+    assert_eq!(cobs::encoding_overhead(0), 1);
+    assert_eq!(cobs::encoding_overhead(254), 1);
+    assert_eq!(cobs::encoding_overhead(255), 2);
+    assert_eq!(cobs::max_encoded_length(255), 257);
+}
+
+#[test]
+fn test_python_cobs_decode_error() {
+    let error = cobs::decode(b"\x05AB").unwrap_err();
+    assert_eq!(error.error(), Error::TruncatedEncodedData);
+    assert_eq!(error.to_string(), "not enough input bytes for length code");
+
+    let error = cobs::decode(b"\x03A\x00").unwrap_err();
+    assert_eq!(error.error(), Error::ZeroInEncodedData);
+    assert_eq!(error.to_string(), "zero byte found in input");
+    assert_eq!(Error::from(error), Error::ZeroInEncodedData);
+
+    // The error converts for the `?` operator, in functions returning a boxed error.
+    fn decode_boxed(data: &[u8]) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        Ok(cobs::decode(data)?)
+    }
+    assert_eq!(
+        decode_boxed(b"\x00").unwrap_err().to_string(),
+        "zero byte found in input"
+    );
+}
+
+#[test]
+fn test_python_cobsr() {
+    assert_eq!(cobsr::encode(b""), b"\x01");
+    assert_eq!(cobsr::encode(b"\x01"), b"\x02\x01");
+    assert_eq!(cobsr::encode(b"\x05"), b"\x05");
+    assert_eq!(cobsr::encode(b"ABC\x00ghij\x00xyz"), b"\x04ABC\x05ghijzxy");
+
+    assert_eq!(cobsr::decode(b"").unwrap(), b"");
+    assert_eq!(cobsr::decode(b"\x05").unwrap(), b"\x05");
+    assert_eq!(
+        cobsr::decode(b"\x04ABC\x05ghijzxy").unwrap(),
+        b"ABC\x00ghij\x00xyz"
+    );
+
+    let error: DecodeError = cobsr::decode(b"\x03A\x00").unwrap_err();
+    assert_eq!(error.to_string(), "zero byte found in input");
+
+    assert_eq!(cobsr::max_encoded_length(0), 1);
+    assert_eq!(cobsr::max_encoded_length(254), 255);
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "deflate")]
+
+use ::cobs2::{cobs, cobsr};
+
+const TELEMETRY: &[u8] = b"{\"temp\":21.5,\"humidity\":40}\n\
+{\"temp\":21.5,\"humidity\":41}\n\
+{\"temp\":21.6,\"humidity\":41}\n\
+{\"temp\":21.6,\"humidity\":41}\n";
+
+#[test]
+fn test_compressed_round_trip() {
+    let samples: [&[u8]; 4] = [b"", b"\x00", b"ABC\0ghij\0xyz", TELEMETRY];
+    for data in samples {
+        let encoded = cobs::encode_compressed(data).unwrap();
+        assert!(!encoded.contains(&0));
+        assert_eq!(cobs::decode_compressed(&encoded, data.len()).unwrap(), data);
+
+        let encoded = cobsr::encode_compressed(data).unwrap();
+        assert!(!encoded.contains(&0));
+        assert_eq!(
+            cobsr::decode_compressed(&encoded, data.len()).unwrap(),
+            data
+        );
+    }
+
+    let encoded = cobs::encode_compressed(TELEMETRY).unwrap();
+    assert!(encoded.len() < TELEMETRY.len() / 2);
+}
+
+#[test]
+fn test_compressed_bad() {
+    // Exceeds the size limit.
+    let encoded = cobs::encode_compressed(TELEMETRY).unwrap();
+    let result = cobs::decode_compressed(&encoded, TELEMETRY.len() - 1);
+    assert_eq!(result, Err(::cobs2::Error::DecompressionFailed));
+
+    // Not compressed data.
+    let encoded = cobs::encode_vector(b"\xFF\xFF\xFF\xFF").unwrap();
+    let result = cobs::decode_compressed(&encoded, 1000);
+    assert_eq!(result, Err(::cobs2::Error::DecompressionFailed));
+
+    // Not valid encoded data.
+    let result = cobs::decode_compressed(b"\x05AB", 1000);
+    assert_eq!(result, Err(::cobs2::Error::TruncatedEncodedData));
+}
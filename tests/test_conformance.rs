@@ -0,0 +1,120 @@
+#![cfg(feature = "std")]
+
+//! Conformance with the cobs-c reference implementation, checked with test vectors generated by
+//! `tests/conformance/gen_vectors.c`.
+//!
+//! Set the environment variable `COBS_C_VECTORS` to the path of a vector file to check it. Without
+//! it, only the sample vectors below are checked.
+
+use ::cobs2::{cobs, cobsr, Result};
+
+use std::env;
+use std::fs;
+
+/// Sample lines of a vector file.
+const SAMPLE_VECTORS: &str = "\
+cobs - 01
+cobsr - 01
+cobs 00 0101
+cobsr 00 0101
+cobs 3132333435 063132333435
+cobsr 3132333435 3531323334
+cobs 31323334350036373839 0631323334350536373839
+cobsr 31323334350036373839 06313233343539363738
+cobs 00000061ff 0101010361ff
+cobsr 00000061ff 010101ff61
+";
+
+fn parse_hex(text: &str) -> Vec<u8> {
+    if text == "-" {
+        return Vec::new();
+    }
+    assert!(text.len().is_multiple_of(2), "odd length hex: {}", text);
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).expect("invalid hex"))
+        .collect()
+}
+
+/// Check the codec's functions against a vector, returning a description of any mismatch.
+fn check_vector(
+    data: &[u8],
+    encoded: &[u8],
+    encode_vector: fn(&[u8]) -> Result<Vec<u8>>,
+    decode_vector: fn(&[u8]) -> Result<Vec<u8>>,
+    encode_max_output_size: fn(usize) -> usize,
+) -> Option<&'static str> {
+    if encode_vector(data).as_deref() != Ok(encoded) {
+        Some("encoding differs")
+    } else if decode_vector(encoded).as_deref() != Ok(data) {
+        Some("decoding differs")
+    } else if encoded.len() > encode_max_output_size(data.len()) {
+        Some("encoding is longer than the maximum size")
+    } else {
+        None
+    }
+}
+
+/// Check each vector of a vector file, and return the number checked.
+fn check_vectors(vectors: &str) -> usize {
+    let mut count = 0;
+    for (line_num, line) in vectors
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+    {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        assert_eq!(fields.len(), 3, "line {}: expected 3 fields", line_num + 1);
+        let data = parse_hex(fields[1]);
+        let encoded = parse_hex(fields[2]);
+        let mismatch = match fields[0] {
+            "cobs" => check_vector(
+                &data,
+                &encoded,
+                cobs::encode_vector,
+                cobs::decode_vector,
+                cobs::encode_max_output_size,
+            ),
+            "cobsr" => check_vector(
+                &data,
+                &encoded,
+                cobsr::encode_vector,
+                cobsr::decode_vector,
+                cobsr::encode_max_output_size,
+            ),
+            codec => panic!("line {}: unknown codec {}", line_num + 1, codec),
+        };
+        if let Some(mismatch) = mismatch {
+            panic!(
+                "line {}: {} {}: {}",
+                line_num + 1,
+                fields[0],
+                mismatch,
+                fields[1]
+            );
+        }
+        count += 1;
+    }
+    count
+}
+
+#[test]
+fn test_conformance_sample_vectors() {
+    assert_eq!(check_vectors(SAMPLE_VECTORS), 10);
+}
+
+#[test]
+#[should_panic(expected = "line 2: cobsr encoding differs: 3132333435")]
+fn test_conformance_mismatch() {
+    check_vectors("cobs 3132333435 063132333435\ncobsr 3132333435 063132333435\n");
+}
+
+#[test]
+fn test_conformance_cobs_c_vectors() {
+    let path = match env::var_os("COBS_C_VECTORS") {
+        Some(path) => path,
+        None => return,
+    };
+    let vectors = fs::read_to_string(&path).expect("failed to read COBS_C_VECTORS file");
+    assert!(check_vectors(&vectors) > 0, "no vectors in {:?}", path);
+}
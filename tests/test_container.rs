@@ -0,0 +1,109 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::container::{ContainerReader, ContainerWriter};
+use ::cobs2::profile::Profile;
+
+use std::io;
+
+#[test]
+fn test_container_format() {
+    let mut writer = ContainerWriter::new(Vec::new(), Profile::Cobsr).unwrap();
+    writer.write_frame(b"ABC").unwrap();
+    writer.write_frame(b"\0x").unwrap();
+    let container = writer.into_inner();
+    assert_eq!(container, b"COBS2CTR\x01\x00\x02CAB\x00\x01x\x00");
+
+    let reader = ContainerReader::new(&container[..]).unwrap();
+    assert_eq!(reader.profile(), Profile::Cobsr);
+    let frames: Vec<Vec<u8>> = reader.collect::<io::Result<_>>().unwrap();
+    assert_eq!(frames, [&b"ABC"[..], b"\0x"]);
+}
+
+#[test]
+fn test_container_profiles() {
+    let profiles = [
+        Profile::Cobs,
+        Profile::Cobsr,
+        Profile::CobsCrc32,
+        Profile::CobsrCrc16Ccitt,
+    ];
+    let frames = [&b"ABC\0ghij\0xyz"[..], b"", b"\0", &[0x55; 251]];
+    for &profile in profiles.iter() {
+        let mut writer = ContainerWriter::new(Vec::new(), profile).unwrap();
+        for frame in frames.iter() {
+            writer.write_frame(frame).unwrap();
+        }
+        let container = writer.into_inner();
+        let reader = ContainerReader::new(&container[..]).unwrap();
+        assert_eq!(reader.profile(), profile);
+        let read: Vec<Vec<u8>> = reader.collect::<io::Result<_>>().unwrap();
+        assert_eq!(read, frames, "{:?}", profile);
+    }
+
+    let mut writer = ContainerWriter::new(Vec::new(), Profile::CobsrCrc16Ccitt).unwrap();
+    let error = writer.write_frame(&[0x55; 252]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_container_damaged() {
+    let mut writer = ContainerWriter::new(Vec::new(), Profile::CobsCrc32).unwrap();
+    writer.write_frame(b"first").unwrap();
+    writer.write_frame(b"second").unwrap();
+    writer.write_frame(b"third").unwrap();
+    let mut container = writer.into_inner();
+    // Damage the second frame, and tear the third.
+    container[25] ^= 0x20;
+    container.truncate(container.len() - 3);
+
+    let mut reader = ContainerReader::new(&container[..]).unwrap();
+    assert_eq!(reader.next().unwrap().unwrap(), b"first");
+    let error = reader.next().unwrap().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        error.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::ChecksumMismatch)
+    );
+    assert_eq!(reader.torn_len(), 0);
+    assert!(reader.next().is_none());
+    assert_eq!(reader.torn_len(), 8);
+}
+
+#[test]
+fn test_container_header() {
+    let error = |data: &[u8]| ContainerReader::new(data).err().unwrap();
+
+    assert_eq!(
+        error(b"COBS2CAP\x01\x00\x01").kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(
+        error(b"COBS2CTR\x02\x00\x01").kind(),
+        io::ErrorKind::InvalidData
+    );
+    let unknown = error(b"COBS2CTR\x01\x00\x03");
+    assert_eq!(unknown.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        unknown.to_string(),
+        ::cobs2::Error::UnknownFormat.to_string()
+    );
+    assert_eq!(error(b"COBS2CTR").kind(), io::ErrorKind::UnexpectedEof);
+
+    let reader = ContainerReader::new(&b"COBS2CTR\x01\x00\x21"[..]).unwrap();
+    assert_eq!(reader.profile(), Profile::CobsCrc32);
+    assert_eq!(reader.count(), 0);
+}
+
+#[test]
+fn test_container_file() {
+    let path = std::env::temp_dir().join("cobs2-test-container.bin");
+    let mut writer = ContainerWriter::create(&path, Profile::CobsCrc32).unwrap();
+    writer.write_frame(b"ABC").unwrap();
+    writer.flush().unwrap();
+    drop(writer);
+
+    let reader = ContainerReader::open(&path).unwrap();
+    let frames: Vec<Vec<u8>> = reader.collect::<io::Result<_>>().unwrap();
+    assert_eq!(frames, [b"ABC"]);
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,141 @@
+#![cfg(feature = "cycles")]
+
+use ::cobs2::cycles::{measure, measure_operation, CycleCounter, Measurement, Operation};
+use ::cobs2::{cobs, cobsr, Error};
+
+/// A simulated counter, which advances by a fixed step on each read.
+struct StepCounter {
+    count: u32,
+    step: u32,
+}
+
+impl CycleCounter for StepCounter {
+    fn cycle_count(&mut self) -> u32 {
+        self.count = self.count.wrapping_add(self.step);
+        self.count
+    }
+}
+
+#[test]
+fn test_cycles_measure() {
+    let mut counter = StepCounter { count: 0, step: 7 };
+    let (result, cycles) = measure(&mut counter, || 42);
+    assert_eq!((result, cycles), (42, 7));
+
+    // The count wraps around.
+    let mut counter = StepCounter {
+        count: u32::MAX - 2,
+        step: 5,
+    };
+    assert_eq!(measure(&mut counter, || ()).1, 5);
+}
+
+#[test]
+fn test_cycles_measure_operation() {
+    let data = b"Hello\x00world";
+    let mut encode_buf = [0_u8; 16];
+    let mut decode_buf = [0_u8; 16];
+    for &operation in Operation::ALL.iter() {
+        let mut counter = StepCounter { count: 0, step: 10 };
+        let measurement = measure_operation(
+            &mut counter,
+            operation,
+            data,
+            &mut encode_buf,
+            &mut decode_buf,
+        )
+        .unwrap();
+        assert_eq!(
+            measurement,
+            Measurement {
+                operation,
+                data_len: data.len() as u32,
+                cycles: 10,
+            }
+        );
+        assert_eq!(Operation::from_id(operation.id()), Some(operation));
+    }
+
+    // The outputs are written to the buffers.
+    let mut counter = || 0_u32;
+    measure_operation(
+        &mut counter,
+        Operation::CobsrDecodeArray,
+        data,
+        &mut encode_buf,
+        &mut decode_buf,
+    )
+    .unwrap();
+    let encoded = cobsr::encode_vector(data).unwrap();
+    assert_eq!(&encode_buf[..encoded.len()], &encoded[..]);
+    assert_eq!(&decode_buf[..data.len()], &data[..]);
+
+    let mut small_buf = [0_u8; 4];
+    let result = measure_operation(
+        &mut counter,
+        Operation::CobsEncodeArray,
+        data,
+        &mut small_buf,
+        &mut decode_buf,
+    );
+    assert_eq!(result, Err(Error::OutputBufferTooSmall));
+}
+
+#[test]
+fn test_cycles_frame() {
+    let measurement = Measurement {
+        operation: Operation::CobsrEncodeArray,
+        data_len: 256,
+        cycles: 0x0001_0000,
+    };
+    let mut frame_buf = [0_u8; Measurement::FRAME_MAX_LEN];
+    let frame = measurement.encode_frame(&mut frame_buf).unwrap();
+    assert_eq!(frame.last(), Some(&0));
+    assert!(!frame[..frame.len() - 1].contains(&0));
+    assert_eq!(
+        cobs::decode_vector(&frame[..frame.len() - 1]).unwrap(),
+        b"\x03\x00\x01\x00\x00\x00\x00\x01\x00"
+    );
+    assert_eq!(
+        Measurement::decode_frame(&frame[..frame.len() - 1]),
+        Ok(measurement)
+    );
+
+    let mut small_buf = [0_u8; Measurement::FRAME_MAX_LEN - 1];
+    assert_eq!(
+        measurement.encode_frame(&mut small_buf),
+        Err(Error::OutputBufferTooSmall)
+    );
+}
+
+#[test]
+fn test_cycles_decode_frame_errors() {
+    let record_with_id = |id: u8| {
+        let mut record = vec![id];
+        record.extend_from_slice(&[1; 8]);
+        cobs::encode_vector(&record).unwrap()
+    };
+    assert!(Measurement::decode_frame(&record_with_id(3)).is_ok());
+    assert_eq!(
+        Measurement::decode_frame(&record_with_id(0)),
+        Err(Error::UnknownFormat)
+    );
+    assert_eq!(
+        Measurement::decode_frame(&record_with_id(5)),
+        Err(Error::UnknownFormat)
+    );
+
+    let short = cobs::encode_vector(b"\x01\x01\x01").unwrap();
+    assert_eq!(Measurement::decode_frame(&short), Err(Error::UnknownFormat));
+    let long = cobs::encode_vector(&[1; 20]).unwrap();
+    assert_eq!(Measurement::decode_frame(&long), Err(Error::UnknownFormat));
+
+    assert_eq!(
+        Measurement::decode_frame(b"\x05AB"),
+        Err(Error::TruncatedEncodedData)
+    );
+    assert_eq!(
+        Measurement::decode_frame(b"\x03A\x00B"),
+        Err(Error::ZeroInEncodedData)
+    );
+}
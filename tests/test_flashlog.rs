@@ -0,0 +1,239 @@
+#![cfg(feature = "embedded-storage")]
+
+use ::cobs2::flashlog::{FlashFrameLog, FlashLogError, FlashLogReader};
+use ::cobs2::Error;
+
+use embedded_storage::nor_flash::{
+    check_erase, check_read, check_write, ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash,
+};
+
+/// NOR flash in RAM, which only allows writes to erased bytes, and can simulate a power failure.
+struct RamFlash {
+    data: Vec<u8>,
+    /// Number of bytes that can be written before a simulated power failure.
+    write_budget: usize,
+}
+
+impl RamFlash {
+    fn new(size: usize) -> RamFlash {
+        RamFlash {
+            data: vec![0xFF; size],
+            write_budget: usize::MAX,
+        }
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check_read(self, offset, bytes.len())?;
+        bytes.copy_from_slice(&self.data[offset as usize..][..bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.data.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = 256;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check_erase(self, from, to)?;
+        self.data[from as usize..to as usize].fill(0xFF);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check_write(self, offset, bytes.len())?;
+        for (i, &byte_val) in bytes.iter().enumerate() {
+            if self.write_budget == 0 {
+                return Err(NorFlashErrorKind::Other);
+            }
+            let flash_byte = &mut self.data[offset as usize + i];
+            assert_eq!(
+                *flash_byte,
+                0xFF,
+                "write to unerased flash at {}",
+                offset as usize + i
+            );
+            *flash_byte = byte_val;
+            self.write_budget -= 1;
+        }
+        Ok(())
+    }
+}
+
+fn read_all<const N: usize>(
+    reader: &mut FlashLogReader<'_, RamFlash, N>,
+) -> Vec<Result<Vec<u8>, FlashLogError<NorFlashErrorKind>>> {
+    let mut records = Vec::new();
+    while let Some(record) = reader.next_record() {
+        records.push(record.map(|frame| frame.to_vec()));
+    }
+    records
+}
+
+#[test]
+fn test_flashlog_append_read() {
+    let frames: [&[u8]; 5] = [b"", b"\0", b"ABC\0ghij\0xyz", &[0x55; 300], &[0xFF; 7]];
+    let mut log = FlashFrameLog::open(RamFlash::new(4096), 1024, 3072).unwrap();
+    assert_eq!(log.used_bytes(), 0);
+    assert_eq!(log.free_bytes(), 2048);
+    for frame in frames.iter() {
+        log.append(frame).unwrap();
+        assert_eq!(log.used_bytes() % 4, 0);
+    }
+    let expected: Vec<_> = frames.iter().map(|frame| Ok(frame.to_vec())).collect();
+    assert_eq!(read_all(&mut log.reader::<512>()), expected);
+    let used_bytes = log.used_bytes();
+
+    // Reopen, as at boot, and append more.
+    let flash = log.into_inner();
+    assert!(flash.data[..1024]
+        .iter()
+        .chain(&flash.data[3072..])
+        .all(|x| *x == 0xFF));
+    let mut log = FlashFrameLog::open(flash, 1024, 3072).unwrap();
+    assert!(!log.is_torn());
+    assert_eq!(log.used_bytes(), used_bytes);
+    log.append(b"after reboot").unwrap();
+    let mut reader = log.reader::<512>();
+    let records = read_all(&mut reader);
+    assert_eq!(records[..5], expected[..]);
+    assert_eq!(records[5], Ok(b"after reboot".to_vec()));
+    assert_eq!(reader.torn_len(), 0);
+}
+
+#[test]
+fn test_flashlog_full_erase() {
+    let mut log = FlashFrameLog::open(RamFlash::new(1024), 0, 256).unwrap();
+    let mut count = 0;
+    loop {
+        match log.append(b"0123456789") {
+            Ok(()) => count += 1,
+            Err(e) => {
+                assert_eq!(e, FlashLogError::Full);
+                break;
+            }
+        }
+    }
+    // Each record is 16 bytes: 10 bytes data, 4 bytes CRC, code byte and delimiter.
+    assert_eq!(count, 16);
+    assert_eq!(log.free_bytes(), 0);
+    assert_eq!(read_all(&mut log.reader::<64>()).len(), 16);
+
+    log.erase().unwrap();
+    assert_eq!(log.used_bytes(), 0);
+    assert!(log.reader::<64>().next_record().is_none());
+    log.append(b"new").unwrap();
+    assert_eq!(read_all(&mut log.reader::<64>()), [Ok(b"new".to_vec())]);
+}
+
+#[test]
+fn test_flashlog_torn() {
+    let mut log = FlashFrameLog::open(RamFlash::new(1024), 0, 1024).unwrap();
+    log.append(b"first").unwrap();
+    log.append(b"second").unwrap();
+
+    // Simulate a power failure part way through writing a record.
+    let mut flash = log.into_inner();
+    flash.write_budget = 6;
+    let mut log = FlashFrameLog::open(flash, 0, 1024).unwrap();
+    assert!(log.append(b"third, torn").is_err());
+
+    let mut flash = log.into_inner();
+    flash.write_budget = usize::MAX;
+    let mut log = FlashFrameLog::open(flash, 0, 1024).unwrap();
+    assert!(log.is_torn());
+    {
+        let mut reader = log.reader::<64>();
+        assert_eq!(
+            read_all(&mut reader),
+            [Ok(b"first".to_vec()), Ok(b"second".to_vec())]
+        );
+        assert_eq!(reader.torn_len(), 8);
+    }
+
+    // The next record ends the torn record first.
+    log.append(b"fourth").unwrap();
+    assert!(!log.is_torn());
+    let records = read_all(&mut log.reader::<64>());
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[..2],
+        [Ok(b"first".to_vec()), Ok(b"second".to_vec())]
+    );
+    assert!(matches!(records[2], Err(FlashLogError::Record(_))));
+    assert_eq!(records[3], Ok(b"fourth".to_vec()));
+}
+
+#[test]
+fn test_flashlog_damaged() {
+    let mut log = FlashFrameLog::open(RamFlash::new(1024), 0, 1024).unwrap();
+    log.append(b"first").unwrap();
+    let second_start = log.used_bytes() as usize;
+    log.append(b"second").unwrap();
+    log.append(&[0x55; 100]).unwrap();
+    log.append(b"fourth").unwrap();
+
+    // Clear a bit of the second record, as a flash bit error might.
+    let mut flash = log.into_inner();
+    flash.data[second_start + 3] &= !0x02;
+    let mut log = FlashFrameLog::open(flash, 0, 1024).unwrap();
+
+    // The third record is too long for the reader's buffer.
+    let records = read_all(&mut log.reader::<64>());
+    assert_eq!(
+        records,
+        [
+            Ok(b"first".to_vec()),
+            Err(FlashLogError::Record(Error::ChecksumMismatch)),
+            Err(FlashLogError::Record(Error::OutputBufferTooSmall)),
+            Ok(b"fourth".to_vec()),
+        ]
+    );
+}
+
+#[test]
+fn test_flashlog_not_aligned() {
+    let result = FlashFrameLog::open(RamFlash::new(1024), 100, 512);
+    assert_eq!(
+        result.err().map(|e| e.to_string()),
+        Some("Log region is not aligned".to_string())
+    );
+    assert!(FlashFrameLog::open(RamFlash::new(1024), 512, 256).is_err());
+    assert!(FlashFrameLog::open(RamFlash::new(1024), 0, 2048).is_err());
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_flashlog_scan_dump() {
+    use ::cobs2::framelog::{self, ScanEntry};
+
+    let mut log = FlashFrameLog::open(RamFlash::new(1024), 0, 512).unwrap();
+    log.append(b"first").unwrap();
+    log.append(b"second").unwrap();
+    let mut flash = log.into_inner();
+    flash.write_budget = 6;
+    let mut log = FlashFrameLog::open(flash, 0, 512).unwrap();
+    assert!(log.append(b"third, torn").is_err());
+
+    // Scan a dump of the flash region.
+    let dump = &log.into_inner().data[..512];
+    let entries: Vec<ScanEntry> = framelog::scan(dump).collect();
+    assert_eq!(entries.len(), 4);
+    assert!(matches!(&entries[0], ScanEntry::Record { data, .. } if data == b"first"));
+    assert!(matches!(&entries[1], ScanEntry::Record { data, .. } if data == b"second"));
+    assert_eq!(entries[2].range().len(), 6);
+    assert!(matches!(entries[2], ScanEntry::Torn { .. }));
+    assert_eq!(entries[3].range().end, 512);
+    assert!(matches!(entries[3], ScanEntry::Erased { .. }));
+}
@@ -0,0 +1,190 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::framelog::{self, FrameLogReader, FrameLogWriter, SyncPolicy};
+use ::cobs2::Error;
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+fn temp_log(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("cobs2-test-{}-{}.log", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn read_all(log: &[u8]) -> Vec<Result<Vec<u8>, Error>> {
+    FrameLogReader::new(log)
+        .map(|record| {
+            record.map_err(|e| {
+                assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+                *e.into_inner().unwrap().downcast::<Error>().unwrap()
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_framelog_record() {
+    let record = framelog::encode_record(b"ABC\0").unwrap();
+    // Data followed by its CRC-32, COBS-encoded, then a delimiter.
+    let mut data_crc = b"ABC\0".to_vec();
+    data_crc.extend_from_slice(&::cobs2::pipeline::Crc32::checksum(b"ABC\0").to_le_bytes());
+    let mut expected = ::cobs2::cobs::encode_vector(&data_crc).unwrap();
+    expected.push(0);
+    assert_eq!(record, expected);
+    assert_eq!(
+        framelog::decode_record(&record[..record.len() - 1]).unwrap(),
+        b"ABC\0"
+    );
+
+    assert_eq!(
+        framelog::decode_record(b"\x04ABC"),
+        Err(Error::ChecksumMismatch)
+    );
+    assert_eq!(
+        framelog::decode_record(b"\x04AB"),
+        Err(Error::TruncatedEncodedData)
+    );
+}
+
+#[test]
+fn test_framelog_file() {
+    let path = temp_log("file");
+    let mut writer = FrameLogWriter::open(&path, SyncPolicy::EveryRecords(2)).unwrap();
+    writer.append(b"ABC\0ghij\0xyz").unwrap();
+    writer.append(b"").unwrap();
+    writer.sync().unwrap();
+    drop(writer.into_inner());
+
+    // Reopen and append more.
+    let mut writer = FrameLogWriter::open(&path, SyncPolicy::Explicit).unwrap();
+    writer.append(&[0x55; 1000]).unwrap();
+    writer.sync().unwrap();
+
+    let reader = FrameLogReader::open(&path).unwrap();
+    let records: Vec<Vec<u8>> = reader.map(|record| record.unwrap()).collect();
+    assert_eq!(records, [&b"ABC\0ghij\0xyz"[..], b"", &[0x55; 1000]]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_framelog_torn() {
+    let mut log = Vec::new();
+    for data in [&b"first"[..], b"second", b"third"] {
+        log.extend(framelog::encode_record(data).unwrap());
+    }
+    let torn_len = 5;
+    log.truncate(log.len() - torn_len);
+
+    let mut reader = FrameLogReader::new(&log[..]);
+    assert_eq!(reader.next().unwrap().unwrap(), b"first");
+    assert_eq!(reader.torn_len(), 0);
+    assert_eq!(reader.next().unwrap().unwrap(), b"second");
+    assert!(reader.next().is_none());
+    assert_eq!(
+        reader.torn_len(),
+        framelog::encode_record(b"third").unwrap().len() - torn_len
+    );
+    assert!(reader.next().is_none());
+
+    // Appending to a torn log ends the torn record first.
+    let path = temp_log("torn");
+    fs::write(&path, &log).unwrap();
+    let mut writer = FrameLogWriter::open(&path, SyncPolicy::EachRecord).unwrap();
+    writer.append(b"fourth").unwrap();
+    let records = read_all(&fs::read(&path).unwrap());
+    assert_eq!(records.len(), 4);
+    assert_eq!(
+        records[..2],
+        [Ok(b"first".to_vec()), Ok(b"second".to_vec())]
+    );
+    assert!(records[2].is_err());
+    assert_eq!(records[3], Ok(b"fourth".to_vec()));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_framelog_damaged() {
+    let mut log = Vec::new();
+    for data in [&b"first"[..], b"second", b"third"] {
+        log.extend(framelog::encode_record(data).unwrap());
+    }
+    // Damage a data byte of the second record, and add empty records.
+    let second_start = framelog::encode_record(b"first").unwrap().len();
+    log[second_start + 2] ^= 0x01;
+    log.insert(second_start, 0);
+    log.write_all(b"\0\0").unwrap();
+
+    let records = read_all(&log);
+    assert_eq!(
+        records,
+        [
+            Ok(b"first".to_vec()),
+            Err(Error::ChecksumMismatch),
+            Ok(b"third".to_vec())
+        ]
+    );
+}
+
+#[test]
+fn test_framelog_scan() {
+    use ::cobs2::framelog::ScanEntry;
+
+    let first = framelog::encode_record(b"first").unwrap();
+    let second = framelog::encode_record(b"second").unwrap();
+    let mut log = first.clone();
+    log.extend_from_slice(b"\0\0");
+    let damaged_start = log.len();
+    let mut damaged = framelog::encode_record(b"damaged").unwrap();
+    damaged[3] ^= 0x10;
+    log.extend(&damaged);
+    let second_start = log.len();
+    log.extend(&second);
+    let torn_start = log.len();
+    log.extend_from_slice(b"\x09torn");
+
+    let entries: Vec<ScanEntry> = framelog::scan(&log).collect();
+    assert_eq!(
+        entries,
+        [
+            ScanEntry::Record {
+                range: 0..first.len(),
+                data: b"first".to_vec()
+            },
+            ScanEntry::Damaged {
+                range: damaged_start..second_start,
+                error: Error::ChecksumMismatch,
+            },
+            ScanEntry::Record {
+                range: second_start..torn_start,
+                data: b"second".to_vec()
+            },
+            ScanEntry::Torn {
+                range: torn_start..log.len()
+            },
+        ]
+    );
+
+    // Erased flash, with a torn record that was later ended by a delimiter.
+    let mut dump = b"\xFF\xFF\0".to_vec();
+    dump.extend_from_slice(b"\x09to\xFF\xFF\xFF\xFF\xFF\xFF\xFF\0");
+    dump.extend(&first);
+    dump.extend_from_slice(&[0xFF; 8]);
+    let entries: Vec<ScanEntry> = framelog::scan(&dump).collect();
+    assert_eq!(
+        entries,
+        [
+            ScanEntry::Erased { range: 0..3 },
+            ScanEntry::Torn { range: 3..6 },
+            ScanEntry::Erased { range: 6..14 },
+            ScanEntry::Record {
+                range: 14..25,
+                data: b"first".to_vec()
+            },
+            ScanEntry::Erased { range: 25..33 },
+        ]
+    );
+    assert!(framelog::scan(b"").next().is_none());
+    assert!(framelog::scan(b"\0\0").next().is_none());
+}
@@ -0,0 +1,102 @@
+use ::cobs2::{HexDump, HexFrame};
+
+#[test]
+fn test_hex_frame_cobs() {
+    assert_eq!(format!("{}", HexFrame(b"")), "");
+    assert_eq!(format!("{}", HexFrame(b"\x01")), "[01]");
+    assert_eq!(format!("{}", HexFrame(b"\x01\x01\x01")), "[01] [01] [01]");
+    assert_eq!(
+        format!("{}", HexFrame(b"\x0612345\x056789")),
+        "[06] 31 32 33 34 35 [05] 36 37 38 39"
+    );
+}
+
+#[test]
+fn test_hex_frame_cobsr() {
+    // COBS/R final length code replaced by final data byte.
+    assert_eq!(
+        format!("{}", HexFrame(b"\x03\x2F\xA2\x26\x92\x73")),
+        "[03] 2F A2 [26] 92 73"
+    );
+}
+
+#[test]
+fn test_hex_frame_delimiters() {
+    assert_eq!(
+        format!("{}", HexFrame(b"\x02A\x00\x03BC\x00")),
+        "[02] 41 00 [03] 42 43 00"
+    );
+    assert_eq!(format!("{}", HexFrame(b"\x00\x00\x02A")), "00 00 [02] 41");
+    // Unexpected zero within a block re-synchronises on the following byte.
+    assert_eq!(
+        format!("{}", HexFrame(b"\x05A\x00\x02B")),
+        "[05] 41 00 [02] 42"
+    );
+}
+
+#[test]
+fn test_hex_frame_debug() {
+    let data = b"\x03\x2F\xA2\x04\x92\x73\x02";
+    assert_eq!(
+        format!("{:?}", HexFrame(data)),
+        format!("{}", HexFrame(data))
+    );
+}
+
+fn hexdump_lines(dump: HexDump) -> Vec<String> {
+    dump.to_string().lines().map(String::from).collect()
+}
+
+#[test]
+fn test_hexdump_cobs() {
+    assert_eq!(HexDump::new(b"").to_string(), "");
+    let data_cobs = b"\x04ABC\x05ghij\x04xyz\x00\xFF";
+    assert_eq!(
+        hexdump_lines(HexDump::new(data_cobs)),
+        [
+            "00000000  04 41 42 43 05 67 68 69 6A 04 78 79 7A 00 FF",
+            "          ^^          ^^             ^^          || ^^",
+            "             41 42 43 00 67 68 69 6A 00 78 79 7A",
+        ]
+    );
+}
+
+#[test]
+fn test_hexdump_lines() {
+    // Maximum length block, then a block which doesn't start with a zero.
+    let mut data_cobs = vec![0xFF_u8];
+    data_cobs.extend(1..=254_u8);
+    data_cobs.extend(b"\x02A\x00");
+    let lines = hexdump_lines(HexDump::new(&data_cobs).offset(0x100));
+    assert_eq!(lines.len(), 17 * 3);
+    assert!(lines[0].starts_with("00000100  FF 01 02 03"));
+    assert_eq!(lines[1], "          ^^");
+    assert!(lines[2].starts_with("             01 02"));
+    assert!(lines[45].ends_with(" FD FE 02"));
+    assert!(lines[46].ends_with("   ^^"));
+    assert!(lines[47].ends_with(" FD FE"));
+    assert_eq!(lines[48], "00000200  41 00");
+    assert_eq!(lines[49], "             ||");
+    assert_eq!(lines[50], "          41");
+}
+
+#[test]
+fn test_hexdump_cobsr_and_errors() {
+    // COBS/R final length code is also the final data byte.
+    let lines = hexdump_lines(HexDump::new_cobsr(b"\x03AB\x7Axy\x00"));
+    assert_eq!(lines[1], "          ^^       ^^       ||");
+    assert_eq!(lines[2], "             41 42 00 78 79 7A");
+
+    // Truncated COBS frame.
+    let lines = hexdump_lines(HexDump::new(b"\x05AB\x00\x02C\x00"));
+    assert_eq!(lines[2], "             41 42 ??    43");
+
+    let text = HexDump::new(b"\x02A\x05\x00").colour(true).to_string();
+    assert!(text.contains("\x1b[1;33m02\x1b[0m"));
+    assert!(text.contains("\x1b[36m00\x1b[0m"));
+    assert!(text.contains("\x1b[1;31m??\x1b[0m"));
+    assert_eq!(
+        format!("{:?}", HexDump::new(b"\x01")),
+        HexDump::new(b"\x01").to_string()
+    );
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::capture::CaptureWriter;
+use ::cobs2::cobs;
+use ::cobs2::index::{FrameIndex, FrameSpan};
+
+use std::io::{self, Cursor};
+
+#[test]
+fn test_index_stream() {
+    // Frames of varying lengths, so the stream is longer than one read, and empty frames.
+    let frames: Vec<Vec<u8>> = (0..2000_u32)
+        .map(|i| vec![b'A' + (i % 26) as u8; (i % 97) as usize])
+        .collect();
+    let mut stream = Vec::new();
+    for frame in &frames {
+        stream.extend(cobs::encode_vector(frame).unwrap());
+        stream.push(0);
+        stream.push(0);
+    }
+    stream.extend_from_slice(b"\x03xy");
+
+    let index = FrameIndex::scan_stream(&stream[..]).unwrap();
+    assert_eq!(index.len(), frames.len() + 1);
+    for (n, frame) in frames.iter().enumerate() {
+        let frame_encoded = index.frame(&stream, n).unwrap();
+        assert_eq!(&cobs::decode_vector(frame_encoded).unwrap(), frame);
+    }
+    assert_eq!(index.frame(&stream, frames.len()), Some(&b"\x03xy"[..]));
+    assert_eq!(index.frame(&stream, frames.len() + 1), None);
+    assert_eq!(index.get(1), Some(FrameSpan { offset: 3, len: 2 }));
+    assert_eq!(index.spans().len(), index.len());
+
+    let mut file = Cursor::new(&stream);
+    let mut frame = Vec::new();
+    index.read_frame(&mut file, 1234, &mut frame).unwrap();
+    assert_eq!(frame, index.frame(&stream, 1234).unwrap());
+    let err = index
+        .read_frame(&mut file, frames.len() + 1, &mut frame)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let err = index
+        .read_frame(&mut Cursor::new(&stream[..10]), 1234, &mut frame)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    let empty = FrameIndex::scan_stream(&b"\0\0"[..]).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(empty, FrameIndex::default());
+}
+
+#[test]
+fn test_index_frame_containing() {
+    let index = FrameIndex::scan_stream(&b"\x01\x00\x03xy\x00\x00\x02z"[..]).unwrap();
+    let expected = [
+        Some(0),
+        None,
+        Some(1),
+        Some(1),
+        Some(1),
+        None,
+        None,
+        Some(2),
+        Some(2),
+        None,
+    ];
+    for (offset, expected) in expected.iter().enumerate() {
+        assert_eq!(
+            index.frame_containing(offset as u64),
+            *expected,
+            "offset {}",
+            offset
+        );
+    }
+    assert_eq!(FrameIndex::default().frame_containing(0), None);
+}
+
+#[test]
+fn test_index_capture() {
+    let mut capture = Vec::new();
+    let mut writer = CaptureWriter::new(&mut capture).unwrap();
+    writer.write_frame(b"\x04ABC").unwrap();
+    writer.write_frame(b"").unwrap();
+    writer.write_frame(b"\x03xy").unwrap();
+
+    let index = FrameIndex::scan_capture(&capture[..]).unwrap();
+    assert_eq!(index.len(), 3);
+    assert_eq!(index.get(0), Some(FrameSpan { offset: 30, len: 4 }));
+    assert_eq!(index.frame(&capture, 0), Some(&b"\x04ABC"[..]));
+    assert_eq!(index.frame(&capture, 1), Some(&b""[..]));
+    assert_eq!(index.frame(&capture, 2), Some(&b"\x03xy"[..]));
+    assert_eq!(index.frame_containing(30), Some(0));
+    assert_eq!(index.frame_containing(34), None);
+
+    let err = FrameIndex::scan_capture(&capture[..capture.len() - 1]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = FrameIndex::scan_capture(&capture[..capture.len() - 5]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = FrameIndex::scan_capture(&b"COBS2CAP\x02\x00"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_last_frames() {
+    use ::cobs2::index::last_frames;
+    use ::cobs2::profile::Profile;
+    use ::cobs2::Error;
+
+    // Enough frames that the stream is several read chunks long.
+    let frames: Vec<Vec<u8>> = (0..5000_u32)
+        .map(|i| format!("message {}", i).into_bytes())
+        .collect();
+    let mut stream = Vec::new();
+    for frame in &frames {
+        stream.extend(Profile::CobsCrc32.encode_frame(frame).unwrap());
+        stream.push(0);
+    }
+    // A partial frame, from a crash during writing.
+    stream.extend_from_slice(b"\x07mess");
+
+    let mut file = Cursor::new(&stream);
+    for &n in &[0, 1, 100, 4999, 5000] {
+        let last = last_frames(&mut file, Profile::CobsCrc32, n).unwrap();
+        let expected: Vec<_> = frames[frames.len() - n..]
+            .iter()
+            .map(|f| Ok(f.clone()))
+            .collect();
+        assert_eq!(last, expected, "{}", n);
+    }
+    let last = last_frames(&mut file, Profile::CobsCrc32, 10_000).unwrap();
+    assert_eq!(last.len(), frames.len());
+
+    let mut stream = Cursor::new(b"\x03xy\x00\x03AB\x00\x05AB\x00");
+    let last = last_frames(&mut stream, Profile::Cobs, 2).unwrap();
+    assert_eq!(last, [Ok(b"AB".to_vec()), Err(Error::TruncatedEncodedData)]);
+    let last = last_frames(&mut stream, Profile::Cobs, 5).unwrap();
+    assert_eq!(last.len(), 3);
+    assert!(last_frames(&mut Cursor::new(b""), Profile::Cobs, 5)
+        .unwrap()
+        .is_empty());
+    assert!(last_frames(&mut Cursor::new(b"\x03xy"), Profile::Cobs, 5)
+        .unwrap()
+        .is_empty());
+}
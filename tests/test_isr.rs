@@ -0,0 +1,144 @@
+#![cfg(feature = "critical-section")]
+
+use ::cobs2::isr::{AsyncStaticDecoder, StaticDecoder};
+use ::cobs2::{cobs, cobsr};
+
+use futures::task::{waker, ArcWake};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread;
+
+static DECODER: StaticDecoder<cobs::FrameAccumulator<300>> =
+    StaticDecoder::new(cobs::FrameAccumulator::new());
+
+static ASYNC_DECODER: AsyncStaticDecoder<cobs::FrameAccumulator<300>> =
+    AsyncStaticDecoder::new(cobs::FrameAccumulator::new());
+
+#[test]
+fn test_static_decoder_threads() {
+    let frames: Vec<Vec<u8>> = (0..100_u32)
+        .map(|i| (0..i * 3).map(|x| x as u8).collect())
+        .collect();
+    let mut stream = Vec::new();
+    for frame in &frames {
+        stream.extend(cobs::encode_vector(frame).unwrap());
+        stream.push(0);
+    }
+
+    // The "interrupt handler" feeds one byte at a time, waiting while a frame is ready.
+    let feeder = thread::spawn(move || {
+        let mut received = &stream[..];
+        while !received.is_empty() {
+            let consumed = DECODER.with(|decoder| decoder.feed(&received[..1]));
+            received = &received[consumed..];
+        }
+    });
+
+    let mut decoded = Vec::new();
+    while decoded.len() < frames.len() {
+        let frame = DECODER.with(|decoder| decoder.poll_frame().map(|f| f.unwrap().to_vec()));
+        decoded.extend(frame);
+    }
+    feeder.join().unwrap();
+    assert_eq!(decoded, frames);
+}
+
+#[test]
+fn test_static_decoder_local() {
+    let decoder = StaticDecoder::new(cobsr::FrameAccumulator::<16>::new());
+    assert_eq!(decoder.with(|d| d.feed(b"\x04ABC\x05ghijzxy\x00")), 13);
+    let frame = decoder.with(|d| d.poll_frame().map(|f| f.unwrap().to_vec()));
+    assert_eq!(frame.unwrap(), b"ABC\0ghij\0xyz");
+}
+
+#[test]
+fn test_static_decoder_default() {
+    let decoder: StaticDecoder<cobs::FrameAccumulator<16>> = StaticDecoder::default();
+    assert_eq!(decoder.with(|d| d.feed(b"\x04ABC\x00")), 5);
+    let frame = decoder.with(|d| d.poll_frame().map(|f| f.unwrap().to_vec()));
+    assert_eq!(frame.unwrap(), b"ABC");
+}
+
+#[test]
+#[should_panic]
+fn test_static_decoder_nested() {
+    let decoder = StaticDecoder::new(cobs::FrameAccumulator::<16>::new());
+    decoder.with(|_| decoder.with(|d| d.clear()));
+}
+
+#[test]
+fn test_async_static_decoder_threads() {
+    let frames: Vec<Vec<u8>> = (0..100_u32)
+        .map(|i| (0..i * 3).map(|x| x as u8).collect())
+        .collect();
+    let mut stream = Vec::new();
+    for frame in &frames {
+        stream.extend(cobs::encode_vector(frame).unwrap());
+        stream.push(0);
+    }
+
+    // The "interrupt handler" feeds one byte at a time, waking the task.
+    let feeder = thread::spawn(move || {
+        let mut received = &stream[..];
+        while !received.is_empty() {
+            let consumed = ASYNC_DECODER.feed_with(|decoder| decoder.feed(&received[..1]));
+            received = &received[consumed..];
+        }
+    });
+
+    let decoded = futures::executor::block_on(async {
+        let mut decoded = Vec::new();
+        while decoded.len() < frames.len() {
+            let frame = ASYNC_DECODER
+                .wait_with(|decoder| decoder.poll_frame().map(|f| f.unwrap().to_vec()))
+                .await;
+            decoded.push(frame);
+        }
+        decoded
+    });
+    feeder.join().unwrap();
+    assert_eq!(decoded, frames);
+}
+
+struct CountWakes(AtomicUsize);
+
+impl ArcWake for CountWakes {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[test]
+fn test_async_static_decoder_wake() {
+    let decoder = AsyncStaticDecoder::new(cobsr::FrameAccumulator::<16>::new());
+    let wakes = Arc::new(CountWakes(AtomicUsize::new(0)));
+    let task_waker = waker(wakes.clone());
+    let mut cx = Context::from_waker(&task_waker);
+    let take_frame =
+        |d: &mut cobsr::FrameAccumulator<16>| d.poll_frame().map(|f| f.unwrap().to_vec());
+
+    // No waiting task, so nothing to wake.
+    assert_eq!(decoder.feed_with(|d| d.feed(b"\x04AB")), 3);
+    assert_eq!(decoder.poll_with(&mut cx, take_frame), Poll::Pending);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 0);
+
+    // The waiting task is woken once.
+    assert_eq!(decoder.feed_with(|d| d.feed(b"C\x00")), 2);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    assert_eq!(decoder.feed_with(|d| d.feed(b"\x03")), 0);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        decoder.poll_with(&mut cx, take_frame),
+        Poll::Ready(b"ABC".to_vec())
+    );
+
+    // `with()` doesn't wake the task.
+    assert_eq!(decoder.poll_with(&mut cx, take_frame), Poll::Pending);
+    assert_eq!(decoder.with(|d| d.feed(b"\x03xy\x00")), 4);
+    assert_eq!(wakes.0.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        decoder.poll_with(&mut cx, take_frame),
+        Poll::Ready(b"xy".to_vec())
+    );
+}
@@ -0,0 +1,571 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::profile::Profile;
+use ::cobs2::stream::{Link, LinkStats};
+
+use std::io::{self, Cursor};
+
+#[test]
+fn test_link() {
+    let mut link = Link::new(
+        Cursor::new(b"\x04ABC\x00\x00\x05AB\x00\x03xy\x00".to_vec()),
+        Vec::new(),
+    );
+    let mut frame = Vec::new();
+    link.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"ABC");
+    let err = link.recv(&mut frame).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    link.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"xy");
+    assert_eq!(
+        link.recv(&mut frame).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    link.send(b"ABC\0xyz").unwrap();
+    link.send(b"").unwrap();
+    assert_eq!(link.get_ref().1, b"\x04ABC\x04xyz\x00\x01\x00");
+    assert_eq!(
+        link.stats(),
+        LinkStats {
+            frames_sent: 2,
+            bytes_sent: 11,
+            frames_received: 2,
+            bytes_received: 14,
+            invalid_frames: 1,
+        }
+    );
+}
+
+#[test]
+fn test_link_profile() {
+    let profile = Profile::CobsrCrc16Ccitt;
+    let mut sender = Link::with_profile(io::empty(), Vec::new(), profile);
+    sender.send(b"ABC\0xyz").unwrap();
+    sender.send(b"\x01\x02").unwrap();
+    let (_, sent) = sender.into_inner();
+
+    let mut receiver = Link::with_profile(Cursor::new(sent), io::sink(), profile);
+    assert_eq!(receiver.profile(), profile);
+    let mut frame = Vec::new();
+    receiver.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"ABC\0xyz");
+    receiver.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"\x01\x02");
+    assert_eq!(receiver.stats().frames_received, 2);
+
+    let mut cobsr = Link::new_cobsr(io::empty(), Vec::new());
+    cobsr.send(b"AB").unwrap();
+    assert_eq!(cobsr.get_ref().1, b"\x42A\x00");
+}
+
+#[test]
+fn test_link_max_frame_len() {
+    let input = b"\x05ABCD\x00\x04ABC\x00\x06ABCDE\x00";
+    let mut link = Link::new(Cursor::new(input.to_vec()), io::sink());
+    link.set_max_frame_len(4);
+    let mut frame = Vec::new();
+    let err = link.recv(&mut frame).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        err.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::FrameTooLong)
+    );
+    link.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"ABC");
+    assert!(link.recv(&mut frame).is_err());
+    assert_eq!(link.stats().frames_received, 1);
+    assert_eq!(link.stats().invalid_frames, 2);
+    assert_eq!(link.stats().bytes_received, input.len() as u64);
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link() {
+    use ::cobs2::async_stream::AsyncLink;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    block_on(async {
+        let input = b"\x04ABC\x00\x00\x05AB\x00\x03xy\x00\x02".to_vec();
+        let mut link = AsyncLink::new(Cursor::new(input), Vec::new());
+        let mut frame = Vec::new();
+        link.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ABC");
+        let err = link.recv(&mut frame).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        link.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"xy");
+        let err = link.recv(&mut frame).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        link.send(b"ABC\0xyz").await.unwrap();
+        assert_eq!(link.get_ref().1, b"\x04ABC\x04xyz\x00");
+        assert_eq!(
+            link.stats(),
+            LinkStats {
+                frames_sent: 1,
+                bytes_sent: 9,
+                frames_received: 2,
+                bytes_received: 14,
+                invalid_frames: 1,
+            }
+        );
+
+        let profile = Profile::CobsrCrc16Ccitt;
+        let mut sender = AsyncLink::with_profile(futures::io::empty(), Vec::new(), profile);
+        sender.send(b"ABC\0xyz").await.unwrap();
+        let (_, sent) = sender.into_inner();
+        let mut receiver = AsyncLink::with_profile(Cursor::new(sent), futures::io::sink(), profile);
+        receiver.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ABC\0xyz");
+    });
+}
+
+/// An async reader and writer which is pending on every other poll, and then reads or writes at
+/// most a few bytes.
+#[cfg(feature = "futures-io")]
+struct Trickle {
+    input: Vec<u8>,
+    output: Vec<u8>,
+    ready: bool,
+}
+
+#[cfg(feature = "futures-io")]
+impl Trickle {
+    fn new(input: &[u8]) -> Trickle {
+        Trickle {
+            input: input.to_vec(),
+            output: Vec::new(),
+            ready: false,
+        }
+    }
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<()> {
+        self.ready = !self.ready;
+        if self.ready {
+            std::task::Poll::Ready(())
+        } else {
+            cx.waker().wake_by_ref();
+            std::task::Poll::Pending
+        }
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl futures::io::AsyncRead for Trickle {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        self.poll_ready(cx).map(|()| {
+            let len = buf.len().min(self.input.len()).min(3);
+            buf[..len].copy_from_slice(&self.input[..len]);
+            self.input.drain(..len);
+            Ok(len)
+        })
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl futures::io::AsyncWrite for Trickle {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        self.poll_ready(cx).map(|()| {
+            let len = buf.len().min(3);
+            self.output.extend_from_slice(&buf[..len]);
+            Ok(len)
+        })
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_recv_cancellation() {
+    use ::cobs2::async_stream::AsyncLink;
+    use futures::FutureExt;
+
+    // Poll each receive once, then drop it, as a losing branch of `select!` would be.
+    let input = b"\x04ABC\x00\x05ghij\x00\x00\x03xy\x00";
+    let mut link = AsyncLink::new(Trickle::new(input), futures::io::sink());
+    let mut frames = Vec::new();
+    let mut cancelled = 0;
+    while frames.len() < 3 {
+        let mut frame = Vec::new();
+        match link.recv(&mut frame).now_or_never() {
+            Some(result) => {
+                result.unwrap();
+                frames.push(frame);
+            }
+            None => cancelled += 1,
+        }
+    }
+    assert!(cancelled > 3);
+    assert_eq!(frames, [&b"ABC"[..], b"ghij", b"xy"]);
+    assert_eq!(link.stats().bytes_received, input.len() as u64);
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_max_frame_len() {
+    use ::cobs2::async_stream::AsyncLink;
+    use futures::executor::block_on;
+
+    // The too-long frame arrives over several reads, so is discarded before its delimiter.
+    let input = b"\x0AABCDEFGHI\x00\x04ABC\x00\x06ABCDE\x00\x05ABCD\x00";
+    let mut link = AsyncLink::new(Trickle::new(input), futures::io::sink());
+    link.set_max_frame_len(5);
+    block_on(async {
+        let mut frame = Vec::new();
+        let err = link.recv(&mut frame).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+            Some(&::cobs2::Error::FrameTooLong)
+        );
+        link.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ABC");
+        assert!(link.recv(&mut frame).await.is_err());
+        link.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ABCD");
+    });
+    assert_eq!(link.stats().frames_received, 2);
+    assert_eq!(link.stats().invalid_frames, 2);
+    assert_eq!(link.stats().bytes_received, input.len() as u64);
+}
+
+/// An async reader which gives each chunk of data once its delay since creation has passed, and
+/// is pending in between.
+#[cfg(feature = "futures-io")]
+struct Paced {
+    start: std::time::Instant,
+    chunks: Vec<(std::time::Duration, &'static [u8])>,
+}
+
+#[cfg(feature = "futures-io")]
+impl futures::io::AsyncRead for Paced {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        let (delay, data) = match self.chunks.first() {
+            Some(&chunk) => chunk,
+            None => return std::task::Poll::Ready(Ok(0)),
+        };
+        let elapsed = self.start.elapsed();
+        if elapsed < delay {
+            let waker = cx.waker().clone();
+            std::thread::spawn(move || {
+                std::thread::sleep(delay - elapsed);
+                waker.wake();
+            });
+            return std::task::Poll::Pending;
+        }
+        buf[..data.len()].copy_from_slice(data);
+        self.chunks.remove(0);
+        std::task::Poll::Ready(Ok(data.len()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_inter_byte_timeout() {
+    use ::cobs2::async_stream::{AsyncLink, TimedOutFrames};
+    use futures::executor::block_on;
+    use std::time::{Duration, Instant};
+
+    // A device which is reset part way through a frame, then sends the next frame.
+    let chunks = vec![
+        (Duration::ZERO, &b"\x04ABC\x00\x05AB"[..]),
+        (Duration::from_millis(100), b"\x03xy\x00"),
+    ];
+    let input_len = chunks.iter().map(|(_, data)| data.len() as u64).sum();
+    for timed_out_frames in [TimedOutFrames::Skip, TimedOutFrames::Report] {
+        let reader = Paced {
+            start: Instant::now(),
+            chunks: chunks.clone(),
+        };
+        let mut link = AsyncLink::new(reader, futures::io::sink());
+        link.set_inter_byte_timeout(Duration::from_millis(20), timed_out_frames);
+        block_on(async {
+            let mut frame = Vec::new();
+            link.recv(&mut frame).await.unwrap();
+            assert_eq!(frame, b"ABC");
+            if timed_out_frames == TimedOutFrames::Report {
+                let err = link.recv(&mut frame).await.unwrap_err();
+                assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+                assert_eq!(
+                    err.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+                    Some(&::cobs2::Error::FrameTimeout)
+                );
+            }
+            link.recv(&mut frame).await.unwrap();
+            assert_eq!(frame, b"xy");
+        });
+        assert_eq!(link.timed_out_frames(), 1);
+        assert_eq!(link.stats().frames_received, 2);
+        assert_eq!(link.stats().bytes_received, input_len);
+    }
+
+    // Without the timeout, or with a longer one, the partial frame runs into the next frame.
+    for timeout in [None, Some(Duration::from_secs(10))] {
+        let reader = Paced {
+            start: Instant::now(),
+            chunks: chunks.clone(),
+        };
+        let mut link = AsyncLink::new(reader, futures::io::sink());
+        if let Some(timeout) = timeout {
+            link.set_inter_byte_timeout(timeout, TimedOutFrames::Report);
+        }
+        block_on(async {
+            let mut frame = Vec::new();
+            link.recv(&mut frame).await.unwrap();
+            assert_eq!(frame, b"ABC");
+            let err = link.recv(&mut frame).await.unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        });
+        assert_eq!(link.timed_out_frames(), 0);
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_send_cancellation() {
+    use ::cobs2::async_stream::AsyncLink;
+    use futures::executor::block_on;
+    use futures::FutureExt;
+
+    let mut link = AsyncLink::new(futures::io::empty(), Trickle::new(b""));
+    assert!(link.send(b"ABC\0xyz").now_or_never().is_none());
+    assert!(link.send(b"hello").now_or_never().is_none());
+    assert_eq!(link.stats().frames_sent, 0);
+    block_on(link.send(b"A")).unwrap();
+    assert_eq!(
+        link.get_ref().1.output,
+        b"\x04ABC\x04xyz\x00\x06hello\x00\x02A\x00"
+    );
+    assert_eq!(link.stats().frames_sent, 3);
+
+    assert!(link.send(b"xy").now_or_never().is_none());
+    block_on(link.flush()).unwrap();
+    assert!(link.get_ref().1.output.ends_with(b"\x02A\x00\x03xy\x00"));
+    assert_eq!(link.stats().frames_sent, 4);
+}
+
+/// An async writer which records the data of each write call.
+#[cfg(feature = "futures-io")]
+#[derive(Default)]
+struct WriteLog {
+    writes: Vec<Vec<u8>>,
+}
+
+#[cfg(feature = "futures-io")]
+impl futures::io::AsyncWrite for WriteLog {
+    fn poll_write(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        self.writes.push(buf.to_vec());
+        std::task::Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_flush_policy() {
+    use ::cobs2::async_stream::AsyncLink;
+    use ::cobs2::stream::FlushPolicy;
+    use futures::executor::block_on;
+
+    let mut link = AsyncLink::new(futures::io::empty(), WriteLog::default());
+    assert_eq!(link.flush_policy(), FlushPolicy::EachFrame);
+    link.set_flush_policy(FlushPolicy::Threshold(10));
+    block_on(async {
+        for _ in 0..5 {
+            link.send(b"AB").await.unwrap();
+        }
+    });
+    // Each frame is 4 bytes encoded, so the buffer exceeds 10 bytes on every third frame.
+    assert_eq!(link.get_ref().1.writes, [b"\x03AB\x00".repeat(3)]);
+    assert_eq!(link.buffered_len(), 8);
+    assert_eq!(link.stats().frames_sent, 3);
+    block_on(link.flush()).unwrap();
+    assert_eq!(link.buffered_len(), 0);
+    assert_eq!(link.stats().frames_sent, 5);
+    assert_eq!(link.stats().bytes_sent, 20);
+
+    let mut link = AsyncLink::new(futures::io::empty(), WriteLog::default());
+    link.set_flush_policy(FlushPolicy::Frames(100));
+    block_on(async {
+        for _ in 0..250 {
+            link.send(b"A").await.unwrap();
+        }
+        link.flush().await.unwrap();
+    });
+    assert_eq!(
+        link.get_ref().1.writes,
+        [
+            b"\x02A\x00".repeat(100),
+            b"\x02A\x00".repeat(100),
+            b"\x02A\x00".repeat(50)
+        ]
+    );
+    assert_eq!(link.stats().frames_sent, 250);
+}
+
+/// An async reader which returns a transient error before each chunk of data.
+#[cfg(feature = "futures-io")]
+struct Resetting {
+    chunks: Vec<&'static [u8]>,
+    fail: bool,
+}
+
+#[cfg(feature = "futures-io")]
+impl futures::io::AsyncRead for Resetting {
+    fn poll_read(
+        mut self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<io::Result<usize>> {
+        self.fail = !self.fail;
+        if self.fail && !self.chunks.is_empty() {
+            return std::task::Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()));
+        }
+        let data = if self.chunks.is_empty() {
+            &b""[..]
+        } else {
+            self.chunks.remove(0)
+        };
+        buf[..data.len()].copy_from_slice(data);
+        std::task::Poll::Ready(Ok(data.len()))
+    }
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_link_io_error_policy() {
+    use ::cobs2::async_stream::AsyncLink;
+    use ::cobs2::stream::IoErrorPolicy;
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    let resetting = || Resetting {
+        chunks: vec![b"\x04AB", b"C\x00"],
+        fail: false,
+    };
+    block_on(async {
+        let mut link = AsyncLink::new(resetting(), futures::io::sink());
+        let mut frame = Vec::new();
+        let error = link.recv(&mut frame).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+
+        let mut link = AsyncLink::new(resetting(), futures::io::sink());
+        link.set_io_error_policy(IoErrorPolicy::Retry {
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(5),
+            max_backoff: Duration::from_millis(5),
+        });
+        link.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ABC");
+        assert_eq!(link.recovered_io_errors(), 2);
+
+        let mut link = AsyncLink::new(resetting(), futures::io::sink());
+        link.set_io_error_policy(IoErrorPolicy::Skip);
+        let error = link.recv(&mut frame).await.unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(link.recovered_io_errors(), 2);
+    });
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_async_stream_pumps() {
+    use ::cobs2::async_stream::{decode_stream_async, encode_stream_async};
+    use futures::executor::block_on;
+
+    block_on(async {
+        // Each read of the trickling reader becomes a frame.
+        let data = b"ABC\0xyz\0";
+        let mut encoded = Vec::new();
+        let bytes = encode_stream_async(Trickle::new(data), &mut encoded, Profile::Cobs)
+            .await
+            .unwrap();
+        assert_eq!(bytes, data.len() as u64);
+        assert_eq!(encoded, b"\x04ABC\x00\x01\x03xy\x00\x02z\x01\x00");
+
+        let mut decoded = Vec::new();
+        let bytes = decode_stream_async(Trickle::new(&encoded), &mut decoded, Profile::Cobs)
+            .await
+            .unwrap();
+        assert_eq!(bytes, encoded.len() as u64);
+        assert_eq!(decoded, data);
+
+        let profiles = [
+            Profile::Cobs,
+            Profile::Cobsr,
+            Profile::CobsCrc32,
+            Profile::CobsrCrc16Ccitt,
+        ];
+        for &profile in profiles.iter() {
+            let data: Vec<u8> = (0..10_000_u32).map(|x| (x % 251) as u8).collect();
+            let mut encoded = Vec::new();
+            encode_stream_async(&data[..], &mut encoded, profile)
+                .await
+                .unwrap();
+            let mut decoded = Vec::new();
+            decode_stream_async(&encoded[..], &mut decoded, profile)
+                .await
+                .unwrap();
+            assert_eq!(decoded, data, "{:?}", profile);
+        }
+
+        // A partial frame at the end is decoded, and an invalid frame stops decoding.
+        let mut decoded = Vec::new();
+        let encoded = b"\x03AB\x00\x00\x02x\x00\x05AB\x00\x02z";
+        let error = decode_stream_async(&encoded[..], &mut decoded, Profile::Cobs)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(decoded, b"ABx");
+        decoded.clear();
+        decode_stream_async(&b"\x03AB\x00\x02z"[..], &mut decoded, Profile::Cobs)
+            .await
+            .unwrap();
+        assert_eq!(decoded, b"ABz");
+    });
+}
@@ -0,0 +1,122 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::cobs;
+use ::cobs2::linktest::{LinkReport, LinkTester, PingOutcome};
+use ::cobs2::stream::Transceiver;
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+/// In-memory device which echoes frames. Of every 3 frames, it echoes the first, drops the second,
+/// and corrupts the third.
+#[derive(Default)]
+struct EchoDevice {
+    frames: u32,
+    received: Vec<u8>,
+    echoed: Vec<u8>,
+}
+
+impl Read for EchoDevice {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.echoed.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        let len = buf.len().min(self.echoed.len());
+        buf[..len].copy_from_slice(&self.echoed[..len]);
+        self.echoed.drain(..len);
+        Ok(len)
+    }
+}
+
+impl Write for EchoDevice {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.received.extend_from_slice(buf);
+        while let Some(end) = self.received.iter().position(|&x| x == 0) {
+            let mut frame = cobs::decode_vector(&self.received[..end]).unwrap();
+            self.received.drain(..=end);
+            match self.frames % 3 {
+                0 => {}
+                1 => frame.clear(),
+                _ => *frame.last_mut().unwrap() ^= 0x10,
+            }
+            if !frame.is_empty() {
+                self.echoed.extend(cobs::encode_vector(&frame).unwrap());
+                self.echoed.push(0);
+            }
+            self.frames += 1;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_link_tester() {
+    let mut transceiver = Transceiver::new(EchoDevice::default());
+    let mut outcomes = Vec::new();
+    let report = LinkTester::new()
+        .count(7)
+        .payload_len(300)
+        .timeout(Duration::from_millis(20))
+        .run_with(&mut transceiver, |sequence, outcome| {
+            outcomes.push((sequence, outcome))
+        })
+        .unwrap();
+
+    assert_eq!(outcomes.len(), 7);
+    for (i, &(sequence, outcome)) in outcomes.iter().enumerate() {
+        assert_eq!(sequence, i as u32);
+        match i % 3 {
+            0 => assert!(matches!(outcome, PingOutcome::Echoed(_))),
+            1 => assert_eq!(outcome, PingOutcome::Lost),
+            _ => assert_eq!(outcome, PingOutcome::Corrupted),
+        }
+    }
+    assert_eq!(
+        (report.sent, report.received, report.lost, report.corrupted),
+        (7, 3, 2, 2)
+    );
+    assert!(report.rtt_min.unwrap() <= report.rtt_mean().unwrap());
+    assert!(report.rtt_mean().unwrap() <= report.rtt_max.unwrap());
+    assert!((report.loss_ratio() - 4.0 / 7.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_link_report_empty() {
+    let report = LinkReport::default();
+    assert_eq!(report.rtt_mean(), None);
+    assert_eq!(report.loss_ratio(), 0.0);
+    assert_eq!(
+        report.to_string(),
+        "0 frames sent, 0 received, 0 corrupted, 0 lost, 0.0% loss"
+    );
+}
+
+#[test]
+fn test_link_tester_eof() {
+    struct Closed;
+
+    impl Read for Closed {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Ok(0)
+        }
+    }
+
+    impl Write for Closed {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let err = LinkTester::new()
+        .run(&mut Transceiver::new(Closed))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "metrics")]
+
+use ::cobs2::cobs;
+use ::cobs2::metrics::{render, Metrics};
+use ::cobs2::router::FrameRouter;
+use ::cobs2::stream::{Link, LinkStats};
+
+use std::io::Cursor;
+use std::sync::mpsc;
+
+#[test]
+fn test_metrics_link() {
+    let metrics = Metrics::new("link");
+    let stream = b"\x04ABC\x00\x00\x05AB\x00\x03xy\x00".to_vec();
+    let mut link = Link::new(Cursor::new(stream), Vec::new()).metrics(metrics.clone());
+    let mut frame = Vec::new();
+    link.recv(&mut frame).unwrap();
+    assert!(link.recv(&mut frame).is_err());
+    link.recv(&mut frame).unwrap();
+    link.send(b"hello").unwrap();
+    assert_eq!(metrics.stats(), link.stats());
+    assert_eq!(
+        metrics.stats(),
+        LinkStats {
+            frames_sent: 1,
+            bytes_sent: 7,
+            frames_received: 2,
+            bytes_received: 14,
+            invalid_frames: 1,
+        }
+    );
+}
+
+#[test]
+fn test_metrics_router() {
+    let metrics = Metrics::new("router");
+    let (tx, rx) = mpsc::channel();
+    let router = FrameRouter::new(cobs::decode_vector)
+        .route_channel_id(0x01, tx)
+        .metrics(metrics.clone());
+    let stream = b"\x03\x01A\x00\x05AB\x00\x03\x02B\x00".to_vec();
+    router.spawn(Cursor::new(stream)).join().unwrap().unwrap();
+    assert_eq!(rx.try_iter().count(), 1);
+    let stats = metrics.stats();
+    assert_eq!(
+        (
+            stats.frames_received,
+            stats.invalid_frames,
+            stats.bytes_received
+        ),
+        (2, 1, 12)
+    );
+}
+
+#[test]
+fn test_metrics_render() {
+    let uart = Metrics::new("uart0");
+    let tricky = Metrics::new("a\"b\\c\nd");
+    let mut link = Link::new(Cursor::new(Vec::new()), Vec::new()).metrics(uart.clone());
+    link.send(b"AB").unwrap();
+    let text = render(&[uart, tricky]);
+    assert!(text.starts_with(
+        "# HELP cobs2_frames_sent_total Number of frames sent.\n\
+         # TYPE cobs2_frames_sent_total counter\n\
+         cobs2_frames_sent_total{decoder=\"uart0\"} 1\n\
+         cobs2_frames_sent_total{decoder=\"a\\\"b\\\\c\\nd\"} 0\n"
+    ));
+    assert!(text.contains("cobs2_sent_bytes_total{decoder=\"uart0\"} 4\n"));
+    assert!(text.contains("# TYPE cobs2_invalid_frames_total counter\n"));
+    assert_eq!(text.lines().count(), 5 * 4);
+    assert_eq!(render(&[]).lines().count(), 5 * 2);
+}
@@ -0,0 +1,115 @@
+#![cfg(feature = "mmap")]
+
+use ::cobs2::capture::{CaptureReader, CaptureWriter};
+use ::cobs2::cobs;
+use ::cobs2::mmap::{CaptureMap, Mmap, StreamMap};
+
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+fn make_capture(frames: &[&[u8]]) -> Vec<u8> {
+    let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        let time = Duration::from_millis(i as u64 * 10);
+        writer
+            .write_record(time, Duration::from_secs(1_700_000_000) + time, frame)
+            .unwrap();
+    }
+    writer.into_inner()
+}
+
+#[test]
+fn test_capture_map() {
+    let frames: [&[u8]; 3] = [b"\x04ABC", b"", &[0x55; 1000]];
+    let capture = make_capture(&frames);
+
+    let capture_map = CaptureMap::new(&capture[..]).unwrap();
+    let records: Vec<_> = capture_map
+        .records()
+        .map(|record| record.unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(record.data, frames[i]);
+        assert_eq!(record.monotonic, Duration::from_millis(i as u64 * 10));
+    }
+
+    // Same records as reading with CaptureReader.
+    let reader = CaptureReader::new(&capture[..]).unwrap();
+    let records_read: Vec<_> = reader.map(|record| record.unwrap()).collect();
+    let records_map: Vec<_> = records.iter().map(|record| record.to_record()).collect();
+    assert_eq!(records_map, records_read);
+}
+
+#[test]
+fn test_capture_map_bad() {
+    for capture in [&b"COBS2CAX\x01\x00"[..], b"COBS2CAP\x02\x00", b"COBS2"] {
+        let e = CaptureMap::new(capture).err().unwrap();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    // Truncated final record.
+    let mut capture = make_capture(&[b"\x04ABC", b"\x03xy"]);
+    capture.truncate(capture.len() - 1);
+    let capture_map = CaptureMap::new(capture).unwrap();
+    let mut records = capture_map.records();
+    assert_eq!(records.next().unwrap().unwrap().data, b"\x04ABC");
+    assert_eq!(
+        records.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert!(records.next().is_none());
+}
+
+#[test]
+fn test_stream_map() {
+    let stream = b"\x04ABC\x00\x00\x05ghij\x04xyz\x00\x03AB".to_vec();
+    let stream_map = StreamMap::new(stream);
+    let frames: Vec<&[u8]> = stream_map.frames().collect();
+    assert_eq!(frames, [&b"\x04ABC"[..], b"\x05ghij\x04xyz", b"\x03AB"]);
+
+    let mut decode_frames = stream_map.decode_frames();
+    let mut decoded = Vec::new();
+    while let Some(frame) = decode_frames.next_frame() {
+        decoded.push(frame.collect::<::cobs2::Result<Vec<u8>>>().unwrap());
+    }
+    assert_eq!(decoded, [&b"ABC"[..], b"ghij\0xyz", b"AB"]);
+
+    let stream_map = StreamMap::new(b"\x04ABC\x00\x03ABz".to_vec());
+    let mut decode_frames = stream_map.decode_frames_cobsr();
+    let frame: ::cobs2::Result<Vec<u8>> = decode_frames.next_frame().unwrap().collect();
+    assert_eq!(frame.unwrap(), b"ABC");
+    let frame: Vec<u8> = decode_frames
+        .next_frame()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(frame, b"AB\0z");
+}
+
+#[test]
+fn test_mmap_file() {
+    let path = std::env::temp_dir().join(format!("cobs2-test-mmap-{}.cap", std::process::id()));
+    let frames: Vec<Vec<u8>> = (0..1000)
+        .map(|i| cobs::encode_vector(&[i as u8; 20]).unwrap())
+        .collect();
+    let frame_refs: Vec<&[u8]> = frames.iter().map(|frame| &frame[..]).collect();
+    fs::write(&path, make_capture(&frame_refs)).unwrap();
+
+    let file = fs::File::open(&path).unwrap();
+    // Safety: the file is not modified while it is mapped.
+    let map = unsafe { Mmap::map(&file) }.unwrap();
+    let capture_map = CaptureMap::new(map).unwrap();
+    let mut count = 0;
+    for (i, record) in capture_map.records().enumerate() {
+        assert_eq!(
+            cobs::decode_vector(record.unwrap().data).unwrap(),
+            [i as u8; 20]
+        );
+        count += 1;
+    }
+    assert_eq!(count, 1000);
+    drop(capture_map);
+    fs::remove_file(&path).unwrap();
+}
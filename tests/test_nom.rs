@@ -0,0 +1,78 @@
+#![cfg(feature = "nom")]
+
+use ::cobs2::{cobs, cobsr};
+
+use nom::bytes::streaming::tag;
+use nom::error::{Error, ErrorKind};
+use nom::sequence::preceded;
+use nom::IResult;
+
+#[test]
+fn test_parse_frame() {
+    let data_cobs = b"\x04ABC\x05ghij\x04xyz\x00\x02C\x00\x03";
+    let result: IResult<_, _> = cobs::parse_frame(&data_cobs[..]);
+    let (remaining, frame) = result.unwrap();
+    assert_eq!(frame, b"ABC\0ghij\0xyz");
+    let result: IResult<_, _> = cobs::parse_frame(remaining);
+    let (remaining, frame) = result.unwrap();
+    assert_eq!(frame, b"C");
+    assert_eq!(remaining, b"\x03");
+
+    // No delimiter.
+    let result: IResult<_, _> = cobs::parse_frame(remaining);
+    assert_eq!(result, Err(nom::Err::Incomplete(nom::Needed::Unknown)));
+
+    // Empty frame.
+    let result: IResult<_, _> = cobsr::parse_frame(&b"\x00\x02C\x00"[..]);
+    assert_eq!(result, Ok((&b"\x02C\x00"[..], Vec::new())));
+}
+
+#[test]
+fn test_parse_frame_combinator() {
+    // A protocol in which each frame is preceded by a header tag.
+    fn tagged_frame(input: &[u8]) -> IResult<&[u8], Vec<u8>> {
+        preceded(tag(b"\x7E"), cobsr::parse_frame)(input)
+    }
+
+    let (remaining, frame) = tagged_frame(b"\x7E\x03ABz\x00\x7E").unwrap();
+    assert_eq!(frame, b"AB\0z");
+    assert_eq!(remaining, b"\x7E");
+}
+
+#[test]
+fn test_parse_frame_bad() {
+    let data_cobs = &b"\x05AB\x00"[..];
+    let result: IResult<_, _> = cobs::parse_frame(data_cobs);
+    assert_eq!(
+        result,
+        Err(nom::Err::Error(Error::new(data_cobs, ErrorKind::MapRes)))
+    );
+
+    // With an error type that keeps the decoding error.
+    #[derive(Debug, PartialEq)]
+    struct FrameError(Option<::cobs2::Error>);
+
+    impl<'a> nom::error::ParseError<&'a [u8]> for FrameError {
+        fn from_error_kind(_input: &'a [u8], _kind: ErrorKind) -> Self {
+            FrameError(None)
+        }
+
+        fn append(_input: &'a [u8], _kind: ErrorKind, other: Self) -> Self {
+            other
+        }
+    }
+
+    impl<'a> nom::error::FromExternalError<&'a [u8], ::cobs2::Error> for FrameError {
+        fn from_external_error(_input: &'a [u8], _kind: ErrorKind, e: ::cobs2::Error) -> Self {
+            FrameError(Some(e))
+        }
+    }
+
+    let result: IResult<_, _, FrameError> = cobs::parse_frame(data_cobs);
+    assert_eq!(
+        result,
+        Err(nom::Err::Error(FrameError(Some(
+            ::cobs2::Error::TruncatedEncodedData
+        ))))
+    );
+}
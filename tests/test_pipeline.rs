@@ -0,0 +1,162 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::pipeline::{Cobs, Cobsr, Crc32, Pipeline, Stage};
+use ::cobs2::{cobs, Error, Result};
+
+#[test]
+fn test_crc32_checksum() {
+    // Standard check value for CRC-32.
+    assert_eq!(Crc32::checksum(b"123456789"), 0xCBF4_3926);
+    assert_eq!(Crc32::checksum(b""), 0);
+}
+
+#[test]
+fn test_pipeline_crc_cobs() {
+    let pipeline = Pipeline::new().stage(Crc32).stage(Cobs);
+    let data = b"ABC\0ghij\0xyz";
+    let encoded = pipeline.encode(data).unwrap();
+    let mut expected = data.to_vec();
+    expected.extend_from_slice(&Crc32::checksum(data).to_le_bytes());
+    assert_eq!(encoded, cobs::encode_vector(&expected).unwrap());
+    assert_eq!(pipeline.decode(&encoded).unwrap(), data);
+
+    // Corrupt one byte.
+    let mut corrupted = encoded.clone();
+    corrupted[2] ^= 0x01;
+    assert_eq!(pipeline.decode(&corrupted), Err(Error::ChecksumMismatch));
+
+    // Too short for a checksum.
+    let encoded = cobs::encode_vector(b"AB").unwrap();
+    assert_eq!(pipeline.decode(&encoded), Err(Error::ChecksumMismatch));
+}
+
+#[test]
+fn test_pipeline_empty() {
+    let pipeline = Pipeline::new();
+    assert_eq!(pipeline.encode(b"AB\0").unwrap(), b"AB\0");
+    assert_eq!(pipeline.decode(b"AB\0").unwrap(), b"AB\0");
+}
+
+#[test]
+fn test_pipeline_custom_stage() {
+    struct Invert;
+
+    impl Stage for Invert {
+        fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+            Ok(data.iter().map(|x| !x).collect())
+        }
+
+        fn decode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+            self.encode(data)
+        }
+    }
+
+    let pipeline = Pipeline::new().stage(Invert).stage(Cobsr);
+    let encoded = pipeline.encode(b"\xFF\xFEz").unwrap();
+    assert_eq!(encoded, b"\x01\x85\x01");
+    assert_eq!(pipeline.decode(&encoded).unwrap(), b"\xFF\xFEz");
+}
+
+#[cfg(feature = "deflate")]
+#[test]
+fn test_pipeline_deflate_crc_cobs() {
+    use ::cobs2::pipeline::Deflate;
+
+    let pipeline = Pipeline::new()
+        .stage(Deflate { max_len: 1000 })
+        .stage(Crc32)
+        .stage(Cobsr);
+    let data = b"temperature=21.5;temperature=21.5;temperature=21.5;temperature=21.5";
+    let encoded = pipeline.encode(data).unwrap();
+    assert!(encoded.len() < data.len());
+    assert_eq!(pipeline.decode(&encoded).unwrap(), data);
+
+    let pipeline = Pipeline::new()
+        .stage(Deflate { max_len: 10 })
+        .stage(Crc32)
+        .stage(Cobsr);
+    assert_eq!(pipeline.decode(&encoded), Err(Error::DecompressionFailed));
+}
+
+#[cfg(feature = "aead")]
+#[test]
+fn test_pipeline_chacha20poly1305() {
+    use ::cobs2::pipeline::{ChaCha20Poly1305, CounterNonce, NonceSource};
+    use std::cell::Cell;
+
+    let key = [0x42_u8; 32];
+    let tx = Pipeline::new()
+        .stage(ChaCha20Poly1305::new(&key, CounterNonce::new(*b"host")))
+        .stage(Cobs);
+    let rx = Pipeline::new()
+        .stage(ChaCha20Poly1305::new(&key, CounterNonce::new(*b"node")))
+        .stage(Cobs);
+
+    let data = b"ABC\0ghij\0xyz";
+    let encoded_1 = tx.encode(data).unwrap();
+    let encoded_2 = tx.encode(data).unwrap();
+    // Nonce, data and tag.
+    assert_eq!(
+        cobs::decode_vector(&encoded_1).unwrap().len(),
+        12 + data.len() + 16
+    );
+    // A different nonce is used for each frame.
+    assert_ne!(encoded_1, encoded_2);
+    assert_eq!(rx.decode(&encoded_1).unwrap(), data);
+    assert_eq!(rx.decode(&encoded_2).unwrap(), data);
+
+    // Tampered data.
+    let mut decoded = cobs::decode_vector(&encoded_1).unwrap();
+    decoded[14] ^= 0x01;
+    let tampered = cobs::encode_vector(&decoded).unwrap();
+    assert_eq!(rx.decode(&tampered), Err(Error::AuthenticationFailed));
+
+    // Wrong key.
+    let rx_wrong_key = Pipeline::new()
+        .stage(ChaCha20Poly1305::new(
+            &[0x43_u8; 32],
+            CounterNonce::new(*b"node"),
+        ))
+        .stage(Cobs);
+    assert_eq!(
+        rx_wrong_key.decode(&encoded_1),
+        Err(Error::AuthenticationFailed)
+    );
+
+    // Nonce hook which rejects replayed frames.
+    struct ReplayCheck {
+        last_counter: Cell<Option<u64>>,
+    }
+
+    impl NonceSource for ReplayCheck {
+        fn next_nonce(&self) -> [u8; 12] {
+            unimplemented!()
+        }
+
+        fn accept_nonce(&self, nonce: &[u8; 12]) -> bool {
+            let mut counter_bytes = [0_u8; 8];
+            counter_bytes.copy_from_slice(&nonce[4..]);
+            let counter = u64::from_le_bytes(counter_bytes);
+            if self.last_counter.get().is_some_and(|last| counter <= last) {
+                return false;
+            }
+            self.last_counter.set(Some(counter));
+            true
+        }
+    }
+
+    let rx_replay = Pipeline::new()
+        .stage(ChaCha20Poly1305::new(
+            &key,
+            ReplayCheck {
+                last_counter: Cell::new(None),
+            },
+        ))
+        .stage(Cobs);
+    assert_eq!(rx_replay.decode(&encoded_1).unwrap(), data);
+    assert_eq!(rx_replay.decode(&encoded_2).unwrap(), data);
+    assert_eq!(
+        rx_replay.decode(&encoded_1),
+        Err(Error::AuthenticationFailed)
+    );
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "heapless")]
+
+use ::cobs2::{cobs, cobsr};
+
+use heapless::box_pool;
+use heapless::pool::boxed::BoxBlock;
+
+box_pool!(TestPool: [u8; 16]);
+
+fn manage_test_pool() {
+    static mut BLOCKS: [BoxBlock<[u8; 16]>; 2] = [const { BoxBlock::new() }; 2];
+    #[allow(static_mut_refs)]
+    let blocks: &'static mut [BoxBlock<[u8; 16]>; 2] = unsafe { &mut BLOCKS };
+    for block in blocks {
+        TestPool.manage(block);
+    }
+}
+
+#[test]
+fn test_decode_to_pool() {
+    manage_test_pool();
+
+    // Two memory blocks, so two frames can be held at once.
+    let frame_a = cobs::decode_to_pool::<TestPool, 16>(b"\x0612345\x056789").unwrap();
+    let frame_b = cobsr::decode_to_pool::<TestPool, 16>(b"zAB").unwrap();
+    assert_eq!(&frame_a[..], b"12345\x006789");
+    assert_eq!(&frame_b[..], b"ABz");
+
+    let result = cobs::decode_to_pool::<TestPool, 16>(b"\x04ABC");
+    assert_eq!(result.unwrap_err(), ::cobs2::Error::PoolExhausted);
+
+    // Dropping a frame returns its memory block to the pool.
+    drop(frame_a);
+    let frame_c = cobs::decode_to_pool::<TestPool, 16>(b"\x04ABC").unwrap();
+    assert_eq!(&frame_c[..], b"ABC");
+    drop(frame_b);
+
+    // Frame too large for a memory block. The memory block is returned to the pool.
+    let result = cobs::decode_to_pool::<TestPool, 16>(b"\x1201234567890123456");
+    assert_eq!(result.unwrap_err(), ::cobs2::Error::OutputBufferTooSmall);
+    let result = cobs::decode_to_pool::<TestPool, 16>(b"\x05AAA");
+    assert_eq!(result.unwrap_err(), ::cobs2::Error::TruncatedEncodedData);
+    let frame_d = cobs::decode_to_pool::<TestPool, 16>(b"\x01").unwrap();
+    assert!(frame_d.is_empty());
+
+    let (data, len) = frame_c.into_inner();
+    assert_eq!(&data[..len], b"ABC");
+}
@@ -0,0 +1,181 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::pipeline::{Crc32, Pipeline};
+use ::cobs2::profile::{decode_tagged, Checksum, Delimiters, Profile, Variant};
+use ::cobs2::{cobs, cobsr, Error};
+
+const PROFILES: [Profile; 4] = [
+    Profile::Cobs,
+    Profile::Cobsr,
+    Profile::CobsCrc32,
+    Profile::CobsrCrc16Ccitt,
+];
+
+#[test]
+fn test_profile_properties() {
+    assert_eq!(Profile::Cobs.variant(), Variant::Cobs);
+    assert_eq!(Profile::CobsrCrc16Ccitt.variant(), Variant::Cobsr);
+    assert_eq!(Profile::Cobsr.checksum(), Checksum::None);
+    assert_eq!(Profile::CobsCrc32.checksum().len(), 4);
+    assert_eq!(Profile::CobsrCrc16Ccitt.checksum().len(), 2);
+    assert!(Checksum::None.is_empty());
+    assert_eq!(Profile::CobsCrc32.delimiters(), Delimiters::Trailing);
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.delimiters(),
+        Delimiters::LeadingAndTrailing
+    );
+    assert_eq!(Profile::Cobs.max_len(), None);
+    assert_eq!(Profile::CobsrCrc16Ccitt.max_len(), Some(251));
+}
+
+#[test]
+fn test_profile_encoding() {
+    let data = b"ABC\0ghij\0xyz";
+    assert_eq!(
+        Profile::Cobs.encode(data).unwrap(),
+        cobs::encode_vector(data).unwrap()
+    );
+    assert_eq!(
+        Profile::Cobsr.encode(data).unwrap(),
+        cobsr::encode_vector(data).unwrap()
+    );
+    let pipeline = Pipeline::new().stage(Crc32).stage(::cobs2::pipeline::Cobs);
+    assert_eq!(
+        Profile::CobsCrc32.encode(data).unwrap(),
+        pipeline.encode(data).unwrap()
+    );
+
+    // Standard check value for CRC-16/CCITT-FALSE, appended little-endian.
+    let encoded = Profile::CobsrCrc16Ccitt.encode(b"123456789").unwrap();
+    assert_eq!(
+        cobsr::decode_vector(&encoded).unwrap(),
+        b"123456789\xB1\x29"
+    );
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.encode_frame(b"123456789").unwrap(),
+        b"\x00\x29123456789\xB1\x00"
+    );
+    assert_eq!(Profile::Cobs.encode_frame(b"").unwrap(), b"\x01\x00");
+}
+
+#[test]
+fn test_profile_roundtrip() {
+    for &profile in PROFILES.iter() {
+        for len in [0, 1, 200, 251] {
+            let data: Vec<u8> = (0..len).map(|x| (x % 7) as u8).collect();
+            let frame = profile.encode_frame(&data).unwrap();
+            assert_eq!(
+                frame.iter().filter(|&&x| x == 0).count(),
+                match profile.delimiters() {
+                    Delimiters::Trailing => 1,
+                    Delimiters::LeadingAndTrailing => 2,
+                }
+            );
+            let frame_encoded = frame.split(|&x| x == 0).find(|f| !f.is_empty()).unwrap();
+            assert_eq!(
+                profile.decode(frame_encoded).unwrap(),
+                data,
+                "{:?}",
+                profile
+            );
+        }
+    }
+}
+
+#[test]
+fn test_profile_bad() {
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.encode(&[1; 252]),
+        Err(Error::FrameTooLong)
+    );
+    let too_long = cobsr::encode_vector(&[1; 254]).unwrap();
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.decode(&too_long),
+        Err(Error::FrameTooLong)
+    );
+
+    let mut encoded = Profile::CobsCrc32.encode(b"ABC").unwrap();
+    encoded[1] ^= 0x01;
+    assert_eq!(
+        Profile::CobsCrc32.decode(&encoded),
+        Err(Error::ChecksumMismatch)
+    );
+    assert_eq!(
+        Profile::CobsCrc32.decode(b"\x04ABC"),
+        Err(Error::ChecksumMismatch)
+    );
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.decode(b"\x02A"),
+        Err(Error::ChecksumMismatch)
+    );
+    assert_eq!(
+        Profile::Cobs.decode(b"\x05AB"),
+        Err(Error::TruncatedEncodedData)
+    );
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn test_profile_stream() {
+    use ::cobs2::router::FrameRouter;
+    use ::cobs2::stream::{BufferedFrameWriter, FlushPolicy};
+    use std::sync::mpsc;
+
+    let profile = Profile::CobsrCrc16Ccitt;
+    let mut writer = BufferedFrameWriter::with_profile(Vec::new(), FlushPolicy::Explicit, profile);
+    writer.write_frame(b"\x01ABC").unwrap();
+    writer.write_frame(b"\x02xyz").unwrap();
+    let stream = writer.into_inner().unwrap();
+    assert_eq!(stream[0], 0);
+
+    let (tx, rx) = mpsc::channel();
+    FrameRouter::with_profile(profile)
+        .route_channel_id(0x02, tx)
+        .run(std::io::Cursor::new(stream))
+        .unwrap();
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [b"\x02xyz"]);
+}
+
+#[test]
+fn test_profile_tagged() {
+    let ids: Vec<u8> = PROFILES.iter().map(|p| p.id()).collect();
+    assert_eq!(ids, [0x01, 0x02, 0x21, 0x12]);
+    for profile in PROFILES.iter() {
+        assert_eq!(Profile::from_id(profile.id()), Some(*profile));
+    }
+    assert_eq!(Profile::from_id(0x00), None);
+    assert_eq!(Profile::from_id(0x11), None);
+
+    // Frames from senders with different profiles, on one bus.
+    let data = b"ABC\0ghij\0xyz";
+    let mut bus = Vec::new();
+    for profile in PROFILES.iter() {
+        let frame = profile.encode_tagged(data).unwrap();
+        assert_eq!(frame[0], profile.id());
+        assert_eq!(&frame[1..], &profile.encode(data).unwrap()[..]);
+        assert!(!frame.contains(&0));
+        bus.extend_from_slice(&frame);
+        bus.push(0);
+    }
+    let received: Vec<_> = bus
+        .split(|&x| x == 0)
+        .filter(|frame| !frame.is_empty())
+        .map(|frame| decode_tagged(frame).unwrap())
+        .collect();
+    let expected: Vec<_> = PROFILES.iter().map(|p| (*p, data.to_vec())).collect();
+    assert_eq!(received, expected);
+
+    assert_eq!(decode_tagged(b""), Err(Error::UnknownFormat));
+    assert_eq!(decode_tagged(b"\x03\x04ABC"), Err(Error::UnknownFormat));
+    assert_eq!(
+        decode_tagged(b"\x01\x05ABC"),
+        Err(Error::TruncatedEncodedData)
+    );
+    let mut bad_crc = Profile::CobsCrc32.encode_tagged(data).unwrap();
+    bad_crc[2] ^= 1;
+    assert_eq!(decode_tagged(&bad_crc), Err(Error::ChecksumMismatch));
+    assert_eq!(
+        Profile::CobsrCrc16Ccitt.encode_tagged(&[1_u8; 252]),
+        Err(Error::FrameTooLong)
+    );
+}
@@ -0,0 +1,95 @@
+#![cfg(feature = "heapless")]
+
+use ::cobs2::ring::RingReceiver;
+use ::cobs2::{cobs, cobsr};
+
+use heapless::spsc::Queue;
+
+#[test]
+fn test_decode_from_ring_wrap_around() {
+    let mut queue: Queue<u8, 8> = Queue::new();
+    let (mut producer, mut consumer) = queue.split();
+    let mut receiver: RingReceiver<16> = RingReceiver::new();
+    let mut decode_buf = [0xCC_u8; 16];
+
+    // Frames are longer than the ring buffer, so they are received in several parts, and wrap
+    // around the end of the ring buffer's storage.
+    let data_cobs = b"\x00\x0612345\x056789\x00\x00\x04ABC\x00";
+    let mut frames = Vec::new();
+    for chunk in data_cobs.chunks(5) {
+        for &byte in chunk {
+            producer.enqueue(byte).unwrap();
+        }
+        while let Some(result) =
+            cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf)
+        {
+            frames.push(result.unwrap().to_vec());
+        }
+    }
+    assert_eq!(frames, [b"12345\x006789".to_vec(), b"ABC".to_vec()]);
+    assert!(receiver.is_empty());
+}
+
+#[test]
+fn test_decode_from_ring_partial() {
+    let mut queue: Queue<u8, 16> = Queue::new();
+    let (mut producer, mut consumer) = queue.split();
+    let mut receiver: RingReceiver<16> = RingReceiver::new();
+    let mut decode_buf = [0xCC_u8; 16];
+
+    for &byte in b"\x03AB\x05gh" {
+        producer.enqueue(byte).unwrap();
+    }
+    let result = cobsr::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert!(result.is_none());
+    assert_eq!(receiver.len(), 6);
+
+    for &byte in b"ij\x00" {
+        producer.enqueue(byte).unwrap();
+    }
+    let result = cobsr::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap().unwrap(), b"AB\0ghij");
+    assert!(receiver.is_empty());
+}
+
+#[test]
+fn test_decode_from_ring_bad() {
+    let mut queue: Queue<u8, 32> = Queue::new();
+    let (mut producer, mut consumer) = queue.split();
+    let mut receiver: RingReceiver<4> = RingReceiver::new();
+    let mut decode_buf = [0xCC_u8; 16];
+
+    // Frame too long for the receiver, followed by a truncated frame, then a good frame.
+    for &byte in b"\x06ABCDE\x00\x05AB\x00\x03AB\x00" {
+        producer.enqueue(byte).unwrap();
+    }
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap(), Err(::cobs2::Error::OutputBufferTooSmall));
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap(), Err(::cobs2::Error::TruncatedEncodedData));
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap().unwrap(), b"AB");
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert!(result.is_none());
+}
+
+#[test]
+fn test_decode_from_ring_empty_frames() {
+    let mut queue: Queue<u8, 16> = Queue::new();
+    let (mut producer, mut consumer) = queue.split();
+    let mut receiver: RingReceiver<16> = RingReceiver::new();
+    receiver.set_empty_frames(::cobs2::EmptyFrames::Surface);
+    let mut decode_buf = [0xCC_u8; 16];
+
+    for &byte in b"\x00\x03AB\x00\x00" {
+        producer.enqueue(byte).unwrap();
+    }
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap().unwrap(), b"");
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap().unwrap(), b"AB");
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert_eq!(result.unwrap().unwrap(), b"");
+    let result = cobs::decode_from_ring(&mut receiver, &mut consumer, &mut decode_buf);
+    assert!(result.is_none());
+}
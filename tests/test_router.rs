@@ -0,0 +1,132 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::router::FrameRouter;
+use ::cobs2::{cobs, cobsr};
+
+use std::io::Cursor;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+
+fn encode_stream(frames: &[&[u8]]) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for frame in frames {
+        stream.extend(cobs::encode_vector(frame).unwrap());
+        stream.push(0);
+    }
+    stream
+}
+
+#[test]
+fn test_router_channel_id() {
+    let (tx_1, rx_1) = mpsc::channel();
+    let (tx_2, rx_2) = mpsc::sync_channel(8);
+    let router = FrameRouter::new(cobs::decode_vector)
+        .route_channel_id(1, tx_1)
+        .route_channel_id(2, tx_2);
+
+    let stream = encode_stream(&[b"\x01A", b"\x02B\0", b"\x03C", b"\x01D"]);
+    router.spawn(Cursor::new(stream)).join().unwrap().unwrap();
+    assert_eq!(
+        rx_1.iter().collect::<Vec<_>>(),
+        [b"\x01A".to_vec(), b"\x01D".to_vec()]
+    );
+    assert_eq!(rx_2.iter().collect::<Vec<_>>(), [b"\x02B\0".to_vec()]);
+}
+
+#[test]
+fn test_router_predicate_handler() {
+    let long_frames = Arc::new(Mutex::new(Vec::new()));
+    let long_frames_handler = Arc::clone(&long_frames);
+    let (tx_other, rx_other) = mpsc::channel();
+    let router = FrameRouter::new(cobsr::decode_vector)
+        .route(
+            |frame| frame.len() > 3,
+            move |frame| long_frames_handler.lock().unwrap().push(frame),
+        )
+        .route(|_| true, tx_other);
+
+    // Empty frames are skipped, and a partial frame at the end is discarded.
+    let stream = b"\x00\x03AB\x00\x00\x05ghij\x00\x02\x00\x03xy".to_vec();
+    router.run(Cursor::new(stream)).unwrap();
+    assert_eq!(*long_frames.lock().unwrap(), [b"ghij".to_vec()]);
+    assert_eq!(
+        rx_other.iter().collect::<Vec<_>>(),
+        [b"AB".to_vec(), b"\x02".to_vec()]
+    );
+}
+
+#[test]
+fn test_router_bad_frames() {
+    let (tx, rx) = mpsc::channel();
+    let router = FrameRouter::new(cobs::decode_vector).route(|_| true, tx);
+
+    // A truncated frame is discarded.
+    let stream = b"\x05AB\x00\x03AB\x00".to_vec();
+    router.run(Cursor::new(stream)).unwrap();
+    assert_eq!(rx.iter().collect::<Vec<_>>(), [b"AB".to_vec()]);
+}
+
+#[test]
+fn test_router_backpressure() {
+    // A rendezvous channel, so the router blocks until each frame is received.
+    let (tx, rx) = mpsc::sync_channel(0);
+    let router = FrameRouter::new(cobs::decode_vector).route_channel_id(7, tx);
+
+    let frames: Vec<Vec<u8>> = (0..100_u8).map(|i| vec![7, i]).collect();
+    let frame_refs: Vec<&[u8]> = frames.iter().map(|frame| &frame[..]).collect();
+    let handle = router.spawn(Cursor::new(encode_stream(&frame_refs)));
+    assert_eq!(rx.iter().collect::<Vec<_>>(), frames);
+    handle.join().unwrap().unwrap();
+}
+
+#[test]
+fn test_router_progress() {
+    use ::cobs2::stream::Progress;
+
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let reports_clone = reports.clone();
+    let router = FrameRouter::new(cobs::decode_vector)
+        .route(|_| true, |_| {})
+        .progress(100, move |progress| {
+            reports_clone.lock().unwrap().push(progress)
+        });
+
+    // Frames of 10 bytes encoded, with one invalid frame, then a partial frame.
+    let mut stream = encode_stream(&[&[0x55_u8; 8][..]; 25]);
+    stream.extend_from_slice(b"\x05ABC\x00");
+    stream.extend(encode_stream(&[&[0x55_u8; 8][..]; 24]));
+    stream.extend_from_slice(b"\x02A");
+    router.run(Cursor::new(stream)).unwrap();
+
+    let reports = reports.lock().unwrap();
+    assert_eq!(
+        *reports,
+        [
+            Progress {
+                bytes: 100,
+                frames: 10,
+                invalid_frames: 0
+            },
+            Progress {
+                bytes: 200,
+                frames: 20,
+                invalid_frames: 0
+            },
+            Progress {
+                bytes: 305,
+                frames: 30,
+                invalid_frames: 1
+            },
+            Progress {
+                bytes: 405,
+                frames: 40,
+                invalid_frames: 1
+            },
+            Progress {
+                bytes: 497,
+                frames: 49,
+                invalid_frames: 1
+            },
+        ]
+    );
+}
@@ -0,0 +1,121 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::profile::Profile;
+use ::cobs2::scheduler::{Priority, Reassembler, TxScheduler};
+use ::cobs2::Error;
+
+/// Decode the frames sent by the scheduler, each as its kind byte and data.
+fn drain(scheduler: &mut TxScheduler, profile: Profile) -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    while let Some(frame_encoded) = scheduler.next_frame() {
+        assert_eq!(frame_encoded.last(), Some(&0));
+        let frame = frame_encoded
+            .split(|&x| x == 0)
+            .find(|frame| !frame.is_empty())
+            .unwrap();
+        frames.push(profile.decode(frame).unwrap());
+    }
+    frames
+}
+
+#[test]
+fn test_scheduler_fragments() {
+    let mut scheduler = TxScheduler::new();
+    assert!(scheduler.is_empty());
+    assert_eq!(scheduler.next_frame(), None);
+
+    let bulk: Vec<u8> = (0..600_u32).map(|x| x as u8).collect();
+    scheduler.push(Priority::Low, bulk.clone()).unwrap();
+    scheduler.push(Priority::Low, b"small".to_vec()).unwrap();
+    assert_eq!(scheduler.low_len(), 2);
+    let frames = drain(&mut scheduler, Profile::Cobs);
+    assert!(scheduler.is_empty());
+
+    assert_eq!(frames.len(), 4);
+    assert_eq!(frames[0][0], 1);
+    assert_eq!(frames[0][1..], bulk[..253]);
+    assert_eq!(frames[1][0], 2);
+    assert_eq!(frames[1][1..], bulk[253..506]);
+    assert_eq!(frames[2][0], 3);
+    assert_eq!(frames[2][1..], bulk[506..]);
+    assert_eq!(frames[3], b"\x00small");
+
+    // A fragment without zero-bytes is a single COBS block.
+    scheduler.push(Priority::Low, vec![0x55; 1000]).unwrap();
+    let frame = scheduler.next_frame().unwrap();
+    assert_eq!(frame.len(), 256);
+    assert!(!frame[1..255].contains(&0));
+}
+
+#[test]
+fn test_scheduler_preemption() {
+    let mut scheduler = TxScheduler::new_cobsr();
+    scheduler.set_fragment_len(10).unwrap();
+    assert_eq!(scheduler.fragment_len(), 10);
+    scheduler.push(Priority::Low, vec![b'L'; 35]).unwrap();
+    let first = scheduler.next_frame().unwrap();
+    scheduler.push(Priority::High, b"H1".to_vec()).unwrap();
+    scheduler.push(Priority::High, b"H2".to_vec()).unwrap();
+    assert_eq!(scheduler.high_len(), 2);
+    assert_eq!(scheduler.low_len(), 1);
+    let mut frames = vec![Profile::Cobsr.decode(&first[..first.len() - 1]).unwrap()];
+    frames.extend(drain(&mut scheduler, Profile::Cobsr));
+    let kinds: Vec<u8> = frames.iter().map(|frame| frame[0]).collect();
+    assert_eq!(kinds, [1, 0, 0, 2, 2, 3]);
+    assert_eq!(frames[1], b"\x00H1");
+    assert_eq!(frames[2], b"\x00H2");
+
+    let mut reassembler = Reassembler::new(100);
+    let mut messages = Vec::new();
+    for frame in &frames {
+        if let Some(message) = reassembler.push(frame).unwrap() {
+            messages.push(message);
+        }
+        assert_eq!(reassembler.in_progress(), frame[0] != 3);
+    }
+    assert_eq!(messages, [b"H1".to_vec(), b"H2".to_vec(), vec![b'L'; 35]]);
+}
+
+#[test]
+fn test_scheduler_profile_max_len() {
+    let profile = Profile::CobsrCrc16Ccitt;
+    let mut scheduler = TxScheduler::with_profile(profile);
+    assert_eq!(scheduler.fragment_len(), 250);
+    assert_eq!(scheduler.set_fragment_len(251), Err(Error::FrameTooLong));
+    assert_eq!(
+        scheduler.push(Priority::High, vec![1; 251]),
+        Err(Error::FrameTooLong)
+    );
+    scheduler.push(Priority::High, vec![1; 250]).unwrap();
+    scheduler.push(Priority::Low, vec![2; 1000]).unwrap();
+    let frames = drain(&mut scheduler, profile);
+    assert_eq!(frames.len(), 5);
+    assert!(frames.iter().all(|frame| frame.len() <= 251));
+}
+
+#[test]
+fn test_reassembler_errors() {
+    let mut reassembler = Reassembler::new(8);
+    assert_eq!(reassembler.push(b""), Err(Error::UnknownFormat));
+    assert_eq!(reassembler.push(b"\x04abc"), Err(Error::UnknownFormat));
+    assert_eq!(reassembler.push(b"\x00123456789"), Err(Error::FrameTooLong));
+
+    // A lost first fragment.
+    assert_eq!(reassembler.push(b"\x02abc"), Err(Error::FragmentLost));
+    assert_eq!(reassembler.push(b"\x03abc"), Err(Error::FragmentLost));
+
+    // A lost last fragment, then a new message.
+    assert_eq!(reassembler.push(b"\x01abc"), Ok(None));
+    assert_eq!(reassembler.push(b"\x01xyz"), Err(Error::FragmentLost));
+    assert_eq!(reassembler.push(b"\x03!"), Ok(Some(b"xyz!".to_vec())));
+
+    // Too long.
+    assert_eq!(reassembler.push(b"\x01abcde"), Ok(None));
+    assert_eq!(reassembler.push(b"\x02fghij"), Err(Error::FrameTooLong));
+    assert!(!reassembler.in_progress());
+    assert_eq!(reassembler.push(b"\x03k"), Err(Error::FragmentLost));
+
+    assert_eq!(reassembler.push(b"\x01abc"), Ok(None));
+    reassembler.clear();
+    assert_eq!(reassembler.push(b"\x00"), Ok(Some(Vec::new())));
+}
@@ -0,0 +1,213 @@
+#![cfg(feature = "self-check")]
+
+use ::cobs2::{cobs, cobsr};
+
+/// Inputs with runs of various lengths, around the 254-byte block boundary.
+fn inputs() -> Vec<Vec<u8>> {
+    let mut inputs = vec![Vec::new(), vec![0], vec![0; 3], b"ABC\0ghij\0xyz".to_vec()];
+    for len in [1, 2, 253, 254, 255, 508, 600] {
+        for last in [0_u8, 1, 2, 0x7F, 0xFF] {
+            let mut data: Vec<u8> = (0..len).map(|x| (x % 255 + 1) as u8).collect();
+            data.push(last);
+            inputs.push(data);
+        }
+    }
+    inputs
+}
+
+// With feature `self-check`, each encode function checks its own output, so these pass only if
+// none of the checks fails.
+
+#[test]
+fn test_self_check_cobs() {
+    let mut out_buf = vec![0_u8; 1024];
+    for data in inputs() {
+        let encoded = cobs::encode_vector(&data).unwrap();
+        assert_eq!(
+            cobs::encode_array(&mut out_buf, &data).unwrap(),
+            &encoded[..]
+        );
+        let (head, tail) = out_buf.split_at_mut(encoded.len() / 2);
+        let (head_len, tail_len) = cobs::encode_into_split(head, tail, &data).unwrap();
+        assert_eq!(head_len + tail_len, encoded.len());
+        for out_len in [255, 256, 300] {
+            let mut partial = Vec::new();
+            let mut rest = &data[..];
+            loop {
+                let (consumed, produced, complete) =
+                    cobs::encode_array_partial(&mut out_buf[..out_len], rest);
+                rest = &rest[consumed..];
+                partial.extend_from_slice(&out_buf[..produced]);
+                if complete {
+                    break;
+                }
+            }
+            assert_eq!(partial, encoded);
+        }
+        if data.len() <= 253 {
+            assert_eq!(
+                cobs::encode_short_array(&mut out_buf, &data).unwrap(),
+                &encoded[..]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_self_check_cobsr() {
+    let mut out_buf = vec![0_u8; 1024];
+    for data in inputs() {
+        let encoded = cobsr::encode_vector(&data).unwrap();
+        assert_eq!(
+            cobsr::encode_array(&mut out_buf, &data).unwrap(),
+            &encoded[..]
+        );
+        let (head, tail) = out_buf.split_at_mut(encoded.len() / 2);
+        let (head_len, tail_len) = cobsr::encode_into_split(head, tail, &data).unwrap();
+        assert_eq!(head_len + tail_len, encoded.len());
+        for out_len in [255, 256, 300] {
+            let mut partial = Vec::new();
+            let mut rest = &data[..];
+            loop {
+                let (consumed, produced, complete) =
+                    cobsr::encode_array_partial(&mut out_buf[..out_len], rest);
+                rest = &rest[consumed..];
+                partial.extend_from_slice(&out_buf[..produced]);
+                if complete {
+                    break;
+                }
+            }
+            assert_eq!(partial, encoded);
+        }
+        if data.len() <= 253 {
+            assert_eq!(
+                cobsr::encode_short_array(&mut out_buf, &data).unwrap(),
+                &encoded[..]
+            );
+        }
+    }
+}
+
+#[test]
+fn test_self_check_cobs_incremental() {
+    let mut out_buf = vec![0_u8; 1024];
+    let mut encoder = cobs::Encoder::new();
+    let mut frames = Vec::new();
+    let mut writer = cobs::FrameWriter::new(|encoded: &[u8]| frames.extend_from_slice(encoded));
+    let mut expected = Vec::new();
+    for data in inputs() {
+        let encoded = cobs::encode_vector(&data).unwrap();
+        let encode_iter: Vec<u8> = cobs::encode_iter(&data).collect();
+        assert_eq!(encode_iter, encoded);
+        let encode_ref_iter: Vec<u8> = cobs::encode_ref_iter(data.iter()).collect();
+        assert_eq!(encode_ref_iter, encoded);
+        let encode_clone_iter: Vec<u8> = cobs::encode_clone_iter(data.iter().copied()).collect();
+        assert_eq!(encode_clone_iter, encoded);
+
+        let text: String = data.iter().map(|&x| (x % 0x80) as char).collect();
+        assert_eq!(
+            cobs::encode_fmt(&mut out_buf, format_args!("{}", text)).unwrap(),
+            &cobs::encode_vector(text.as_bytes()).unwrap()[..]
+        );
+
+        for part in data.chunks(100) {
+            writer.write_bytes(part);
+        }
+        writer.finish();
+        expected.extend_from_slice(&encoded);
+        expected.push(0);
+    }
+    drop(writer);
+    assert_eq!(frames, expected);
+
+    // The encoder's output buffers are of various sizes, so a frame's delimiter is sometimes
+    // written by the same call as the start of the next frame.
+    let mut sent = Vec::new();
+    for (i, data) in inputs().iter().enumerate() {
+        let mut tx_buf = vec![0_u8; 1 + i % 7];
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let progress = encoder.encode(rest, &mut tx_buf);
+            rest = &rest[progress.consumed..];
+            sent.extend_from_slice(&tx_buf[..progress.produced]);
+        }
+        if i % 2 == 0 {
+            loop {
+                let progress = encoder.finish(&mut tx_buf);
+                sent.extend_from_slice(&tx_buf[..progress.produced]);
+                if progress.done {
+                    break;
+                }
+            }
+        } else {
+            let progress = encoder.finish(&mut tx_buf);
+            sent.extend_from_slice(&tx_buf[..progress.produced]);
+        }
+    }
+    let mut tx_buf = [0_u8; 300];
+    let progress = encoder.finish(&mut tx_buf);
+    sent.extend_from_slice(&tx_buf[..progress.produced]);
+    assert!(progress.done);
+}
+
+#[test]
+fn test_self_check_cobsr_incremental() {
+    let mut out_buf = vec![0_u8; 1024];
+    let mut encoder = cobsr::Encoder::new();
+    let mut frames = Vec::new();
+    let mut writer = cobsr::FrameWriter::new(|encoded: &[u8]| frames.extend_from_slice(encoded));
+    let mut expected = Vec::new();
+    for data in inputs() {
+        let encoded = cobsr::encode_vector(&data).unwrap();
+        let encode_iter: Vec<u8> = cobsr::encode_iter(&data).collect();
+        assert_eq!(encode_iter, encoded);
+        let encode_ref_iter: Vec<u8> = cobsr::encode_ref_iter(data.iter()).collect();
+        assert_eq!(encode_ref_iter, encoded);
+        let encode_clone_iter: Vec<u8> = cobsr::encode_clone_iter(data.iter().copied()).collect();
+        assert_eq!(encode_clone_iter, encoded);
+
+        let text: String = data.iter().map(|&x| (x % 0x80) as char).collect();
+        assert_eq!(
+            cobsr::encode_fmt(&mut out_buf, format_args!("{}", text)).unwrap(),
+            &cobsr::encode_vector(text.as_bytes()).unwrap()[..]
+        );
+
+        for part in data.chunks(100) {
+            writer.write_bytes(part);
+        }
+        writer.finish();
+        expected.extend_from_slice(&encoded);
+        expected.push(0);
+    }
+    drop(writer);
+    assert_eq!(frames, expected);
+
+    // The encoder's output buffers are of various sizes, so a frame's delimiter is sometimes
+    // written by the same call as the start of the next frame.
+    let mut sent = Vec::new();
+    for (i, data) in inputs().iter().enumerate() {
+        let mut tx_buf = vec![0_u8; 1 + i % 7];
+        let mut rest = &data[..];
+        while !rest.is_empty() {
+            let progress = encoder.encode(rest, &mut tx_buf);
+            rest = &rest[progress.consumed..];
+            sent.extend_from_slice(&tx_buf[..progress.produced]);
+        }
+        if i % 2 == 0 {
+            loop {
+                let progress = encoder.finish(&mut tx_buf);
+                sent.extend_from_slice(&tx_buf[..progress.produced]);
+                if progress.done {
+                    break;
+                }
+            }
+        } else {
+            let progress = encoder.finish(&mut tx_buf);
+            sent.extend_from_slice(&tx_buf[..progress.produced]);
+        }
+    }
+    let mut tx_buf = [0_u8; 300];
+    let progress = encoder.finish(&mut tx_buf);
+    sent.extend_from_slice(&tx_buf[..progress.produced]);
+    assert!(progress.done);
+}
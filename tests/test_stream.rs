@@ -0,0 +1,852 @@
+#![cfg(feature = "std")]
+
+use ::cobs2::stream::{
+    BufferedFrameWriter, CobsStream, CobsTcpStream, FlushPolicy, FrameEvent, FrameReader,
+    IoErrorPolicy, Progress, RejectedFrames, Transceiver,
+};
+
+use std::io::{self, Cursor, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// In-memory stream, which reads from one buffer and writes to another.
+struct Duplex {
+    input: Cursor<Vec<u8>>,
+    output: Vec<u8>,
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn duplex(input: &[u8]) -> Duplex {
+    Duplex {
+        input: Cursor::new(input.to_vec()),
+        output: Vec::new(),
+    }
+}
+
+#[test]
+fn test_stream_send() {
+    let mut stream = CobsStream::new(duplex(b""));
+    stream.send(b"ABC\0ghij\0xyz").unwrap();
+    stream.send(b"").unwrap();
+    assert_eq!(
+        stream.get_ref().output,
+        b"\x04ABC\x05ghij\x04xyz\x00\x01\x00"
+    );
+
+    let mut stream = CobsStream::new_cobsr(duplex(b""));
+    stream.send(b"ABC\0ghij\0xyz").unwrap();
+    assert_eq!(stream.into_inner().output, b"\x04ABC\x05ghijzxy\x00");
+}
+
+#[test]
+fn test_stream_recv_resync() {
+    // Empty frames, then a truncated frame, then good frames, then a partial frame.
+    let mut stream = CobsStream::new(duplex(b"\x00\x00\x05AB\x00\x03AB\x00\x01\x00\x02X"));
+    let mut frame = Vec::new();
+
+    let e = stream.recv(&mut frame).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        e.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::TruncatedEncodedData)
+    );
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"AB");
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"");
+    let e = stream.recv(&mut frame).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_stream_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    // Echo server.
+    let server = thread::spawn(move || {
+        let mut stream = CobsTcpStream::new(listener.accept().unwrap().0);
+        let mut frame = Vec::new();
+        for _ in 0..100 {
+            stream.recv(&mut frame).unwrap();
+            stream.send(&frame).unwrap();
+        }
+    });
+
+    let mut stream = CobsTcpStream::new(TcpStream::connect(addr).unwrap());
+    let mut frame = Vec::new();
+    for i in 0..100_usize {
+        let data: Vec<u8> = (0..i * 10).map(|x| (x % 7) as u8).collect();
+        stream.send(&data).unwrap();
+        stream.recv(&mut frame).unwrap();
+        assert_eq!(frame, data);
+    }
+    server.join().unwrap();
+}
+
+/// Writer which records the data of each write call, and the number of flushes.
+#[derive(Default)]
+struct WriteLog {
+    writes: Vec<Vec<u8>>,
+    flushes: usize,
+}
+
+impl Write for WriteLog {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes.push(buf.to_vec());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushes += 1;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_buffered_frame_writer_each_frame() {
+    let mut writer = BufferedFrameWriter::new(WriteLog::default(), FlushPolicy::EachFrame);
+    writer.write_frame(b"ABC\0ghij\0xyz").unwrap();
+    writer.write_frame(b"").unwrap();
+    assert_eq!(writer.buffered_len(), 0);
+    let log = writer.into_inner().unwrap();
+    assert_eq!(
+        log.writes,
+        [&b"\x04ABC\x05ghij\x04xyz\x00"[..], b"\x01\x00"]
+    );
+    assert_eq!(log.flushes, 3);
+
+    let mut writer = BufferedFrameWriter::new_cobsr(WriteLog::default(), FlushPolicy::EachFrame);
+    writer.write_frame(b"ABC\0ghij\0xyz").unwrap();
+    assert_eq!(writer.get_ref().writes, [b"\x04ABC\x05ghijzxy\x00"]);
+}
+
+#[test]
+fn test_buffered_frame_writer_threshold() {
+    let mut writer = BufferedFrameWriter::new(WriteLog::default(), FlushPolicy::Threshold(10));
+    for _ in 0..5 {
+        writer.write_frame(b"AB").unwrap();
+    }
+    // Each frame is 4 bytes encoded, so the buffer exceeds 10 bytes on every third frame.
+    assert_eq!(writer.get_ref().writes, [b"\x03AB\x00".repeat(3)]);
+    assert_eq!(writer.get_ref().flushes, 1);
+    assert_eq!(writer.buffered_len(), 8);
+
+    writer.set_policy(FlushPolicy::EachFrame);
+    assert_eq!(writer.policy(), FlushPolicy::EachFrame);
+    writer.write_frame(b"AB").unwrap();
+    assert_eq!(
+        writer.get_ref().writes,
+        [b"\x03AB\x00".repeat(3), b"\x03AB\x00".repeat(3)]
+    );
+}
+
+#[test]
+fn test_buffered_frame_writer_frames() {
+    let mut writer = BufferedFrameWriter::new(WriteLog::default(), FlushPolicy::Frames(3));
+    for _ in 0..8 {
+        writer.write_frame(b"AB").unwrap();
+    }
+    assert_eq!(
+        writer.get_ref().writes,
+        [b"\x03AB\x00".repeat(3), b"\x03AB\x00".repeat(3)]
+    );
+    assert_eq!(writer.buffered_len(), 8);
+    let log = writer.into_inner().unwrap();
+    assert_eq!(log.writes.len(), 3);
+    assert_eq!(log.flushes, 3);
+}
+
+#[test]
+fn test_buffered_frame_writer_explicit() {
+    let mut writer = BufferedFrameWriter::new(WriteLog::default(), FlushPolicy::Explicit);
+    for _ in 0..1000 {
+        writer.write_frame(b"A").unwrap();
+    }
+    assert!(writer.get_ref().writes.is_empty());
+    assert_eq!(writer.buffered_len(), 3000);
+    writer.flush().unwrap();
+    assert_eq!(writer.get_ref().writes, [b"\x02A\x00".repeat(1000)]);
+    assert_eq!(writer.buffered_len(), 0);
+
+    // Flush with no buffered data only flushes the underlying writer.
+    writer.flush().unwrap();
+    let log = writer.into_inner().unwrap();
+    assert_eq!(log.writes.len(), 1);
+    assert_eq!(log.flushes, 3);
+}
+
+#[test]
+fn test_stream_progress() {
+    let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let reports_clone = reports.clone();
+    let mut stream = CobsStream::new(duplex(b"\x04ABC\x00\x00\x03AB\x00\x05AB\x00\x02A"));
+    stream.set_progress(0, move |progress| {
+        reports_clone.lock().unwrap().push(progress)
+    });
+
+    let mut frame = Vec::new();
+    stream.recv(&mut frame).unwrap();
+    stream.recv(&mut frame).unwrap();
+    assert!(stream.recv(&mut frame).is_err());
+    assert_eq!(
+        stream.recv(&mut frame).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        *reports.lock().unwrap(),
+        [
+            Progress {
+                bytes: 5,
+                frames: 1,
+                invalid_frames: 0
+            },
+            Progress {
+                bytes: 10,
+                frames: 2,
+                invalid_frames: 0
+            },
+            Progress {
+                bytes: 14,
+                frames: 2,
+                invalid_frames: 1
+            },
+            Progress {
+                bytes: 16,
+                frames: 2,
+                invalid_frames: 1
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_stream_validator() {
+    // Frames must start with a magic byte 'M', and be at least 2 bytes long.
+    let input = b"\x03MA\x00\x03XA\x00\x02M\x00\x05MABC\x00";
+    let mut stream = CobsStream::new(duplex(input));
+    stream.set_validator(RejectedFrames::Skip, |frame| {
+        frame.len() >= 2 && frame[0] == b'M'
+    });
+    let mut frame = Vec::new();
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"MA");
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"MABC");
+    assert_eq!(stream.rejected_frames(), 2);
+    assert_eq!(
+        stream.recv(&mut frame).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    let mut stream = CobsStream::new(duplex(input));
+    stream.set_validator(RejectedFrames::Report, |frame| {
+        frame.len() >= 2 && frame[0] == b'M'
+    });
+    let mut frame = Vec::new();
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"MA");
+    for _ in 0..2 {
+        let err = stream.recv(&mut frame).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+            Some(&::cobs2::Error::FrameRejected)
+        );
+    }
+    assert_eq!(frame, b"MA");
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"MABC");
+    assert_eq!(stream.rejected_frames(), 2);
+}
+
+#[test]
+fn test_stream_max_frame_len() {
+    let mut stream = CobsStream::new(duplex(b"\x05ABCD\x00\x04ABC\x00\x06ABCDE\x00\x02A"));
+    stream.set_max_frame_len(4);
+    let mut frame = Vec::new();
+    let e = stream.recv(&mut frame).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        e.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::FrameTooLong)
+    );
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"ABC");
+    assert!(stream.recv(&mut frame).is_err());
+    assert_eq!(
+        stream.recv(&mut frame).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    // A frame longer than the read buffer, and the default maximum, is discarded.
+    let mut input = Vec::new();
+    for _ in 0..300 {
+        input.push(0xFF);
+        input.extend_from_slice(&[b'A'; 254]);
+    }
+    input.extend_from_slice(b"\x00\x03xy\x00");
+    let mut stream = CobsStream::new(duplex(&input));
+    let mut frame = Vec::new();
+    let e = stream.recv(&mut frame).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"xy");
+
+    let mut stream = CobsStream::new(duplex(&input));
+    stream.set_max_frame_len(input.len());
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, [b'A'; 254].repeat(300));
+}
+
+#[test]
+fn test_stream_frame_hook() {
+    use std::sync::mpsc;
+
+    let mut stream = CobsStream::new(duplex(
+        b"\x00\x04ABC\x00\x05AB\x00\x06ABCDE\x00\x02x\x00\x02y",
+    ));
+    stream.set_max_frame_len(4);
+    stream.set_validator(RejectedFrames::Skip, |frame| frame != b"x");
+    let (tx, rx) = mpsc::channel();
+    stream.set_frame_hook(move |event| tx.send(event).unwrap());
+    let mut frame = Vec::new();
+    stream.recv(&mut frame).unwrap();
+    assert_eq!(frame, b"ABC");
+    assert!(stream.recv(&mut frame).is_err());
+    assert!(stream.recv(&mut frame).is_err());
+    assert_eq!(
+        stream.recv(&mut frame).unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [
+            FrameEvent::Start,
+            FrameEvent::Complete { len: 3 },
+            FrameEvent::Start,
+            FrameEvent::Error {
+                reason: ::cobs2::Error::TruncatedEncodedData,
+                discarded: 4,
+            },
+            FrameEvent::Start,
+            FrameEvent::Error {
+                reason: ::cobs2::Error::FrameTooLong,
+                discarded: 7,
+            },
+            FrameEvent::Start,
+            FrameEvent::Error {
+                reason: ::cobs2::Error::FrameRejected,
+                discarded: 3,
+            },
+            FrameEvent::Start,
+        ]
+    );
+}
+
+/// Stream which gives scripted results for each read, then reads which time out.
+#[derive(Default)]
+struct Scripted {
+    reads: std::collections::VecDeque<Option<Vec<u8>>>,
+    output: Vec<u8>,
+}
+
+impl Read for Scripted {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reads.pop_front() {
+            Some(Some(data)) => {
+                buf[..data.len()].copy_from_slice(&data);
+                Ok(data.len())
+            }
+            Some(None) => Ok(0),
+            None => Err(io::ErrorKind::WouldBlock.into()),
+        }
+    }
+}
+
+impl Write for Scripted {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.output.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_transceiver() {
+    let timeout = std::time::Duration::from_millis(20);
+    let mut stream = Scripted::default();
+    // Response in several parts, after a truncated frame and an empty frame.
+    stream.reads.push_back(Some(b"\x05AB".to_vec()));
+    stream.reads.push_back(Some(b"\x00\x00\x03O".to_vec()));
+    stream.reads.push_back(Some(b"K\x00\x02Z".to_vec()));
+    let mut transceiver = Transceiver::new(stream);
+    assert_eq!(transceiver.request(b"ABC\0", timeout).unwrap(), b"OK");
+
+    // The partial frame received after the response is discarded.
+    transceiver
+        .get_mut()
+        .reads
+        .push_back(Some(b"\x00\x02R\x00".to_vec()));
+    assert_eq!(transceiver.request(b"", timeout).unwrap(), b"R");
+
+    // No response.
+    let err = transceiver.request(b"x", timeout).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // End of stream.
+    transceiver
+        .get_mut()
+        .reads
+        .push_back(Some(b"\x03O".to_vec()));
+    transceiver.get_mut().reads.push_back(None);
+    let err = transceiver.request(b"y", timeout).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    assert_eq!(
+        transceiver.into_inner().output,
+        b"\x04ABC\x01\x00\x01\x00\x02x\x00\x02y\x00"
+    );
+}
+
+#[test]
+fn test_transceiver_late_response() {
+    let timeout = std::time::Duration::from_millis(20);
+    let mut transceiver = Transceiver::new(Scripted::default());
+    let err = transceiver.request(b"1", timeout).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    // The late response to the first request is still in the stream, so it is taken as the
+    // response to the second request, and the second response is taken by the third request.
+    transceiver
+        .get_mut()
+        .reads
+        .push_back(Some(b"\x03R1\x00".to_vec()));
+    transceiver
+        .get_mut()
+        .reads
+        .push_back(Some(b"\x03R2\x00".to_vec()));
+    assert_eq!(transceiver.request(b"2", timeout).unwrap(), b"R1");
+    assert_eq!(transceiver.request(b"3", timeout).unwrap(), b"R2");
+    assert_eq!(
+        transceiver.into_inner().output,
+        b"\x021\x00\x022\x00\x023\x00"
+    );
+}
+
+#[test]
+fn test_transceiver_cobsr() {
+    let mut stream = Scripted::default();
+    stream.reads.push_back(Some(b"\x03OKz\x00".to_vec()));
+    let mut transceiver = Transceiver::new_cobsr(stream);
+    let response = transceiver
+        .request(b"ABC\0xyz", std::time::Duration::from_secs(1))
+        .unwrap();
+    assert_eq!(response, b"OK\0z");
+    assert_eq!(transceiver.get_ref().output, b"\x04ABCzxy\x00");
+}
+
+#[test]
+fn test_transceiver_max_frame_len() {
+    let timeout = std::time::Duration::from_millis(20);
+    let mut stream = Scripted::default();
+    stream.reads.push_back(Some(b"\x05ABCD\x00".to_vec()));
+    let mut transceiver = Transceiver::new(stream);
+    transceiver.set_max_frame_len(4);
+    let e = transceiver.request(b"1", timeout).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        e.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::FrameTooLong)
+    );
+    transceiver
+        .get_mut()
+        .reads
+        .push_back(Some(b"\x04ABC\x00".to_vec()));
+    assert_eq!(transceiver.request(b"2", timeout).unwrap(), b"ABC");
+
+    // A response longer than the default maximum is discarded up to its delimiter.
+    let mut input = vec![b'A'; 1 << 20];
+    input.push(0);
+    let mut transceiver = Transceiver::new(duplex(&input));
+    let e = transceiver.request(b"1", timeout).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_frame_reader_try_read() {
+    let mut stream = Scripted::default();
+    stream.reads.push_back(Some(b"\x00\x04AB".to_vec()));
+    let mut reader = FrameReader::new(stream);
+    assert_eq!(reader.try_read_frame().unwrap(), None);
+    assert_eq!(reader.buffered_len(), 3);
+
+    // The partial frame is kept through the timeout.
+    reader
+        .get_mut()
+        .reads
+        .push_back(Some(b"C\x00\x05AB\x00\x03xy\x00\x02".to_vec()));
+    assert_eq!(reader.try_read_frame().unwrap().unwrap(), b"ABC");
+    let err = reader.try_read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(reader.try_read_frame().unwrap().unwrap(), b"xy");
+    assert_eq!(reader.try_read_frame().unwrap(), None);
+
+    reader.get_mut().reads.push_back(None);
+    let err = reader.try_read_frame().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_frame_reader_frame_hook() {
+    use std::sync::mpsc;
+
+    let mut stream = Scripted::default();
+    stream.reads.push_back(Some(b"\x00\x00\x04A".to_vec()));
+    let mut reader = FrameReader::new(stream);
+    reader.set_max_frame_len(4);
+    let (tx, rx) = mpsc::channel();
+    reader.set_frame_hook(move |event| tx.send(event).unwrap());
+
+    // The start of a frame is given when its first byte is read.
+    assert_eq!(reader.try_read_frame().unwrap(), None);
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [FrameEvent::Start]);
+
+    reader
+        .get_mut()
+        .reads
+        .push_back(Some(b"BC\x00\x05AB\x00\x06ABCD".to_vec()));
+    reader
+        .get_mut()
+        .reads
+        .push_back(Some(b"E\x00\x02".to_vec()));
+    assert_eq!(reader.try_read_frame().unwrap().unwrap(), b"ABC");
+    assert!(reader.try_read_frame().is_err());
+    assert!(reader.try_read_frame().is_err());
+    assert_eq!(reader.try_read_frame().unwrap(), None);
+    assert_eq!(
+        rx.try_iter().collect::<Vec<_>>(),
+        [
+            FrameEvent::Complete { len: 3 },
+            FrameEvent::Start,
+            FrameEvent::Error {
+                reason: ::cobs2::Error::TruncatedEncodedData,
+                discarded: 4,
+            },
+            FrameEvent::Start,
+            FrameEvent::Error {
+                reason: ::cobs2::Error::FrameTooLong,
+                discarded: 7,
+            },
+            FrameEvent::Start,
+        ]
+    );
+}
+
+#[test]
+fn test_frame_reader_read() {
+    /// Reader which times out before each read of 2 bytes.
+    struct Slow {
+        data: Cursor<Vec<u8>>,
+        timed_out: bool,
+    }
+
+    impl Read for Slow {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.timed_out = !self.timed_out;
+            if self.timed_out {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            let len = buf.len().min(2);
+            self.data.read(&mut buf[..len])
+        }
+    }
+
+    let data = b"\x7AAB\x00\x04ABC\x00".to_vec();
+    let slow = Slow {
+        data: Cursor::new(data),
+        timed_out: false,
+    };
+    let mut reader = FrameReader::new_cobsr(slow);
+    assert_eq!(reader.read_frame().unwrap(), b"ABz");
+    assert_eq!(reader.read_frame().unwrap(), b"ABC");
+    assert_eq!(
+        reader.read_frame().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_frame_reader_max_frame_len() {
+    let mut reader = FrameReader::new(&b"\x05ABCD\x00\x04ABC\x00\x06ABCDE\x00\x02A"[..]);
+    reader.set_max_frame_len(4);
+    let e = reader.read_frame().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(
+        e.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::FrameTooLong)
+    );
+    assert_eq!(reader.read_frame().unwrap(), b"ABC");
+    assert!(reader.read_frame().is_err());
+    assert_eq!(
+        reader.read_frame().unwrap_err().kind(),
+        io::ErrorKind::UnexpectedEof
+    );
+
+    // A frame longer than the default maximum is discarded up to its delimiter.
+    let input = io::repeat(b'A').take(1 << 20).chain(&b"\x00\x03xy\x00"[..]);
+    let mut reader = FrameReader::new(input);
+    let e = reader.read_frame().unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(reader.read_frame().unwrap(), b"xy");
+
+    /// Reader of endless non-zero data, which times out before each read.
+    struct Endless {
+        timed_out: bool,
+    }
+
+    impl Read for Endless {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.timed_out = !self.timed_out;
+            if self.timed_out {
+                return Err(io::ErrorKind::TimedOut.into());
+            }
+            buf.fill(b'A');
+            Ok(buf.len())
+        }
+    }
+
+    // The buffered data stays bounded, however long the frame is.
+    let mut reader = FrameReader::new(Endless { timed_out: false });
+    reader.set_max_frame_len(1000);
+    for _ in 0..10_000 {
+        assert_eq!(reader.try_read_frame().unwrap(), None);
+        assert!(reader.buffered_len() <= 1000);
+    }
+}
+
+#[test]
+fn test_dyn_helpers() {
+    use ::cobs2::profile::Profile;
+    use ::cobs2::stream::{decode_stream, recv_frame, send_frame, DEFAULT_MAX_FRAME_LEN};
+
+    let profile = Profile::CobsrCrc16Ccitt;
+    let mut sent = Vec::new();
+    {
+        let writer: &mut dyn Write = &mut sent;
+        send_frame(writer, profile, b"ABC\0xyz").unwrap();
+        send_frame(writer, profile, b"").unwrap();
+    }
+    let err = send_frame(&mut Vec::new(), profile, &[1; 300]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+    let mut reader = io::BufReader::new(Cursor::new(sent.clone()));
+    let mut frame = Vec::new();
+    recv_frame(&mut reader, profile, &mut frame).unwrap();
+    assert_eq!(frame, b"ABC\0xyz");
+    recv_frame(&mut reader, profile, &mut frame).unwrap();
+    assert_eq!(frame, b"");
+    let err = recv_frame(&mut reader, profile, &mut frame).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+    /// Reader which reads one byte at a time.
+    struct OneByte(Cursor<Vec<u8>>);
+
+    impl Read for OneByte {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let len = buf.len().min(1);
+            self.0.read(&mut buf[..len])
+        }
+    }
+
+    // Invalid and partial frames, read one byte at a time.
+    sent.extend_from_slice(b"\x03AB\x00\x02x");
+    let mut frames = Vec::new();
+    let mut reader = OneByte(Cursor::new(sent.clone()));
+    let mut on_frame = |frame: ::cobs2::Result<&[u8]>| frames.push(frame.map(<[u8]>::to_vec));
+    let bytes = decode_stream(&mut reader, profile, &mut on_frame).unwrap();
+    assert_eq!(bytes, sent.len() as u64);
+    assert_eq!(
+        frames,
+        [
+            Ok(b"ABC\0xyz".to_vec()),
+            Ok(Vec::new()),
+            Err(::cobs2::Error::ChecksumMismatch),
+            Err(::cobs2::Error::ChecksumMismatch),
+        ]
+    );
+
+    // Frames longer than the default maximum are discarded without being buffered.
+    let mut long = vec![b'A'; DEFAULT_MAX_FRAME_LEN + 1];
+    long.extend_from_slice(b"\x00\x02x\x00");
+    long.extend_from_slice(&[b'A'; DEFAULT_MAX_FRAME_LEN + 1]);
+    let mut reader = Cursor::new(long.clone());
+    let err = recv_frame(&mut reader, Profile::Cobs, &mut frame).unwrap_err();
+    assert_eq!(
+        err.into_inner().unwrap().downcast_ref::<::cobs2::Error>(),
+        Some(&::cobs2::Error::FrameTooLong)
+    );
+    recv_frame(&mut reader, Profile::Cobs, &mut frame).unwrap();
+    assert_eq!(frame, b"x");
+
+    let mut frames = Vec::new();
+    let mut on_frame = |frame: ::cobs2::Result<&[u8]>| frames.push(frame.map(<[u8]>::to_vec));
+    let bytes = decode_stream(&mut Cursor::new(long.clone()), Profile::Cobs, &mut on_frame);
+    assert_eq!(bytes.unwrap(), long.len() as u64);
+    assert_eq!(
+        frames,
+        [
+            Err(::cobs2::Error::FrameTooLong),
+            Ok(b"x".to_vec()),
+            Err(::cobs2::Error::FrameTooLong),
+        ]
+    );
+}
+
+#[test]
+fn test_frame_reader_read_to_sink() {
+    use std::sync::mpsc;
+
+    let mut reader = FrameReader::new(&b"\x04ABC\x00\x05AB\x00\x00\x02z\x00\x03xy"[..]);
+    let (mut tx, rx) = mpsc::channel();
+    assert_eq!(reader.read_to_sink(&mut tx).unwrap(), 1);
+    drop(tx);
+    assert_eq!(rx.iter().collect::<Vec<_>>(), [&b"ABC"[..], b"z"]);
+    // The partial frame is kept.
+    assert_eq!(reader.buffered_len(), 3);
+
+    // An error from the sink stops reading.
+    let mut reader = FrameReader::new(&b"\x04ABC\x00\x02z\x00"[..]);
+    let mut frames = Vec::new();
+    let mut sink = |frame: &[u8]| {
+        frames.push(frame.to_vec());
+        Err("full")
+    };
+    let error = reader.read_to_sink(&mut sink).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::Other);
+    assert_eq!(error.to_string(), "full");
+    assert_eq!(frames, [b"ABC"]);
+    assert_eq!(reader.read_frame().unwrap(), b"z");
+
+    // An error from the reader is returned, rather than counted as an invalid frame.
+    struct Failing;
+    impl io::Read for Failing {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::InvalidData.into())
+        }
+    }
+    let mut frames: Vec<Vec<u8>> = Vec::new();
+    let error = FrameReader::new(Failing)
+        .read_to_sink(&mut frames)
+        .unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+}
+
+/// A reader which returns each chunk of data in turn, or an error for each `None`.
+struct Flaky {
+    chunks: Vec<Option<&'static [u8]>>,
+    kind: io::ErrorKind,
+}
+
+impl Read for Flaky {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.chunks.is_empty() {
+            return Ok(0);
+        }
+        match self.chunks.remove(0) {
+            Some(data) => {
+                buf[..data.len()].copy_from_slice(data);
+                Ok(data.len())
+            }
+            None => Err(self.kind.into()),
+        }
+    }
+}
+
+#[test]
+fn test_frame_reader_io_error_policy() {
+    use ::cobs2::stream::is_transient_io_error;
+    use std::time::{Duration, Instant};
+
+    let flaky = |kind| Flaky {
+        chunks: vec![Some(b"\x04AB"), None, None, Some(b"C\x00\x02z\x00")],
+        kind,
+    };
+
+    // By default, the error is returned.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::ConnectionReset));
+    let error = reader.read_frame().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(reader.recovered_io_errors(), 0);
+
+    // Retrying keeps the partial frame, with a doubling delay.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::ConnectionReset));
+    reader.set_io_error_policy(IoErrorPolicy::Retry {
+        max_retries: 2,
+        initial_backoff: Duration::from_millis(20),
+        max_backoff: Duration::from_secs(1),
+    });
+    let start = Instant::now();
+    assert_eq!(reader.read_frame().unwrap(), b"ABC");
+    assert!(start.elapsed() >= Duration::from_millis(60));
+    assert_eq!(reader.read_frame().unwrap(), b"z");
+    assert_eq!(reader.recovered_io_errors(), 2);
+
+    // Too many consecutive errors are returned.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::ConnectionReset));
+    reader.set_io_error_policy(IoErrorPolicy::Retry {
+        max_retries: 1,
+        initial_backoff: Duration::from_millis(1),
+        max_backoff: Duration::from_millis(1),
+    });
+    let error = reader.read_frame().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+    assert_eq!(reader.recovered_io_errors(), 1);
+
+    // Skipping discards the partial frame.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::BrokenPipe));
+    reader.set_io_error_policy(IoErrorPolicy::Skip);
+    assert_eq!(
+        reader.read_frame().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(reader.read_frame().unwrap(), b"z");
+    assert_eq!(reader.recovered_io_errors(), 2);
+
+    // Errors which are not transient are returned.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::PermissionDenied));
+    reader.set_io_error_policy(IoErrorPolicy::Skip);
+    let error = reader.read_frame().unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+
+    // Unless the function which decides says so.
+    let mut reader = FrameReader::new(flaky(io::ErrorKind::PermissionDenied));
+    reader.set_io_error_policy(IoErrorPolicy::Skip);
+    reader.set_transient_io_errors(|e| e.kind() == io::ErrorKind::PermissionDenied);
+    reader.read_frame().unwrap_err();
+    assert_eq!(reader.read_frame().unwrap(), b"z");
+
+    assert!(is_transient_io_error(
+        &io::ErrorKind::ConnectionAborted.into()
+    ));
+    assert!(!is_transient_io_error(&io::ErrorKind::InvalidInput.into()));
+    #[cfg(target_os = "linux")]
+    assert!(is_transient_io_error(&io::Error::from_raw_os_error(19)));
+}
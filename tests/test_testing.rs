@@ -0,0 +1,314 @@
+#![cfg(feature = "test-util")]
+
+mod common;
+
+use ::cobs2::cobs;
+use ::cobs2::stream::{CobsStream, Link};
+use ::cobs2::testing::{loopback, Corruptor, MockTransport};
+
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn encode_stream(frame_count: usize) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for i in 0..frame_count {
+        let frame: Vec<u8> = (0..i % 50).map(|x| (x * 3 + i) as u8).collect();
+        stream.extend(cobs::encode_vector(&frame).unwrap());
+        stream.push(0);
+    }
+    stream
+}
+
+#[test]
+fn test_corruptor_none() {
+    let stream = encode_stream(100);
+    let mut corruptor = Corruptor::new(1);
+    assert_eq!(corruptor.corrupt(&stream), stream);
+    assert_eq!(corruptor.corrupt(b"\x02A"), b"\x02A");
+}
+
+#[test]
+fn test_corruptor_each() {
+    let stream = encode_stream(1000);
+
+    let corrupt = Corruptor::new(2).flip_bytes(0.1).corrupt(&stream);
+    assert_eq!(corrupt.len(), stream.len());
+    let changed = corrupt.iter().zip(&stream).filter(|(a, b)| a != b).count();
+    assert!(changed > stream.len() / 20 && changed < stream.len() / 5);
+    assert!(corrupt
+        .iter()
+        .zip(&stream)
+        .all(|(a, b)| (a ^ b).count_ones() <= 1));
+
+    let corrupt = Corruptor::new(3).drop_bytes(0.1).corrupt(&stream);
+    assert!(corrupt.len() > stream.len() * 8 / 10 && corrupt.len() < stream.len() * 95 / 100);
+
+    let corrupt = Corruptor::new(4).inject_zeros(0.1).corrupt(&stream);
+    let zeros = |x: &[u8]| x.iter().filter(|x| **x == 0).count();
+    assert!(corrupt.len() > stream.len());
+    assert_eq!(
+        zeros(&corrupt),
+        zeros(&stream) + corrupt.len() - stream.len()
+    );
+
+    // Truncated frames keep their delimiter.
+    let corrupt = Corruptor::new(5).truncate_frames(0.5).corrupt(&stream);
+    assert!(corrupt.len() < stream.len());
+    assert_eq!(zeros(&corrupt), zeros(&stream));
+    let bad_frames = corrupt
+        .split(|x| *x == 0)
+        .filter(|frame| !frame.is_empty() && cobs::decode_vector(frame).is_err())
+        .count();
+    assert!(bad_frames > 300 && bad_frames < 700);
+}
+
+#[test]
+fn test_corruptor_iter_reader() {
+    let stream = encode_stream(500);
+    let make_corruptor = |seed| {
+        Corruptor::new(seed)
+            .flip_bytes(0.01)
+            .drop_bytes(0.01)
+            .inject_zeros(0.01)
+            .truncate_frames(0.1)
+    };
+    let corruptor = make_corruptor(6);
+    let corrupt = corruptor.clone().corrupt(&stream);
+    assert_ne!(corrupt, stream);
+
+    // Same results, for the same seed.
+    let corrupt_iter: Vec<u8> = corruptor
+        .clone()
+        .corrupt_iter(stream.iter().copied())
+        .collect();
+    assert_eq!(corrupt_iter, corrupt);
+    let mut corrupt_reader = Vec::new();
+    corruptor
+        .corrupt_reader(&stream[..])
+        .read_to_end(&mut corrupt_reader)
+        .unwrap();
+    assert_eq!(corrupt_reader, corrupt);
+
+    // A different seed gives different results.
+    let corrupt_other = make_corruptor(7).corrupt(&stream);
+    assert_ne!(corrupt_other, corrupt);
+}
+
+#[test]
+fn test_assert_cobs_roundtrip() {
+    let long_run = vec![0x55_u8; 1000];
+    let zeros = vec![0_u8; 300];
+    ::cobs2::assert_cobs_roundtrip!(b"", b"\0", b"\0\0\0", b"ABC\0ghij\0xyz", long_run, zeros);
+    for len in [252, 253, 254, 255, 256, 507, 508, 509] {
+        let data: Vec<u8> = (0..len).map(|x| (x % 255 + 1) as u8).collect();
+        let data_zero_end = [&data[..], b"\0"].concat();
+        let data_zero_start = [b"\0", &data[..]].concat();
+        ::cobs2::assert_cobs_roundtrip!(data, data_zero_end, data_zero_start);
+    }
+}
+
+#[test]
+fn test_assert_roundtrip_random() {
+    let mut state = 0x1234_5678;
+    for _ in 0..200 {
+        let len = common::xorshift32(&mut state) % 3000;
+        let zero_density = common::xorshift32(&mut state) % 32;
+        let data: Vec<u8> = (0..len)
+            .map(|_| match common::xorshift32(&mut state) {
+                x if x % 256 < zero_density => 0,
+                x => (x >> 8) as u8,
+            })
+            .collect();
+        ::cobs2::testing::assert_roundtrip(&data);
+    }
+}
+
+#[test]
+fn test_mock_transport_read() {
+    let mut transport = MockTransport::new()
+        .read_data(b"abcde")
+        .read_error(io::ErrorKind::TimedOut)
+        .read_delay(Duration::from_millis(20))
+        .read_data(b"")
+        .read_data(b"fg")
+        .read_chunk_len(2);
+    let mut buf = [0_u8; 10];
+    assert_eq!(transport.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"ab");
+    assert_eq!(transport.read(&mut buf).unwrap(), 2);
+    assert_eq!(transport.read(&mut buf[..1]).unwrap(), 1);
+    assert_eq!(&buf[..1], b"e");
+    assert_eq!(
+        transport.read(&mut buf).unwrap_err().kind(),
+        io::ErrorKind::TimedOut
+    );
+    let start = Instant::now();
+    assert_eq!(transport.read(&mut buf).unwrap(), 2);
+    assert!(start.elapsed() >= Duration::from_millis(20));
+    assert_eq!(&buf[..2], b"fg");
+    assert!(transport.is_read_done());
+    assert_eq!(transport.read(&mut buf).unwrap(), 0);
+}
+
+#[test]
+fn test_mock_transport_write() {
+    let mut transport = MockTransport::new()
+        .write_chunk_len(4)
+        .write_error(10, io::ErrorKind::BrokenPipe)
+        .write_error(6, io::ErrorKind::Interrupted);
+    assert_eq!(transport.write(b"0123456789abc").unwrap(), 4);
+    assert_eq!(transport.write(b"456789abc").unwrap(), 2);
+    assert_eq!(
+        transport.write(b"6789abc").unwrap_err().kind(),
+        io::ErrorKind::Interrupted
+    );
+    assert_eq!(transport.write(b"6789abc").unwrap(), 4);
+    assert_eq!(
+        transport.write(b"abc").unwrap_err().kind(),
+        io::ErrorKind::BrokenPipe
+    );
+    assert_eq!(transport.written(), b"0123456789");
+    assert_eq!(transport.take_written(), b"0123456789");
+    assert_eq!(transport.write(b"abc").unwrap(), 3);
+    assert_eq!(transport.written(), b"abc");
+
+    // Writing a frame retries the interrupted write, but fails at the error.
+    let transport = MockTransport::new()
+        .write_chunk_len(1)
+        .write_error(2, io::ErrorKind::Interrupted)
+        .write_error(8, io::ErrorKind::BrokenPipe);
+    let mut stream = CobsStream::new(transport);
+    stream.send(b"ABC").unwrap();
+    assert_eq!(
+        stream.send(b"xyz").unwrap_err().kind(),
+        io::ErrorKind::BrokenPipe
+    );
+    assert_eq!(stream.get_ref().written(), b"\x04ABC\x00\x04xy");
+}
+
+#[test]
+fn test_mock_transport_frames() {
+    let stream = encode_stream(50);
+    for chunk_len in [1, 2, 7, 100] {
+        let transport = MockTransport::new()
+            .read_data(&stream[..30])
+            .read_error(io::ErrorKind::Interrupted)
+            .read_data(&stream[30..])
+            .read_chunk_len(chunk_len);
+        let mut link = Link::new(transport, Vec::new());
+        let mut frame = Vec::new();
+        for i in 0..50 {
+            link.recv(&mut frame).unwrap();
+            assert_eq!(frame.len(), i % 50);
+        }
+        assert_eq!(
+            link.recv(&mut frame).unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+    }
+}
+
+#[test]
+fn test_loopback() {
+    let (host, device) = loopback();
+    let device_thread = thread::spawn(move || {
+        let mut device = CobsStream::new(device);
+        let mut frame = Vec::new();
+        while device.recv(&mut frame).is_ok() {
+            frame.reverse();
+            device.send(&frame).unwrap();
+        }
+    });
+    let mut host = CobsStream::new(host);
+    let mut frame = Vec::new();
+    for request in [&b"ABC"[..], b"x\0y", b""] {
+        host.send(request).unwrap();
+        host.recv(&mut frame).unwrap();
+        let mut expected = request.to_vec();
+        expected.reverse();
+        assert_eq!(frame, expected);
+    }
+    assert_eq!(host.get_ref().available(), 0);
+    drop(host);
+    device_thread.join().unwrap();
+
+    // Writing to a dropped end fails.
+    let (mut a, b) = loopback();
+    a.write_all(b"data").unwrap();
+    drop(b);
+    assert_eq!(
+        a.write(b"more").unwrap_err().kind(),
+        io::ErrorKind::BrokenPipe
+    );
+    assert_eq!(a.read(&mut [0_u8; 4]).unwrap(), 0);
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_loopback_async() {
+    use ::cobs2::async_stream::AsyncLink;
+    use futures::executor::block_on;
+    use futures::io::AsyncReadExt;
+
+    let (host, device) = loopback();
+    let device_thread = thread::spawn(move || {
+        block_on(async {
+            let (reader, writer) = device.split();
+            let mut device = AsyncLink::new(reader, writer);
+            let mut frame = Vec::new();
+            while device.recv(&mut frame).await.is_ok() {
+                frame.push(b'!');
+                device.send(&frame).await.unwrap();
+            }
+        })
+    });
+    block_on(async {
+        let (reader, writer) = host.split();
+        let mut host = AsyncLink::new(reader, writer);
+        let mut frame = Vec::new();
+        host.send(b"ping").await.unwrap();
+        host.recv(&mut frame).await.unwrap();
+        assert_eq!(frame, b"ping!");
+    });
+    device_thread.join().unwrap();
+}
+
+#[cfg(feature = "futures-io")]
+#[test]
+fn test_mock_transport_async() {
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut transport = MockTransport::new()
+        .read_data(b"abc")
+        .read_delay(Duration::from_millis(20))
+        .read_error(io::ErrorKind::ConnectionReset)
+        .read_chunk_len(2)
+        .write_chunk_len(1);
+    block_on(async {
+        let mut buf = [0_u8; 10];
+        assert_eq!(
+            AsyncReadExt::read(&mut transport, &mut buf).await.unwrap(),
+            2
+        );
+        assert_eq!(
+            AsyncReadExt::read(&mut transport, &mut buf).await.unwrap(),
+            1
+        );
+        let start = Instant::now();
+        let err = AsyncReadExt::read(&mut transport, &mut buf)
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+        assert_eq!(
+            AsyncReadExt::read(&mut transport, &mut buf).await.unwrap(),
+            0
+        );
+        AsyncWriteExt::write_all(&mut transport, b"xyz")
+            .await
+            .unwrap();
+    });
+    assert_eq!(transport.written(), b"xyz");
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::transcode::{Direction, Transcoder};
+use ::cobs2::{cobs, cobsr, Error};
+
+/// Frames of various lengths, with zero bytes at various intervals, and various final bytes.
+fn test_frames() -> Vec<Vec<u8>> {
+    let mut frames = Vec::new();
+    for data_len in [0, 1, 2, 5, 253, 254, 255, 508, 509, 1000] {
+        for zero_step in [0, 1, 3, 254, 255] {
+            for final_byte in [
+                None,
+                Some(0x00),
+                Some(0x01),
+                Some(0x03),
+                Some(0x80),
+                Some(0xFF),
+            ] {
+                let mut data: Vec<u8> = (0..data_len)
+                    .map(|i| {
+                        if zero_step != 0 && i % zero_step == 0 {
+                            0
+                        } else {
+                            (i % 255 + 1) as u8
+                        }
+                    })
+                    .collect();
+                data.extend(final_byte);
+                frames.push(data);
+            }
+        }
+    }
+    frames
+}
+
+/// Encode frames into a stream, each with a trailing zero-byte delimiter.
+fn encode_stream(frames: &[Vec<u8>], encode: fn(&[u8]) -> ::cobs2::Result<Vec<u8>>) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for frame in frames {
+        stream.extend_from_slice(&encode(frame).unwrap());
+        stream.push(0);
+    }
+    stream
+}
+
+/// Transcode a stream, received in chunks of the given size.
+fn transcode(transcoder: &mut Transcoder, stream: &[u8], chunk_size: usize) -> Vec<u8> {
+    let mut output = Vec::new();
+    for chunk in stream.chunks(chunk_size) {
+        transcoder.push(chunk, |chunk| output.extend_from_slice(chunk.unwrap()));
+    }
+    output
+}
+
+#[test]
+fn test_transcode() {
+    let frames = test_frames();
+    let cobs_stream = encode_stream(&frames, cobs::encode_vector);
+    let cobsr_stream = encode_stream(&frames, cobsr::encode_vector);
+
+    for chunk_size in [1, 2, 7, 300, cobs_stream.len()] {
+        let mut transcoder = Transcoder::cobs_to_cobsr();
+        assert_eq!(transcoder.direction(), Direction::CobsToCobsr);
+        assert_eq!(
+            transcode(&mut transcoder, &cobs_stream, chunk_size),
+            cobsr_stream
+        );
+        assert!(!transcoder.in_progress());
+
+        let mut transcoder = Transcoder::new(Direction::CobsrToCobs);
+        assert_eq!(
+            transcode(&mut transcoder, &cobsr_stream, chunk_size),
+            cobs_stream
+        );
+        assert!(!transcoder.in_progress());
+    }
+}
+
+#[test]
+fn test_transcode_empty_frames() {
+    // Leading and consecutive delimiters are passed through.
+    for mut transcoder in [Transcoder::cobs_to_cobsr(), Transcoder::cobsr_to_cobs()] {
+        assert_eq!(
+            transcode(&mut transcoder, b"\x00\x00\x01\x00\x00", 1),
+            b"\x00\x00\x01\x00\x00"
+        );
+    }
+}
+
+#[test]
+fn test_transcode_partial_frame() {
+    // The current block is held until the following byte is received.
+    let mut transcoder = Transcoder::cobs_to_cobsr();
+    assert_eq!(transcode(&mut transcoder, b"\x04ABC", 100), b"");
+    assert!(transcoder.in_progress());
+    assert_eq!(transcode(&mut transcoder, b"\x02", 100), b"\x04ABC");
+
+    // Clearing discards the held block.
+    transcoder.clear();
+    assert!(!transcoder.in_progress());
+    assert_eq!(transcode(&mut transcoder, b"\x02z\x00", 100), b"z\x00");
+}
+
+#[test]
+fn test_transcode_truncated() {
+    // A COBS frame whose final block is truncated is reported, and its final block isn't output.
+    let mut transcoder = Transcoder::cobs_to_cobsr();
+    let mut output = Vec::new();
+    let mut errors = Vec::new();
+    transcoder.push(b"\x02A\x05AB\x00\x03xy\x00", |chunk| match chunk {
+        Ok(chunk) => output.extend_from_slice(chunk),
+        Err(e) => errors.push(e),
+    });
+    assert_eq!(output, b"\x02A\x00yx\x00");
+    assert_eq!(errors, [Error::TruncatedEncodedData]);
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "alloc")]
+
+use ::cobs2::typed::{Cobs, Cobsr, Decoded, Encoded};
+use ::cobs2::{cobs, cobsr, Error};
+
+#[test]
+fn test_typed_cobs() {
+    let data = b"ABC\0ghij\0xyz";
+    let payload = Decoded::new(&data[..]);
+    let encoded: Encoded<Vec<u8>> = payload.encode().unwrap();
+    assert_eq!(encoded.get(), &cobs::encode_vector(data).unwrap());
+    let decoded = encoded.decode().unwrap();
+    assert_eq!(decoded.into_inner(), data);
+}
+
+#[test]
+fn test_typed_cobsr() {
+    let data = b"ABC\0ghij\0xyz";
+    let encoded = Decoded::new(data).encode::<Cobsr>().unwrap();
+    assert_eq!(encoded.get(), &cobsr::encode_vector(data).unwrap());
+    assert_eq!(encoded.decode().unwrap().get(), data);
+}
+
+#[test]
+fn test_typed_received() {
+    // Received data, wrapped with its encoding.
+    let received = Encoded::<_, Cobsr>::new(b"\x04ABC\x05ghijzxy".to_vec());
+    assert_eq!(
+        received.decode().unwrap(),
+        Decoded::new(b"ABC\0ghij\0xyz".to_vec())
+    );
+    assert_eq!(received.into_inner(), b"\x04ABC\x05ghijzxy");
+
+    // The same data is not valid COBS.
+    let received = Encoded::<_, Cobs>::new(b"\x04ABC\x05ghijzxy");
+    assert_eq!(received.decode(), Err(Error::TruncatedEncodedData));
+
+    let received = Encoded::<_, Cobs>::new([3_u8, b'A', 0, b'B']);
+    assert_eq!(received.decode(), Err(Error::ZeroInEncodedData));
+}
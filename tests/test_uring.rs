@@ -0,0 +1,111 @@
+#![cfg(all(feature = "io-uring", target_os = "linux"))]
+
+use ::cobs2::profile::Variant;
+use ::cobs2::uring::UringDecoder;
+use ::cobs2::{cobs, cobsr, Error};
+
+use std::io::Write;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+
+fn test_frames() -> Vec<Vec<u8>> {
+    (0..200_usize)
+        .map(|i| (0..i * 3 % 700).map(|x| (x * 7 + i) as u8).collect())
+        .collect()
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("cobs2-{}-{}.bin", name, std::process::id()))
+}
+
+fn decode_file(decoder: &UringDecoder, data: &[u8], name: &str) -> (u64, Vec<Vec<u8>>, Vec<Error>) {
+    let path = temp_path(name);
+    std::fs::write(&path, data).unwrap();
+    let mut frames = Vec::new();
+    let mut errors = Vec::new();
+    let bytes = decoder
+        .decode_file(&path, |frame| match frame {
+            Ok(frame) => frames.push(frame.to_vec()),
+            Err(e) => errors.push(e),
+        })
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+    (bytes, frames, errors)
+}
+
+#[test]
+fn test_uring_decode_file() {
+    let frames = test_frames();
+    for &variant in [Variant::Cobs, Variant::Cobsr].iter() {
+        let mut data = Vec::new();
+        for frame in frames.iter().filter(|frame| !frame.is_empty()) {
+            data.extend(match variant {
+                Variant::Cobs => cobs::encode_vector(frame).unwrap(),
+                Variant::Cobsr => cobsr::encode_vector(frame).unwrap(),
+            });
+            data.push(0);
+        }
+        for &(chunk_len, queue_depth) in [(1, 1), (7, 3), (4096, 8), (1 << 20, 4)].iter() {
+            let decoder = UringDecoder::new(variant)
+                .chunk_len(chunk_len)
+                .queue_depth(queue_depth);
+            let (bytes, decoded, errors) = decode_file(&decoder, &data, "uring-file");
+            assert_eq!(bytes, data.len() as u64);
+            assert!(errors.is_empty());
+            assert_eq!(decoded.len(), frames.len() - 1);
+            assert!(decoded
+                .iter()
+                .eq(frames.iter().filter(|frame| !frame.is_empty())));
+        }
+    }
+}
+
+#[test]
+fn test_uring_decode_file_errors() {
+    let decoder = UringDecoder::new(Variant::Cobs)
+        .max_frame_len(4)
+        .chunk_len(3);
+    let (bytes, frames, errors) = decode_file(
+        &decoder,
+        b"\x04ABC\x00\x06ABCDE\x00\x00\x03xy\x00\x05AB",
+        "uring-errors",
+    );
+    assert_eq!(bytes, 20);
+    assert_eq!(frames, [&b"ABC"[..], b"xy"]);
+    assert_eq!(
+        errors,
+        [Error::OutputBufferTooSmall, Error::TruncatedEncodedData]
+    );
+
+    let err = decoder
+        .decode_file(temp_path("uring-missing"), |_| {})
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn test_uring_decode_tcp() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+    let server = listener.accept().unwrap().0;
+
+    let frames = test_frames();
+    let sent = frames.clone();
+    let sender = thread::spawn(move || {
+        for frame in sent.iter() {
+            client
+                .write_all(&cobs::encode_vector(frame).unwrap())
+                .unwrap();
+            client.write_all(b"\x00").unwrap();
+        }
+    });
+
+    let mut decoded = Vec::new();
+    UringDecoder::new(Variant::Cobs)
+        .chunk_len(100)
+        .decode_tcp(server, |frame| decoded.push(frame.unwrap().to_vec()))
+        .unwrap();
+    sender.join().unwrap();
+    assert_eq!(decoded, frames);
+}